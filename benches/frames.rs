@@ -0,0 +1,164 @@
+//! Parsing and encoding over a mix of traffic resembling a page load:
+//! connection setup, a burst of requests and their responses, large DATA
+//! frames and the WINDOW_UPDATE and PING frames interleaved with them.
+
+#[macro_use]
+extern crate criterion;
+extern crate http2parse;
+
+use criterion::{Criterion, Throughput, black_box};
+
+use http2parse::{Frame, FrameHeader, Frames, Kind, Flag, Payload, StreamIdentifier,
+                 SizeIncrement, Setting, Settings, SettingIdentifier, HttpError};
+use http2parse::hpack::Encoder;
+
+const MAX_FRAME_SIZE: usize = 16384;
+
+fn frame<'a>(kind: Kind, flag: Flag, id: u32, payload: Payload<'a>) -> Frame<'a> {
+    Frame {
+        header: FrameHeader {
+            length: payload.encoded_len() as u32,
+            kind: kind,
+            flag: flag,
+            id: StreamIdentifier(id)
+        },
+        payload: payload
+    }
+}
+
+fn encode(frames: &[Frame]) -> Vec<u8> {
+    let mut buf = Vec::new();
+    for frame in frames {
+        let start = buf.len();
+        buf.resize(start + frame.encoded_len(), 0);
+        frame.encode(&mut buf[start..]);
+    }
+    buf
+}
+
+// Encoded traffic, with the control frames and DATA frames also apart.
+struct Traffic {
+    mixed: Vec<u8>,
+    control: Vec<u8>,
+    data: Vec<u8>
+}
+
+fn traffic() -> Traffic {
+    let settings = [Setting::new(SettingIdentifier::MaxConcurrentStreams, 100),
+                    Setting::new(SettingIdentifier::InitialWindowSize, 1 << 20)];
+    let body = vec![0x5a; MAX_FRAME_SIZE];
+
+    let mut encoder = Encoder::new();
+    let mut blocks = Vec::new();
+    for id in 0..20 {
+        let path = format!("/assets/{}.js", id);
+        blocks.push(encoder.encode(vec![
+            (&b":method"[..], &b"GET"[..]),
+            (b":scheme", b"https"),
+            (b":authority", b"www.example.com"),
+            (b":path", path.as_bytes()),
+            (b"user-agent", b"Mozilla/5.0 (X11; Linux x86_64)"),
+            (b"accept", b"*/*"),
+            (b"accept-encoding", b"gzip, deflate, br")
+        ]));
+        blocks.push(encoder.encode(vec![
+            (&b":status"[..], &b"200"[..]),
+            (b"content-type", b"application/javascript"),
+            (b"cache-control", b"max-age=31536000")
+        ]));
+    }
+
+    let mut control = vec![
+        frame(Kind::Settings, Flag::empty(), 0, Payload::Settings(Settings::new(&settings))),
+        frame(Kind::WindowUpdate, Flag::empty(), 0, Payload::WindowUpdate(SizeIncrement(1 << 24))),
+        frame(Kind::Settings, Flag::ack(), 0, Payload::Settings(Settings::new(&[])))
+    ];
+    let mut data = Vec::new();
+    let mut mixed = control.clone();
+
+    for (n, pair) in blocks.chunks(2).enumerate() {
+        let id = 2 * n as u32 + 1;
+        let request = frame(Kind::Headers, Flag::end_stream() | Flag::end_headers(), id,
+                            Payload::Headers { priority: None, block: &pair[0] });
+        let response = frame(Kind::Headers, Flag::end_headers(), id,
+                             Payload::Headers { priority: None, block: &pair[1] });
+        control.push(request);
+        control.push(response);
+        mixed.push(request);
+        mixed.push(response);
+
+        for chunk in 0..4 {
+            let flag = if chunk == 3 { Flag::end_stream() } else { Flag::empty() };
+            let frame = frame(Kind::Data, flag, id, Payload::Data { data: &body });
+            data.push(frame);
+            mixed.push(frame);
+        }
+
+        let update = frame(Kind::WindowUpdate, Flag::empty(), 0,
+                           Payload::WindowUpdate(SizeIncrement(4 * MAX_FRAME_SIZE as u32)));
+        control.push(update);
+        mixed.push(update);
+
+        if n % 5 == 4 {
+            let ping = frame(Kind::Ping, Flag::empty(), 0, Payload::ping([n as u8; 8]));
+            control.push(ping);
+            mixed.push(ping);
+        }
+    }
+
+    let goaway = Frame::goaway(StreamIdentifier(39), HttpError::NoError, None);
+    control.push(goaway);
+    mixed.push(goaway);
+
+    Traffic { mixed: encode(&mixed), control: encode(&control), data: encode(&data) }
+}
+
+fn parse_all(buf: &[u8]) -> usize {
+    Frames::new(buf).map(|frame| black_box(frame.unwrap()).header.length as usize).sum()
+}
+
+fn bench_parse(c: &mut Criterion) {
+    let traffic = traffic();
+    let mut group = c.benchmark_group("parse");
+
+    for &(name, ref buf) in &[("mixed", &traffic.mixed), ("control", &traffic.control),
+                             ("data", &traffic.data)] {
+        group.throughput(Throughput::Bytes(buf.len() as u64));
+        group.bench_function(name, |b| b.iter(|| parse_all(black_box(buf))));
+    }
+
+    group.finish();
+}
+
+fn bench_encode(c: &mut Criterion) {
+    let traffic = traffic();
+    let frames = Frames::new(&traffic.mixed).map(Result::unwrap).collect::<Vec<_>>();
+    let mut buf = vec![0; traffic.mixed.len()];
+
+    let mut group = c.benchmark_group("encode");
+    group.throughput(Throughput::Bytes(traffic.mixed.len() as u64));
+    group.bench_function("mixed", |b| b.iter(|| {
+        let mut offset = 0;
+        for frame in &frames {
+            offset += frame.encode(&mut buf[offset..]);
+        }
+        black_box(&buf);
+    }));
+    group.finish();
+}
+
+fn bench_header_parse(c: &mut Criterion) {
+    let buf = [
+        0x1, 0x2, 0x3, // length
+        0x4, // type/kind
+        0x1, // flags
+        0x6, 0x7, 0x8, 0x9 // reserved bit + stream identifier
+    ];
+
+    c.bench_function("header_parse", |b| {
+        b.iter(|| FrameHeader::parse(black_box(&buf)).unwrap())
+    });
+}
+
+criterion_group!(benches, bench_parse, bench_encode, bench_header_parse);
+criterion_main!(benches);
@@ -0,0 +1,133 @@
+use StreamIdentifier;
+use consts::MAX_STREAM_ID;
+
+/// Which end of the connection we are.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Role {
+    /// Clients initiate odd-numbered streams.
+    Client,
+
+    /// Servers initiate even-numbered streams.
+    Server
+}
+
+/// Errors produced by a `StreamIdAllocator`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum StreamIdError {
+    /// Every stream identifier for our role has been used, so a new
+    /// connection is required.
+    Exhausted,
+
+    /// The peer used stream 0, or an identifier with our parity.
+    WrongParity(StreamIdentifier),
+
+    /// The peer used an identifier no larger than one it used before.
+    NotIncreasing(StreamIdentifier)
+}
+
+/// Hands out identifiers for locally initiated streams and checks the
+/// identifiers of streams the peer initiates.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct StreamIdAllocator {
+    role: Role,
+    next_local: u32,
+    last_remote: u32
+}
+
+impl StreamIdAllocator {
+    pub fn new(role: Role) -> StreamIdAllocator {
+        StreamIdAllocator {
+            role: role,
+            next_local: match role { Role::Client => 1, Role::Server => 2 },
+            last_remote: 0
+        }
+    }
+
+    pub fn role(&self) -> Role {
+        self.role
+    }
+
+    /// Allocate the next identifier for a stream we initiate.
+    pub fn allocate(&mut self) -> Result<StreamIdentifier, StreamIdError> {
+        if self.next_local > MAX_STREAM_ID {
+            return Err(StreamIdError::Exhausted)
+        }
+
+        let id = self.next_local;
+        self.next_local += 2;
+        Ok(StreamIdentifier(id))
+    }
+
+    /// The last identifier we allocated, or 0 if none.
+    pub fn last_local(&self) -> StreamIdentifier {
+        StreamIdentifier(self.next_local.saturating_sub(2))
+    }
+
+    /// Check and record the identifier of a stream the peer initiated.
+    pub fn accept_remote(&mut self, id: StreamIdentifier) -> Result<(), StreamIdError> {
+        let remote_parity = match self.role { Role::Client => 0, Role::Server => 1 };
+
+        if id.0 == 0 || id.0 % 2 != remote_parity {
+            return Err(StreamIdError::WrongParity(id))
+        }
+
+        if id.0 <= self.last_remote {
+            return Err(StreamIdError::NotIncreasing(id))
+        }
+
+        self.last_remote = id.0;
+        Ok(())
+    }
+
+    /// The largest identifier the peer has used, or 0 if none.
+    pub fn last_remote(&self) -> StreamIdentifier {
+        StreamIdentifier(self.last_remote)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use StreamIdentifier;
+    use consts::MAX_STREAM_ID;
+    use super::{StreamIdAllocator, StreamIdError, Role};
+
+    #[test]
+    fn test_allocate_parity() {
+        let mut client = StreamIdAllocator::new(Role::Client);
+        assert_eq!(client.last_local(), StreamIdentifier(0));
+        assert_eq!(client.allocate(), Ok(StreamIdentifier(1)));
+        assert_eq!(client.allocate(), Ok(StreamIdentifier(3)));
+        assert_eq!(client.last_local(), StreamIdentifier(3));
+
+        let mut server = StreamIdAllocator::new(Role::Server);
+        assert_eq!(server.allocate(), Ok(StreamIdentifier(2)));
+        assert_eq!(server.allocate(), Ok(StreamIdentifier(4)));
+    }
+
+    #[test]
+    fn test_allocate_exhaustion() {
+        let mut client = StreamIdAllocator::new(Role::Client);
+        client.next_local = MAX_STREAM_ID;
+
+        assert_eq!(client.allocate(), Ok(StreamIdentifier(MAX_STREAM_ID)));
+        assert_eq!(client.allocate(), Err(StreamIdError::Exhausted));
+        assert_eq!(client.allocate(), Err(StreamIdError::Exhausted));
+    }
+
+    #[test]
+    fn test_accept_remote() {
+        let mut server = StreamIdAllocator::new(Role::Server);
+
+        assert_eq!(server.accept_remote(StreamIdentifier(3)), Ok(()));
+        assert_eq!(server.accept_remote(StreamIdentifier(7)), Ok(()));
+        assert_eq!(server.last_remote(), StreamIdentifier(7));
+
+        assert_eq!(server.accept_remote(StreamIdentifier(5)),
+                   Err(StreamIdError::NotIncreasing(StreamIdentifier(5))));
+        assert_eq!(server.accept_remote(StreamIdentifier(8)),
+                   Err(StreamIdError::WrongParity(StreamIdentifier(8))));
+        assert_eq!(server.accept_remote(StreamIdentifier(0)),
+                   Err(StreamIdError::WrongParity(StreamIdentifier(0))));
+    }
+}
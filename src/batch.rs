@@ -0,0 +1,192 @@
+use std::io::{self, Write};
+use std::ops::Range;
+
+use {Frame, ConsistencyError};
+
+/// Errors produced by `encode_all`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum EncodeError {
+    /// The buffer cannot hold every frame. Holds the number of bytes
+    /// needed.
+    BufferTooSmall(usize),
+
+    /// The frame at the held index does not agree with its header.
+    Inconsistent(usize, ConsistencyError)
+}
+
+/// Encode `frames` one after another into `buf`, returning where each one
+/// lies in it.
+///
+/// Every frame is checked with `Frame::validate`, and the whole batch must
+/// fit, before anything is written.
+pub fn encode_all(frames: &[Frame], buf: &mut [u8]) -> Result<Vec<Range<usize>>, EncodeError> {
+    let mut needed = 0;
+    for (index, frame) in frames.iter().enumerate() {
+        try!(frame.validate().map_err(|error| EncodeError::Inconsistent(index, error)));
+        needed += frame.encoded_len();
+    }
+
+    if buf.len() < needed {
+        return Err(EncodeError::BufferTooSmall(needed))
+    }
+
+    let mut position = 0;
+    Ok(frames.iter().map(|frame| {
+        let start = position;
+        position += frame.encode(&mut buf[start..]);
+        start..position
+    }).collect())
+}
+
+/// Collects encoded frames into one buffer and writes them with a single
+/// call per flush.
+///
+/// Meant for the small control frames a busy connection sends many of,
+/// such as WINDOW_UPDATE, RST_STREAM and SETTINGS acknowledgements.
+#[derive(Debug)]
+pub struct BatchWriter<W> {
+    inner: W,
+    buf: Vec<u8>,
+    frames: usize,
+    flush_at: Option<usize>
+}
+
+impl<W: Write> BatchWriter<W> {
+    pub fn new(inner: W) -> BatchWriter<W> {
+        BatchWriter {
+            inner: inner,
+            buf: Vec::new(),
+            frames: 0,
+            flush_at: None
+        }
+    }
+
+    /// Flush automatically once at least `size` bytes are buffered, or
+    /// only when asked with `None`.
+    pub fn set_flush_at(&mut self, size: Option<usize>) {
+        self.flush_at = size;
+    }
+
+    /// Encode `frame` onto the end of the buffer.
+    pub fn push(&mut self, frame: &Frame) -> io::Result<()> {
+        let start = self.buf.len();
+        self.buf.resize(start + frame.encoded_len(), 0);
+        frame.encode(&mut self.buf[start..]);
+        self.frames += 1;
+
+        match self.flush_at {
+            Some(size) if self.buf.len() >= size => self.flush(),
+            _ => Ok(())
+        }
+    }
+
+    /// The number of frames buffered.
+    pub fn len(&self) -> usize {
+        self.frames
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames == 0
+    }
+
+    /// The encoded frames waiting to be written.
+    pub fn buffered(&self) -> &[u8] {
+        &self.buf
+    }
+
+    /// Write every buffered frame in one call and flush the writer.
+    ///
+    /// On error the buffer is kept, so the flush can be retried.
+    pub fn flush(&mut self) -> io::Result<()> {
+        if !self.buf.is_empty() {
+            try!(self.inner.write_all(&self.buf));
+            self.buf.clear();
+            self.frames = 0;
+        }
+
+        self.inner.flush()
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// Give back the writer, discarding any buffered frames.
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::{self, Write};
+
+    use {Frame, Frames, StreamIdentifier, HttpError, ConsistencyError};
+    use super::{BatchWriter, EncodeError, encode_all};
+
+    struct Counting {
+        writes: usize,
+        data: Vec<u8>
+    }
+
+    impl Write for Counting {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.writes += 1;
+            self.data.write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> { Ok(()) }
+    }
+
+    #[test]
+    fn test_batch_writer() {
+        let mut writer = BatchWriter::new(Counting { writes: 0, data: Vec::new() });
+
+        for _ in 0..10 {
+            writer.push(&Frame::settings_ack()).unwrap();
+        }
+        writer.push(&Frame::goaway(StreamIdentifier(0), HttpError::NoError, None)).unwrap();
+        assert_eq!(writer.len(), 11);
+        assert_eq!(writer.get_ref().writes, 0);
+
+        writer.flush().unwrap();
+        assert!(writer.is_empty());
+
+        let out = writer.into_inner();
+        assert_eq!(out.writes, 1);
+        assert_eq!(Frames::new(&out.data).count(), 11);
+    }
+
+    #[test]
+    fn test_encode_all() {
+        let frames = [Frame::settings_ack(), Frame::ping([7; 8]),
+                      Frame::goaway(StreamIdentifier(1), HttpError::Cancel, Some("x"))];
+        let mut buf = [0; 64];
+
+        let ranges = encode_all(&frames, &mut buf).unwrap();
+        assert_eq!(ranges, [0..9, 9..26, 26..44]);
+        for (frame, range) in frames.iter().zip(ranges) {
+            assert_eq!(&buf[range], &Vec::from(frame)[..]);
+        }
+
+        assert_eq!(encode_all(&frames, &mut [0; 43]), Err(EncodeError::BufferTooSmall(44)));
+
+        let mut bad = Frame::ping([0; 8]);
+        bad.header.length = 7;
+        assert_eq!(encode_all(&[frames[0], bad], &mut buf),
+                   Err(EncodeError::Inconsistent(1, ConsistencyError::LengthMismatch(8))));
+    }
+
+    #[test]
+    fn test_batch_writer_flush_at() {
+        let mut writer = BatchWriter::new(Vec::new());
+        writer.set_flush_at(Some(18));
+
+        writer.push(&Frame::settings_ack()).unwrap();
+        assert_eq!(writer.buffered().len(), 9);
+        writer.push(&Frame::settings_ack()).unwrap();
+        assert!(writer.buffered().is_empty());
+        assert_eq!(writer.get_ref().len(), 18);
+    }
+}
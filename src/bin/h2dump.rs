@@ -0,0 +1,143 @@
+//! Print every frame in a stream of raw HTTP/2 frames or a capture.
+//!
+//! ```text
+//! h2dump [--hpack] [FILE]
+//! ```
+//!
+//! Reads from stdin when no file is given. Raw input may start with the
+//! client connection preface. With `--hpack`, header blocks are decoded
+//! and printed below the frames which complete them.
+
+extern crate http2parse;
+
+use std::collections::HashMap;
+use std::env;
+use std::fs::File;
+use std::io::{self, Read, Write, Cursor};
+use std::process;
+
+use http2parse::{Frame, Frames, Kind, HeaderBlockCollector, PREFACE};
+use http2parse::capture::{self, Direction};
+use http2parse::hpack::Decoder;
+
+struct Dump {
+    hpack: bool,
+    contexts: HashMap<(u64, Direction), (HeaderBlockCollector, Decoder)>
+}
+
+impl Dump {
+    fn print<W: Write>(&mut self, out: &mut W, context: (u64, Direction),
+                       frame: &Frame) -> io::Result<()> {
+        try!(writeln!(out, "{}", frame));
+
+        match frame.header.kind {
+            Kind::Headers | Kind::PushPromise | Kind::Continuation if self.hpack => {},
+            _ => return Ok(())
+        }
+
+        let &mut (ref mut collector, ref mut decoder) = self.contexts.entry(context)
+            .or_insert_with(|| (HeaderBlockCollector::new(), Decoder::new()));
+
+        let block = match collector.push(frame) {
+            Ok(Some(block)) => block,
+            Ok(None) => return Ok(()),
+            Err(error) => return writeln!(out, "    invalid header block: {:?}", error)
+        };
+
+        match decoder.decode(&block.block) {
+            Ok(fields) => {
+                for (name, value) in fields {
+                    try!(writeln!(out, "    {}: {}", String::from_utf8_lossy(&name),
+                                  String::from_utf8_lossy(&value)));
+                }
+                Ok(())
+            },
+            Err(error) => writeln!(out, "    undecodable header block: {:?}", error)
+        }
+    }
+
+    fn raw<W: Write>(&mut self, out: &mut W, input: &[u8]) -> Result<(), String> {
+        let mut frames = if input.starts_with(PREFACE) {
+            try!(Frames::after_preface(input).map_err(|e| format!("{:?}", e)))
+        } else {
+            Frames::new(input)
+        };
+
+        for frame in &mut frames {
+            let frame = try!(frame.map_err(|e| e.to_string()));
+            try!(self.print(out, (0, Direction::Inbound), &frame).map_err(|e| e.to_string()));
+        }
+
+        if frames.offset() < input.len() {
+            return Err(format!("{} trailing bytes after the last complete frame",
+                               input.len() - frames.offset()))
+        }
+
+        Ok(())
+    }
+
+    fn capture<W: Write>(&mut self, out: &mut W, input: &[u8]) -> Result<(), String> {
+        let reader = try!(capture::Reader::new(Cursor::new(input)).map_err(|e| e.to_string()));
+
+        for record in reader {
+            let record = try!(record.map_err(|e| e.to_string()));
+            let frame = try!(record.frame().map_err(|e| format!("{:?}", e)));
+            let arrow = match record.direction {
+                Direction::Inbound => "<-",
+                Direction::Outbound => "->"
+            };
+
+            try!(write!(out, "{}.{:09} #{} {} ", record.timestamp.as_secs(),
+                        record.timestamp.subsec_nanos(), record.connection, arrow)
+                 .map_err(|e| e.to_string()));
+            try!(self.print(out, (record.connection, record.direction), &frame)
+                 .map_err(|e| e.to_string()));
+        }
+
+        Ok(())
+    }
+}
+
+fn main() {
+    let mut dump = Dump { hpack: false, contexts: HashMap::new() };
+    let mut path = None;
+
+    for arg in env::args().skip(1) {
+        match &*arg {
+            "--hpack" => dump.hpack = true,
+            "-h" | "--help" => {
+                println!("usage: h2dump [--hpack] [FILE]");
+                return
+            },
+            _ if path.is_none() => path = Some(arg),
+            _ => {
+                let _ = writeln!(io::stderr(), "usage: h2dump [--hpack] [FILE]");
+                process::exit(2)
+            }
+        }
+    }
+
+    let mut input = Vec::new();
+    let read = match path {
+        Some(ref path) => File::open(path).and_then(|mut file| file.read_to_end(&mut input)),
+        None => io::stdin().read_to_end(&mut input)
+    };
+
+    if let Err(error) = read {
+        let _ = writeln!(io::stderr(), "h2dump: {}", error);
+        process::exit(1)
+    }
+
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    let result = if input.starts_with(b"h2cap") {
+        dump.capture(&mut out, &input)
+    } else {
+        dump.raw(&mut out, &input)
+    };
+
+    if let Err(error) = result {
+        let _ = writeln!(io::stderr(), "h2dump: {}", error);
+        process::exit(1)
+    }
+}
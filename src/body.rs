@@ -0,0 +1,162 @@
+use std::cmp;
+use std::io::{self, Read};
+
+use {Flag, StreamIdentifier, SizeIncrement, WindowSize, HttpError,
+     FRAME_HEADER_BYTES};
+use consts::DEFAULT_MAX_FRAME_SIZE;
+
+/// Frames the contents of a `Read` as DATA frames on one stream, reading
+/// no more than one frame's worth at a time.
+///
+/// The last frame carries END_STREAM. If the source ends exactly at a
+/// frame boundary, an empty DATA frame with END_STREAM follows.
+#[derive(Debug)]
+pub struct DataFrames<R> {
+    source: R,
+    id: StreamIdentifier,
+    max_frame_size: u32,
+    window: Option<WindowSize>,
+    finished: bool
+}
+
+impl<R: Read> DataFrames<R> {
+    /// Frame `source` on stream `id`, with the default
+    /// SETTINGS_MAX_FRAME_SIZE and no flow-control limit.
+    pub fn new(source: R, id: StreamIdentifier) -> DataFrames<R> {
+        DataFrames {
+            source: source,
+            id: id,
+            max_frame_size: DEFAULT_MAX_FRAME_SIZE,
+            window: None,
+            finished: false
+        }
+    }
+
+    /// Limit payloads to the peer's SETTINGS_MAX_FRAME_SIZE.
+    pub fn set_max_frame_size(&mut self, size: u32) {
+        self.max_frame_size = size;
+    }
+
+    /// Limit the data sent to a flow-control window, or lift the limit
+    /// with `None`.
+    ///
+    /// The window should be the smaller of the stream and connection
+    /// windows. It shrinks as frames are produced.
+    pub fn set_window(&mut self, window: Option<WindowSize>) {
+        self.window = window;
+    }
+
+    /// The remaining flow-control window, if one is set.
+    pub fn window(&self) -> Option<WindowSize> {
+        self.window
+    }
+
+    /// Grow the window by a WINDOW_UPDATE increment. Does nothing if no
+    /// window is set.
+    pub fn window_update(&mut self, increment: SizeIncrement) -> Result<(), HttpError> {
+        if let Some(window) = self.window {
+            self.window = Some(try!(window.checked_add(increment)));
+        }
+
+        Ok(())
+    }
+
+    /// Whether the frame carrying END_STREAM has been produced.
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Encode the next DATA frame into `buf`, replacing its contents.
+    ///
+    /// Returns `false`, leaving `buf` empty, once the stream is finished
+    /// or while the window is exhausted. Nothing is read from the source
+    /// while the window is exhausted, even to find out if it has ended.
+    pub fn next_frame(&mut self, buf: &mut Vec<u8>) -> io::Result<bool> {
+        buf.clear();
+
+        let available = self.window.map(|window| window.available()).unwrap_or(!0);
+        let limit = cmp::min(self.max_frame_size, available) as usize;
+        if self.finished || limit == 0 { return Ok(false) }
+
+        buf.resize(FRAME_HEADER_BYTES + limit, 0);
+        let mut len = 0;
+        while len < limit {
+            match self.source.read(&mut buf[FRAME_HEADER_BYTES + len..]) {
+                Ok(0) => break,
+                Ok(read) => len += read,
+                Err(ref error) if error.kind() == io::ErrorKind::Interrupted => {},
+                Err(error) => {
+                    buf.clear();
+                    return Err(error)
+                }
+            }
+        }
+        buf.truncate(FRAME_HEADER_BYTES + len);
+
+        self.finished = len < limit;
+        if let Some(window) = self.window {
+            self.window = Some(window.checked_sub(len as u32).unwrap());
+        }
+
+        let flag = if self.finished { Flag::end_stream() } else { Flag::empty() };
+        ::encode_data_header(len as u32, self.id, flag, buf);
+
+        Ok(true)
+    }
+
+    /// Give back the source.
+    pub fn into_inner(self) -> R {
+        self.source
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Cursor;
+
+    use {Frames, Frame, Payload, Flag, StreamIdentifier, SizeIncrement, WindowSize};
+    use super::DataFrames;
+
+    fn frames<R: ::std::io::Read>(data: &mut DataFrames<R>) -> Vec<(usize, bool)> {
+        let mut buf = Vec::new();
+        let mut frames = Vec::new();
+
+        while data.next_frame(&mut buf).unwrap() {
+            let frame = Frames::new(&buf).next().unwrap().unwrap();
+            match frame {
+                Frame { payload: Payload::Data { data }, header } => {
+                    assert_eq!(header.id, StreamIdentifier(1));
+                    frames.push((data.len(), header.flag.contains(Flag::end_stream())));
+                },
+                _ => panic!("not a DATA frame: {}", frame)
+            }
+        }
+
+        frames
+    }
+
+    #[test]
+    fn test_data_frames() {
+        let mut data = DataFrames::new(Cursor::new(vec![7; 40000]), StreamIdentifier(1));
+        assert_eq!(frames(&mut data), vec![(16384, false), (16384, false), (7232, true)]);
+        assert!(data.is_finished());
+
+        let mut data = DataFrames::new(Cursor::new(vec![7; 20]), StreamIdentifier(1));
+        data.set_max_frame_size(10);
+        assert_eq!(frames(&mut data), vec![(10, false), (10, false), (0, true)]);
+    }
+
+    #[test]
+    fn test_data_frames_window() {
+        let mut data = DataFrames::new(Cursor::new(vec![7; 100]), StreamIdentifier(1));
+        data.set_window(Some(WindowSize::new(60).unwrap()));
+
+        assert_eq!(frames(&mut data), vec![(60, false)]);
+        assert!(!data.is_finished());
+        assert_eq!(data.window(), Some(WindowSize::new(0).unwrap()));
+
+        data.window_update(SizeIncrement(1000)).unwrap();
+        assert_eq!(frames(&mut data), vec![(40, true)]);
+        assert_eq!(data.window(), Some(WindowSize::new(960).unwrap()));
+    }
+}
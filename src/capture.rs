@@ -0,0 +1,216 @@
+//! A compact binary format for recording frames and replaying them later.
+//!
+//! A capture starts with the magic bytes `h2cap` and a version byte,
+//! followed by one record per frame:
+//!
+//! ```text
+//! direction (1) | connection id (8) | timestamp in ns (8) | frame (9 + length)
+//! ```
+//!
+//! Integers are big-endian, and frames are stored exactly as on the wire,
+//! so they can be fed back through `FrameHeader::parse` and `Frame::parse`.
+
+use std::io::{self, Read, Write};
+use std::time::Duration;
+
+use byteorder::{BigEndian, ByteOrder};
+
+use {Frame, FrameHeader, Error, FRAME_HEADER_BYTES};
+
+const MAGIC: &'static [u8; 5] = b"h2cap";
+const VERSION: u8 = 1;
+
+// Direction, connection id and timestamp.
+const RECORD_PREFIX_BYTES: usize = 17;
+
+/// Which way a frame was travelling.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Direction {
+    /// Received from the peer.
+    Inbound,
+
+    /// Sent to the peer.
+    Outbound
+}
+
+/// A frame read back from a capture.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Record {
+    pub direction: Direction,
+
+    /// Distinguishes the connections recorded in one capture.
+    pub connection: u64,
+
+    /// When the frame was seen, relative to a point of the recorder's
+    /// choosing such as the start of the capture.
+    pub timestamp: Duration,
+
+    /// The frame as sent on the wire, header included.
+    pub data: Vec<u8>
+}
+
+impl Record {
+    /// Parse the recorded frame.
+    pub fn frame(&self) -> Result<Frame, Error> {
+        let header = try!(FrameHeader::parse(&self.data));
+        Frame::parse(header, &self.data[FRAME_HEADER_BYTES..])
+    }
+}
+
+/// Writes frames to a capture.
+#[derive(Debug)]
+pub struct Writer<W> {
+    writer: W,
+    buf: Vec<u8>
+}
+
+impl<W: Write> Writer<W> {
+    /// Start a capture, writing its magic bytes.
+    pub fn new(mut writer: W) -> io::Result<Writer<W>> {
+        try!(writer.write_all(MAGIC));
+        try!(writer.write_all(&[VERSION]));
+
+        Ok(Writer { writer: writer, buf: Vec::new() })
+    }
+
+    /// Record a frame.
+    pub fn write(&mut self, direction: Direction, connection: u64,
+                 timestamp: Duration, frame: &Frame) -> io::Result<()> {
+        self.buf.clear();
+        self.buf.resize(frame.encoded_len(), 0);
+        frame.encode(&mut self.buf);
+
+        let buf = ::std::mem::replace(&mut self.buf, Vec::new());
+        let result = self.write_raw(direction, connection, timestamp, &buf);
+        self.buf = buf;
+        result
+    }
+
+    /// Record a frame exactly as it was read from the wire, header
+    /// included.
+    pub fn write_raw(&mut self, direction: Direction, connection: u64,
+                     timestamp: Duration, frame: &[u8]) -> io::Result<()> {
+        let mut prefix = [0; RECORD_PREFIX_BYTES];
+        prefix[0] = match direction { Direction::Inbound => 0, Direction::Outbound => 1 };
+        BigEndian::write_u64(&mut prefix[1..], connection);
+        BigEndian::write_u64(&mut prefix[9..],
+                             timestamp.as_secs() * 1_000_000_000 + timestamp.subsec_nanos() as u64);
+
+        try!(self.writer.write_all(&prefix));
+        self.writer.write_all(frame)
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+/// Reads the records of a capture in order.
+#[derive(Debug)]
+pub struct Reader<R> {
+    reader: R
+}
+
+impl<R: Read> Reader<R> {
+    /// Open a capture, checking its magic bytes.
+    pub fn new(mut reader: R) -> io::Result<Reader<R>> {
+        let mut magic = [0; 6];
+        try!(reader.read_exact(&mut magic));
+
+        if &magic[..5] != MAGIC || magic[5] != VERSION {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "not an h2cap capture"))
+        }
+
+        Ok(Reader { reader: reader })
+    }
+
+    fn read_record(&mut self) -> io::Result<Option<Record>> {
+        let mut prefix = [0; RECORD_PREFIX_BYTES];
+
+        // Only a clean end between records is the end of the capture.
+        match try!(self.reader.read(&mut prefix[..1])) {
+            0 => return Ok(None),
+            _ => try!(self.reader.read_exact(&mut prefix[1..]))
+        }
+
+        let direction = match prefix[0] {
+            0 => Direction::Inbound,
+            1 => Direction::Outbound,
+            _ => return Err(io::Error::new(io::ErrorKind::InvalidData, "bad direction"))
+        };
+
+        let nanos = BigEndian::read_u64(&prefix[9..]);
+
+        let mut data = vec![0; FRAME_HEADER_BYTES];
+        try!(self.reader.read_exact(&mut data));
+        let length = BigEndian::read_uint(&data, 3) as usize;
+        data.resize(FRAME_HEADER_BYTES + length, 0);
+        try!(self.reader.read_exact(&mut data[FRAME_HEADER_BYTES..]));
+
+        Ok(Some(Record {
+            direction: direction,
+            connection: BigEndian::read_u64(&prefix[1..]),
+            timestamp: Duration::new(nanos / 1_000_000_000, (nanos % 1_000_000_000) as u32),
+            data: data
+        }))
+    }
+}
+
+impl<R: Read> Iterator for Reader<R> {
+    type Item = io::Result<Record>;
+
+    fn next(&mut self) -> Option<io::Result<Record>> {
+        match self.read_record() {
+            Ok(Some(record)) => Some(Ok(record)),
+            Ok(None) => None,
+            Err(error) => Some(Err(error))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::{self, Cursor};
+    use std::time::Duration;
+
+    use {Frame, StreamIdentifier, HttpError};
+    use super::{Writer, Reader, Direction};
+
+    #[test]
+    fn test_capture_round_trip() {
+        let frames = [Frame::settings_ack(),
+                      Frame::goaway(StreamIdentifier(3), HttpError::Protocol, Some("oops"))];
+
+        let mut writer = Writer::new(Vec::new()).unwrap();
+        writer.write(Direction::Inbound, 1, Duration::new(0, 500), &frames[0]).unwrap();
+        writer.write(Direction::Outbound, 2, Duration::new(3, 7), &frames[1]).unwrap();
+
+        let capture = writer.into_inner();
+        let records = Reader::new(Cursor::new(capture)).unwrap()
+            .collect::<io::Result<Vec<_>>>().unwrap();
+
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].direction, Direction::Inbound);
+        assert_eq!(records[0].connection, 1);
+        assert_eq!(records[0].timestamp, Duration::new(0, 500));
+        assert_eq!(records[0].frame(), Ok(frames[0]));
+        assert_eq!(records[1].direction, Direction::Outbound);
+        assert_eq!(records[1].timestamp, Duration::new(3, 7));
+        assert_eq!(records[1].frame(), Ok(frames[1]));
+    }
+
+    #[test]
+    fn test_bad_captures() {
+        assert_eq!(Reader::new(Cursor::new(b"pcap\x00\x01")).err().unwrap().kind(),
+                   io::ErrorKind::InvalidData);
+
+        let mut writer = Writer::new(Vec::new()).unwrap();
+        writer.write(Direction::Inbound, 1, Duration::new(0, 0), &Frame::ping([0; 8])).unwrap();
+        let mut capture = writer.into_inner();
+        capture.pop();
+
+        let mut reader = Reader::new(Cursor::new(capture)).unwrap();
+        assert_eq!(reader.next().unwrap().unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+    }
+}
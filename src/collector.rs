@@ -0,0 +1,316 @@
+use {Frame, Payload, Priority, Kind, Flag, StreamIdentifier};
+
+/// Errors that can occur while collecting a header block.
+///
+/// All of these are connection errors, of type PROTOCOL_ERROR unless
+/// noted otherwise.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum CollectorError {
+    /// A CONTINUATION frame arrived while no header block was open.
+    UnexpectedContinuation,
+
+    /// A frame other than CONTINUATION arrived while a header block was open.
+    ExpectedContinuation(Kind),
+
+    /// A CONTINUATION frame arrived on a different stream than the
+    /// header block it should continue.
+    StreamMismatch(StreamIdentifier),
+
+    /// A frame which does not start a header block arrived while no
+    /// header block was open.
+    NotHeaderBlock(Kind),
+
+    /// The header block grew beyond the configured maximum size.
+    ///
+    /// This may instead be answered with ENHANCE_YOUR_CALM, or with a
+    /// 431 response once the block has been read.
+    BlockTooLarge,
+
+    /// More CONTINUATION frames arrived for one header block than the
+    /// configured maximum, which should be answered with ENHANCE_YOUR_CALM.
+    TooManyContinuations
+}
+
+/// A header block reassembled from a HEADERS or PUSH_PROMISE frame and
+/// the CONTINUATION frames following it.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct HeaderBlock {
+    /// The stream the header block was sent on.
+    pub id: StreamIdentifier,
+
+    /// Either `Kind::Headers` or `Kind::PushPromise`.
+    pub kind: Kind,
+
+    /// The priority carried by the HEADERS frame, if any.
+    pub priority: Option<Priority>,
+
+    /// The stream reserved by a PUSH_PROMISE frame.
+    pub promised: Option<StreamIdentifier>,
+
+    /// Whether the HEADERS frame also ended the stream.
+    pub end_stream: bool,
+
+    /// The complete header block fragment, ready for HPACK decoding.
+    pub block: Vec<u8>
+}
+
+/// Reassembles header blocks split across CONTINUATION frames.
+///
+/// Frames are fed in the order they are received and a `HeaderBlock` is
+/// produced once a frame with END_HEADERS arrives.
+///
+/// By default blocks may grow without bound. Use `set_max_block_size` and
+/// `set_max_continuations` to guard against CONTINUATION floods.
+#[derive(Clone, Debug, Default)]
+pub struct HeaderBlockCollector {
+    state: CollectorState<HeaderBlock>
+}
+
+impl HeaderBlockCollector {
+    pub fn new() -> HeaderBlockCollector {
+        HeaderBlockCollector { state: CollectorState::new() }
+    }
+
+    /// Limit the total size of a header block's fragments.
+    pub fn set_max_block_size(&mut self, max: Option<usize>) {
+        self.state.max_block_size = max;
+    }
+
+    /// Limit the number of CONTINUATION frames following a HEADERS or
+    /// PUSH_PROMISE frame.
+    pub fn set_max_continuations(&mut self, max: Option<usize>) {
+        self.state.max_continuations = max;
+    }
+
+    /// Is a header block waiting for CONTINUATION frames?
+    pub fn is_collecting(&self) -> bool {
+        self.state.pending.is_some()
+    }
+
+    /// The stream of the header block being collected, if any.
+    pub fn stream(&self) -> Option<StreamIdentifier> {
+        self.state.pending.as_ref().map(|pending| pending.id)
+    }
+
+    /// Feed the next HEADERS, PUSH_PROMISE or CONTINUATION frame.
+    ///
+    /// Returns the complete header block once END_HEADERS is seen, or
+    /// `None` if more CONTINUATION frames are needed.
+    pub fn push(&mut self, frame: &Frame) -> Result<Option<HeaderBlock>, CollectorError> {
+        self.state.push(frame)
+    }
+}
+
+/// A header block being collected, however its fragment is stored.
+pub trait PendingBlock: Sized {
+    /// The block begun by a HEADERS or PUSH_PROMISE frame, with an empty
+    /// fragment.
+    fn start(id: StreamIdentifier, kind: Kind, priority: Option<Priority>,
+             promised: Option<StreamIdentifier>, end_stream: bool) -> Self;
+
+    fn id(&self) -> StreamIdentifier;
+
+    /// The length of the fragment so far.
+    fn len(&self) -> usize;
+
+    /// Append to the fragment, failing with `BlockTooLarge` if there is no
+    /// room.
+    fn append(&mut self, fragment: &[u8]) -> Result<(), CollectorError>;
+}
+
+impl PendingBlock for HeaderBlock {
+    fn start(id: StreamIdentifier, kind: Kind, priority: Option<Priority>,
+             promised: Option<StreamIdentifier>, end_stream: bool) -> HeaderBlock {
+        HeaderBlock {
+            id: id,
+            kind: kind,
+            priority: priority,
+            promised: promised,
+            end_stream: end_stream,
+            block: Vec::new()
+        }
+    }
+
+    fn id(&self) -> StreamIdentifier { self.id }
+
+    fn len(&self) -> usize { self.block.len() }
+
+    fn append(&mut self, fragment: &[u8]) -> Result<(), CollectorError> {
+        self.block.extend_from_slice(fragment);
+        Ok(())
+    }
+}
+
+/// The state machine of a header block collector, shared by
+/// `HeaderBlockCollector` and `FixedHeaderBlockCollector`.
+#[derive(Clone, Debug)]
+pub struct CollectorState<B> {
+    pub pending: Option<B>,
+    pub continuations: usize,
+    pub max_block_size: Option<usize>,
+    pub max_continuations: Option<usize>
+}
+
+impl<B: PendingBlock> CollectorState<B> {
+    pub fn new() -> CollectorState<B> {
+        CollectorState {
+            pending: None,
+            continuations: 0,
+            max_block_size: None,
+            max_continuations: None
+        }
+    }
+
+    pub fn push(&mut self, frame: &Frame) -> Result<Option<B>, CollectorError> {
+        let header = frame.header;
+
+        let (mut block, fragment) = match (self.pending.take(), frame.payload) {
+            (None, Payload::Headers { priority, block }) => {
+                let end_stream = header.flag.contains(Flag::end_stream());
+                (B::start(header.id, Kind::Headers, priority, None, end_stream), block)
+            },
+            (None, Payload::PushPromise { promised, block }) =>
+                (B::start(header.id, Kind::PushPromise, None, Some(promised), false), block),
+            (None, Payload::Continuation(_)) => return Err(CollectorError::UnexpectedContinuation),
+            (None, _) => return Err(CollectorError::NotHeaderBlock(header.kind)),
+            (Some(pending), Payload::Continuation(fragment)) => {
+                if pending.id() != header.id {
+                    return Err(CollectorError::StreamMismatch(header.id))
+                }
+
+                self.continuations += 1;
+                if self.max_continuations.map_or(false, |max| self.continuations > max) {
+                    return Err(CollectorError::TooManyContinuations)
+                }
+
+                (pending, fragment)
+            },
+            (Some(_), _) => return Err(CollectorError::ExpectedContinuation(header.kind))
+        };
+
+        if header.kind != Kind::Continuation {
+            self.continuations = 0;
+        }
+        if self.max_block_size.map_or(false, |max| block.len() + fragment.len() > max) {
+            return Err(CollectorError::BlockTooLarge)
+        }
+        try!(block.append(fragment));
+
+        if header.flag.contains(Flag::end_headers()) {
+            Ok(Some(block))
+        } else {
+            self.pending = Some(block);
+            Ok(None)
+        }
+    }
+}
+
+impl<B: PendingBlock> Default for CollectorState<B> {
+    fn default() -> CollectorState<B> {
+        CollectorState::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use {Frame, FrameHeader, Payload, Kind, Flag, StreamIdentifier};
+    use super::{HeaderBlockCollector, CollectorError};
+
+    fn frame<'a>(kind: Kind, flag: Flag, id: u32, payload: Payload<'a>) -> Frame<'a> {
+        Frame {
+            header: FrameHeader {
+                length: payload.encoded_len() as u32,
+                kind: kind,
+                flag: flag,
+                id: StreamIdentifier(id)
+            },
+            payload: payload
+        }
+    }
+
+    #[test]
+    fn test_collect_headers_with_continuations() {
+        let mut collector = HeaderBlockCollector::new();
+
+        let headers = frame(Kind::Headers, Flag::end_stream(), 3,
+                            Payload::Headers { priority: None, block: &[1, 2] });
+        assert_eq!(collector.push(&headers), Ok(None));
+        assert_eq!(collector.stream(), Some(StreamIdentifier(3)));
+
+        let continuation = frame(Kind::Continuation, Flag::empty(), 3, Payload::Continuation(&[3]));
+        assert_eq!(collector.push(&continuation), Ok(None));
+
+        let last = frame(Kind::Continuation, Flag::end_headers(), 3, Payload::Continuation(&[4]));
+        let block = collector.push(&last).unwrap().unwrap();
+
+        assert_eq!(block.id, StreamIdentifier(3));
+        assert_eq!(block.kind, Kind::Headers);
+        assert!(block.end_stream);
+        assert_eq!(block.block, vec![1, 2, 3, 4]);
+        assert!(!collector.is_collecting());
+    }
+
+    #[test]
+    fn test_collect_single_push_promise() {
+        let mut collector = HeaderBlockCollector::new();
+        let promise = frame(Kind::PushPromise, Flag::end_headers(), 1,
+                            Payload::PushPromise { promised: StreamIdentifier(2), block: &[5] });
+
+        let block = collector.push(&promise).unwrap().unwrap();
+        assert_eq!(block.kind, Kind::PushPromise);
+        assert_eq!(block.promised, Some(StreamIdentifier(2)));
+        assert_eq!(block.block, vec![5]);
+    }
+
+    #[test]
+    fn test_collector_errors() {
+        let mut collector = HeaderBlockCollector::new();
+
+        let continuation = frame(Kind::Continuation, Flag::end_headers(), 1, Payload::Continuation(&[]));
+        assert_eq!(collector.push(&continuation), Err(CollectorError::UnexpectedContinuation));
+
+        let ping = frame(Kind::Ping, Flag::empty(), 0, Payload::Ping(0));
+        assert_eq!(collector.push(&ping), Err(CollectorError::NotHeaderBlock(Kind::Ping)));
+
+        let headers = frame(Kind::Headers, Flag::empty(), 1,
+                            Payload::Headers { priority: None, block: &[] });
+        assert_eq!(collector.push(&headers), Ok(None));
+        assert_eq!(collector.push(&ping), Err(CollectorError::ExpectedContinuation(Kind::Ping)));
+
+        assert_eq!(collector.push(&headers), Ok(None));
+        let other = frame(Kind::Continuation, Flag::end_headers(), 5, Payload::Continuation(&[]));
+        assert_eq!(collector.push(&other), Err(CollectorError::StreamMismatch(StreamIdentifier(5))));
+    }
+
+    #[test]
+    fn test_collector_limits() {
+        let mut collector = HeaderBlockCollector::new();
+        collector.set_max_block_size(Some(4));
+        collector.set_max_continuations(Some(2));
+
+        let big = frame(Kind::Headers, Flag::end_headers(), 1,
+                        Payload::Headers { priority: None, block: &[0; 5] });
+        assert_eq!(collector.push(&big), Err(CollectorError::BlockTooLarge));
+
+        let headers = frame(Kind::Headers, Flag::empty(), 1,
+                            Payload::Headers { priority: None, block: &[0; 2] });
+        let continuation = frame(Kind::Continuation, Flag::empty(), 1, Payload::Continuation(&[0]));
+        assert_eq!(collector.push(&headers), Ok(None));
+        assert_eq!(collector.push(&continuation), Ok(None));
+        assert_eq!(collector.push(&continuation), Ok(None));
+        assert_eq!(collector.push(&continuation), Err(CollectorError::TooManyContinuations));
+
+        let empty = frame(Kind::Continuation, Flag::empty(), 1, Payload::Continuation(&[]));
+        assert_eq!(collector.push(&headers), Ok(None));
+        assert_eq!(collector.push(&empty), Ok(None));
+        assert_eq!(collector.push(&empty), Ok(None));
+        assert_eq!(collector.push(&empty), Err(CollectorError::TooManyContinuations));
+
+        collector.set_max_continuations(None);
+        assert_eq!(collector.push(&headers), Ok(None));
+        assert_eq!(collector.push(&continuation), Ok(None));
+        assert_eq!(collector.push(&continuation), Ok(None));
+        assert_eq!(collector.push(&continuation), Err(CollectorError::BlockTooLarge));
+    }
+}
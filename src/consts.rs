@@ -0,0 +1,26 @@
+//! The defaults and limits set by RFC 7540 and RFC 7541.
+
+/// The initial value of SETTINGS_INITIAL_WINDOW_SIZE, and the initial size
+/// of the connection's flow-control window.
+pub const DEFAULT_INITIAL_WINDOW_SIZE: u32 = 65_535;
+
+/// The initial value of SETTINGS_MAX_FRAME_SIZE, which is also the
+/// smallest value it may be given.
+pub const DEFAULT_MAX_FRAME_SIZE: u32 = 16_384;
+
+/// The weight of streams given no priority, from 1 to 256. Weights are
+/// sent on the wire as one less than this.
+pub const DEFAULT_WEIGHT: u32 = 16;
+
+/// The largest stream identifier, since identifiers are 31 bits.
+pub const MAX_STREAM_ID: u32 = (1 << 31) - 1;
+
+/// The largest any flow-control window may grow, 2^31 - 1.
+pub const MAX_WINDOW_SIZE: u32 = (1 << 31) - 1;
+
+/// The largest value SETTINGS_MAX_FRAME_SIZE may be given, 2^24 - 1.
+pub const MAX_MAX_FRAME_SIZE: u32 = (1 << 24) - 1;
+
+/// The initial value of SETTINGS_HEADER_TABLE_SIZE, and the initial
+/// maximum size of the HPACK dynamic table.
+pub const DEFAULT_HEADER_TABLE_SIZE: u32 = 4_096;
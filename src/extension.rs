@@ -0,0 +1,176 @@
+use {FrameHeader, Payload, Kind, Error, FRAME_HEADER_BYTES};
+
+/// A frame type defined by an extension to HTTP/2.
+pub trait ExtensionFrame<'a>: Sized {
+    /// The frame type byte identifying this frame.
+    const KIND: u8;
+
+    /// Parse the payload of a frame of this type.
+    fn parse(header: FrameHeader, buf: &'a [u8]) -> Result<Self, Error>;
+
+    /// Encode the payload of this frame into a buffer.
+    fn encode(&self, buf: &mut [u8]) -> usize;
+
+    /// How many bytes the payload of this frame will use when encoding.
+    fn encoded_len(&self) -> usize;
+}
+
+/// A set of extension frames, dispatched to by frame type.
+///
+/// Every `ExtensionFrame` is a registry containing only itself. To
+/// handle several extensions, implement this for an enum wrapping them.
+pub trait ExtensionRegistry<'a>: Sized {
+    /// Parse the payload of a frame, or return `None` if no frame in the
+    /// registry has the type of the frame.
+    fn parse(header: FrameHeader, buf: &'a [u8]) -> Option<Result<Self, Error>>;
+
+    /// The frame type byte of this frame.
+    fn kind(&self) -> u8;
+
+    fn encode(&self, buf: &mut [u8]) -> usize;
+
+    fn encoded_len(&self) -> usize;
+}
+
+impl<'a, E: ExtensionFrame<'a>> ExtensionRegistry<'a> for E {
+    fn parse(header: FrameHeader, buf: &'a [u8]) -> Option<Result<E, Error>> {
+        if header.kind == Kind::Unregistered(E::KIND) {
+            Some(E::parse(header, buf))
+        } else {
+            None
+        }
+    }
+
+    fn kind(&self) -> u8 { E::KIND }
+
+    fn encode(&self, buf: &mut [u8]) -> usize { ExtensionFrame::encode(self, buf) }
+
+    fn encoded_len(&self) -> usize { ExtensionFrame::encoded_len(self) }
+}
+
+/// Either a standard payload or an extension frame from the registry `E`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ExtendedPayload<'a, E> {
+    Standard(Payload<'a>),
+    Extension(E)
+}
+
+impl<'a, E: ExtensionRegistry<'a>> ExtendedPayload<'a, E> {
+    /// Parse a payload, dispatching unregistered frame types to `E`.
+    ///
+    /// Frame types known to `E` are parsed by it, all others are parsed
+    /// as usual, so types `E` doesn't know are `Payload::Unregistered`.
+    pub fn parse(header: FrameHeader, buf: &'a [u8]) -> Result<ExtendedPayload<'a, E>, Error> {
+        match E::parse(header, buf) {
+            Some(extension) => extension.map(ExtendedPayload::Extension),
+            None => Payload::parse(header, buf).map(ExtendedPayload::Standard)
+        }
+    }
+
+    /// The frame type of this payload.
+    pub fn kind(&self) -> Kind {
+        match *self {
+            ExtendedPayload::Standard(ref payload) => payload.kind(),
+            ExtendedPayload::Extension(ref extension) => Kind::Unregistered(extension.kind())
+        }
+    }
+
+    pub fn encode(&self, buf: &mut [u8]) -> usize {
+        match *self {
+            ExtendedPayload::Standard(ref payload) => payload.encode(buf),
+            ExtendedPayload::Extension(ref extension) => extension.encode(buf)
+        }
+    }
+
+    pub fn encoded_len(&self) -> usize {
+        match *self {
+            ExtendedPayload::Standard(ref payload) => payload.encoded_len(),
+            ExtendedPayload::Extension(ref extension) => extension.encoded_len()
+        }
+    }
+}
+
+/// A frame whose unregistered frame types are parsed by the registry `E`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ExtendedFrame<'a, E> {
+    pub header: FrameHeader,
+    pub payload: ExtendedPayload<'a, E>
+}
+
+impl<'a, E: ExtensionRegistry<'a>> ExtendedFrame<'a, E> {
+    /// Parse a frame, dispatching unregistered frame types to `E`.
+    pub fn parse(header: FrameHeader, buf: &'a [u8]) -> Result<ExtendedFrame<'a, E>, Error> {
+        Ok(ExtendedFrame {
+            header: header,
+            payload: try!(ExtendedPayload::parse(header, buf))
+        })
+    }
+
+    /// Encodes this frame into a buffer.
+    pub fn encode(&self, buf: &mut [u8]) -> usize {
+        self.header.encode(buf);
+        self.payload.encode(&mut buf[FRAME_HEADER_BYTES..]) + FRAME_HEADER_BYTES
+    }
+
+    /// How many bytes this frame will use in a buffer when encoding.
+    pub fn encoded_len(&self) -> usize {
+        FRAME_HEADER_BYTES + self.payload.encoded_len()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use {FrameHeader, Payload, Kind, Error};
+    use super::{ExtensionFrame, ExtendedFrame, ExtendedPayload};
+
+    // A made up extension frame carrying an origin.
+    #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+    struct Origin<'a>(&'a [u8]);
+
+    impl<'a> ExtensionFrame<'a> for Origin<'a> {
+        const KIND: u8 = 0xc;
+
+        fn parse(_: FrameHeader, buf: &'a [u8]) -> Result<Origin<'a>, Error> {
+            if buf.is_empty() { return Err(Error::PayloadLengthTooShort) }
+            Ok(Origin(buf))
+        }
+
+        fn encode(&self, buf: &mut [u8]) -> usize {
+            buf[..self.0.len()].copy_from_slice(self.0);
+            self.0.len()
+        }
+
+        fn encoded_len(&self) -> usize { self.0.len() }
+    }
+
+    fn parse(buf: &[u8]) -> Result<ExtendedFrame<Origin>, Error> {
+        ExtendedFrame::parse(FrameHeader::parse(buf).unwrap(), &buf[9..])
+    }
+
+    #[test]
+    fn test_parse_registered_extension() {
+        let buf = [0x0, 0x0, 0x3, 0xc, 0x0, 0x0, 0x0, 0x0, 0x0, b'a', b'b', b'c'];
+        let frame = parse(&buf).unwrap();
+        assert_eq!(frame.payload, ExtendedPayload::Extension(Origin(b"abc")));
+        assert_eq!(frame.payload.kind(), Kind::Unregistered(0xc));
+
+        let mut encoded = [0; 12];
+        assert_eq!(frame.encode(&mut encoded), 12);
+        assert_eq!(encoded, buf);
+        assert_eq!(frame.encoded_len(), 12);
+    }
+
+    #[test]
+    fn test_parse_other_kinds() {
+        let unknown = [0x0, 0x0, 0x1, 0xd, 0x0, 0x0, 0x0, 0x0, 0x0, 0x1];
+        assert_eq!(parse(&unknown).unwrap().payload,
+                   ExtendedPayload::Standard(Payload::Unregistered { kind: 0xd, data: &[0x1] }));
+
+        let data = [0x0, 0x0, 0x1, 0x0, 0x0, 0x0, 0x0, 0x0, 0x1, 0x1];
+        assert_eq!(parse(&data).unwrap().payload,
+                   ExtendedPayload::Standard(Payload::Data { data: &[0x1] }));
+
+        let empty = [0x0, 0x0, 0x0, 0xc, 0x0, 0x0, 0x0, 0x0, 0x0];
+        assert_eq!(parse(&empty), Err(Error::PayloadLengthTooShort));
+    }
+}
@@ -0,0 +1,255 @@
+//! Fixed-capacity versions of the stateful helpers, built on `heapless`
+//! so that their storage is inline rather than on the heap. They share
+//! their logic with the allocating helpers and still need `std`.
+//!
+//! Capacities are const parameters. Running out of capacity is reported
+//! the same way the limits of the allocating helpers are.
+
+use std::time::{Duration, Instant};
+
+use heapless::{Deque, Vec};
+
+use {Frame, Priority, Setting, SettingsState, Kind, StreamIdentifier, HttpError,
+     CollectorError};
+use collector::{CollectorState, PendingBlock};
+use settings::{SyncState, PendingSettings, settings_frame};
+
+/// A `HeaderBlock` whose fragment is stored inline.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FixedHeaderBlock<const N: usize> {
+    pub id: StreamIdentifier,
+    pub kind: Kind,
+    pub priority: Option<Priority>,
+    pub promised: Option<StreamIdentifier>,
+    pub end_stream: bool,
+    pub block: Vec<u8, N>
+}
+
+/// A `HeaderBlockCollector` holding at most `N` bytes of header block.
+///
+/// Larger blocks are a `CollectorError::BlockTooLarge`.
+#[derive(Clone, Debug, Default)]
+pub struct FixedHeaderBlockCollector<const N: usize> {
+    state: CollectorState<FixedHeaderBlock<N>>
+}
+
+impl<const N: usize> FixedHeaderBlockCollector<N> {
+    pub fn new() -> FixedHeaderBlockCollector<N> {
+        FixedHeaderBlockCollector { state: CollectorState::new() }
+    }
+
+    /// Limit the number of CONTINUATION frames following a HEADERS or
+    /// PUSH_PROMISE frame.
+    pub fn set_max_continuations(&mut self, max: Option<usize>) {
+        self.state.max_continuations = max;
+    }
+
+    /// Is a header block waiting for CONTINUATION frames?
+    pub fn is_collecting(&self) -> bool {
+        self.state.pending.is_some()
+    }
+
+    /// Feed the next HEADERS, PUSH_PROMISE or CONTINUATION frame, as
+    /// `HeaderBlockCollector::push` does.
+    pub fn push(&mut self, frame: &Frame) -> Result<Option<FixedHeaderBlock<N>>, CollectorError> {
+        self.state.push(frame)
+    }
+}
+
+impl<const N: usize> PendingBlock for FixedHeaderBlock<N> {
+    fn start(id: StreamIdentifier, kind: Kind, priority: Option<Priority>,
+             promised: Option<StreamIdentifier>, end_stream: bool) -> FixedHeaderBlock<N> {
+        FixedHeaderBlock {
+            id: id,
+            kind: kind,
+            priority: priority,
+            promised: promised,
+            end_stream: end_stream,
+            block: Vec::new()
+        }
+    }
+
+    fn id(&self) -> StreamIdentifier { self.id }
+
+    fn len(&self) -> usize { self.block.len() }
+
+    fn append(&mut self, fragment: &[u8]) -> Result<(), CollectorError> {
+        self.block.extend_from_slice(fragment).map_err(|()| CollectorError::BlockTooLarge)
+    }
+}
+
+/// A `SettingsSync` allowing `P` unacknowledged SETTINGS frames of up to
+/// `S` settings each.
+#[derive(Clone, Debug, Default)]
+pub struct FixedSettingsSync<const S: usize, const P: usize> {
+    state: SyncState<Deque<(Vec<Setting, S>, Instant), P>>
+}
+
+impl<const S: usize, const P: usize> FixedSettingsSync<S, P> {
+    pub fn new() -> FixedSettingsSync<S, P> {
+        FixedSettingsSync::default()
+    }
+
+    /// Our settings, as acknowledged by the peer.
+    pub fn local(&self) -> &SettingsState {
+        &self.state.local
+    }
+
+    /// The peer's settings, which we must abide by.
+    pub fn remote(&self) -> &SettingsState {
+        &self.state.remote
+    }
+
+    /// How many of our SETTINGS frames are awaiting acknowledgement.
+    pub fn pending(&self) -> usize {
+        self.state.pending.len()
+    }
+
+    /// Record `settings` as sent at `now`, returning the SETTINGS frame
+    /// to send, or `None` if there are more than `S` settings or `P`
+    /// frames are already awaiting acknowledgement.
+    pub fn send<'a>(&mut self, settings: &'a [Setting], now: Instant) -> Option<Frame<'a>> {
+        let saved = match Vec::from_slice(settings) {
+            Ok(saved) => saved,
+            Err(()) => return None
+        };
+        if self.state.pending.push_back((saved, now)).is_err() { return None }
+
+        Some(settings_frame(settings))
+    }
+
+    /// Process a frame received from the peer, as `SettingsSync::receive`
+    /// does.
+    pub fn receive(&mut self, frame: &Frame) -> Result<Option<Frame<'static>>, HttpError> {
+        self.state.receive(frame)
+    }
+
+    /// Check whether the peer has taken longer than `timeout` to
+    /// acknowledge our settings.
+    pub fn check_timeout(&self, now: Instant, timeout: Duration) -> Result<(), HttpError> {
+        self.state.check_timeout(now, timeout)
+    }
+}
+
+impl<const S: usize, const P: usize> PendingSettings for Deque<(Vec<Setting, S>, Instant), P> {
+    type Saved = Vec<Setting, S>;
+
+    fn pop_oldest(&mut self) -> Option<(Vec<Setting, S>, Instant)> {
+        self.pop_front()
+    }
+
+    fn oldest_sent(&self) -> Option<Instant> {
+        self.front().map(|&(_, sent)| sent)
+    }
+}
+
+/// A queue of up to `N` encoded frames of up to `B` bytes each, such as
+/// the control frames waiting to be written.
+#[derive(Clone, Debug, Default)]
+pub struct FrameQueue<const N: usize, const B: usize> {
+    frames: Deque<Vec<u8, B>, N>
+}
+
+impl<const N: usize, const B: usize> FrameQueue<N, B> {
+    pub fn new() -> FrameQueue<N, B> {
+        FrameQueue::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// Encode `frame` onto the back of the queue, returning `false` if
+    /// the queue is full or the frame is longer than `B` bytes.
+    pub fn push(&mut self, frame: &Frame) -> bool {
+        let len = frame.encoded_len();
+        if len > B || self.frames.is_full() { return false }
+
+        let mut buf = Vec::new();
+        buf.resize(len, 0).unwrap();
+        frame.encode(&mut buf);
+        self.frames.push_back(buf).is_ok()
+    }
+
+    /// The encoded frame at the front of the queue.
+    pub fn front(&self) -> Option<&[u8]> {
+        self.frames.front().map(|frame| &frame[..])
+    }
+
+    /// Remove the frame at the front of the queue.
+    pub fn pop(&mut self) -> Option<Vec<u8, B>> {
+        self.frames.pop_front()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Instant;
+
+    use {Frame, FrameHeader, Payload, Kind, Flag, StreamIdentifier, Setting,
+         SettingIdentifier, CollectorError};
+    use super::{FixedHeaderBlockCollector, FixedSettingsSync, FrameQueue};
+
+    fn frame<'a>(kind: Kind, flag: Flag, payload: Payload<'a>) -> Frame<'a> {
+        Frame {
+            header: FrameHeader {
+                length: payload.encoded_len() as u32,
+                kind: kind,
+                flag: flag,
+                id: StreamIdentifier(1)
+            },
+            payload: payload
+        }
+    }
+
+    #[test]
+    fn test_fixed_collector() {
+        let mut collector = FixedHeaderBlockCollector::<4>::new();
+        let headers = frame(Kind::Headers, Flag::empty(),
+                            Payload::Headers { priority: None, block: &[1, 2] });
+        let last = frame(Kind::Continuation, Flag::end_headers(), Payload::Continuation(&[3]));
+
+        assert_eq!(collector.push(&headers), Ok(None));
+        let block = collector.push(&last).unwrap().unwrap();
+        assert_eq!(&block.block[..], &[1, 2, 3]);
+
+        let more = frame(Kind::Continuation, Flag::empty(), Payload::Continuation(&[3, 4, 5]));
+        assert_eq!(collector.push(&headers), Ok(None));
+        assert_eq!(collector.push(&more), Err(CollectorError::BlockTooLarge));
+    }
+
+    #[test]
+    fn test_fixed_settings_sync() {
+        let mut sync = FixedSettingsSync::<1, 1>::new();
+        let now = Instant::now();
+        let one = [Setting::new(SettingIdentifier::InitialWindowSize, 1000)];
+        let two = [one[0], Setting::new(SettingIdentifier::EnablePush, 0)];
+
+        assert!(sync.send(&two, now).is_none());
+        assert!(sync.send(&one, now).is_some());
+        assert!(sync.send(&one, now).is_none());
+
+        assert_eq!(sync.receive(&Frame::settings_ack()), Ok(None));
+        assert_eq!(sync.local().initial_window_size, 1000);
+        assert_eq!(sync.pending(), 0);
+    }
+
+    #[test]
+    fn test_frame_queue() {
+        let mut queue = FrameQueue::<2, 17>::new();
+        let ping = Frame::ping([1, 2, 3, 4, 5, 6, 7, 8]);
+
+        assert!(queue.push(&Frame::settings_ack()));
+        assert!(queue.push(&ping));
+        assert!(!queue.push(&ping));
+        assert_eq!(queue.front(), Some(&[0, 0, 0, 4, 1, 0, 0, 0, 0][..]));
+
+        queue.pop();
+        assert_eq!(queue.len(), 1);
+        assert!(!FrameQueue::<2, 16>::new().push(&ping));
+    }
+}
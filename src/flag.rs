@@ -1,3 +1,7 @@
+use std::fmt;
+
+use Kind;
+
 bitflags! {
     #[derive(Debug)]
     flags Flag: u8 {
@@ -17,18 +21,285 @@ impl Flag {
         }
     }
 
+    /// Parse flags, ignoring bits with no defined meaning as RFC 7540
+    /// requires, and returning those bits alongside the known flags.
+    pub const fn new_lenient(data: u8) -> (Flag, u8) {
+        let known = END_STREAM.bits | END_HEADERS.bits | PADDED.bits | PRIORITY.bits;
+        (Flag { bits: data & known }, data & !known)
+    }
+
+    /// The flags as sent on the wire.
+    pub const fn encode(&self) -> u8 {
+        self.bits
+    }
+
+    /// No flags, the same as `empty`, which bitflags does not generate as
+    /// a `const fn` and so cannot be used in constants such as
+    /// `Frame::ping`.
+    pub const fn none() -> Flag {
+        Flag { bits: 0 }
+    }
+
+    /// The flags defined for frames of kind `kind`.
+    pub fn allowed_for(kind: Kind) -> Flag {
+        FrameFlags::new(kind, Flag::all()).flag()
+    }
+
+    /// The flags defined for frames of kind `kind`, one at a time and with
+    /// their names, in order of their bits.
+    pub fn defined_for(kind: Kind) -> Vec<(Flag, &'static str)> {
+        Flag::allowed_for(kind).iter().map(|flag| (flag, flag.name(kind).unwrap())).collect()
+    }
+
+    /// Iterate over the flags set, one at a time, in order of their bits.
+    pub fn iter(&self) -> FlagIter {
+        FlagIter { bits: self.bits }
+    }
+
+    /// These flags, formatted by name for frames of kind `kind`, since
+    /// ACK and END_STREAM share a bit.
+    pub fn named(&self, kind: Kind) -> NamedFlags {
+        NamedFlags { kind: kind, flag: *self }
+    }
+
+    /// The name of a single flag, if it is defined for frames of kind
+    /// `kind`. No flags, or more than one, have no name.
+    pub fn name(&self, kind: Kind) -> Option<&'static str> {
+        if !Flag::allowed_for(kind).contains(*self) {
+            return None
+        }
+
+        match self.bits {
+            0x1 if kind == Kind::Settings || kind == Kind::Ping => Some("ACK"),
+            0x1 => Some("END_STREAM"),
+            0x4 => Some("END_HEADERS"),
+            0x8 => Some("PADDED"),
+            0x20 => Some("PRIORITY"),
+            _ => None
+        }
+    }
+
     // Note that ACK and END_STREAM are the same value, but they are only present
     // on different frame types.
-    pub fn ack() -> Flag { ACK }
-    pub fn end_stream() -> Flag { END_STREAM }
-    pub fn end_headers() -> Flag { END_HEADERS }
-    pub fn padded() -> Flag { PADDED }
-    pub fn priority() -> Flag { PRIORITY }
+    pub const fn ack() -> Flag { ACK }
+    pub const fn end_stream() -> Flag { END_STREAM }
+    pub const fn end_headers() -> Flag { END_HEADERS }
+    pub const fn padded() -> Flag { PADDED }
+    pub const fn priority() -> Flag { PRIORITY }
+}
+
+#[cfg(feature = "defmt")]
+impl ::defmt::Format for Flag {
+    /// The raw bits, since their meaning depends on the frame type.
+    fn format(&self, f: ::defmt::Formatter) {
+        ::defmt::write!(f, "{=u8:#04x}", self.encode())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl ::serde::Serialize for Flag {
+    fn serialize<S: ::serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u8(self.bits())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> ::serde::Deserialize<'de> for Flag {
+    /// Undefined flags are ignored, as when parsing.
+    fn deserialize<D: ::serde::Deserializer<'de>>(deserializer: D) -> Result<Flag, D::Error> {
+        let bits = try!(<u8 as ::serde::Deserialize>::deserialize(deserializer));
+        Ok(Flag::new_lenient(bits).0)
+    }
+}
+
+/// Iterates over the flags set in a `Flag`, one at a time.
+#[derive(Copy, Clone, Debug)]
+pub struct FlagIter {
+    bits: u8
+}
+
+impl Iterator for FlagIter {
+    type Item = Flag;
+
+    fn next(&mut self) -> Option<Flag> {
+        if self.bits == 0 {
+            return None
+        }
+
+        let lowest = self.bits & self.bits.wrapping_neg();
+        self.bits &= !lowest;
+        Some(Flag { bits: lowest })
+    }
+}
+
+/// Flags formatted by name for a kind of frame, as `END_STREAM | PADDED`.
+///
+/// Flags not defined for the kind are shown as their bits, and no flags
+/// as `(empty)`; `Debug` and `Display` agree.
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+pub struct NamedFlags {
+    pub kind: Kind,
+    pub flag: Flag
+}
+
+impl fmt::Display for NamedFlags {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.flag.is_empty() {
+            f.write_str("(empty)")
+        } else {
+            write_names(f, self.flag, self.kind)
+        }
+    }
+}
+
+impl fmt::Debug for NamedFlags {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+// Write the names of the flags set, separated by ` | `, and the bits of
+// any not defined for `kind`.
+fn write_names(f: &mut fmt::Formatter, flag: Flag, kind: Kind) -> fmt::Result {
+    for (index, flag) in flag.iter().enumerate() {
+        if index > 0 {
+            try!(f.write_str(" | "));
+        }
+        match flag.name(kind) {
+            Some(name) => try!(f.write_str(name)),
+            None => try!(write!(f, "{:#04x}", flag.bits))
+        }
+    }
+
+    Ok(())
+}
+
+macro_rules! typed_flags {
+    ($(#[$attr:meta])* struct $name:ident: $kind:ident { $($field:ident),* }) => {
+        $(#[$attr])*
+        #[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+        pub struct $name {
+            $(pub $field: bool),*
+        }
+
+        impl From<Flag> for $name {
+            /// Pick out the flags defined for this frame type, ignoring the rest.
+            fn from(flag: Flag) -> $name {
+                $name { $($field: flag.contains(Flag::$field())),* }
+            }
+        }
+
+        impl From<$name> for Flag {
+            fn from(flags: $name) -> Flag {
+                let mut flag = Flag::empty();
+                $(if flags.$field { flag.insert(Flag::$field()) })*
+                flag
+            }
+        }
+
+        impl fmt::Display for $name {
+            /// The names of the flags set, separated by ` | `, as for
+            /// `NamedFlags`, but with nothing when none are set.
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write_names(f, Flag::from(*self), Kind::$kind)
+            }
+        }
+    }
+}
+
+typed_flags! {
+    /// The flags defined for DATA frames.
+    struct DataFlags: Data { end_stream, padded }
+}
+
+typed_flags! {
+    /// The flags defined for HEADERS frames.
+    struct HeadersFlags: Headers { end_stream, end_headers, padded, priority }
+}
+
+typed_flags! {
+    /// The flags defined for PUSH_PROMISE frames.
+    struct PushPromiseFlags: PushPromise { end_headers, padded }
+}
+
+typed_flags! {
+    /// The flags defined for CONTINUATION frames.
+    struct ContinuationFlags: Continuation { end_headers }
+}
+
+typed_flags! {
+    /// The flags defined for SETTINGS frames.
+    struct SettingsFlags: Settings { ack }
+}
+
+typed_flags! {
+    /// The flags defined for PING frames.
+    struct PingFlags: Ping { ack }
+}
+
+/// The flags of a frame, typed by the kind of the frame so that only
+/// flags meaningful for that kind can be expressed.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum FrameFlags {
+    Data(DataFlags),
+    Headers(HeadersFlags),
+    PushPromise(PushPromiseFlags),
+    Continuation(ContinuationFlags),
+    Settings(SettingsFlags),
+    Ping(PingFlags),
+
+    /// PRIORITY, RST_STREAM, GOAWAY, WINDOW_UPDATE and unregistered frames
+    /// define no flags.
+    None
+}
+
+impl FrameFlags {
+    /// Interpret `flag` for a frame of kind `kind`, ignoring any flags
+    /// not defined for that kind.
+    pub fn new(kind: Kind, flag: Flag) -> FrameFlags {
+        match kind {
+            Kind::Data => FrameFlags::Data(flag.into()),
+            Kind::Headers => FrameFlags::Headers(flag.into()),
+            Kind::PushPromise => FrameFlags::PushPromise(flag.into()),
+            Kind::Continuation => FrameFlags::Continuation(flag.into()),
+            Kind::Settings => FrameFlags::Settings(flag.into()),
+            Kind::Ping => FrameFlags::Ping(flag.into()),
+            _ => FrameFlags::None
+        }
+    }
+
+    /// The raw flags, for use in a `FrameHeader`.
+    pub fn flag(&self) -> Flag {
+        match *self {
+            FrameFlags::Data(flags) => flags.into(),
+            FrameFlags::Headers(flags) => flags.into(),
+            FrameFlags::PushPromise(flags) => flags.into(),
+            FrameFlags::Continuation(flags) => flags.into(),
+            FrameFlags::Settings(flags) => flags.into(),
+            FrameFlags::Ping(flags) => flags.into(),
+            FrameFlags::None => Flag::empty()
+        }
+    }
+}
+
+impl fmt::Display for FrameFlags {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            FrameFlags::Data(flags) => flags.fmt(f),
+            FrameFlags::Headers(flags) => flags.fmt(f),
+            FrameFlags::PushPromise(flags) => flags.fmt(f),
+            FrameFlags::Continuation(flags) => flags.fmt(f),
+            FrameFlags::Settings(flags) => flags.fmt(f),
+            FrameFlags::Ping(flags) => flags.fmt(f),
+            FrameFlags::None => Ok(())
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::Flag;
+    use Kind;
+    use super::{Flag, FrameFlags, DataFlags, HeadersFlags, PingFlags};
 
     const FLAG_EMPTY: u8 = 0x0;
     const FLAG_END_STREAM_OR_ACK: u8 = 0x1;
@@ -38,6 +309,17 @@ mod tests {
     #[test]
     fn test_flag_empty() {
         assert_eq!(Flag::empty().bits(), FLAG_EMPTY);
+        assert_eq!(Flag::none(), Flag::empty());
+    }
+
+    #[test]
+    fn test_flag_new_lenient() {
+        assert_eq!(Flag::new_lenient(FLAG_END_HEADERS | 0x2),
+                   (Flag::end_headers(), 0x2));
+        assert_eq!(Flag::new_lenient(0xFF),
+                   (Flag::all(), 0xFF & !(FLAG_END_STREAM_OR_ACK | FLAG_END_HEADERS |
+                                          FLAG_PADDED | 0x20)));
+        assert!(Flag::new(0xFF).is_err());
     }
 
     #[test]
@@ -48,5 +330,51 @@ mod tests {
         assert_eq!(Flag::from_bits(FLAG_END_STREAM_OR_ACK | FLAG_PADDED).unwrap(),
                    Flag::end_stream() | Flag::padded());
     }
-}
 
+    #[test]
+    fn test_allowed_for() {
+        assert_eq!(Flag::allowed_for(Kind::Data), Flag::end_stream() | Flag::padded());
+        assert_eq!(Flag::allowed_for(Kind::Headers),
+                   Flag::end_stream() | Flag::end_headers() | Flag::padded() | Flag::priority());
+        assert_eq!(Flag::allowed_for(Kind::Settings), Flag::ack());
+        assert_eq!(Flag::allowed_for(Kind::WindowUpdate), Flag::empty());
+        assert_eq!(Flag::allowed_for(Kind::Unregistered(0xa)), Flag::empty());
+    }
+
+    #[test]
+    fn test_flag_names() {
+        let flag = Flag::end_stream() | Flag::padded() | Flag::priority();
+        assert_eq!(flag.iter().collect::<Vec<_>>(),
+                   [Flag::end_stream(), Flag::padded(), Flag::priority()]);
+
+        assert_eq!(format!("{:?}", flag.named(Kind::Data)), "END_STREAM | PADDED | 0x20");
+        assert_eq!(Flag::ack().named(Kind::Ping).to_string(), "ACK");
+        assert_eq!(Flag::empty().named(Kind::Headers).to_string(), "(empty)");
+        assert_eq!(Flag::priority().name(Kind::Headers), Some("PRIORITY"));
+        assert_eq!(Flag::empty().name(Kind::Data), None);
+        assert_eq!((Flag::end_stream() | Flag::padded()).name(Kind::Data), None);
+        assert_eq!(FrameFlags::new(Kind::Data, flag).to_string(), "END_STREAM | PADDED");
+        assert_eq!(FrameFlags::new(Kind::GoAway, flag).to_string(), "");
+
+        assert_eq!(Flag::defined_for(Kind::Settings), [(Flag::ack(), "ACK")]);
+        assert_eq!(Flag::defined_for(Kind::PushPromise),
+                   [(Flag::end_headers(), "END_HEADERS"), (Flag::padded(), "PADDED")]);
+        assert!(Flag::defined_for(Kind::GoAway).is_empty());
+    }
+
+    #[test]
+    fn test_typed_flags() {
+        let flag = Flag::end_stream() | Flag::padded() | Flag::priority();
+
+        assert_eq!(FrameFlags::new(Kind::Data, flag),
+                   FrameFlags::Data(DataFlags { end_stream: true, padded: true }));
+        assert_eq!(FrameFlags::new(Kind::Ping, flag),
+                   FrameFlags::Ping(PingFlags { ack: true }));
+        assert_eq!(FrameFlags::new(Kind::GoAway, flag), FrameFlags::None);
+
+        let headers = HeadersFlags { end_headers: true, priority: true, ..Default::default() };
+        assert_eq!(Flag::from(headers), Flag::end_headers() | Flag::priority());
+        assert_eq!(FrameFlags::Headers(headers).flag(), Flag::end_headers() | Flag::priority());
+        assert_eq!(FrameFlags::new(Kind::Data, flag).flag(), Flag::end_stream() | Flag::padded());
+    }
+}
@@ -0,0 +1,220 @@
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+use {FrameHeader, Kind, Flag};
+
+/// Detects "rapid reset" floods (CVE-2023-44487), where a peer opens
+/// streams and immediately cancels them to dodge stream limits.
+///
+/// Frames from the peer are fed in the order they are received. Each
+/// RST_STREAM arriving within `window` of the HEADERS frame that opened
+/// the stream counts as a rapid reset, and the detector trips once more
+/// than `threshold` rapid resets fall within the last `window`.
+///
+/// Once tripped, servers typically send GOAWAY with ENHANCE_YOUR_CALM.
+#[derive(Clone, Debug)]
+pub struct RapidResetDetector {
+    threshold: usize,
+    window: Duration,
+    opened: HashMap<u32, Instant>,
+    resets: VecDeque<Instant>
+}
+
+impl RapidResetDetector {
+    pub fn new(threshold: usize, window: Duration) -> RapidResetDetector {
+        RapidResetDetector {
+            threshold: threshold,
+            window: window,
+            opened: HashMap::new(),
+            resets: VecDeque::new()
+        }
+    }
+
+    /// Record a frame received at `now`, returning whether the threshold
+    /// has been crossed.
+    pub fn push(&mut self, header: &FrameHeader, now: Instant) -> bool {
+        self.expire(now);
+
+        match header.kind {
+            Kind::Headers => {
+                self.opened.entry(header.id.0).or_insert(now);
+            },
+            Kind::Reset => {
+                if self.opened.remove(&header.id.0).is_some() {
+                    self.resets.push_back(now);
+                }
+            },
+            _ => {}
+        }
+
+        self.is_tripped()
+    }
+
+    /// How many rapid resets fall within the current window.
+    pub fn count(&self) -> usize {
+        self.resets.len()
+    }
+
+    /// Has the threshold been crossed within the current window?
+    pub fn is_tripped(&self) -> bool {
+        self.resets.len() > self.threshold
+    }
+
+    fn expire(&mut self, now: Instant) {
+        let window = self.window;
+
+        while self.resets.front().map_or(false, |&reset| now.duration_since(reset) > window) {
+            self.resets.pop_front();
+        }
+
+        self.opened.retain(|_, &mut opened| now.duration_since(opened) <= window);
+    }
+}
+
+/// A pattern of cheap frames that cost the receiver work to handle.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Flood {
+    /// SETTINGS frames with no settings, each of which must be
+    /// acknowledged.
+    EmptySettings = 0,
+
+    /// PING frames, each of which must be acknowledged.
+    Ping = 1,
+
+    /// PRIORITY frames, each of which may reshape the priority tree.
+    Priority = 2
+}
+
+/// Detects floods of control frames from a peer.
+///
+/// Each `Flood` is counted over a sliding `window`, and is reported once
+/// more than its limit arrive within that window. The report is advisory;
+/// applications typically respond with a GOAWAY carrying ENHANCE_YOUR_CALM.
+#[derive(Clone, Debug)]
+pub struct ControlFloodDetector {
+    window: Duration,
+    limits: [usize; 3],
+    seen: [VecDeque<Instant>; 3]
+}
+
+impl ControlFloodDetector {
+    /// Allow up to `limit` frames of each kind within `window`.
+    pub fn new(limit: usize, window: Duration) -> ControlFloodDetector {
+        ControlFloodDetector {
+            window: window,
+            limits: [limit; 3],
+            seen: [VecDeque::new(), VecDeque::new(), VecDeque::new()]
+        }
+    }
+
+    /// Change the limit for one kind of flood.
+    pub fn set_limit(&mut self, flood: Flood, limit: usize) {
+        self.limits[flood as usize] = limit;
+    }
+
+    /// Record a frame received at `now`, returning the flood it is part
+    /// of if its limit has been exceeded.
+    pub fn push(&mut self, header: &FrameHeader, now: Instant) -> Option<Flood> {
+        let flood = match header.kind {
+            Kind::Settings if header.length == 0 && !header.flag.contains(Flag::ack()) =>
+                Flood::EmptySettings,
+            Kind::Ping if !header.flag.contains(Flag::ack()) => Flood::Ping,
+            Kind::Priority => Flood::Priority,
+            _ => return None
+        };
+
+        let window = self.window;
+        let seen = &mut self.seen[flood as usize];
+        while seen.front().map_or(false, |&at| now.duration_since(at) > window) {
+            seen.pop_front();
+        }
+        seen.push_back(now);
+
+        if seen.len() > self.limits[flood as usize] { Some(flood) } else { None }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::{Duration, Instant};
+
+    use {FrameHeader, Kind, Flag, StreamIdentifier};
+    use super::{RapidResetDetector, ControlFloodDetector, Flood};
+
+    fn header(kind: Kind, id: u32) -> FrameHeader {
+        FrameHeader {
+            length: 0,
+            kind: kind,
+            flag: Flag::empty(),
+            id: StreamIdentifier(id)
+        }
+    }
+
+    fn control(kind: Kind, flag: Flag, length: u32) -> FrameHeader {
+        FrameHeader {
+            length: length,
+            kind: kind,
+            flag: flag,
+            id: StreamIdentifier(0)
+        }
+    }
+
+    #[test]
+    fn test_rapid_resets_trip() {
+        let mut detector = RapidResetDetector::new(2, Duration::from_secs(1));
+        let now = Instant::now();
+
+        for id in &[1, 3, 5] {
+            detector.push(&header(Kind::Headers, *id), now);
+        }
+
+        assert!(!detector.push(&header(Kind::Reset, 1), now));
+        assert!(!detector.push(&header(Kind::Reset, 3), now));
+        assert!(detector.push(&header(Kind::Reset, 5), now));
+        assert_eq!(detector.count(), 3);
+
+        // The window slides past the resets.
+        assert!(!detector.push(&header(Kind::Ping, 0), now + Duration::from_secs(2)));
+        assert_eq!(detector.count(), 0);
+    }
+
+    #[test]
+    fn test_slow_and_unknown_resets_ignored() {
+        let mut detector = RapidResetDetector::new(0, Duration::from_secs(1));
+        let now = Instant::now();
+
+        detector.push(&header(Kind::Headers, 1), now);
+        assert!(!detector.push(&header(Kind::Reset, 1), now + Duration::from_secs(5)));
+        assert!(!detector.push(&header(Kind::Reset, 3), now + Duration::from_secs(5)));
+
+        detector.push(&header(Kind::Headers, 5), now + Duration::from_secs(5));
+        assert!(detector.push(&header(Kind::Reset, 5), now + Duration::from_secs(5)));
+    }
+
+    #[test]
+    fn test_control_floods() {
+        let mut detector = ControlFloodDetector::new(2, Duration::from_secs(1));
+        detector.set_limit(Flood::Priority, 0);
+        let now = Instant::now();
+
+        let ping = control(Kind::Ping, Flag::empty(), 8);
+        assert_eq!(detector.push(&ping, now), None);
+        assert_eq!(detector.push(&ping, now), None);
+        assert_eq!(detector.push(&ping, now), Some(Flood::Ping));
+        assert_eq!(detector.push(&ping, now + Duration::from_secs(2)), None);
+
+        let empty = control(Kind::Settings, Flag::empty(), 0);
+        let ack = control(Kind::Settings, Flag::ack(), 0);
+        let settings = control(Kind::Settings, Flag::empty(), 6);
+        for _ in 0..5 {
+            assert_eq!(detector.push(&ack, now), None);
+            assert_eq!(detector.push(&settings, now), None);
+            assert_eq!(detector.push(&control(Kind::Ping, Flag::ack(), 8), now), None);
+        }
+        assert_eq!(detector.push(&empty, now), None);
+        assert_eq!(detector.push(&empty, now), None);
+        assert_eq!(detector.push(&empty, now), Some(Flood::EmptySettings));
+
+        assert_eq!(detector.push(&header(Kind::Priority, 1), now), Some(Flood::Priority));
+    }
+}
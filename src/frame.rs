@@ -1,9 +1,55 @@
-use {Payload, Error, Flag, Kind, StreamIdentifier, FRAME_HEADER_BYTES};
+use std::cmp;
+use std::fmt;
+use std::convert::TryFrom;
 
+use {Payload, Setting, Settings, Error, ParseError, ParserOptions, Flag, FrameFlags, Kind,
+     StreamIdentifier, HttpError, OwnedFrame, OwnedPayload, FRAME_HEADER_BYTES};
+
+#[cfg(feature = "random")]
+use rand::Rng;
+#[cfg(feature = "random")]
+use rand::distributions::{Distribution, Standard};
 #[cfg(feature = "random")]
-use rand::{Rand, Rng};
+use rand::seq::SliceRandom;
+#[cfg(feature = "arbitrary")]
+use arbitrary::{self, Unstructured};
+#[cfg(feature = "quickcheck")]
+use quickcheck::{self, Gen};
 
+// The largest length a frame header can hold.
+const MAX_LENGTH: usize = ::consts::MAX_MAX_FRAME_SIZE as usize;
+
+/// A way in which the header of a `Frame` disagrees with its payload,
+/// found by `Frame::validate`.
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum ConsistencyError {
+    /// The header's length is not the encoded length of the payload,
+    /// which is held.
+    LengthMismatch(usize),
+
+    /// The payload is too long for the 24 bit length of a frame header.
+    TooLong(usize),
+
+    /// The header's kind is not the kind of the payload, which is held.
+    KindMismatch(Kind),
+
+    /// Flags not defined for the frame's kind are set.
+    BadFlag(u8),
+
+    /// PADDED is set, but payloads are encoded without padding.
+    Padded,
+
+    /// The PRIORITY flag is set on a HEADERS frame without a priority, or
+    /// clear on one with a priority.
+    PriorityMismatch,
+
+    /// A SETTINGS frame with ACK set carries settings.
+    AckWithSettings
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
 pub struct Frame<'a> {
     pub header: FrameHeader,
     pub payload: Payload<'a>
@@ -17,6 +63,30 @@ impl<'a> Frame<'a> {
         })
     }
 
+    /// Like `parse`, but also making the checks turned on in `options`.
+    pub fn parse_with_options(header: FrameHeader, buf: &'a [u8], options: &ParserOptions)
+                              -> Result<Frame<'a>, Error> {
+        try!(options.check_header(&header));
+        let frame = try!(Frame::parse(header, buf));
+        try!(options.check_padding(&header, buf));
+        Ok(frame)
+    }
+
+    /// Like `parse`, but without checking that `buf` holds the whole
+    /// payload.
+    ///
+    /// # Safety
+    ///
+    /// `buf` must be at least `header.length` bytes long.
+    #[cfg(feature = "unchecked")]
+    #[inline]
+    pub unsafe fn parse_unchecked(header: FrameHeader, buf: &[u8]) -> Result<Frame, Error> {
+        Ok(Frame {
+            header: header,
+            payload: try!(Payload::parse_unchecked(header, buf))
+        })
+    }
+
     /// Encodes this Frame into a buffer.
     pub fn encode(&self, buf: &mut [u8]) -> usize {
         self.header.encode(buf);
@@ -27,9 +97,393 @@ impl<'a> Frame<'a> {
     pub fn encoded_len(&self) -> usize {
         FRAME_HEADER_BYTES + self.payload.encoded_len()
     }
+
+    /// Check that the header agrees with the payload, so that `encode`
+    /// produces a frame which parses back to this one.
+    ///
+    /// This is meant for frames built by hand; parsed frames are always
+    /// consistent, unless they were padded.
+    pub fn validate(&self) -> Result<(), ConsistencyError> {
+        let header = self.header;
+        let len = self.payload.encoded_len();
+
+        if header.kind != self.payload.kind() {
+            return Err(ConsistencyError::KindMismatch(self.payload.kind()))
+        }
+        if len > MAX_LENGTH {
+            return Err(ConsistencyError::TooLong(len))
+        }
+        if header.length as usize != len {
+            return Err(ConsistencyError::LengthMismatch(len))
+        }
+        if let Err(Error::BadFlag(extra)) = header.validate_flags() {
+            return Err(ConsistencyError::BadFlag(extra))
+        }
+        if header.flag.contains(Flag::padded()) {
+            return Err(ConsistencyError::Padded)
+        }
+
+        match self.payload {
+            Payload::Headers { priority, .. }
+                if priority.is_some() != header.flag.contains(Flag::priority()) =>
+                    Err(ConsistencyError::PriorityMismatch),
+            Payload::Settings(settings)
+                if settings.len() != 0 && header.flag.contains(Flag::ack()) =>
+                    Err(ConsistencyError::AckWithSettings),
+            _ => Ok(())
+        }
+    }
+
+    /// This frame in a canonical form, so that frames with the same
+    /// meaning have equal canonical forms.
+    ///
+    /// Flags not defined for the frame's kind and PADDED are cleared, and
+    /// PRIORITY is set exactly when a HEADERS frame carries a priority.
+    /// Settings are sorted by identifier, keeping only the last value of
+    /// each, as that is the one which takes effect. The length is then
+    /// recomputed from the payload. Flags of unregistered kinds are kept,
+    /// as their meaning is unknown.
+    pub fn canonicalize(&self) -> OwnedFrame {
+        let kind = self.payload.kind();
+        let mut flag = match kind {
+            Kind::Unregistered(_) => self.header.flag,
+            kind => self.header.flag & Flag::allowed_for(kind)
+        } - Flag::padded();
+
+        let payload = match self.payload {
+            Payload::Headers { priority, .. } => {
+                flag = if priority.is_some() {
+                    flag | Flag::priority()
+                } else {
+                    flag - Flag::priority()
+                };
+                self.payload.into()
+            },
+            Payload::Settings(settings) => {
+                let mut canonical: Vec<Setting> = Vec::new();
+                for setting in settings.iter() {
+                    canonical.retain(|earlier| earlier.code() != setting.code());
+                    canonical.push(setting);
+                }
+
+                canonical.sort_by_key(|setting| setting.code());
+                OwnedPayload::Settings(canonical)
+            },
+            payload => payload.into()
+        };
+
+        OwnedFrame {
+            header: FrameHeader {
+                length: payload.as_payload().encoded_len() as u32,
+                kind: kind,
+                flag: flag,
+                id: self.header.id
+            },
+            payload: payload
+        }
+    }
+
+    /// Do the two frames have the same meaning, whatever their padding,
+    /// undefined flags or order of settings?
+    pub fn semantically_eq(&self, other: &Frame) -> bool {
+        self.canonicalize() == other.canonicalize()
+    }
+
+    /// An empty SETTINGS frame with the ACK flag set, acknowledging
+    /// receipt of the peer's settings.
+    pub const fn settings_ack() -> Frame<'static> {
+        Frame {
+            header: FrameHeader {
+                length: 0,
+                kind: Kind::Settings,
+                flag: Flag::ack(),
+                id: StreamIdentifier(0)
+            },
+            payload: Payload::Settings(Settings::new(&[]))
+        }
+    }
+
+    /// Is this a SETTINGS frame acknowledging our settings?
+    pub fn is_settings_ack(&self) -> bool {
+        self.header.kind == Kind::Settings && self.header.flag.contains(Flag::ack())
+    }
+
+    /// A GOAWAY frame reporting `error`, with `last` as the highest
+    /// stream that was or may still be processed and optional debug text.
+    pub fn goaway(last: StreamIdentifier, error: HttpError,
+                  debug: Option<&'a str>) -> Frame<'a> {
+        let data = debug.map(|debug| debug.as_bytes()).unwrap_or(&[]);
+
+        Frame {
+            header: FrameHeader {
+                length: 8 + data.len() as u32,
+                kind: Kind::GoAway,
+                flag: Flag::empty(),
+                id: StreamIdentifier(0)
+            },
+            payload: Payload::goaway(last, error, data)
+        }
+    }
+
+    /// A PING frame carrying `data`, to be echoed back by the peer.
+    pub const fn ping(data: [u8; 8]) -> Frame<'static> {
+        Frame {
+            header: FrameHeader {
+                length: 8,
+                kind: Kind::Ping,
+                flag: Flag::none(),
+                id: StreamIdentifier(0)
+            },
+            payload: Payload::ping(data)
+        }
+    }
+
+    /// The PING frame with ACK set that must be sent in response to this
+    /// frame, or `None` if this is not a PING awaiting acknowledgement.
+    pub fn ping_ack(&self) -> Option<Frame<'static>> {
+        match self.payload {
+            Payload::Ping(data) if !self.is_ping_ack() => Some(Frame {
+                header: FrameHeader {
+                    length: 8,
+                    kind: Kind::Ping,
+                    flag: Flag::ack(),
+                    id: StreamIdentifier(0)
+                },
+                payload: Payload::Ping(data)
+            }),
+            _ => None
+        }
+    }
+
+    /// Is this a PING frame acknowledging one of our pings?
+    pub fn is_ping_ack(&self) -> bool {
+        self.header.kind == Kind::Ping && self.header.flag.contains(Flag::ack())
+    }
+}
+
+impl<'a> TryFrom<&'a [u8]> for Frame<'a> {
+    type Error = Error;
+
+    /// Parse the frame at the start of `buf`, ignoring any bytes after it.
+    fn try_from(buf: &'a [u8]) -> Result<Frame<'a>, Error> {
+        let header = try!(FrameHeader::parse(buf));
+        Frame::parse(header, &buf[FRAME_HEADER_BYTES..])
+    }
+}
+
+impl<'a, 'b> From<&'b Frame<'a>> for Vec<u8> {
+    fn from(frame: &'b Frame<'a>) -> Vec<u8> {
+        let mut buf = vec![0; frame.encoded_len()];
+        frame.encode(&mut buf);
+        buf
+    }
+}
+
+impl<'a> From<Frame<'a>> for Vec<u8> {
+    fn from(frame: Frame<'a>) -> Vec<u8> {
+        Vec::from(&frame)
+    }
+}
+
+impl<'a> fmt::Display for Frame<'a> {
+    /// A one-line summary of the header and payload, such as
+    /// `HEADERS stream=5 len=312 END_STREAM | END_HEADERS block=312`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        try!(self.header.fmt(f));
+        ::payload::fmt_details(&self.payload, f)
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl<'a> ::defmt::Format for Frame<'a> {
+    /// The header and the fixed-size parts of the payload, with the
+    /// lengths of any data.
+    fn format(&self, f: ::defmt::Formatter) {
+        ::defmt::write!(f, "{}", self.header);
+
+        match self.payload {
+            Payload::Data { data } | Payload::Unregistered { data, .. } =>
+                ::defmt::write!(f, " data={=usize}", data.len()),
+            Payload::Headers { block, .. } | Payload::Continuation(block) =>
+                ::defmt::write!(f, " block={=usize}", block.len()),
+            Payload::Priority(priority) =>
+                ::defmt::write!(f, " dependency={=u32} weight={=u8}",
+                                priority.dependency().0, priority.weight()),
+            Payload::Reset(error) => ::defmt::write!(f, " error={=u32:#x}", error.0),
+            Payload::Settings(settings) => ::defmt::write!(f, " settings={=usize}", settings.len()),
+            Payload::PushPromise { promised, block } =>
+                ::defmt::write!(f, " promised={=u32} block={=usize}", promised.0, block.len()),
+            Payload::Ping(data) => ::defmt::write!(f, " data={=u64:#018x}", data),
+            Payload::GoAway { last, error, data } =>
+                ::defmt::write!(f, " last={=u32} error={=u32:#x} debug={=usize}",
+                                last.0, error.0, data.len()),
+            Payload::WindowUpdate(increment) =>
+                ::defmt::write!(f, " increment={=u32}", increment.0)
+        }
+    }
+}
+
+/// An iterator over the complete frames at the start of a buffer.
+///
+/// Iteration ends at the first error, or once no complete frame is left.
+/// A trailing partial frame is not an error, and `offset` tells where
+/// it starts.
+#[derive(Copy, Clone, Debug)]
+pub struct Frames<'a> {
+    buf: &'a [u8],
+    offset: usize,
+    failed: bool
+}
+
+impl<'a> Frames<'a> {
+    pub fn new(buf: &'a [u8]) -> Frames<'a> {
+        Frames { buf: buf, offset: 0, failed: false }
+    }
+
+    /// Iterate over the frames following the client connection preface,
+    /// as a server reads them.
+    ///
+    /// Offsets still count from the start of `buf`, preface included.
+    pub fn after_preface(buf: &'a [u8]) -> Result<Frames<'a>, Error> {
+        let offset = try!(::parse_preface(buf));
+        Ok(Frames { buf: buf, offset: offset, failed: false })
+    }
+
+    /// The offset of the next frame to parse.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+}
+
+impl<'a> Iterator for Frames<'a> {
+    type Item = Result<Frame<'a>, ParseError>;
+
+    fn next(&mut self) -> Option<Result<Frame<'a>, ParseError>> {
+        if self.failed { return None }
+
+        let buf = &self.buf[self.offset..];
+
+        let header = match FrameHeader::parse(buf) {
+            Ok(header) => header,
+            Err(Error::Short(_)) => return None,
+            Err(error) => {
+                self.failed = true;
+                return Some(Err(ParseError {
+                    error: error,
+                    kind: None,
+                    id: None,
+                    frame_offset: self.offset,
+                    offset: self.offset
+                }))
+            }
+        };
+
+        let end = FRAME_HEADER_BYTES + header.length as usize;
+        if buf.len() < end { return None }
+
+        match Frame::parse(header, &buf[FRAME_HEADER_BYTES..end]) {
+            Ok(frame) => {
+                self.offset += end;
+                Some(Ok(frame))
+            },
+            Err(error) => {
+                self.failed = true;
+                let payload = &buf[FRAME_HEADER_BYTES..end];
+                Some(Err(parse_error(error, header, self.offset, payload)))
+            }
+        }
+    }
+}
+
+/// Parse every complete frame at the start of `buf` into `frames`,
+/// returning the number of bytes consumed.
+///
+/// This is the batched equivalent of `Frames`, for readers which receive
+/// many frames at once: each header is read straight out of a slice
+/// already known to be long enough, and no iterator state is kept between
+/// frames. A trailing partial frame is left unconsumed. On error, the
+/// frames before the bad one have already been pushed.
+pub fn parse_many<'a>(buf: &'a [u8], frames: &mut Vec<Frame<'a>>)
+                      -> Result<usize, ParseError> {
+    let mut offset = 0;
+
+    while buf.len() - offset >= FRAME_HEADER_BYTES {
+        let rest = &buf[offset..];
+        let length = ((rest[0] as usize) << 16) | ((rest[1] as usize) << 8) | rest[2] as usize;
+        let end = FRAME_HEADER_BYTES + length;
+        if rest.len() < end { break }
+
+        let header = FrameHeader {
+            length: length as u32,
+            kind: Kind::new(rest[3]),
+            flag: Flag::new_lenient(rest[4]).0,
+            id: StreamIdentifier::parse(&rest[5..FRAME_HEADER_BYTES])
+        };
+
+        match Payload::parse(header, &rest[FRAME_HEADER_BYTES..end]) {
+            Ok(payload) => frames.push(Frame { header: header, payload: payload }),
+            Err(error) => {
+                let payload = &rest[FRAME_HEADER_BYTES..end];
+                return Err(parse_error(error, header, offset, payload))
+            }
+        }
+
+        offset += end;
+    }
+
+    Ok(offset)
+}
+
+/// A `ParseError` for `error` in the frame at `frame_offset`, pointing at
+/// the part of the frame at fault. `payload` holds as much of the payload
+/// as is available, and may be empty for errors in the header.
+pub fn parse_error(error: Error, header: FrameHeader, frame_offset: usize,
+                   payload: &[u8]) -> ParseError {
+    let length = header.length as usize;
+    let within = match error {
+        Error::BadKind(_) => 3,
+        Error::BadFlag(_) => 4,
+        Error::ZeroStreamId => 5,
+        Error::TooMuchPadding(_) => FRAME_HEADER_BYTES,
+        Error::PartialSettingLength => FRAME_HEADER_BYTES + length - length % 6,
+        Error::NonZeroPadding => {
+            let end = cmp::min(length, payload.len());
+            let start = end - cmp::min(payload.first().map_or(0, |&pad| pad as usize), end);
+            let dirty = payload[start..end].iter().position(|&b| b != 0).unwrap_or(0);
+            FRAME_HEADER_BYTES + start + dirty
+        },
+        Error::Short(_) => FRAME_HEADER_BYTES + payload.len(),
+        _ => 0
+    };
+
+    ParseError {
+        error: error,
+        kind: Some(header.kind),
+        id: Some(header.id),
+        frame_offset: frame_offset,
+        offset: frame_offset + within
+    }
+}
+
+/// Write only the 9-byte header of a DATA frame carrying `len` bytes,
+/// returning the number of bytes written.
+///
+/// The payload can then be written straight from where it lives, with
+/// `sendfile` or a vectored write, instead of being copied after the header.
+#[inline]
+pub fn encode_data_header(len: u32, id: StreamIdentifier, flag: Flag, buf: &mut [u8]) -> usize {
+    FrameHeader {
+        length: len,
+        kind: Kind::Data,
+        flag: flag,
+        id: id
+    }.encode(buf);
+
+    FRAME_HEADER_BYTES
 }
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct FrameHeader {
     pub length: u32,
     pub kind: Kind,
@@ -39,30 +493,148 @@ pub struct FrameHeader {
 
 impl FrameHeader {
     #[inline]
-    pub fn parse(buf: &[u8]) -> Result<FrameHeader, Error> {
+    pub const fn parse(buf: &[u8]) -> Result<FrameHeader, Error> {
         if buf.len() < FRAME_HEADER_BYTES {
-            return Err(Error::Short);
+            return Err(Error::Short(FRAME_HEADER_BYTES - buf.len()));
         }
 
         Ok(FrameHeader {
             length: ((buf[0] as u32) << 16) | ((buf[1] as u32) << 8) | buf[2] as u32,
             kind: Kind::new(buf[3]),
-            flag: try!(Flag::new(buf[4]).map_err(|()| { Error::BadFlag(buf[4]) })),
-            id: StreamIdentifier::parse(&buf[5..])
+            flag: Flag::new_lenient(buf[4]).0,
+            id: StreamIdentifier::parse(buf.split_at(5).1)
         })
     }
 
+    /// The total size on the wire, header included, of the frame starting
+    /// at `buf`, which only needs to hold the 3 bytes of its length.
+    ///
+    /// This lets readers size their next read before the whole header has
+    /// arrived. Fewer than 3 bytes are an `Error::Short`.
+    #[inline]
+    pub const fn peek_length(buf: &[u8]) -> Result<usize, Error> {
+        if buf.len() < 3 {
+            return Err(Error::Short(3 - buf.len()));
+        }
+
+        let length = ((buf[0] as usize) << 16) | ((buf[1] as usize) << 8) | buf[2] as usize;
+        Ok(FRAME_HEADER_BYTES + length)
+    }
+
+    /// Like `parse`, but without checking the length of `buf`.
+    ///
+    /// # Safety
+    ///
+    /// `buf` must be at least `FRAME_HEADER_BYTES` (9) bytes long.
+    #[cfg(feature = "unchecked")]
+    #[inline]
+    pub unsafe fn parse_unchecked(buf: &[u8]) -> FrameHeader {
+        debug_assert!(buf.len() >= FRAME_HEADER_BYTES);
+        let byte = |at| *buf.get_unchecked(at) as u32;
+
+        FrameHeader {
+            length: (byte(0) << 16) | (byte(1) << 8) | byte(2),
+            kind: Kind::new(byte(3) as u8),
+            flag: Flag::new_lenient(byte(4) as u8).0,
+            id: StreamIdentifier(((byte(5) << 24) | (byte(6) << 16) | (byte(7) << 8) | byte(8))
+                                 & ::consts::MAX_STREAM_ID)
+        }
+    }
+
+    /// Like `parse`, but unknown frame types are an `Error::BadKind` and
+    /// undefined flags are an `Error::BadFlag` instead of being ignored.
+    #[inline]
+    pub fn parse_strict(buf: &[u8]) -> Result<FrameHeader, Error> {
+        let header = try!(FrameHeader::parse(buf));
+        try!(Kind::new_strict(header.kind.encode()));
+        try!(Flag::new(buf[4]).map_err(|()| { Error::BadFlag(buf[4]) }));
+        Ok(header)
+    }
+
+    /// The flags of this frame, typed by its kind.
+    #[inline]
+    pub fn flags(&self) -> FrameFlags {
+        FrameFlags::new(self.kind, self.flag)
+    }
+
+    /// Check the declared length against what the kind and flags of the
+    /// frame require, before any of the payload is read.
+    ///
+    /// PING, RST_STREAM, WINDOW_UPDATE and PRIORITY frames and SETTINGS ACKs
+    /// have a fixed length, reported as an `Error::InvalidPayloadLength`,
+    /// and other SETTINGS frames must hold whole settings. Frames shorter
+    /// than `min_length` are an `Error::PayloadLengthTooShort`.
+    pub fn validate(&self) -> Result<(), Error> {
+        let exact = match self.kind {
+            Kind::Settings if self.flag.contains(Flag::ack()) => Some(0),
+            kind => kind.has_fixed_length()
+        };
+
+        if exact.map_or(false, |exact| self.length != exact) {
+            Err(Error::InvalidPayloadLength)
+        } else if self.kind == Kind::Settings && self.length % 6 != 0 {
+            Err(Error::PartialSettingLength)
+        } else if self.length < self.min_length() {
+            Err(Error::PayloadLengthTooShort)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// The shortest payload the kind and flags of this frame allow: the
+    /// pad length byte, and any priority, promised stream or GOAWAY fields.
+    pub fn min_length(&self) -> u32 {
+        let padded = self.kind.allows_padding() && self.flag.contains(Flag::padded());
+
+        padded as u32 + match self.kind {
+            Kind::Headers if self.flag.contains(Flag::priority()) => 5,
+            Kind::PushPromise => 4,
+            Kind::GoAway => 8,
+            kind => kind.has_fixed_length().unwrap_or(0)
+        }
+    }
+
+    /// Check that only flags defined for this kind of frame are set,
+    /// returning the others in an `Error::BadFlag` if not.
+    ///
+    /// Receivers must ignore such flags, so this is only useful for
+    /// conformance testing and for checking frames before sending them.
+    #[inline]
+    pub fn validate_flags(&self) -> Result<(), Error> {
+        let extra = self.flag - Flag::allowed_for(self.kind);
+
+        if extra.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::BadFlag(extra.bits()))
+        }
+    }
+
+    /// Check that a frame which applies to a single stream is not sent on
+    /// stream 0, returning an `Error::ZeroStreamId` if it is.
+    ///
+    /// This covers DATA, HEADERS, PRIORITY, RST_STREAM, PUSH_PROMISE and
+    /// CONTINUATION frames, which receivers must reject on stream 0.
+    #[inline]
+    pub fn validate_stream_id(&self) -> Result<(), Error> {
+        if self.kind.is_stream_level() && self.id.0 == 0 {
+            Err(Error::ZeroStreamId)
+        } else {
+            Ok(())
+        }
+    }
+
     #[inline]
-    pub fn encode(&self, buf: &mut [u8]) {
+    pub const fn encode(&self, buf: &mut [u8]) {
         ::encode_u24(buf, self.length);
         buf[3] = self.kind.encode();
-        buf[4] = self.flag.bits();
-        self.id.encode(&mut buf[5..]);
+        buf[4] = self.flag.encode();
+        self.id.encode(buf.split_at_mut(5).1);
     }
 
     #[inline]
     #[cfg(feature = "random")]
-    fn rand_for_payload<R: Rng>(rng: &mut R, payload: &Payload) -> FrameHeader {
+    fn rand_for_payload<R: Rng + ?Sized>(rng: &mut R, payload: &Payload) -> FrameHeader {
         let len = payload.encoded_len();
 
         if len > 1 << 24 {
@@ -82,6 +654,28 @@ impl FrameHeader {
     }
 }
 
+impl<'a> TryFrom<&'a [u8]> for FrameHeader {
+    type Error = Error;
+
+    fn try_from(buf: &'a [u8]) -> Result<FrameHeader, Error> {
+        FrameHeader::parse(buf)
+    }
+}
+
+impl fmt::Display for FrameHeader {
+    /// A one-line summary, such as `HEADERS stream=5 len=312 END_HEADERS`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        try!(write!(f, "{} stream={} len={}", self.kind, self.id.0, self.length));
+
+        let flags = self.flags().to_string();
+        if !flags.is_empty() {
+            try!(write!(f, " {}", flags));
+        }
+
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 #[cfg(feature = "random")]
 pub fn rand_for_payload(payload: &Payload) -> FrameHeader {
@@ -89,21 +683,22 @@ pub fn rand_for_payload(payload: &Payload) -> FrameHeader {
 }
 
 #[cfg(feature = "random")]
-impl Rand for FrameHeader {
-    fn rand<R: Rng>(rng: &mut R) -> Self {
+impl Distribution<FrameHeader> for Standard {
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> FrameHeader {
         FrameHeader {
-            length: rng.gen_range(0, 1 << 24),
-            kind: Kind::new(rng.gen_range(0, 9)),
-            flag: *rng.choose(&[Flag::padded() | Flag::priority()])
+            length: rng.gen_range(0..1 << 24),
+            kind: Kind::new(rng.gen_range(0..9)),
+            flag: *[Flag::padded() | Flag::priority()].choose(rng)
                     .unwrap_or(&Flag::empty()),
-            id: StreamIdentifier(rng.gen_range(0, 1 << 31))
+            id: StreamIdentifier(rng.gen_range(0..=::consts::MAX_STREAM_ID))
         }
     }
 }
 
+/// Leaks each payload, as `Payload`'s distribution does.
 #[cfg(feature = "random")]
-impl Rand for Frame<'static> {
-    fn rand<R: Rng>(rng: &mut R) -> Self {
+impl Distribution<Frame<'static>> for Standard {
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> Frame<'static> {
         let payload = rng.gen::<Payload>();
         let header = FrameHeader::rand_for_payload(rng, &payload);
 
@@ -114,11 +709,84 @@ impl Rand for Frame<'static> {
     }
 }
 
+/// Generate a random frame whose header matches its payload.
+///
+/// Nothing is leaked, and frames are reproducible when `rng` is seeded,
+/// such as with `StdRng::seed_from_u64`.
+#[cfg(feature = "random")]
+pub fn random_frame<R: Rng + ?Sized>(rng: &mut R) -> OwnedFrame {
+    let payload = rng.gen::<OwnedPayload>();
+    let header = FrameHeader::rand_for_payload(rng, &payload.as_payload());
+
+    OwnedFrame {
+        header: header,
+        payload: payload
+    }
+}
+
+#[cfg(feature = "quickcheck")]
+impl quickcheck::Arbitrary for FrameHeader {
+    fn arbitrary(g: &mut Gen) -> FrameHeader {
+        FrameHeader {
+            length: u32::arbitrary(g) & ((1 << 24) - 1),
+            kind: Kind::new(u8::arbitrary(g) % 10),
+            flag: *g.choose(&[Flag::empty(), Flag::padded() | Flag::priority()]).unwrap(),
+            id: StreamIdentifier(u32::arbitrary(g) & ::consts::MAX_STREAM_ID)
+        }
+    }
+}
+
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for FrameHeader {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        Ok(FrameHeader {
+            length: try!(u.int_in_range(0..=(1 << 24) - 1)),
+            kind: Kind::new(try!(u.arbitrary())),
+            flag: Flag::new_lenient(try!(u.arbitrary())).0,
+            id: StreamIdentifier(try!(u.arbitrary::<u32>()) & ::consts::MAX_STREAM_ID)
+        })
+    }
+}
+
+/// Frames whose header matches their payload, so they encode correctly.
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for Frame<'a> {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        let payload = try!(u.arbitrary::<Payload>());
+
+        // Padding is never encoded, so only priority can be implied by
+        // the payload.
+        let mut flag = Flag::new_lenient(try!(u.arbitrary())).0 - Flag::padded() - Flag::priority();
+        if payload.priority().is_some() { flag = flag | Flag::priority() }
+
+        Ok(Frame {
+            header: FrameHeader {
+                length: payload.encoded_len() as u32,
+                kind: payload.kind(),
+                flag: flag,
+                id: StreamIdentifier(try!(u.arbitrary::<u32>()) & ::consts::MAX_STREAM_ID)
+            },
+            payload: payload
+        })
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl ::defmt::Format for FrameHeader {
+    fn format(&self, f: ::defmt::Formatter) {
+        ::defmt::write!(f, "{} stream={=u32} len={=u32} flags={}",
+                        self.kind, self.id.0, self.length, self.flag)
+    }
+}
+
 #[cfg(test)]
 mod test {
-    use {Kind, Flag, FrameHeader, StreamIdentifier};
-    #[cfg(feature = "random")]
-    use {Frame};
+    use std::convert::TryFrom;
+
+    use {Kind, Flag, Frame, Frames, FrameHeader, Payload, StreamIdentifier, Error,
+         ParseError, HttpError, Setting, SettingIdentifier, Settings, parse_many,
+         encode_data_header};
+    use super::ConsistencyError;
 
     #[test]
     fn test_frame_header_parse_empty() {
@@ -139,7 +807,7 @@ mod test {
     fn test_frame_header_parse_full() {
         assert_eq!(FrameHeader {
             length: 16777215,
-            kind: Kind::Unregistered,
+            kind: Kind::Unregistered(0xFF),
             flag: Flag::empty(),
             id: StreamIdentifier(2147483647)
         }, FrameHeader::parse(&[
@@ -165,6 +833,434 @@ mod test {
         ]).unwrap());
     }
 
+    #[test]
+    fn test_frame_header_parse_strict() {
+        let mut buf = [0x0, 0x0, 0x0, 0x9, 0x0, 0x0, 0x0, 0x0, 0x1];
+        assert_eq!(FrameHeader::parse_strict(&buf).unwrap().kind, Kind::Continuation);
+
+        buf[3] = 0xa;
+        assert_eq!(FrameHeader::parse_strict(&buf), Err(Error::BadKind(0xa)));
+        assert_eq!(FrameHeader::parse(&buf).unwrap().kind, Kind::Unregistered(0xa));
+    }
+
+    #[test]
+    fn test_frame_header_ignores_unknown_flags() {
+        let buf = [0x0, 0x0, 0x0, 0x1, 0x6, 0x0, 0x0, 0x0, 0x1];
+        assert_eq!(FrameHeader::parse(&buf).unwrap().flag, Flag::end_headers());
+        assert_eq!(FrameHeader::parse_strict(&buf), Err(Error::BadFlag(0x6)));
+    }
+
+    #[test]
+    fn test_display() {
+        let header = FrameHeader {
+            length: 312,
+            kind: Kind::Headers,
+            flag: Flag::end_headers() | Flag::end_stream(),
+            id: StreamIdentifier(5)
+        };
+        assert_eq!(header.to_string(), "HEADERS stream=5 len=312 END_STREAM | END_HEADERS");
+
+        assert_eq!(Frame::settings_ack().to_string(), "SETTINGS stream=0 len=0 ACK");
+        assert_eq!(Frame::ping([0, 0, 0, 0, 0, 0, 0, 42]).to_string(),
+                   "PING stream=0 len=8 data=000000000000002a");
+        assert_eq!(Frame::goaway(StreamIdentifier(3), HttpError::Protocol, Some("bad")).to_string(),
+                   "GOAWAY stream=0 len=11 last=3 error=PROTOCOL_ERROR debug=\"bad\"");
+
+        let unknown = Frame {
+            header: FrameHeader {
+                length: 2,
+                kind: Kind::Unregistered(0xc),
+                flag: Flag::end_stream(),
+                id: StreamIdentifier(0)
+            },
+            payload: Payload::Unregistered { kind: 0xc, data: &[1, 2] }
+        };
+        assert_eq!(unknown.to_string(), "UNKNOWN(0x0c) stream=0 len=2 data=2");
+    }
+
+    #[test]
+    fn test_goaway() {
+        let goaway = Frame::goaway(StreamIdentifier(5), HttpError::EnhanceYourCalm,
+                                   Some("too many resets"));
+
+        let mut buf = vec![0; goaway.encoded_len()];
+        goaway.encode(&mut buf);
+
+        let header = FrameHeader::parse(&buf).unwrap();
+        let frame = Frame::parse(header, &buf[9..]).unwrap();
+        assert_eq!(frame, goaway);
+        assert_eq!(frame.payload.error(), Some(HttpError::EnhanceYourCalm));
+        assert_eq!(frame.payload.debug_data().unwrap(), "too many resets");
+
+        let bare = Frame::goaway(StreamIdentifier(0), HttpError::NoError, None);
+        assert_eq!(bare.header.length, 8);
+        assert_eq!(bare.payload.debug_data().unwrap(), "");
+    }
+
+    #[test]
+    fn test_ping_ack() {
+        let ping = Frame::ping([1, 2, 3, 4, 5, 6, 7, 8]);
+        assert!(!ping.is_ping_ack());
+
+        let ack = ping.ping_ack().unwrap();
+        assert!(ack.is_ping_ack());
+        assert_eq!(ack.header.id, StreamIdentifier(0));
+        assert_eq!(ack.payload.ping_data(), Some([1, 2, 3, 4, 5, 6, 7, 8]));
+
+        let mut buf = [0; 17];
+        ack.encode(&mut buf);
+        assert_eq!(&buf[..9], &[0x0, 0x0, 0x8, 0x6, 0x1, 0x0, 0x0, 0x0, 0x0]);
+
+        assert_eq!(ack.ping_ack(), None);
+        assert_eq!(Frame::settings_ack().ping_ack(), None);
+    }
+
+    #[test]
+    fn test_frames_reports_context() {
+        let buf = [
+            0x0, 0x0, 0x0, 0x4, 0x1, 0x0, 0x0, 0x0, 0x0, // SETTINGS ACK
+            0x0, 0x0, 0x2, 0x8, 0x0, 0x0, 0x0, 0x0, 0x3, 0x0, 0x1, // bad WINDOW_UPDATE
+            0x0, 0x0, 0x0, 0x4, 0x1, 0x0, 0x0, 0x0, 0x0 // SETTINGS ACK
+        ];
+
+        let mut frames = Frames::new(&buf);
+        assert_eq!(frames.next(), Some(Ok(Frame::settings_ack())));
+
+        let error = frames.next().unwrap().unwrap_err();
+        assert_eq!(error, ParseError {
+            error: Error::InvalidPayloadLength,
+            kind: Some(Kind::WindowUpdate),
+            id: Some(StreamIdentifier(3)),
+            frame_offset: 9,
+            offset: 9
+        });
+        assert_eq!(error.to_string(),
+                   "InvalidPayloadLength at offset 9 in WindowUpdate frame on stream 3");
+
+        assert_eq!(frames.next(), None);
+    }
+
+    #[test]
+    fn test_frames_after_preface() {
+        let mut buf = ::PREFACE.to_vec();
+        buf.extend_from_slice(&[0x0, 0x0, 0x0, 0x4, 0x1, 0x0, 0x0, 0x0, 0x0]);
+
+        let mut frames = Frames::after_preface(&buf).unwrap();
+        assert_eq!(frames.offset(), 24);
+        assert_eq!(frames.next(), Some(Ok(Frame::settings_ack())));
+        assert_eq!(frames.offset(), 33);
+
+        assert_eq!(Frames::after_preface(&buf[..10]).err(), Some(Error::Short(14)));
+        assert_eq!(Frames::after_preface(&buf[24..]).err(), Some(Error::BadPreface));
+    }
+
+    #[test]
+    fn test_frames_stops_at_partial_frame() {
+        let buf = [0x0, 0x0, 0x0, 0x4, 0x1, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x8];
+        let mut frames = Frames::new(&buf);
+
+        assert_eq!(frames.next(), Some(Ok(Frame::settings_ack())));
+        assert_eq!(frames.next(), None);
+        assert_eq!(frames.offset(), 9);
+    }
+
+    #[test]
+    fn test_parse_many() {
+        let mut buf = vec![];
+        for frame in &[Frame::settings_ack(), Frame::ping([1, 2, 3, 4, 5, 6, 7, 8]),
+                       Frame::goaway(StreamIdentifier(1), HttpError::Protocol, Some("x"))] {
+            let start = buf.len();
+            buf.resize(start + frame.encoded_len(), 0);
+            frame.encode(&mut buf[start..]);
+        }
+        let complete = buf.len();
+        buf.extend_from_slice(&[0x0, 0x0, 0x8, 0x6]);
+
+        let mut frames = vec![];
+        assert_eq!(parse_many(&buf, &mut frames), Ok(complete));
+        assert_eq!(frames, Frames::new(&buf).collect::<Result<Vec<_>, _>>().unwrap());
+
+        let bad = [
+            0x0, 0x0, 0x0, 0x4, 0x1, 0x0, 0x0, 0x0, 0x0, // SETTINGS ACK
+            0x0, 0x0, 0x2, 0x8, 0x0, 0x0, 0x0, 0x0, 0x3, 0x0, 0x1 // bad WINDOW_UPDATE
+        ];
+        let mut frames = vec![];
+        assert_eq!(parse_many(&bad, &mut frames), Err(ParseError {
+            error: Error::InvalidPayloadLength,
+            kind: Some(Kind::WindowUpdate),
+            id: Some(StreamIdentifier(3)),
+            frame_offset: 9,
+            offset: 9
+        }));
+        assert_eq!(frames, vec![Frame::settings_ack()]);
+
+        // Errors inside a payload point at the byte at fault.
+        let padded = [
+            0x0, 0x0, 0x0, 0x4, 0x1, 0x0, 0x0, 0x0, 0x0, // SETTINGS ACK
+            0x0, 0x0, 0x3, 0x0, 0x8, 0x0, 0x0, 0x0, 0x1, 0x3, 0x0, 0x0 // too much padding
+        ];
+        let error = parse_many(&padded, &mut vec![]).unwrap_err();
+        assert_eq!((error.error, error.frame_offset, error.offset),
+                   (Error::TooMuchPadding(3), 9, 18));
+
+        let settings = [0x0, 0x0, 0x8, 0x4, 0x0, 0x0, 0x0, 0x0, 0x0,
+                        0x0, 0x1, 0x0, 0x0, 0x0, 0x0, 0x0, 0x2];
+        let error = Frames::new(&settings).next().unwrap().unwrap_err();
+        assert_eq!((error.error, error.frame_offset, error.offset),
+                   (Error::PartialSettingLength, 0, 15));
+    }
+
+    #[test]
+    fn test_validate() {
+        let settings = [Setting::new(SettingIdentifier::EnablePush, 0)];
+        let mut frame = Frame {
+            header: FrameHeader {
+                length: 6,
+                kind: Kind::Settings,
+                flag: Flag::empty(),
+                id: StreamIdentifier(0)
+            },
+            payload: Payload::Settings(Settings::new(&settings))
+        };
+        assert_eq!(frame.validate(), Ok(()));
+        assert_eq!(Frame::goaway(StreamIdentifier(1), HttpError::NoError, Some("x")).validate(),
+                   Ok(()));
+
+        frame.header.flag = Flag::ack();
+        assert_eq!(frame.validate(), Err(ConsistencyError::AckWithSettings));
+        frame.header.flag = Flag::end_headers();
+        assert_eq!(frame.validate(), Err(ConsistencyError::BadFlag(0x4)));
+        frame.header.length = 12;
+        assert_eq!(frame.validate(), Err(ConsistencyError::LengthMismatch(6)));
+        frame.header.kind = Kind::Ping;
+        assert_eq!(frame.validate(), Err(ConsistencyError::KindMismatch(Kind::Settings)));
+
+        let headers = Frame {
+            header: FrameHeader {
+                length: 2,
+                kind: Kind::Headers,
+                flag: Flag::end_headers() | Flag::priority(),
+                id: StreamIdentifier(1)
+            },
+            payload: Payload::Headers { priority: None, block: &[0x82, 0x86] }
+        };
+        assert_eq!(headers.validate(), Err(ConsistencyError::PriorityMismatch));
+
+        let data = Frame {
+            header: FrameHeader {
+                length: 1,
+                kind: Kind::Data,
+                flag: Flag::padded(),
+                id: StreamIdentifier(1)
+            },
+            payload: Payload::Data { data: &[0] }
+        };
+        assert_eq!(data.validate(), Err(ConsistencyError::Padded));
+    }
+
+    #[test]
+    fn test_canonicalize() {
+        let padded = [0x0, 0x0, 0x5, 0x0, 0xd, 0x0, 0x0, 0x0, 0x1, 0x2, b'h', b'i', 0x0, 0x0];
+        let plain = [0x0, 0x0, 0x2, 0x0, 0x1, 0x0, 0x0, 0x0, 0x1, b'h', b'i'];
+        let padded = Frame::try_from(&padded[..]).unwrap();
+        let plain = Frame::try_from(&plain[..]).unwrap();
+
+        assert!(padded != plain);
+        assert!(padded.semantically_eq(&plain));
+        assert_eq!(padded.canonicalize().header, plain.header);
+
+        let first = [Setting::new(SettingIdentifier::EnablePush, 1),
+                     Setting::new(SettingIdentifier::HeaderTableSize, 0),
+                     Setting::new(SettingIdentifier::EnablePush, 0)];
+        let second = [Setting::new(SettingIdentifier::HeaderTableSize, 0),
+                      Setting::new(SettingIdentifier::EnablePush, 0)];
+        let settings = |settings| Frame {
+            header: FrameHeader {
+                length: 0,
+                kind: Kind::Settings,
+                flag: Flag::empty(),
+                id: StreamIdentifier(0)
+            },
+            payload: Payload::Settings(Settings::new(settings))
+        };
+
+        assert!(settings(&first).semantically_eq(&settings(&second)));
+        assert!(!settings(&first).semantically_eq(&settings(&second[..1])));
+        assert_eq!(settings(&first).canonicalize().header.length, 12);
+    }
+
+    #[test]
+    fn test_try_from() {
+        let frame = Frame::ping([1, 2, 3, 4, 5, 6, 7, 8]);
+        let mut buf = Vec::from(frame);
+        buf.extend_from_slice(&[0xff; 3]);
+
+        assert_eq!(FrameHeader::try_from(&buf[..]), Ok(frame.header));
+        assert_eq!(Frame::try_from(&buf[..]), Ok(frame));
+        assert_eq!(Frame::try_from(&buf[..12]), Err(Error::Short(5)));
+        assert_eq!(FrameHeader::try_from(&buf[..4]), Err(Error::Short(5)));
+    }
+
+    #[cfg(feature = "unchecked")]
+    #[test]
+    fn test_parse_unchecked() {
+        let frame = Frame::goaway(StreamIdentifier(1), HttpError::Protocol, Some("x"));
+        let mut buf = vec![0; frame.encoded_len()];
+        frame.encode(&mut buf);
+        buf[5] = 0x80;
+
+        let header = unsafe { FrameHeader::parse_unchecked(&buf) };
+        assert_eq!(header, FrameHeader::parse(&buf).unwrap());
+        assert_eq!(unsafe { Frame::parse_unchecked(header, &buf[9..]) }, Ok(frame));
+
+        buf[2] = 0x3;
+        let header = unsafe { FrameHeader::parse_unchecked(&buf) };
+        assert_eq!(unsafe { Frame::parse_unchecked(header, &buf[9..]) },
+                   Err(Error::PayloadLengthTooShort));
+    }
+
+    #[test]
+    fn test_encode_data_header() {
+        let frame = Frame {
+            header: FrameHeader {
+                length: 5,
+                kind: Kind::Data,
+                flag: Flag::end_stream(),
+                id: StreamIdentifier(3)
+            },
+            payload: Payload::Data { data: b"hello" }
+        };
+        let mut expected = [0; 14];
+        frame.encode(&mut expected);
+
+        let mut buf = [0; 9];
+        assert_eq!(encode_data_header(5, StreamIdentifier(3), Flag::end_stream(), &mut buf), 9);
+        assert_eq!(&buf[..], &expected[..9]);
+    }
+
+    #[test]
+    fn test_const_frames() {
+        const SETTINGS_ACK: Frame<'static> = Frame::settings_ack();
+        const PING: Frame<'static> = Frame::ping([1, 2, 3, 4, 5, 6, 7, 8]);
+        const ENCODED: [u8; 9] = {
+            let mut buf = [0; 9];
+            SETTINGS_ACK.header.encode(&mut buf);
+            buf
+        };
+        const HEADER: Result<FrameHeader, Error> = FrameHeader::parse(&ENCODED);
+
+        assert!(SETTINGS_ACK.is_settings_ack());
+        assert_eq!(PING.payload.ping_data(), Some([1, 2, 3, 4, 5, 6, 7, 8]));
+        assert_eq!(ENCODED, [0x0, 0x0, 0x0, 0x4, 0x1, 0x0, 0x0, 0x0, 0x0]);
+        assert_eq!(HEADER, Ok(SETTINGS_ACK.header));
+    }
+
+    #[test]
+    fn test_validate_stream_id() {
+        let mut header = FrameHeader {
+            length: 0,
+            kind: Kind::Continuation,
+            flag: Flag::empty(),
+            id: StreamIdentifier(0)
+        };
+        assert_eq!(header.validate_stream_id(), Err(Error::ZeroStreamId));
+
+        header.id = StreamIdentifier(1);
+        assert_eq!(header.validate_stream_id(), Ok(()));
+
+        header.kind = Kind::WindowUpdate;
+        header.id = StreamIdentifier(0);
+        assert_eq!(header.validate_stream_id(), Ok(()));
+    }
+
+    #[test]
+    fn test_header_validate() {
+        let header = |length, kind, flag| FrameHeader {
+            length: length,
+            kind: kind,
+            flag: flag,
+            id: StreamIdentifier(1)
+        };
+
+        assert_eq!(header(8, Kind::Ping, Flag::empty()).validate(), Ok(()));
+        assert_eq!(header(9, Kind::Ping, Flag::empty()).validate(),
+                   Err(Error::InvalidPayloadLength));
+        assert_eq!(header(3, Kind::Reset, Flag::empty()).validate(),
+                   Err(Error::InvalidPayloadLength));
+        assert_eq!(header(4, Kind::WindowUpdate, Flag::empty()).validate(), Ok(()));
+        assert_eq!(header(12, Kind::Settings, Flag::empty()).validate(), Ok(()));
+        assert_eq!(header(7, Kind::Settings, Flag::empty()).validate(),
+                   Err(Error::PartialSettingLength));
+        assert_eq!(header(6, Kind::Settings, Flag::ack()).validate(),
+                   Err(Error::InvalidPayloadLength));
+
+        let flag = Flag::padded() | Flag::priority();
+        assert_eq!(header(6, Kind::Headers, flag).validate(), Ok(()));
+        assert_eq!(header(5, Kind::Headers, flag).validate(),
+                   Err(Error::PayloadLengthTooShort));
+        assert_eq!(header(0, Kind::Data, Flag::padded()).validate(),
+                   Err(Error::PayloadLengthTooShort));
+        assert_eq!(header(0, Kind::Continuation, Flag::padded()).validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_short_reports_missing_bytes() {
+        assert_eq!(FrameHeader::parse(&[0x0, 0x0, 0x4]), Err(Error::Short(6)));
+
+        let header = FrameHeader::parse(&[0x0, 0x0, 0x8, 0x6, 0x0, 0x0, 0x0, 0x0, 0x0]).unwrap();
+        assert_eq!(Frame::parse(header, &[0x1, 0x2, 0x3]), Err(Error::Short(5)));
+    }
+
+    #[test]
+    fn test_peek_length() {
+        assert_eq!(FrameHeader::peek_length(&[0x0, 0x1]), Err(Error::Short(1)));
+        assert_eq!(FrameHeader::peek_length(&[0x0, 0x1, 0x2]), Ok(9 + 258));
+
+        let ping = Vec::from(Frame::ping([1; 8]));
+        assert_eq!(FrameHeader::peek_length(&ping[..4]), Ok(ping.len()));
+    }
+
+    #[test]
+    fn test_validate_flags() {
+        let mut header = FrameHeader {
+            length: 8,
+            kind: Kind::Ping,
+            flag: Flag::ack(),
+            id: StreamIdentifier(0)
+        };
+        assert_eq!(header.validate_flags(), Ok(()));
+
+        header.flag = Flag::ack() | Flag::priority() | Flag::padded();
+        assert_eq!(header.validate_flags(),
+                   Err(Error::BadFlag((Flag::priority() | Flag::padded()).bits())));
+    }
+
+    #[test]
+    fn test_settings_ack() {
+        let ack = Frame::settings_ack();
+        assert!(ack.is_settings_ack());
+
+        let mut buf = [0; 9];
+        assert_eq!(ack.encode(&mut buf), 9);
+        assert_eq!(buf, [0x0, 0x0, 0x0, 0x4, 0x1, 0x0, 0x0, 0x0, 0x0]);
+
+        let header = FrameHeader::parse(&buf).unwrap();
+        assert_eq!(Frame::parse(header, &[]).unwrap(), ack);
+    }
+
+    #[test]
+    fn test_settings_ack_with_payload_is_rejected() {
+        let header = FrameHeader {
+            length: 6,
+            kind: Kind::Settings,
+            flag: Flag::ack(),
+            id: StreamIdentifier(0)
+        };
+
+        assert_eq!(Frame::parse(header, &[0x0, 0x1, 0x0, 0x0, 0x10, 0x0]),
+                   Err(Error::InvalidPayloadLength));
+    }
+
     #[cfg(feature = "random")]
     #[test]
     fn test_frame_header_encoding() {
@@ -202,63 +1298,46 @@ mod test {
     #[test]
     fn no_frame_encoding_test_because_no_rand() {}
 
-    #[bench]
     #[cfg(feature = "random")]
-    fn bench_frame_parse(b: &mut ::test::Bencher) {
-        // Each iter = 5 frames
-        let frames = vec![::rand::random::<Frame>(); 5];
-        let bufs = frames.iter().map(|frame| {
-            let mut buf = vec![0; 2000];
-            frame.encode(&mut buf);
-            buf
-        }).collect::<Vec<_>>();
-
-        b.bytes = frames.iter().map(|frame| frame.encoded_len() as u64)
-            .fold(0, |a, b| a + b);
-
-        b.iter(|| {
-            for buf in &bufs {
-                Frame::parse(FrameHeader::parse(&buf[..9]).unwrap(),
-                             &buf[9..]).unwrap();
-            }
-        });
-    }
+    #[test]
+    fn test_random_frames_are_reproducible() {
+        use rand::SeedableRng;
+        use rand::rngs::StdRng;
 
-    #[bench]
-    #[cfg(feature = "random")]
-    fn bench_frame_encode(b: &mut ::test::Bencher) {
-        let frames = vec![::rand::random::<Frame>(); 5];
+        let generate = |seed| {
+            let mut rng = StdRng::seed_from_u64(seed);
+            (0..100).map(|_| ::random_frame(&mut rng)).collect::<Vec<_>>()
+        };
 
-        b.bytes = frames.iter().map(|frame| frame.encoded_len() as u64)
-            .fold(0, |a, b| a + b);
+        let frames = generate(7);
+        assert_eq!(frames, generate(7));
+        assert!(frames != generate(8));
 
-        let mut buf = vec![0; 2000];
-        b.iter(|| {
-            for frame in &frames {
-                frame.encode(&mut buf);
-                ::test::black_box(&buf);
-            }
-        });
+        let mut buf = vec![0; 5000];
+        for frame in &frames {
+            let frame = frame.as_frame();
+            frame.encode(&mut buf);
+            assert_eq!(Frame::parse(FrameHeader::parse(&buf).unwrap(), &buf[9..]), Ok(frame));
+        }
     }
 
-    #[bench]
-    fn bench_frame_header_parse(b: &mut ::test::Bencher) {
-        b.bytes = ::FRAME_HEADER_BYTES as u64;
+    #[cfg(feature = "arbitrary")]
+    #[test]
+    fn test_arbitrary_frame_encoding() {
+        use arbitrary::Unstructured;
 
-        b.iter(|| {
-            let mut buf = &[
-                0x1, 0x2, 0x3, // length
-                0x4, // type/kind
-                0x1, // flags
-                0x6, 0x7, 0x8, 0x9 // reserved bit + stream identifier
-            ];
+        let input = (0..20000u32).map(|i| (i.wrapping_mul(2654435761) >> 13) as u8)
+            .collect::<Vec<u8>>();
+        let mut u = Unstructured::new(&input);
+        let mut buf = vec![0; input.len() + 9];
 
-            // Prevent constant propagation.
-            buf = ::test::black_box(buf);
+        while let Ok(frame) = u.arbitrary::<Frame>() {
+            let len = frame.encode(&mut buf);
+            assert_eq!(len, frame.header.length as usize + 9);
+            assert_eq!(Frame::parse(FrameHeader::parse(&buf).unwrap(), &buf[9..len]),
+                       Ok(frame));
 
-            // Prevent dead code elimination.
-            ::test::black_box(FrameHeader::parse(buf).unwrap());
-        });
+            if u.is_empty() { break }
+        }
     }
 }
-
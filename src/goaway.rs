@@ -0,0 +1,161 @@
+use std::cmp;
+
+use {Frame, StreamIdentifier, HttpError, Role};
+
+/// Errors produced by a `GoAwayTracker`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum GoAwayError {
+    /// The peer sent a GOAWAY with a larger last stream identifier than an
+    /// earlier one, holding the earlier one. This should be treated as a
+    /// connection error of type PROTOCOL_ERROR.
+    Increased(StreamIdentifier)
+}
+
+/// Tracks the last stream identifiers of GOAWAY frames in both directions.
+///
+/// The GOAWAY we send names the highest stream the peer initiated or
+/// promised that we have processed. The GOAWAY we receive tells us which
+/// of our streams the peer never processed, and so may be retried on a
+/// new connection.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct GoAwayTracker {
+    role: Role,
+    processed: StreamIdentifier,
+    promised: StreamIdentifier,
+    sent: Option<StreamIdentifier>,
+    received: Option<StreamIdentifier>
+}
+
+impl GoAwayTracker {
+    pub fn new(role: Role) -> GoAwayTracker {
+        GoAwayTracker {
+            role: role,
+            processed: StreamIdentifier(0),
+            promised: StreamIdentifier(0),
+            sent: None,
+            received: None
+        }
+    }
+
+    /// Record that we have processed, or begun processing, a stream the
+    /// peer initiated.
+    pub fn processed(&mut self, id: StreamIdentifier) {
+        self.processed = cmp::max(self.processed, id);
+    }
+
+    /// Record a stream the peer reserved with PUSH_PROMISE.
+    pub fn promised(&mut self, id: StreamIdentifier) {
+        self.promised = cmp::max(self.promised, id);
+    }
+
+    /// The highest stream the peer initiated that we have processed.
+    pub fn last_processed(&self) -> StreamIdentifier {
+        self.processed
+    }
+
+    /// The highest stream the peer has promised.
+    pub fn last_promised(&self) -> StreamIdentifier {
+        self.promised
+    }
+
+    /// The last stream identifier for a GOAWAY sent now.
+    ///
+    /// This never exceeds that of a GOAWAY already sent, as RFC 7540,
+    /// Section 6.8 requires.
+    pub fn last_stream_id(&self) -> StreamIdentifier {
+        let last = cmp::max(self.processed, self.promised);
+        self.sent.map_or(last, |sent| cmp::min(sent, last))
+    }
+
+    /// A GOAWAY frame for the streams processed so far, which is recorded
+    /// as sent.
+    pub fn goaway<'a>(&mut self, error: HttpError, debug: Option<&'a str>) -> Frame<'a> {
+        let last = self.last_stream_id();
+        self.sent = Some(last);
+        Frame::goaway(last, error, debug)
+    }
+
+    /// The last stream identifier of the GOAWAY we sent, if any.
+    pub fn sent(&self) -> Option<StreamIdentifier> {
+        self.sent
+    }
+
+    /// Record a GOAWAY from the peer, checking that it does not increase
+    /// the last stream identifier of one received before.
+    pub fn recv(&mut self, last: StreamIdentifier) -> Result<(), GoAwayError> {
+        match self.received {
+            Some(previous) if last > previous => Err(GoAwayError::Increased(previous)),
+            _ => {
+                self.received = Some(last);
+                Ok(())
+            }
+        }
+    }
+
+    /// The last stream identifier of the latest GOAWAY from the peer, if any.
+    pub fn received(&self) -> Option<StreamIdentifier> {
+        self.received
+    }
+
+    /// Whether a stream we initiated was never processed by the peer, so
+    /// may be retried on a new connection. Only true after a GOAWAY.
+    pub fn is_retryable(&self, id: StreamIdentifier) -> bool {
+        let local = match self.role {
+            Role::Client => id.0 % 2 == 1,
+            Role::Server => id.0 != 0 && id.0 % 2 == 0
+        };
+
+        local && self.received.map_or(false, |last| id > last)
+    }
+
+    /// The in-flight streams which may be retried, in the order given.
+    pub fn retryable<I>(&self, in_flight: I) -> Vec<StreamIdentifier>
+    where I: IntoIterator<Item = StreamIdentifier> {
+        in_flight.into_iter().filter(|&id| self.is_retryable(id)).collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use {StreamIdentifier, HttpError, Payload, Role};
+    use super::{GoAwayTracker, GoAwayError};
+
+    #[test]
+    fn test_goaway_tracker() {
+        let mut tracker = GoAwayTracker::new(Role::Client);
+        assert!(!tracker.is_retryable(StreamIdentifier(5)));
+
+        tracker.recv(StreamIdentifier(5)).unwrap();
+        assert!(!tracker.is_retryable(StreamIdentifier(5)));
+        assert!(tracker.is_retryable(StreamIdentifier(7)));
+        assert!(!tracker.is_retryable(StreamIdentifier(8)));
+        assert_eq!(tracker.retryable((1..6).map(|id| StreamIdentifier(id * 2 - 1))),
+                   [StreamIdentifier(7), StreamIdentifier(9)]);
+
+        assert_eq!(tracker.recv(StreamIdentifier(7)),
+                   Err(GoAwayError::Increased(StreamIdentifier(5))));
+        tracker.recv(StreamIdentifier(3)).unwrap();
+        assert_eq!(tracker.received(), Some(StreamIdentifier(3)));
+        assert!(tracker.is_retryable(StreamIdentifier(5)));
+    }
+
+    #[test]
+    fn test_sent_goaway_never_increases() {
+        let mut tracker = GoAwayTracker::new(Role::Server);
+        tracker.processed(StreamIdentifier(3));
+        tracker.processed(StreamIdentifier(1));
+        assert_eq!(tracker.last_stream_id(), StreamIdentifier(3));
+
+        let goaway = tracker.goaway(HttpError::NoError, None);
+        match goaway.payload {
+            Payload::GoAway { last, .. } => assert_eq!(last, StreamIdentifier(3)),
+            _ => panic!("expected GOAWAY")
+        }
+
+        tracker.processed(StreamIdentifier(9));
+        assert_eq!(tracker.last_processed(), StreamIdentifier(9));
+        assert_eq!(tracker.last_stream_id(), StreamIdentifier(3));
+        assert_eq!(tracker.sent(), Some(StreamIdentifier(3)));
+    }
+}
@@ -0,0 +1,179 @@
+//! The length-prefixed messages of gRPC, carried in DATA payloads.
+//!
+//! Each message is a compressed flag byte and a 4 byte big-endian length,
+//! followed by the message. Messages may be split across DATA frames, and
+//! one DATA frame may hold several.
+
+use byteorder::{BigEndian, ByteOrder};
+
+// The flag byte and the length.
+const PREFIX_BYTES: usize = 5;
+
+// The largest message accepted by default, as in most gRPC libraries.
+const DEFAULT_MAX_MESSAGE_SIZE: u32 = 4 * 1024 * 1024;
+
+/// Errors produced while splitting gRPC messages.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum GrpcError {
+    /// The compressed flag was neither 0 nor 1.
+    InvalidFlag(u8),
+
+    /// A message was longer than the maximum message size.
+    TooLarge(u32),
+
+    /// The stream ended in the middle of a message.
+    Truncated
+}
+
+/// A gRPC message, borrowed from the buffer it was parsed from.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Message<'a> {
+    pub compressed: bool,
+    pub data: &'a [u8]
+}
+
+impl<'a> Message<'a> {
+    /// Parse the message at the start of `buf`, returning it with the
+    /// number of bytes it took, or `None` if `buf` holds only part of it.
+    pub fn parse(buf: &'a [u8]) -> Result<Option<(Message<'a>, usize)>, GrpcError> {
+        if buf.len() < PREFIX_BYTES { return Ok(None) }
+
+        let compressed = try!(parse_flag(buf[0]));
+        let len = BigEndian::read_u32(&buf[1..PREFIX_BYTES]) as usize;
+        if buf.len() < PREFIX_BYTES + len { return Ok(None) }
+
+        Ok(Some((Message {
+            compressed: compressed,
+            data: &buf[PREFIX_BYTES..PREFIX_BYTES + len]
+        }, PREFIX_BYTES + len)))
+    }
+
+    /// The number of bytes `encode` appends.
+    pub fn encoded_len(&self) -> usize {
+        PREFIX_BYTES + self.data.len()
+    }
+
+    /// Append the length-prefixed message to `buf`.
+    pub fn encode(&self, buf: &mut Vec<u8>) {
+        let mut prefix = [0; PREFIX_BYTES];
+        prefix[0] = self.compressed as u8;
+        BigEndian::write_u32(&mut prefix[1..], self.data.len() as u32);
+
+        buf.extend_from_slice(&prefix);
+        buf.extend_from_slice(self.data);
+    }
+}
+
+/// Reassembles gRPC messages from the DATA payloads of one stream.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MessageDecoder {
+    buf: Vec<u8>,
+    max_message_size: u32
+}
+
+impl MessageDecoder {
+    pub fn new() -> MessageDecoder {
+        MessageDecoder {
+            buf: Vec::new(),
+            max_message_size: DEFAULT_MAX_MESSAGE_SIZE
+        }
+    }
+
+    /// Limit the length of a single message.
+    pub fn set_max_message_size(&mut self, size: u32) {
+        self.max_message_size = size;
+    }
+
+    /// Add the payload of the next DATA frame.
+    pub fn push(&mut self, data: &[u8]) {
+        self.buf.extend_from_slice(data);
+    }
+
+    /// Take the next complete message, as its compressed flag and data.
+    ///
+    /// Oversized messages are rejected as soon as their prefix arrives.
+    pub fn next_message(&mut self) -> Result<Option<(bool, Vec<u8>)>, GrpcError> {
+        if self.buf.len() < PREFIX_BYTES { return Ok(None) }
+
+        let len = BigEndian::read_u32(&self.buf[1..PREFIX_BYTES]);
+        if len > self.max_message_size { return Err(GrpcError::TooLarge(len)) }
+
+        let (compressed, used) = match try!(Message::parse(&self.buf)) {
+            Some((message, used)) => (message.compressed, used),
+            None => return Ok(None)
+        };
+
+        let data = self.buf[PREFIX_BYTES..used].to_vec();
+        self.buf.drain(..used);
+        Ok(Some((compressed, data)))
+    }
+
+    /// The number of bytes of incomplete messages held.
+    pub fn buffered(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// Check that the stream did not end in the middle of a message,
+    /// once END_STREAM is received.
+    pub fn finish(&self) -> Result<(), GrpcError> {
+        if self.buf.is_empty() { Ok(()) } else { Err(GrpcError::Truncated) }
+    }
+}
+
+impl Default for MessageDecoder {
+    fn default() -> MessageDecoder {
+        MessageDecoder::new()
+    }
+}
+
+fn parse_flag(flag: u8) -> Result<bool, GrpcError> {
+    match flag {
+        0 => Ok(false),
+        1 => Ok(true),
+        flag => Err(GrpcError::InvalidFlag(flag))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Message, MessageDecoder, GrpcError};
+
+    #[test]
+    fn test_parse_message() {
+        let mut buf = Vec::new();
+        Message { compressed: false, data: b"hello" }.encode(&mut buf);
+        Message { compressed: true, data: b"" }.encode(&mut buf);
+        assert_eq!(buf, [0, 0, 0, 0, 5, b'h', b'e', b'l', b'l', b'o', 1, 0, 0, 0, 0]);
+
+        assert_eq!(Message::parse(&buf),
+                   Ok(Some((Message { compressed: false, data: b"hello" }, 10))));
+        assert_eq!(Message::parse(&buf[10..]),
+                   Ok(Some((Message { compressed: true, data: b"" }, 5))));
+        assert_eq!(Message::parse(&buf[..9]), Ok(None));
+        assert_eq!(Message::parse(&[2, 0, 0, 0, 0]), Err(GrpcError::InvalidFlag(2)));
+    }
+
+    #[test]
+    fn test_reassemble_messages() {
+        let mut buf = Vec::new();
+        Message { compressed: false, data: b"first" }.encode(&mut buf);
+        Message { compressed: true, data: b"second" }.encode(&mut buf);
+
+        let mut decoder = MessageDecoder::new();
+        decoder.push(&buf[..3]);
+        assert_eq!(decoder.next_message(), Ok(None));
+        decoder.push(&buf[3..14]);
+        assert_eq!(decoder.next_message(), Ok(Some((false, b"first".to_vec()))));
+        assert_eq!(decoder.next_message(), Ok(None));
+        assert_eq!(decoder.finish(), Err(GrpcError::Truncated));
+
+        decoder.push(&buf[14..]);
+        assert_eq!(decoder.next_message(), Ok(Some((true, b"second".to_vec()))));
+        assert_eq!(decoder.finish(), Ok(()));
+
+        decoder.set_max_message_size(4);
+        decoder.push(&buf);
+        assert_eq!(decoder.next_message(), Err(GrpcError::TooLarge(5)));
+    }
+}
@@ -0,0 +1,445 @@
+//! Checks on decoded header lists, per RFC 9113, Sections 8.2 and 8.3,
+//! and typed views of their pseudo-header fields.
+//!
+//! A header list breaking these rules is a malformed message, which must
+//! be treated as a stream error of type PROTOCOL_ERROR.
+
+use {StreamIdentifier, HttpError, ErrorScope, ValidationError, SettingsState};
+
+// Fields which only have meaning on an HTTP/1.1 connection.
+const CONNECTION_SPECIFIC: &'static [&'static [u8]] = &[
+    b"connection", b"proxy-connection", b"keep-alive", b"transfer-encoding", b"upgrade"
+];
+
+/// A rule broken by a header field.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum FieldError {
+    /// The name contains an uppercase letter.
+    UppercaseName,
+
+    /// The name is empty, or contains a control character, space,
+    /// non-ASCII byte, or a colon other than a leading one.
+    InvalidName,
+
+    /// The value contains NUL, CR or LF, or starts or ends with
+    /// whitespace.
+    InvalidValue,
+
+    /// The field is connection-specific, such as `connection` or
+    /// `transfer-encoding`, and may not be used in HTTP/2.
+    ConnectionSpecific,
+
+    /// A `te` field with a value other than `trailers`.
+    InvalidTe
+}
+
+impl FieldError {
+    /// The stream error to respond to the malformed message on `id` with.
+    pub fn malformed(&self, id: StreamIdentifier) -> ValidationError {
+        ValidationError { error: HttpError::Protocol, scope: ErrorScope::Stream(id) }
+    }
+}
+
+/// A rule broken by the pseudo-header fields of a header list, which
+/// also makes the message malformed.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum HeadError {
+    /// A required pseudo-header field is missing: `:method`, `:scheme`
+    /// and `:path` in requests other than CONNECT, `:authority` in
+    /// CONNECT requests and `:status` in responses.
+    MissingPseudoHeader,
+
+    /// A pseudo-header field appeared more than once.
+    DuplicatePseudoHeader,
+
+    /// A pseudo-header field followed a regular field.
+    LatePseudoHeader,
+
+    /// A pseudo-header field which is undefined, or not allowed in this
+    /// kind of message.
+    UnexpectedPseudoHeader,
+
+    /// `:path` is empty.
+    EmptyPath,
+
+    /// `:status` is not three digits.
+    InvalidStatus,
+
+    /// An extended CONNECT request, with `:protocol`, was received
+    /// without SETTINGS_ENABLE_CONNECT_PROTOCOL having been sent.
+    ConnectProtocolDisabled
+}
+
+impl HeadError {
+    /// The stream error to respond to the malformed message on `id` with.
+    pub fn malformed(&self, id: StreamIdentifier) -> ValidationError {
+        ValidationError { error: HttpError::Protocol, scope: ErrorScope::Stream(id) }
+    }
+}
+
+/// The request pseudo-header fields of a decoded header list, with the
+/// remaining fields in their original order.
+///
+/// `protocol` is only present in the extended CONNECT requests of RFC
+/// 8441, such as those opening a WebSocket.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct RequestHead {
+    pub method: Vec<u8>,
+    pub scheme: Option<Vec<u8>>,
+    pub authority: Option<Vec<u8>>,
+    pub path: Option<Vec<u8>>,
+    pub protocol: Option<Vec<u8>>,
+    pub fields: Vec<(Vec<u8>, Vec<u8>)>
+}
+
+impl RequestHead {
+    /// Take the pseudo-header fields out of a header list, such as one
+    /// returned by `hpack::Decoder::decode`.
+    ///
+    /// Only pseudo-header fields are checked; see `check_fields` for the
+    /// rules on the others.
+    pub fn from_fields(fields: Vec<(Vec<u8>, Vec<u8>)>) -> Result<RequestHead, HeadError> {
+        let (pseudo, fields) = try!(split_pseudo(fields));
+
+        let mut method = None;
+        let mut scheme = None;
+        let mut authority = None;
+        let mut path = None;
+        let mut protocol = None;
+        for (name, value) in pseudo {
+            let slot = match &name[..] {
+                b":method" => &mut method,
+                b":scheme" => &mut scheme,
+                b":authority" => &mut authority,
+                b":path" => &mut path,
+                b":protocol" => &mut protocol,
+                _ => return Err(HeadError::UnexpectedPseudoHeader)
+            };
+            try!(set_once(slot, value));
+        }
+
+        let method = try!(method.ok_or(HeadError::MissingPseudoHeader));
+        let connect = method == b"CONNECT";
+        if protocol.is_some() && !connect {
+            return Err(HeadError::UnexpectedPseudoHeader)
+        }
+
+        if connect && protocol.is_none() {
+            if scheme.is_some() || path.is_some() {
+                return Err(HeadError::UnexpectedPseudoHeader)
+            }
+            if authority.is_none() { return Err(HeadError::MissingPseudoHeader) }
+        } else {
+            if scheme.is_none() { return Err(HeadError::MissingPseudoHeader) }
+            if connect && authority.is_none() { return Err(HeadError::MissingPseudoHeader) }
+            match path {
+                Some(ref path) if path.is_empty() => return Err(HeadError::EmptyPath),
+                Some(_) => {},
+                None => return Err(HeadError::MissingPseudoHeader)
+            }
+        }
+
+        Ok(RequestHead {
+            method: method,
+            scheme: scheme,
+            authority: authority,
+            path: path,
+            protocol: protocol,
+            fields: fields
+        })
+    }
+
+    /// An extended CONNECT request opening a tunnel for `protocol`, such
+    /// as `websocket`.
+    pub fn extended_connect(protocol: &[u8], scheme: &[u8], authority: &[u8],
+                            path: &[u8]) -> RequestHead {
+        RequestHead {
+            method: b"CONNECT".to_vec(),
+            scheme: Some(scheme.to_vec()),
+            authority: Some(authority.to_vec()),
+            path: Some(path.to_vec()),
+            protocol: Some(protocol.to_vec()),
+            fields: Vec::new()
+        }
+    }
+
+    /// Is this an extended CONNECT request?
+    pub fn is_extended_connect(&self) -> bool {
+        self.protocol.is_some()
+    }
+
+    /// Check that an extended CONNECT request was allowed by our
+    /// SETTINGS_ENABLE_CONNECT_PROTOCOL, as acknowledged by the peer.
+    pub fn check_connect_protocol(&self, local: &SettingsState) -> Result<(), HeadError> {
+        if self.is_extended_connect() && !local.enable_connect_protocol {
+            Err(HeadError::ConnectProtocolDisabled)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// The header list, pseudo-header fields first, ready for
+    /// `hpack::Encoder::encode`.
+    pub fn to_fields(&self) -> Vec<(&[u8], &[u8])> {
+        let mut fields = vec![(&b":method"[..], &self.method[..])];
+
+        let pseudo = [(&b":scheme"[..], &self.scheme), (&b":authority"[..], &self.authority),
+                      (&b":path"[..], &self.path), (&b":protocol"[..], &self.protocol)];
+        for &(name, value) in &pseudo {
+            if let Some(ref value) = *value { fields.push((name, &value[..])) }
+        }
+
+        fields.extend(self.fields.iter().map(|&(ref name, ref value)| (&name[..], &value[..])));
+        fields
+    }
+
+    /// The value of the first regular field named `name`.
+    pub fn get(&self, name: &[u8]) -> Option<&[u8]> {
+        get(&self.fields, name)
+    }
+}
+
+/// The `:status` of a decoded response header list, with the remaining
+/// fields in their original order.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ResponseHead {
+    pub status: u16,
+    pub fields: Vec<(Vec<u8>, Vec<u8>)>
+}
+
+impl ResponseHead {
+    /// Take the `:status` field out of a header list, such as one
+    /// returned by `hpack::Decoder::decode`.
+    pub fn from_fields(fields: Vec<(Vec<u8>, Vec<u8>)>) -> Result<ResponseHead, HeadError> {
+        let (pseudo, fields) = try!(split_pseudo(fields));
+
+        let mut status = None;
+        for (name, value) in pseudo {
+            if name != b":status" { return Err(HeadError::UnexpectedPseudoHeader) }
+            try!(set_once(&mut status, value));
+        }
+
+        let status = try!(status.ok_or(HeadError::MissingPseudoHeader));
+        if status.len() != 3 || !status.iter().all(|byte| b'0' <= *byte && *byte <= b'9') {
+            return Err(HeadError::InvalidStatus)
+        }
+
+        Ok(ResponseHead {
+            status: status.iter().fold(0, |status, digit| status * 10 + (digit - b'0') as u16),
+            fields: fields
+        })
+    }
+
+    /// Is this a 1xx response, to be followed by another?
+    pub fn is_informational(&self) -> bool {
+        self.status >= 100 && self.status < 200
+    }
+
+    /// The value of the first regular field named `name`.
+    pub fn get(&self, name: &[u8]) -> Option<&[u8]> {
+        get(&self.fields, name)
+    }
+}
+
+type Fields = Vec<(Vec<u8>, Vec<u8>)>;
+
+// Split off the leading pseudo-header fields.
+fn split_pseudo(mut fields: Fields) -> Result<(Fields, Fields), HeadError> {
+    let count = fields.iter().take_while(|&&(ref name, _)| name.starts_with(b":")).count();
+    if fields[count..].iter().any(|&(ref name, _)| name.starts_with(b":")) {
+        return Err(HeadError::LatePseudoHeader)
+    }
+
+    let rest = fields.split_off(count);
+    Ok((fields, rest))
+}
+
+fn set_once(slot: &mut Option<Vec<u8>>, value: Vec<u8>) -> Result<(), HeadError> {
+    if slot.is_some() { return Err(HeadError::DuplicatePseudoHeader) }
+    *slot = Some(value);
+    Ok(())
+}
+
+fn get<'a>(fields: &'a [(Vec<u8>, Vec<u8>)], name: &[u8]) -> Option<&'a [u8]> {
+    fields.iter().find(|&&(ref field, _)| &field[..] == name).map(|&(_, ref value)| &value[..])
+}
+
+/// Check a single header field.
+pub fn check_field(name: &[u8], value: &[u8]) -> Result<(), FieldError> {
+    try!(check_name(name));
+    try!(check_value(value));
+
+    if CONNECTION_SPECIFIC.contains(&name) {
+        return Err(FieldError::ConnectionSpecific)
+    }
+    if name == b"te" && value != b"trailers" {
+        return Err(FieldError::InvalidTe)
+    }
+
+    Ok(())
+}
+
+/// Check every field in a header list, such as one returned by
+/// `hpack::Decoder::decode`, stopping at the first broken rule.
+///
+/// Returns the index of the field along with the rule it broke.
+pub fn check_fields<N, V>(fields: &[(N, V)]) -> Result<(), (usize, FieldError)>
+where N: AsRef<[u8]>, V: AsRef<[u8]> {
+    for (index, &(ref name, ref value)) in fields.iter().enumerate() {
+        try!(check_field(name.as_ref(), value.as_ref()).map_err(|error| (index, error)));
+    }
+
+    Ok(())
+}
+
+fn check_name(name: &[u8]) -> Result<(), FieldError> {
+    let rest = match name.split_first() {
+        Some((&b':', rest)) => rest,
+        Some(_) => name,
+        None => return Err(FieldError::InvalidName)
+    };
+    if rest.is_empty() { return Err(FieldError::InvalidName) }
+
+    for &byte in rest {
+        match byte {
+            b'A'..=b'Z' => return Err(FieldError::UppercaseName),
+            0x00..=0x20 | b':' | 0x7f..=0xff => return Err(FieldError::InvalidName),
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+fn check_value(value: &[u8]) -> Result<(), FieldError> {
+    let whitespace = |byte: &u8| *byte == b' ' || *byte == b'\t';
+
+    if value.first().map_or(false, &whitespace) || value.last().map_or(false, &whitespace) ||
+            value.iter().any(|&byte| byte == 0 || byte == b'\r' || byte == b'\n') {
+        return Err(FieldError::InvalidValue)
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use {StreamIdentifier, HttpError, ErrorScope, ValidationError, SettingsState};
+    use super::{check_field, check_fields, FieldError, RequestHead, ResponseHead, HeadError};
+
+    fn fields(headers: &[(&str, &str)]) -> Vec<(Vec<u8>, Vec<u8>)> {
+        headers.iter().map(|&(name, value)| {
+            (name.as_bytes().to_vec(), value.as_bytes().to_vec())
+        }).collect()
+    }
+
+    #[test]
+    fn test_check_field() {
+        assert_eq!(check_field(b":path", b"/"), Ok(()));
+        assert_eq!(check_field(b"content-type", b"text/plain; charset=utf-8"), Ok(()));
+        assert_eq!(check_field(b"te", b"trailers"), Ok(()));
+        assert_eq!(check_field(b"x-empty", b""), Ok(()));
+
+        assert_eq!(check_field(b"Content-Type", b"text/plain"), Err(FieldError::UppercaseName));
+        assert_eq!(check_field(b"", b"x"), Err(FieldError::InvalidName));
+        assert_eq!(check_field(b":", b"x"), Err(FieldError::InvalidName));
+        assert_eq!(check_field(b"x:y", b"x"), Err(FieldError::InvalidName));
+        assert_eq!(check_field(b"x y", b"x"), Err(FieldError::InvalidName));
+        assert_eq!(check_field(b"x", b"a\r\nb"), Err(FieldError::InvalidValue));
+        assert_eq!(check_field(b"x", b" a"), Err(FieldError::InvalidValue));
+        assert_eq!(check_field(b"x", b"a\t"), Err(FieldError::InvalidValue));
+        assert_eq!(check_field(b"connection", b"close"), Err(FieldError::ConnectionSpecific));
+        assert_eq!(check_field(b"transfer-encoding", b"chunked"),
+                   Err(FieldError::ConnectionSpecific));
+        assert_eq!(check_field(b"te", b"gzip"), Err(FieldError::InvalidTe));
+    }
+
+    #[test]
+    fn test_check_fields() {
+        let fields = vec![(b":method".to_vec(), b"GET".to_vec()),
+                          (b"keep-alive".to_vec(), b"300".to_vec())];
+
+        let (index, error) = check_fields(&fields).unwrap_err();
+        assert_eq!(index, 1);
+        assert_eq!(error.malformed(StreamIdentifier(3)), ValidationError {
+            error: HttpError::Protocol,
+            scope: ErrorScope::Stream(StreamIdentifier(3))
+        });
+        assert_eq!(check_fields(&fields[..1]), Ok(()));
+    }
+
+    #[test]
+    fn test_request_head() {
+        let head = RequestHead::from_fields(fields(&[
+            (":method", "GET"), (":scheme", "https"), (":path", "/index.html"),
+            (":authority", "example.com"), ("accept", "*/*")
+        ])).unwrap();
+        assert_eq!(head.method, b"GET");
+        assert_eq!(head.path, Some(b"/index.html".to_vec()));
+        assert_eq!(head.authority, Some(b"example.com".to_vec()));
+        assert_eq!(head.get(b"accept"), Some(&b"*/*"[..]));
+        assert_eq!(head.fields.len(), 1);
+
+        let connect = RequestHead::from_fields(fields(&[
+            (":method", "CONNECT"), (":authority", "example.com:443")
+        ])).unwrap();
+        assert_eq!(connect.path, None);
+
+        assert_eq!(RequestHead::from_fields(fields(&[(":method", "GET"), (":path", "/")])),
+                   Err(HeadError::MissingPseudoHeader));
+        assert_eq!(RequestHead::from_fields(fields(&[
+            (":method", "GET"), (":scheme", "https"), (":path", "")
+        ])), Err(HeadError::EmptyPath));
+        assert_eq!(RequestHead::from_fields(fields(&[
+            (":method", "GET"), (":method", "GET"), (":scheme", "https"), (":path", "/")
+        ])), Err(HeadError::DuplicatePseudoHeader));
+        assert_eq!(RequestHead::from_fields(fields(&[
+            (":method", "GET"), ("accept", "*/*"), (":scheme", "https"), (":path", "/")
+        ])), Err(HeadError::LatePseudoHeader));
+        assert_eq!(RequestHead::from_fields(fields(&[
+            (":method", "CONNECT"), (":authority", "example.com:443"), (":path", "/")
+        ])), Err(HeadError::UnexpectedPseudoHeader));
+    }
+
+    #[test]
+    fn test_extended_connect() {
+        let head = RequestHead::extended_connect(b"websocket", b"https", b"example.com", b"/chat");
+        let encoded = head.to_fields().iter()
+            .map(|&(name, value)| (name.to_vec(), value.to_vec())).collect();
+        let decoded = RequestHead::from_fields(encoded).unwrap();
+        assert_eq!(decoded, head);
+        assert!(decoded.is_extended_connect());
+
+        let mut local = SettingsState::default();
+        assert_eq!(decoded.check_connect_protocol(&local),
+                   Err(HeadError::ConnectProtocolDisabled));
+        local.enable_connect_protocol = true;
+        assert_eq!(decoded.check_connect_protocol(&local), Ok(()));
+
+        assert_eq!(RequestHead::from_fields(fields(&[
+            (":method", "CONNECT"), (":protocol", "websocket"), (":authority", "example.com")
+        ])), Err(HeadError::MissingPseudoHeader));
+        assert_eq!(RequestHead::from_fields(fields(&[
+            (":method", "GET"), (":protocol", "websocket"), (":scheme", "https"), (":path", "/")
+        ])), Err(HeadError::UnexpectedPseudoHeader));
+    }
+
+    #[test]
+    fn test_response_head() {
+        let head = ResponseHead::from_fields(fields(&[
+            (":status", "103"), ("link", "</style.css>")
+        ])).unwrap();
+        assert_eq!(head.status, 103);
+        assert!(head.is_informational());
+        assert_eq!(head.get(b"link"), Some(&b"</style.css>"[..]));
+
+        assert_eq!(ResponseHead::from_fields(fields(&[(":status", "20")])),
+                   Err(HeadError::InvalidStatus));
+        assert_eq!(ResponseHead::from_fields(fields(&[(":status", "200"), (":path", "/")])),
+                   Err(HeadError::UnexpectedPseudoHeader));
+        assert_eq!(ResponseHead::from_fields(fields(&[("server", "x")])),
+                   Err(HeadError::MissingPseudoHeader));
+    }
+}
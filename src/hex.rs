@@ -0,0 +1,103 @@
+use std::str;
+
+use {Frame, FrameHeader, OwnedFrame, Error, FRAME_HEADER_BYTES};
+
+/// Errors produced while reading a hex-encoded frame.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum HexError {
+    /// The text is not an even number of hex digits.
+    InvalidHex,
+
+    /// The decoded bytes are not a valid frame.
+    Frame(Error),
+
+    /// The decoded bytes continue past the end of the frame. Holds the
+    /// number of extra bytes.
+    TrailingData(usize)
+}
+
+impl<'a> Frame<'a> {
+    /// Parse a frame written in hex, as in RFC examples and Wireshark
+    /// exports, decoding its bytes into `buf`.
+    ///
+    /// Whitespace and `:` separators between digits are ignored.
+    pub fn from_hex(hex: &str, buf: &'a mut Vec<u8>) -> Result<Frame<'a>, HexError> {
+        *buf = try!(decode(hex).ok_or(HexError::InvalidHex));
+
+        let header = try!(FrameHeader::parse(buf).map_err(HexError::Frame));
+        let end = FRAME_HEADER_BYTES + header.length as usize;
+        if buf.len() > end {
+            return Err(HexError::TrailingData(buf.len() - end))
+        }
+
+        Frame::parse(header, &buf[FRAME_HEADER_BYTES..]).map_err(HexError::Frame)
+    }
+
+    /// Encode this frame as lowercase hex, without separators.
+    pub fn to_hex(&self) -> String {
+        let mut buf = vec![0; self.encoded_len()];
+        self.encode(&mut buf);
+        encode(&buf)
+    }
+}
+
+impl OwnedFrame {
+    /// Parse a frame written in hex, as `Frame::from_hex` does.
+    pub fn from_hex(hex: &str) -> Result<OwnedFrame, HexError> {
+        let mut buf = Vec::new();
+        Frame::from_hex(hex, &mut buf).map(OwnedFrame::from)
+    }
+
+    /// Encode this frame as lowercase hex, without separators.
+    pub fn to_hex(&self) -> String {
+        self.as_frame().to_hex()
+    }
+}
+
+/// Decode hex digits, ignoring whitespace and `:` separators.
+pub fn decode(hex: &str) -> Option<Vec<u8>> {
+    let digits = hex.bytes()
+        .filter(|&byte| !(byte as char).is_whitespace() && byte != b':')
+        .collect::<Vec<u8>>();
+
+    if digits.len() % 2 != 0 {
+        return None
+    }
+
+    digits.chunks(2)
+        .map(|pair| str::from_utf8(pair).ok().and_then(|byte| u8::from_str_radix(byte, 16).ok()))
+        .collect()
+}
+
+pub fn encode(buf: &[u8]) -> String {
+    buf.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use {Frame, OwnedFrame, StreamIdentifier, HttpError, Error};
+    use super::HexError;
+
+    #[test]
+    fn test_hex_round_trip() {
+        let frame = Frame::goaway(StreamIdentifier(1), HttpError::Protocol, Some("x"));
+        assert_eq!(frame.to_hex(), "000009070000000000000000010000000178");
+
+        let mut buf = Vec::new();
+        assert_eq!(Frame::from_hex(&frame.to_hex(), &mut buf), Ok(frame));
+
+        let owned = OwnedFrame::from_hex("00 00 08 06 01 00 00 00 00\n\
+                                          01:02:03:04:05:06:07:08").unwrap();
+        assert_eq!(owned.as_frame(), Frame::ping([1, 2, 3, 4, 5, 6, 7, 8]).ping_ack().unwrap());
+        assert_eq!(owned.to_hex(), "0000080601000000000102030405060708");
+    }
+
+    #[test]
+    fn test_bad_hex() {
+        assert_eq!(OwnedFrame::from_hex("0000000"), Err(HexError::InvalidHex));
+        assert_eq!(OwnedFrame::from_hex("zz"), Err(HexError::InvalidHex));
+        assert_eq!(OwnedFrame::from_hex("000000"), Err(HexError::Frame(Error::Short(6))));
+        assert_eq!(OwnedFrame::from_hex("000000040100000000ff"), Err(HexError::TrailingData(1)));
+    }
+}
@@ -0,0 +1,378 @@
+use std::borrow::Cow;
+
+use {Settings, SettingIdentifier};
+
+use super::huffman::HuffmanError;
+use super::static_table;
+use super::table::{self, DynamicTable, DEFAULT_TABLE_SIZE};
+use super::primitives::{decode_integer, decode_string};
+
+/// Errors that can occur while decoding a header block.
+///
+/// All of these except `HeaderListTooLarge` should be treated as a
+/// COMPRESSION_ERROR.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum DecoderError {
+    /// The header block ended in the middle of a representation.
+    Truncated,
+
+    /// An integer was too large to represent.
+    IntegerOverflow,
+
+    /// An index did not refer to an entry in either table.
+    InvalidIndex(usize),
+
+    /// A Huffman coded string literal was malformed.
+    Huffman(HuffmanError),
+
+    /// A dynamic table size update exceeded the maximum we allow.
+    InvalidTableSizeUpdate(usize),
+
+    /// A dynamic table size update followed a header field.
+    LateTableSizeUpdate,
+
+    /// We reduced the maximum table size, but the header block did not
+    /// start with a dynamic table size update acknowledging it.
+    MissingTableSizeUpdate,
+
+    /// The decoded header list exceeded the maximum header list size.
+    ///
+    /// Unlike the other errors this is not a COMPRESSION_ERROR, but the
+    /// dynamic table is no longer in sync with the peer's and the
+    /// connection cannot continue.
+    HeaderListTooLarge
+}
+
+/// An HPACK decoder, turning header blocks into header lists.
+///
+/// Like the `Encoder`, a Decoder holds the dynamic table for one direction
+/// of a connection and must see every header block on it, in order.
+#[derive(Clone, Debug)]
+pub struct Decoder {
+    table: DynamicTable,
+
+    // The SETTINGS_HEADER_TABLE_SIZE we advertised, which bounds the
+    // size updates the peer may send.
+    max_table_size: usize,
+    size_update_required: bool,
+
+    max_header_list_size: Option<usize>
+}
+
+impl Decoder {
+    pub fn new() -> Decoder {
+        Decoder {
+            table: DynamicTable::new(DEFAULT_TABLE_SIZE),
+            max_table_size: DEFAULT_TABLE_SIZE,
+            size_update_required: false,
+            max_header_list_size: None
+        }
+    }
+
+    pub fn table(&self) -> &DynamicTable {
+        &self.table
+    }
+
+    /// Change the maximum dynamic table size the peer may use, once it has
+    /// acknowledged our SETTINGS_HEADER_TABLE_SIZE.
+    ///
+    /// If this is smaller than the current table size, the next header
+    /// block must start with a dynamic table size update.
+    pub fn set_max_table_size(&mut self, size: usize) {
+        if size < self.table.max_size() {
+            self.size_update_required = true;
+        }
+
+        self.max_table_size = size;
+    }
+
+    /// Limit the size of decoded header lists, as computed for
+    /// SETTINGS_MAX_HEADER_LIST_SIZE. There is no limit by default.
+    ///
+    /// Decoding stops with `HeaderListTooLarge` as soon as the sum of the
+    /// sizes of the fields decoded so far exceeds the limit.
+    pub fn set_max_header_list_size(&mut self, size: usize) {
+        self.max_header_list_size = Some(size);
+    }
+
+    /// Apply any SETTINGS_HEADER_TABLE_SIZE and SETTINGS_MAX_HEADER_LIST_SIZE
+    /// in our settings, once the peer has acknowledged them.
+    pub fn apply_settings(&mut self, settings: Settings) {
+        for setting in settings {
+            match setting.identifier() {
+                Some(SettingIdentifier::HeaderTableSize) =>
+                    self.set_max_table_size(setting.value() as usize),
+                Some(SettingIdentifier::MaxHeaderListSize) =>
+                    self.set_max_header_list_size(setting.value() as usize),
+                _ => ()
+            }
+        }
+    }
+
+    /// Decode a complete header block into a list of header fields.
+    pub fn decode(&mut self, block: &[u8]) -> Result<Vec<(Vec<u8>, Vec<u8>)>, DecoderError> {
+        self.decode_iter(block).map(|field| {
+            field.map(|(name, value)| (name.into_owned(), value.into_owned()))
+        }).collect()
+    }
+
+    /// Decode a complete header block lazily, one header field at a time.
+    ///
+    /// Names and values are borrowed from the block or the static table
+    /// where possible. The block must be decoded to the end, or the
+    /// dynamic table will be left out of sync with the peer's.
+    pub fn decode_iter<'d, 'b>(&'d mut self, block: &'b [u8]) -> DecodeIter<'d, 'b> {
+        DecodeIter {
+            decoder: self,
+            block: block,
+            seen_field: false,
+            list_size: 0,
+            done: false
+        }
+    }
+
+    fn field<'b>(&self, index: usize) -> Result<HeaderField<'b>, DecoderError> {
+        if index <= static_table::LEN {
+            static_table::get(index)
+                .map(|(name, value)| (Cow::Borrowed(name), Cow::Borrowed(value)))
+                .ok_or(DecoderError::InvalidIndex(index))
+        } else {
+            table::get(&self.table, index)
+                .map(|(name, value)| (Cow::Owned(name.to_vec()), Cow::Owned(value.to_vec())))
+                .ok_or(DecoderError::InvalidIndex(index))
+        }
+    }
+}
+
+/// A decoded header field, borrowed from the header block where possible.
+pub type HeaderField<'b> = (Cow<'b, [u8]>, Cow<'b, [u8]>);
+
+/// An iterator over the header fields in a header block.
+///
+/// Iteration stops after the first error.
+pub struct DecodeIter<'d, 'b> {
+    decoder: &'d mut Decoder,
+    block: &'b [u8],
+    seen_field: bool,
+    list_size: usize,
+    done: bool
+}
+
+impl<'d, 'b> DecodeIter<'d, 'b> {
+    fn next_field(&mut self) -> Result<Option<HeaderField<'b>>, DecoderError> {
+        let field = try!(self.decode_field());
+
+        if let (Some(field), Some(max)) = (field.as_ref(), self.decoder.max_header_list_size) {
+            self.list_size = self.list_size.saturating_add(table::entry_size(&field.0, &field.1));
+            if self.list_size > max { return Err(DecoderError::HeaderListTooLarge) }
+        }
+
+        Ok(field)
+    }
+
+    fn decode_field(&mut self) -> Result<Option<HeaderField<'b>>, DecoderError> {
+        while !self.block.is_empty() {
+            let block = self.block;
+            let first = block[0];
+
+            if first & 0xe0 == 0x20 {
+                // Dynamic Table Size Update
+                if self.seen_field { return Err(DecoderError::LateTableSizeUpdate) }
+
+                let (size, read) = try!(decode_integer(block, 5));
+                if size > self.decoder.max_table_size {
+                    return Err(DecoderError::InvalidTableSizeUpdate(size))
+                }
+
+                self.decoder.table.set_max_size(size);
+                self.decoder.size_update_required = false;
+                self.block = &block[read..];
+                continue
+            }
+
+            if self.decoder.size_update_required {
+                return Err(DecoderError::MissingTableSizeUpdate)
+            }
+
+            self.seen_field = true;
+
+            if first & 0x80 == 0x80 {
+                // Indexed Header Field
+                let (index, read) = try!(decode_integer(block, 7));
+                self.block = &block[read..];
+                return self.decoder.field(index).map(Some)
+            }
+
+            // Literal Header Field with Incremental Indexing (01),
+            // without Indexing (0000) or Never Indexed (0001).
+            let indexing = first & 0xc0 == 0x40;
+            let prefix_bits = if indexing { 6 } else { 4 };
+
+            let (index, mut read) = try!(decode_integer(block, prefix_bits));
+            let name = if index == 0 {
+                let (name, name_read) = try!(decode_string(&block[read..]));
+                read += name_read;
+                name
+            } else {
+                try!(self.decoder.field(index)).0
+            };
+
+            let (value, value_read) = try!(decode_string(&block[read..]));
+            read += value_read;
+
+            if indexing {
+                self.decoder.table.insert(name.to_vec(), value.to_vec());
+            }
+
+            self.block = &block[read..];
+            return Ok(Some((name, value)))
+        }
+
+        if self.decoder.size_update_required {
+            return Err(DecoderError::MissingTableSizeUpdate)
+        }
+
+        Ok(None)
+    }
+}
+
+impl<'d, 'b> Iterator for DecodeIter<'d, 'b> {
+    type Item = Result<HeaderField<'b>, DecoderError>;
+
+    fn next(&mut self) -> Option<Result<HeaderField<'b>, DecoderError>> {
+        if self.done { return None }
+
+        match self.next_field() {
+            Ok(Some(field)) => Some(Ok(field)),
+            Ok(None) => { self.done = true; None },
+            Err(err) => { self.done = true; Some(Err(err)) }
+        }
+    }
+}
+
+impl Default for Decoder {
+    fn default() -> Decoder { Decoder::new() }
+}
+
+#[cfg(test)]
+mod test {
+    use std::borrow::Cow;
+
+    use {Setting, Settings, SettingIdentifier};
+    use hpack::Encoder;
+    use super::{Decoder, DecoderError};
+
+    fn fields(headers: &[(&str, &str)]) -> Vec<(Vec<u8>, Vec<u8>)> {
+        headers.iter().map(|&(name, value)| {
+            (name.as_bytes().to_vec(), value.as_bytes().to_vec())
+        }).collect()
+    }
+
+    // RFC 7541 C.4, requests with Huffman coding on one connection.
+    #[test]
+    fn test_decode_request_sequence() {
+        let mut decoder = Decoder::new();
+
+        assert_eq!(decoder.decode(&[
+            0x82, 0x86, 0x84, 0x41, 0x8c, 0xf1, 0xe3, 0xc2, 0xe5, 0xf2, 0x3a,
+            0x6b, 0xa0, 0xab, 0x90, 0xf4, 0xff
+        ]), Ok(fields(&[
+            (":method", "GET"), (":scheme", "http"), (":path", "/"),
+            (":authority", "www.example.com")
+        ])));
+
+        assert_eq!(decoder.decode(&[
+            0x82, 0x86, 0x84, 0xbe, 0x58, 0x86, 0xa8, 0xeb, 0x10, 0x64, 0x9c,
+            0xbf
+        ]), Ok(fields(&[
+            (":method", "GET"), (":scheme", "http"), (":path", "/"),
+            (":authority", "www.example.com"), ("cache-control", "no-cache")
+        ])));
+
+        assert_eq!(decoder.table().len(), 2);
+        assert_eq!(decoder.table().size(), 110);
+    }
+
+    #[test]
+    fn test_roundtrip_with_encoder() {
+        let mut encoder = Encoder::new();
+        let mut decoder = Decoder::new();
+
+        let headers = fields(&[
+            (":status", "302"), ("cache-control", "private"),
+            ("location", "https://www.example.com"), ("x-custom", "")
+        ]);
+
+        for _ in 0..3 {
+            let block = encoder.encode(headers.iter().map(|&(ref n, ref v)| (&**n, &**v)));
+            assert_eq!(decoder.decode(&block), Ok(headers.clone()));
+        }
+    }
+
+    #[test]
+    fn test_table_size_enforcement() {
+        let mut decoder = Decoder::new();
+
+        assert_eq!(decoder.decode(&[0x3f, 0xe2, 0x1f]),
+                   Err(DecoderError::InvalidTableSizeUpdate(4097)));
+        assert_eq!(decoder.decode(&[0x82, 0x20]), Err(DecoderError::LateTableSizeUpdate));
+
+        decoder.set_max_table_size(0);
+        assert_eq!(decoder.decode(&[0x82]), Err(DecoderError::MissingTableSizeUpdate));
+        assert_eq!(decoder.decode(&[0x20, 0x82]), Ok(fields(&[(":method", "GET")])));
+        assert_eq!(decoder.table().max_size(), 0);
+    }
+
+    #[test]
+    fn test_decode_iter_borrows() {
+        let mut decoder = Decoder::new();
+        let block = [
+            0x82, // :method: GET
+            0x04, 0x05, b'/', b'p', b'a', b't', b'h', // :path: /path, not indexed
+            0x40, 0x01, b'a', 0x01, b'b', // a: b, indexed
+            0xbe // a: b
+        ];
+
+        let fields = decoder.decode_iter(&block).collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(fields.len(), 4);
+
+        assert_eq!(fields[0], (Cow::Borrowed(&b":method"[..]), Cow::Borrowed(&b"GET"[..])));
+        assert_eq!(fields[1], (Cow::Borrowed(&b":path"[..]), Cow::Borrowed(&b"/path"[..])));
+        assert_eq!(fields[2], (Cow::Borrowed(&b"a"[..]), Cow::Borrowed(&b"b"[..])));
+        assert_eq!(&*fields[3].0, b"a");
+        assert_eq!(&*fields[3].1, b"b");
+    }
+
+    #[test]
+    fn test_decode_iter_stops_after_error() {
+        let mut decoder = Decoder::new();
+        let mut iter = decoder.decode_iter(&[0x82, 0x80, 0x82]);
+
+        assert!(iter.next().unwrap().is_ok());
+        assert_eq!(iter.next(), Some(Err(DecoderError::InvalidIndex(0))));
+        assert_eq!(iter.next(), None);
+    }
+
+    #[test]
+    fn test_max_header_list_size() {
+        let mut decoder = Decoder::new();
+        decoder.apply_settings(Settings::new(&[
+            Setting::new(SettingIdentifier::MaxHeaderListSize, 80)
+        ]));
+
+        // :method: GET is 7 + 3 + 32 = 42 bytes.
+        assert_eq!(decoder.decode(&[0x82]), Ok(fields(&[(":method", "GET")])));
+
+        let mut iter = decoder.decode_iter(&[0x82, 0x82, 0x82]);
+        assert!(iter.next().unwrap().is_ok());
+        assert_eq!(iter.next(), Some(Err(DecoderError::HeaderListTooLarge)));
+        assert_eq!(iter.next(), None);
+    }
+
+    #[test]
+    fn test_invalid_index() {
+        assert_eq!(Decoder::new().decode(&[0x80]), Err(DecoderError::InvalidIndex(0)));
+        assert_eq!(Decoder::new().decode(&[0xbe]), Err(DecoderError::InvalidIndex(62)));
+    }
+}
@@ -0,0 +1,269 @@
+use {Settings, SettingIdentifier};
+
+use super::static_table::Match;
+use super::table::{self, DynamicTable, DEFAULT_TABLE_SIZE};
+use super::primitives::{encode_integer, encode_string};
+
+/// An HPACK encoder, turning header lists into header block fragments.
+///
+/// An Encoder holds the dynamic table for one direction of a connection,
+/// so every header block sent on that connection must be produced by
+/// the same Encoder, in order.
+#[derive(Clone, Debug)]
+pub struct Encoder {
+    table: DynamicTable,
+    huffman: bool,
+    sensitive: Vec<Vec<u8>>,
+
+    // The smallest and the latest table size set since the last header
+    // block, which must be signalled at the start of the next one.
+    size_update: Option<(usize, usize)>
+}
+
+impl Encoder {
+    pub fn new() -> Encoder {
+        Encoder {
+            table: DynamicTable::new(DEFAULT_TABLE_SIZE),
+            huffman: true,
+            sensitive: Vec::new(),
+            size_update: None
+        }
+    }
+
+    pub fn table(&self) -> &DynamicTable {
+        &self.table
+    }
+
+    /// Mark a header name as sensitive, such as `authorization` or `cookie`.
+    ///
+    /// Fields with a sensitive name always use the never indexed literal
+    /// representation, so they are never added to the dynamic table here
+    /// or by any intermediary re-encoding them.
+    pub fn mark_sensitive(&mut self, name: &[u8]) {
+        if !self.is_sensitive(name) {
+            self.sensitive.push(name.to_vec());
+        }
+    }
+
+    pub fn is_sensitive(&self, name: &[u8]) -> bool {
+        self.sensitive.iter().any(|sensitive| &**sensitive == name)
+    }
+
+    /// Change the maximum size of the dynamic table, for instance when the
+    /// peer changes SETTINGS_HEADER_TABLE_SIZE.
+    ///
+    /// The change is signalled with a dynamic table size update at the
+    /// start of the next header block.
+    pub fn set_max_table_size(&mut self, size: usize) {
+        self.size_update = Some(match self.size_update {
+            Some((smallest, _)) if smallest < size => (smallest, size),
+            _ => (size, size)
+        });
+
+        self.table.set_max_size(size);
+    }
+
+    /// Apply any SETTINGS_HEADER_TABLE_SIZE in settings received from the peer.
+    pub fn apply_settings(&mut self, settings: Settings) {
+        for setting in settings {
+            if setting.identifier() == Some(SettingIdentifier::HeaderTableSize) {
+                self.set_max_table_size(setting.value() as usize);
+            }
+        }
+    }
+
+    /// Whether string literals are Huffman coded when that makes them
+    /// shorter. Enabled by default.
+    pub fn set_huffman(&mut self, huffman: bool) {
+        self.huffman = huffman;
+    }
+
+    /// Encode a header list into a new header block.
+    pub fn encode<'h, I>(&mut self, headers: I) -> Vec<u8>
+    where I: IntoIterator<Item=(&'h [u8], &'h [u8])> {
+        let mut block = Vec::new();
+        self.encode_into(headers, &mut block);
+        block
+    }
+
+    /// Encode a header list, appending the header block to `dst`.
+    pub fn encode_into<'h, I>(&mut self, headers: I, dst: &mut Vec<u8>)
+    where I: IntoIterator<Item=(&'h [u8], &'h [u8])> {
+        if let Some((smallest, latest)) = self.size_update.take() {
+            encode_integer(smallest, 5, 0x20, dst);
+            if latest != smallest { encode_integer(latest, 5, 0x20, dst) }
+        }
+
+        for (name, value) in headers {
+            self.encode_field(name, value, dst);
+        }
+    }
+
+    fn encode_field(&mut self, name: &[u8], value: &[u8], dst: &mut Vec<u8>) {
+        if self.is_sensitive(name) {
+            return self.encode_never_indexed(name, value, dst)
+        }
+
+        match table::find(&self.table, name, value) {
+            // Indexed Header Field
+            Some(Match::Full(index)) => encode_integer(index, 7, 0x80, dst),
+
+            // Literal Header Field with Incremental Indexing -- Indexed Name
+            Some(Match::Name(index)) => {
+                encode_integer(index, 6, 0x40, dst);
+                encode_string(value, self.huffman, dst);
+                self.table.insert(name.to_vec(), value.to_vec());
+            },
+
+            // Literal Header Field with Incremental Indexing -- New Name
+            None => {
+                dst.push(0x40);
+                encode_string(name, self.huffman, dst);
+                encode_string(value, self.huffman, dst);
+                self.table.insert(name.to_vec(), value.to_vec());
+            }
+        }
+    }
+
+    // Literal Header Field Never Indexed, with an indexed name if possible.
+    fn encode_never_indexed(&self, name: &[u8], value: &[u8], dst: &mut Vec<u8>) {
+        match table::find(&self.table, name, value) {
+            Some(Match::Full(index)) | Some(Match::Name(index)) => {
+                encode_integer(index, 4, 0x10, dst);
+            },
+            None => {
+                dst.push(0x10);
+                encode_string(name, self.huffman, dst);
+            }
+        }
+
+        encode_string(value, self.huffman, dst);
+    }
+}
+
+impl Default for Encoder {
+    fn default() -> Encoder { Encoder::new() }
+}
+
+#[cfg(test)]
+mod test {
+    use {Setting, Settings, SettingIdentifier};
+    use super::Encoder;
+
+    #[test]
+    fn test_table_size_update() {
+        let mut encoder = Encoder::new();
+        encoder.encode(vec![(&b"custom-key"[..], &b"custom-value"[..])]);
+        assert_eq!(encoder.table().len(), 1);
+
+        encoder.apply_settings(Settings::new(&[
+            Setting::new(SettingIdentifier::HeaderTableSize, 0)
+        ]));
+        assert_eq!(encoder.table().len(), 0);
+        assert_eq!(encoder.encode(vec![(&b":method"[..], &b"GET"[..])]), vec![0x20, 0x82]);
+
+        // Only the smallest and final sizes are signalled.
+        encoder.set_max_table_size(256);
+        encoder.set_max_table_size(1024);
+        encoder.set_max_table_size(4096);
+        assert_eq!(encoder.encode(vec![]), vec![0x3f, 0xe1, 0x01, 0x3f, 0xe1, 0x1f]);
+        assert_eq!(encoder.encode(vec![]), Vec::<u8>::new());
+    }
+
+    #[test]
+    fn test_sensitive_headers_are_never_indexed() {
+        let mut encoder = Encoder::new();
+        encoder.set_huffman(false);
+        encoder.mark_sensitive(b"authorization");
+        encoder.mark_sensitive(b"x-token");
+
+        let headers = vec![(&b"authorization"[..], &b"secret"[..]), (b"x-token", b"t")];
+        let expected = vec![
+            0x1f, 0x08, 0x06, b's', b'e', b'c', b'r', b'e', b't',
+            0x10, 0x07, b'x', b'-', b't', b'o', b'k', b'e', b'n', 0x01, b't'
+        ];
+
+        assert_eq!(encoder.encode(headers.clone()), expected);
+        assert_eq!(encoder.encode(headers), expected);
+        assert_eq!(encoder.table().len(), 0);
+    }
+
+    // RFC 7541 C.3, requests without Huffman coding on one connection.
+    #[test]
+    fn test_encode_request_sequence() {
+        let mut encoder = Encoder::new();
+        encoder.set_huffman(false);
+
+        assert_eq!(encoder.encode(vec![
+            (&b":method"[..], &b"GET"[..]),
+            (b":scheme", b"http"),
+            (b":path", b"/"),
+            (b":authority", b"www.example.com")
+        ]), vec![
+            0x82, 0x86, 0x84, 0x41, 0x0f, 0x77, 0x77, 0x77, 0x2e, 0x65, 0x78,
+            0x61, 0x6d, 0x70, 0x6c, 0x65, 0x2e, 0x63, 0x6f, 0x6d
+        ]);
+
+        assert_eq!(encoder.encode(vec![
+            (&b":method"[..], &b"GET"[..]),
+            (b":scheme", b"http"),
+            (b":path", b"/"),
+            (b":authority", b"www.example.com"),
+            (b"cache-control", b"no-cache")
+        ]), vec![
+            0x82, 0x86, 0x84, 0xbe, 0x58, 0x08, 0x6e, 0x6f, 0x2d, 0x63, 0x61,
+            0x63, 0x68, 0x65
+        ]);
+
+        assert_eq!(encoder.encode(vec![
+            (&b":method"[..], &b"GET"[..]),
+            (b":scheme", b"https"),
+            (b":path", b"/index.html"),
+            (b":authority", b"www.example.com"),
+            (b"custom-key", b"custom-value")
+        ]), vec![
+            0x82, 0x87, 0x85, 0xbf, 0x40, 0x0a, 0x63, 0x75, 0x73, 0x74, 0x6f,
+            0x6d, 0x2d, 0x6b, 0x65, 0x79, 0x0c, 0x63, 0x75, 0x73, 0x74, 0x6f,
+            0x6d, 0x2d, 0x76, 0x61, 0x6c, 0x75, 0x65
+        ]);
+    }
+
+    // RFC 7541 C.4, the same requests with Huffman coding.
+    #[test]
+    fn test_encode_request_sequence_huffman() {
+        let mut encoder = Encoder::new();
+
+        assert_eq!(encoder.encode(vec![
+            (&b":method"[..], &b"GET"[..]),
+            (b":scheme", b"http"),
+            (b":path", b"/"),
+            (b":authority", b"www.example.com")
+        ]), vec![
+            0x82, 0x86, 0x84, 0x41, 0x8c, 0xf1, 0xe3, 0xc2, 0xe5, 0xf2, 0x3a,
+            0x6b, 0xa0, 0xab, 0x90, 0xf4, 0xff
+        ]);
+
+        assert_eq!(encoder.encode(vec![
+            (&b":method"[..], &b"GET"[..]),
+            (b":scheme", b"http"),
+            (b":path", b"/"),
+            (b":authority", b"www.example.com"),
+            (b"cache-control", b"no-cache")
+        ]), vec![
+            0x82, 0x86, 0x84, 0xbe, 0x58, 0x86, 0xa8, 0xeb, 0x10, 0x64, 0x9c,
+            0xbf
+        ]);
+
+        assert_eq!(encoder.encode(vec![
+            (&b":method"[..], &b"GET"[..]),
+            (b":scheme", b"https"),
+            (b":path", b"/index.html"),
+            (b":authority", b"www.example.com"),
+            (b"custom-key", b"custom-value")
+        ]), vec![
+            0x82, 0x87, 0x85, 0xbf, 0x40, 0x88, 0x25, 0xa8, 0x49, 0xe9, 0x5b,
+            0xa9, 0x7d, 0x7f, 0x89, 0x25, 0xa8, 0x49, 0xe9, 0x5b, 0xb8, 0xe8,
+            0xb4, 0xbf
+        ]);
+    }
+}
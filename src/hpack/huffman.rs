@@ -0,0 +1,512 @@
+//! The static Huffman code used by HPACK string literals (RFC 7541, Appendix B).
+//!
+//! Both directions are streaming: an `Encoder` or `Decoder` can be fed a
+//! string in arbitrary chunks, and `encode`/`decode` handle the common
+//! case of a whole string at once.
+
+/// Errors that can occur while decoding a Huffman-coded string.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum HuffmanError {
+    /// A sequence of more than 30 bits did not match any code.
+    InvalidCode,
+
+    /// The EOS symbol appeared in the encoded string.
+    EndOfString,
+
+    /// The string was padded with more than 7 bits, or with bits other
+    /// than the most significant bits of EOS.
+    InvalidPadding
+}
+
+/// Huffman-encode `src`, appending the encoded bytes to `dst`.
+pub fn encode(src: &[u8], dst: &mut Vec<u8>) {
+    let mut encoder = Encoder::new();
+    encoder.encode(src, dst);
+    encoder.finish(dst);
+}
+
+/// How many bytes `src` occupies once Huffman-encoded.
+pub fn encoded_len(src: &[u8]) -> usize {
+    let bits = src.iter().fold(0, |bits, &byte| bits + ENCODE_TABLE[byte as usize].1 as usize);
+    (bits + 7) / 8
+}
+
+/// Decode the Huffman-encoded `src`, appending the decoded bytes to `dst`.
+pub fn decode(src: &[u8], dst: &mut Vec<u8>) -> Result<(), HuffmanError> {
+    let mut decoder = Decoder::new();
+    try!(decoder.decode(src, dst));
+    decoder.finish()
+}
+
+/// A streaming Huffman encoder.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Encoder {
+    bits: u64,
+    len: u32
+}
+
+impl Encoder {
+    pub fn new() -> Encoder {
+        Encoder { bits: 0, len: 0 }
+    }
+
+    /// Encode `src`, appending every completed byte to `dst`.
+    pub fn encode(&mut self, src: &[u8], dst: &mut Vec<u8>) {
+        for &byte in src {
+            let (code, len) = ENCODE_TABLE[byte as usize];
+
+            // At most 7 bits are left over between bytes and codes are
+            // at most 30 bits long, so this never overflows.
+            self.bits = (self.bits << len) | code as u64;
+            self.len += len as u32;
+
+            while self.len >= 8 {
+                self.len -= 8;
+                dst.push((self.bits >> self.len) as u8);
+            }
+        }
+    }
+
+    /// Flush the last partial byte, padded with the prefix of EOS.
+    pub fn finish(self, dst: &mut Vec<u8>) {
+        if self.len > 0 {
+            let pad = 8 - self.len;
+            dst.push(((self.bits << pad) | ((1 << pad) - 1)) as u8);
+        }
+    }
+}
+
+/// A streaming Huffman decoder.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Decoder {
+    code: u32,
+    len: u8
+}
+
+impl Decoder {
+    pub fn new() -> Decoder {
+        Decoder { code: 0, len: 0 }
+    }
+
+    /// Decode `src`, appending every completed symbol to `dst`.
+    ///
+    /// Bits belonging to an incomplete code are kept until the next call.
+    pub fn decode(&mut self, src: &[u8], dst: &mut Vec<u8>) -> Result<(), HuffmanError> {
+        for &byte in src {
+            for shift in (0..8).rev() {
+                self.code = (self.code << 1) | ((byte >> shift) & 1) as u32;
+                self.len += 1;
+
+                if let Some(symbol) = lookup(self.code, self.len) {
+                    if symbol == EOS { return Err(HuffmanError::EndOfString) }
+
+                    dst.push(symbol as u8);
+                    self.code = 0;
+                    self.len = 0;
+                } else if self.len == MAX_CODE_LEN {
+                    return Err(HuffmanError::InvalidCode)
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Check that the decoded string ended on valid padding.
+    pub fn finish(self) -> Result<(), HuffmanError> {
+        if self.len > 7 || self.code != (1 << self.len) - 1 {
+            Err(HuffmanError::InvalidPadding)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+const EOS: u16 = 256;
+const MAX_CODE_LEN: u8 = 30;
+
+#[inline]
+fn lookup(code: u32, len: u8) -> Option<u16> {
+    let len = len as usize;
+    let offset = code.wrapping_sub(FIRST_CODE[len]);
+
+    if code >= FIRST_CODE[len] && offset < CODE_COUNT[len] as u32 {
+        Some(DECODE_SYMBOLS[FIRST_INDEX[len] as usize + offset as usize])
+    } else {
+        None
+    }
+}
+
+/// The canonical HPACK Huffman code, as `(code, bit length)` indexed by symbol.
+///
+/// Symbol 256 is EOS.
+pub static ENCODE_TABLE: [(u32, u8); 257] = [
+    (0x1ff8, 13),
+    (0x7fffd8, 23),
+    (0xfffffe2, 28),
+    (0xfffffe3, 28),
+    (0xfffffe4, 28),
+    (0xfffffe5, 28),
+    (0xfffffe6, 28),
+    (0xfffffe7, 28),
+    (0xfffffe8, 28),
+    (0xffffea, 24),
+    (0x3ffffffc, 30),
+    (0xfffffe9, 28),
+    (0xfffffea, 28),
+    (0x3ffffffd, 30),
+    (0xfffffeb, 28),
+    (0xfffffec, 28),
+    (0xfffffed, 28),
+    (0xfffffee, 28),
+    (0xfffffef, 28),
+    (0xffffff0, 28),
+    (0xffffff1, 28),
+    (0xffffff2, 28),
+    (0x3ffffffe, 30),
+    (0xffffff3, 28),
+    (0xffffff4, 28),
+    (0xffffff5, 28),
+    (0xffffff6, 28),
+    (0xffffff7, 28),
+    (0xffffff8, 28),
+    (0xffffff9, 28),
+    (0xffffffa, 28),
+    (0xffffffb, 28),
+    (0x14, 6),
+    (0x3f8, 10),
+    (0x3f9, 10),
+    (0xffa, 12),
+    (0x1ff9, 13),
+    (0x15, 6),
+    (0xf8, 8),
+    (0x7fa, 11),
+    (0x3fa, 10),
+    (0x3fb, 10),
+    (0xf9, 8),
+    (0x7fb, 11),
+    (0xfa, 8),
+    (0x16, 6),
+    (0x17, 6),
+    (0x18, 6),
+    (0x0, 5),
+    (0x1, 5),
+    (0x2, 5),
+    (0x19, 6),
+    (0x1a, 6),
+    (0x1b, 6),
+    (0x1c, 6),
+    (0x1d, 6),
+    (0x1e, 6),
+    (0x1f, 6),
+    (0x5c, 7),
+    (0xfb, 8),
+    (0x7ffc, 15),
+    (0x20, 6),
+    (0xffb, 12),
+    (0x3fc, 10),
+    (0x1ffa, 13),
+    (0x21, 6),
+    (0x5d, 7),
+    (0x5e, 7),
+    (0x5f, 7),
+    (0x60, 7),
+    (0x61, 7),
+    (0x62, 7),
+    (0x63, 7),
+    (0x64, 7),
+    (0x65, 7),
+    (0x66, 7),
+    (0x67, 7),
+    (0x68, 7),
+    (0x69, 7),
+    (0x6a, 7),
+    (0x6b, 7),
+    (0x6c, 7),
+    (0x6d, 7),
+    (0x6e, 7),
+    (0x6f, 7),
+    (0x70, 7),
+    (0x71, 7),
+    (0x72, 7),
+    (0xfc, 8),
+    (0x73, 7),
+    (0xfd, 8),
+    (0x1ffb, 13),
+    (0x7fff0, 19),
+    (0x1ffc, 13),
+    (0x3ffc, 14),
+    (0x22, 6),
+    (0x7ffd, 15),
+    (0x3, 5),
+    (0x23, 6),
+    (0x4, 5),
+    (0x24, 6),
+    (0x5, 5),
+    (0x25, 6),
+    (0x26, 6),
+    (0x27, 6),
+    (0x6, 5),
+    (0x74, 7),
+    (0x75, 7),
+    (0x28, 6),
+    (0x29, 6),
+    (0x2a, 6),
+    (0x7, 5),
+    (0x2b, 6),
+    (0x76, 7),
+    (0x2c, 6),
+    (0x8, 5),
+    (0x9, 5),
+    (0x2d, 6),
+    (0x77, 7),
+    (0x78, 7),
+    (0x79, 7),
+    (0x7a, 7),
+    (0x7b, 7),
+    (0x7ffe, 15),
+    (0x7fc, 11),
+    (0x3ffd, 14),
+    (0x1ffd, 13),
+    (0xffffffc, 28),
+    (0xfffe6, 20),
+    (0x3fffd2, 22),
+    (0xfffe7, 20),
+    (0xfffe8, 20),
+    (0x3fffd3, 22),
+    (0x3fffd4, 22),
+    (0x3fffd5, 22),
+    (0x7fffd9, 23),
+    (0x3fffd6, 22),
+    (0x7fffda, 23),
+    (0x7fffdb, 23),
+    (0x7fffdc, 23),
+    (0x7fffdd, 23),
+    (0x7fffde, 23),
+    (0xffffeb, 24),
+    (0x7fffdf, 23),
+    (0xffffec, 24),
+    (0xffffed, 24),
+    (0x3fffd7, 22),
+    (0x7fffe0, 23),
+    (0xffffee, 24),
+    (0x7fffe1, 23),
+    (0x7fffe2, 23),
+    (0x7fffe3, 23),
+    (0x7fffe4, 23),
+    (0x1fffdc, 21),
+    (0x3fffd8, 22),
+    (0x7fffe5, 23),
+    (0x3fffd9, 22),
+    (0x7fffe6, 23),
+    (0x7fffe7, 23),
+    (0xffffef, 24),
+    (0x3fffda, 22),
+    (0x1fffdd, 21),
+    (0xfffe9, 20),
+    (0x3fffdb, 22),
+    (0x3fffdc, 22),
+    (0x7fffe8, 23),
+    (0x7fffe9, 23),
+    (0x1fffde, 21),
+    (0x7fffea, 23),
+    (0x3fffdd, 22),
+    (0x3fffde, 22),
+    (0xfffff0, 24),
+    (0x1fffdf, 21),
+    (0x3fffdf, 22),
+    (0x7fffeb, 23),
+    (0x7fffec, 23),
+    (0x1fffe0, 21),
+    (0x1fffe1, 21),
+    (0x3fffe0, 22),
+    (0x1fffe2, 21),
+    (0x7fffed, 23),
+    (0x3fffe1, 22),
+    (0x7fffee, 23),
+    (0x7fffef, 23),
+    (0xfffea, 20),
+    (0x3fffe2, 22),
+    (0x3fffe3, 22),
+    (0x3fffe4, 22),
+    (0x7ffff0, 23),
+    (0x3fffe5, 22),
+    (0x3fffe6, 22),
+    (0x7ffff1, 23),
+    (0x3ffffe0, 26),
+    (0x3ffffe1, 26),
+    (0xfffeb, 20),
+    (0x7fff1, 19),
+    (0x3fffe7, 22),
+    (0x7ffff2, 23),
+    (0x3fffe8, 22),
+    (0x1ffffec, 25),
+    (0x3ffffe2, 26),
+    (0x3ffffe3, 26),
+    (0x3ffffe4, 26),
+    (0x7ffffde, 27),
+    (0x7ffffdf, 27),
+    (0x3ffffe5, 26),
+    (0xfffff1, 24),
+    (0x1ffffed, 25),
+    (0x7fff2, 19),
+    (0x1fffe3, 21),
+    (0x3ffffe6, 26),
+    (0x7ffffe0, 27),
+    (0x7ffffe1, 27),
+    (0x3ffffe7, 26),
+    (0x7ffffe2, 27),
+    (0xfffff2, 24),
+    (0x1fffe4, 21),
+    (0x1fffe5, 21),
+    (0x3ffffe8, 26),
+    (0x3ffffe9, 26),
+    (0xffffffd, 28),
+    (0x7ffffe3, 27),
+    (0x7ffffe4, 27),
+    (0x7ffffe5, 27),
+    (0xfffec, 20),
+    (0xfffff3, 24),
+    (0xfffed, 20),
+    (0x1fffe6, 21),
+    (0x3fffe9, 22),
+    (0x1fffe7, 21),
+    (0x1fffe8, 21),
+    (0x7ffff3, 23),
+    (0x3fffea, 22),
+    (0x3fffeb, 22),
+    (0x1ffffee, 25),
+    (0x1ffffef, 25),
+    (0xfffff4, 24),
+    (0xfffff5, 24),
+    (0x3ffffea, 26),
+    (0x7ffff4, 23),
+    (0x3ffffeb, 26),
+    (0x7ffffe6, 27),
+    (0x3ffffec, 26),
+    (0x3ffffed, 26),
+    (0x7ffffe7, 27),
+    (0x7ffffe8, 27),
+    (0x7ffffe9, 27),
+    (0x7ffffea, 27),
+    (0x7ffffeb, 27),
+    (0xffffffe, 28),
+    (0x7ffffec, 27),
+    (0x7ffffed, 27),
+    (0x7ffffee, 27),
+    (0x7ffffef, 27),
+    (0x7fffff0, 27),
+    (0x3ffffee, 26),
+    (0x3fffffff, 30)
+];
+
+// Symbols ordered by (code length, code), for canonical decoding.
+static DECODE_SYMBOLS: [u16; 257] = [
+    48, 49, 50, 97, 99, 101, 105, 111, 115, 116, 32, 37,
+    45, 46, 47, 51, 52, 53, 54, 55, 56, 57, 61, 65,
+    95, 98, 100, 102, 103, 104, 108, 109, 110, 112, 114, 117,
+    58, 66, 67, 68, 69, 70, 71, 72, 73, 74, 75, 76,
+    77, 78, 79, 80, 81, 82, 83, 84, 85, 86, 87, 89,
+    106, 107, 113, 118, 119, 120, 121, 122, 38, 42, 44, 59,
+    88, 90, 33, 34, 40, 41, 63, 39, 43, 124, 35, 62,
+    0, 36, 64, 91, 93, 126, 94, 125, 60, 96, 123, 92,
+    195, 208, 128, 130, 131, 162, 184, 194, 224, 226, 153, 161,
+    167, 172, 176, 177, 179, 209, 216, 217, 227, 229, 230, 129,
+    132, 133, 134, 136, 146, 154, 156, 160, 163, 164, 169, 170,
+    173, 178, 181, 185, 186, 187, 189, 190, 196, 198, 228, 232,
+    233, 1, 135, 137, 138, 139, 140, 141, 143, 147, 149, 150,
+    151, 152, 155, 157, 158, 165, 166, 168, 174, 175, 180, 182,
+    183, 188, 191, 197, 231, 239, 9, 142, 144, 145, 148, 159,
+    171, 206, 215, 225, 236, 237, 199, 207, 234, 235, 192, 193,
+    200, 201, 202, 205, 210, 213, 218, 219, 238, 240, 242, 243,
+    255, 203, 204, 211, 212, 214, 221, 222, 223, 241, 244, 245,
+    246, 247, 248, 250, 251, 252, 253, 254, 2, 3, 4, 5,
+    6, 7, 8, 11, 12, 14, 15, 16, 17, 18, 19, 20,
+    21, 23, 24, 25, 26, 27, 28, 29, 30, 31, 127, 220,
+    249, 10, 13, 22, 256
+];
+
+// For each code length, the first code of that length, its position in
+// DECODE_SYMBOLS, and how many codes have that length.
+static FIRST_CODE: [u32; 31] = [
+    0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x14, 0x5c,
+    0xf8, 0x0, 0x3f8, 0x7fa, 0xffa, 0x1ff8, 0x3ffc, 0x7ffc,
+    0x0, 0x0, 0x0, 0x7fff0, 0xfffe6, 0x1fffdc, 0x3fffd2, 0x7fffd8,
+    0xffffea, 0x1ffffec, 0x3ffffe0, 0x7ffffde, 0xfffffe2, 0x0, 0x3ffffffc
+];
+
+static FIRST_INDEX: [u16; 31] = [
+    0, 0, 0, 0, 0, 0, 10, 36,
+    68, 0, 74, 79, 82, 84, 90, 92,
+    0, 0, 0, 95, 98, 106, 119, 145,
+    174, 186, 190, 205, 224, 0, 253
+];
+
+static CODE_COUNT: [u16; 31] = [
+    0, 0, 0, 0, 0, 10, 26, 32,
+    6, 0, 5, 3, 2, 6, 2, 3,
+    0, 0, 0, 3, 8, 13, 26, 29,
+    12, 4, 15, 19, 29, 0, 4
+];
+
+#[cfg(test)]
+mod test {
+    use super::{encode, encoded_len, decode, Decoder, HuffmanError};
+
+    // RFC 7541 C.4.1
+    const WWW_EXAMPLE_COM: &'static [u8] = &[
+        0xf1, 0xe3, 0xc2, 0xe5, 0xf2, 0x3a, 0x6b, 0xa0, 0xab, 0x90, 0xf4, 0xff
+    ];
+
+    #[test]
+    fn test_encode() {
+        let mut buf = vec![];
+        encode(b"www.example.com", &mut buf);
+        assert_eq!(buf, WWW_EXAMPLE_COM);
+        assert_eq!(encoded_len(b"www.example.com"), WWW_EXAMPLE_COM.len());
+    }
+
+    #[test]
+    fn test_decode() {
+        let mut buf = vec![];
+        decode(WWW_EXAMPLE_COM, &mut buf).unwrap();
+        assert_eq!(buf, b"www.example.com");
+    }
+
+    #[test]
+    fn test_decode_streaming() {
+        let mut decoder = Decoder::new();
+        let mut buf = vec![];
+        for chunk in WWW_EXAMPLE_COM.chunks(5) {
+            decoder.decode(chunk, &mut buf).unwrap();
+        }
+        decoder.finish().unwrap();
+        assert_eq!(buf, b"www.example.com");
+    }
+
+    #[test]
+    fn test_roundtrip_all_bytes() {
+        let src = (0..256).map(|b| b as u8).collect::<Vec<u8>>();
+        let mut encoded = vec![];
+        encode(&src, &mut encoded);
+        assert_eq!(encoded.len(), encoded_len(&src));
+
+        let mut decoded = vec![];
+        decode(&encoded, &mut decoded).unwrap();
+        assert_eq!(decoded, src);
+    }
+
+    #[test]
+    fn test_decode_errors() {
+        // A full EOS symbol.
+        assert_eq!(decode(&[0xff, 0xff, 0xff, 0xff], &mut vec![]),
+                   Err(HuffmanError::EndOfString));
+
+        // 'a' (00011) padded with zeros instead of ones.
+        assert_eq!(decode(&[0x18], &mut vec![]), Err(HuffmanError::InvalidPadding));
+
+        // A whole byte of padding.
+        assert_eq!(decode(&[0x1f, 0xff], &mut vec![]), Err(HuffmanError::InvalidPadding));
+    }
+}
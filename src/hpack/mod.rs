@@ -0,0 +1,13 @@
+//! HPACK header compression, as specified in RFC 7541.
+
+pub use self::encoder::Encoder;
+pub use self::decoder::{Decoder, DecoderError, DecodeIter, HeaderField};
+pub use self::table::DynamicTable;
+
+pub mod huffman;
+pub mod static_table;
+pub mod primitives;
+
+mod encoder;
+mod decoder;
+mod table;
@@ -0,0 +1,141 @@
+//! The integer and string literal representations used by HPACK
+//! (RFC 7541, Sections 5.1 and 5.2).
+//!
+//! Integers are encoded with an N-bit prefix sharing the first byte with
+//! flags in its high bits, and strings are a length-prefixed sequence of
+//! octets, optionally Huffman coded.
+
+use std::borrow::Cow;
+
+use super::huffman;
+use super::DecoderError;
+
+/// Encode `value` as an HPACK integer with an N-bit prefix, or-ing
+/// `flags` into the high bits of the first byte.
+pub fn encode_integer(value: usize, prefix_bits: u8, flags: u8, dst: &mut Vec<u8>) {
+    let max_prefix = (1 << prefix_bits) - 1;
+
+    if value < max_prefix {
+        dst.push(flags | value as u8);
+        return
+    }
+
+    dst.push(flags | max_prefix as u8);
+    let mut rest = value - max_prefix;
+
+    while rest >= 128 {
+        dst.push((rest % 128) as u8 | 0x80);
+        rest /= 128;
+    }
+
+    dst.push(rest as u8);
+}
+
+/// Encode a string literal, Huffman coding it if allowed and shorter.
+pub fn encode_string(string: &[u8], huffman: bool, dst: &mut Vec<u8>) {
+    let huffman_len = huffman::encoded_len(string);
+
+    if huffman && huffman_len < string.len() {
+        encode_integer(huffman_len, 7, 0x80, dst);
+        huffman::encode(string, dst);
+    } else {
+        encode_integer(string.len(), 7, 0, dst);
+        dst.extend(string.iter().cloned());
+    }
+}
+
+/// Decode an HPACK integer with an N-bit prefix from the start of `buf`,
+/// returning the value and the number of bytes read.
+pub fn decode_integer(buf: &[u8], prefix_bits: u8) -> Result<(usize, usize), DecoderError> {
+    let max_prefix = (1 << prefix_bits) - 1;
+
+    if buf.is_empty() { return Err(DecoderError::Truncated) }
+
+    let mut value = (buf[0] & max_prefix as u8) as usize;
+    if value < max_prefix { return Ok((value, 1)) }
+
+    let mut shift = 0;
+    for (index, &byte) in buf[1..].iter().enumerate() {
+        let bits = ((byte & 0x7f) as usize).checked_shl(shift);
+        value = match bits.and_then(|bits| value.checked_add(bits)) {
+            Some(value) if shift < 32 => value,
+            _ => return Err(DecoderError::IntegerOverflow)
+        };
+
+        if byte & 0x80 == 0 { return Ok((value, index + 2)) }
+        shift += 7;
+    }
+
+    Err(DecoderError::Truncated)
+}
+
+/// Decode a string literal from the start of `buf`, returning the string
+/// and the number of bytes read.
+///
+/// Plain literals are borrowed from `buf`, Huffman coded ones are decoded
+/// into a new buffer.
+pub fn decode_string(buf: &[u8]) -> Result<(Cow<[u8]>, usize), DecoderError> {
+    let (len, read) = try!(decode_integer(buf, 7));
+    let end = try!(read.checked_add(len).ok_or(DecoderError::Truncated));
+
+    if buf.len() < end { return Err(DecoderError::Truncated) }
+    let string = &buf[read..end];
+
+    if buf[0] & 0x80 == 0x80 {
+        let mut decoded = Vec::with_capacity(len * 8 / 5);
+        try!(huffman::decode(string, &mut decoded).map_err(DecoderError::Huffman));
+        Ok((Cow::Owned(decoded), end))
+    } else {
+        Ok((Cow::Borrowed(string), end))
+    }
+}
+
+#[test]
+fn test_encode_integer() {
+    // RFC 7541 C.1.1 - C.1.3
+    let mut buf = vec![];
+    encode_integer(10, 5, 0, &mut buf);
+    assert_eq!(buf, [0x0a]);
+
+    buf.clear();
+    encode_integer(1337, 5, 0, &mut buf);
+    assert_eq!(buf, [0x1f, 0x9a, 0x0a]);
+
+    buf.clear();
+    encode_integer(42, 8, 0, &mut buf);
+    assert_eq!(buf, [0x2a]);
+}
+
+#[test]
+fn test_encode_string() {
+    let mut buf = vec![];
+    encode_string(b"no-cache", false, &mut buf);
+    assert_eq!(buf, [0x08, 0x6e, 0x6f, 0x2d, 0x63, 0x61, 0x63, 0x68, 0x65]);
+
+    buf.clear();
+    encode_string(b"no-cache", true, &mut buf);
+    assert_eq!(buf, [0x86, 0xa8, 0xeb, 0x10, 0x64, 0x9c, 0xbf]);
+}
+
+#[test]
+fn test_decode_integer() {
+    assert_eq!(decode_integer(&[0x0a], 5), Ok((10, 1)));
+    assert_eq!(decode_integer(&[0xff, 0x9a, 0x0a, 0x00], 5), Ok((1337, 3)));
+    assert_eq!(decode_integer(&[0x2a], 8), Ok((42, 1)));
+    assert_eq!(decode_integer(&[0x1f, 0x9a], 5), Err(DecoderError::Truncated));
+    assert_eq!(decode_integer(&[0x1f, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x7f], 5),
+               Err(DecoderError::IntegerOverflow));
+}
+
+#[test]
+fn test_decode_string() {
+    let (string, read) = decode_string(&[0x86, 0xa8, 0xeb, 0x10, 0x64, 0x9c, 0xbf, 0x00]).unwrap();
+    assert_eq!(&*string, b"no-cache");
+    assert_eq!(read, 7);
+
+    let (string, read) = decode_string(&[0x03, b'a', b'b', b'c']).unwrap();
+    assert_eq!(string, Cow::Borrowed(&b"abc"[..]));
+    assert_eq!(read, 4);
+
+    assert_eq!(decode_string(&[0x03, b'a']), Err(DecoderError::Truncated));
+}
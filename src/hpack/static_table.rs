@@ -0,0 +1,131 @@
+//! The HPACK static table (RFC 7541, Appendix A).
+//!
+//! Indices are 1-based, as they appear in header blocks.
+
+/// The number of entries in the static table.
+pub const LEN: usize = 61;
+
+/// A header field found in a table.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Match {
+    /// Both the name and the value matched the entry at this index.
+    Full(usize),
+
+    /// Only the name matched the entry at this index.
+    Name(usize)
+}
+
+/// Get the entry at a 1-based index.
+#[inline]
+pub fn get(index: usize) -> Option<(&'static [u8], &'static [u8])> {
+    if index == 0 || index > LEN {
+        None
+    } else {
+        Some(ENTRIES[index - 1])
+    }
+}
+
+/// Find the first entry matching a header field, preferring an entry
+/// where both the name and the value match.
+pub fn find(name: &[u8], value: &[u8]) -> Option<Match> {
+    let mut result = None;
+
+    for (index, &(n, v)) in ENTRIES.iter().enumerate() {
+        if n != name { continue }
+
+        if v == value {
+            return Some(Match::Full(index + 1))
+        } else if result.is_none() {
+            result = Some(Match::Name(index + 1))
+        }
+    }
+
+    result
+}
+
+/// Every `(name, value)` entry, in index order.
+pub static ENTRIES: [(&'static [u8], &'static [u8]); LEN] = [
+    (b":authority", b""),
+    (b":method", b"GET"),
+    (b":method", b"POST"),
+    (b":path", b"/"),
+    (b":path", b"/index.html"),
+    (b":scheme", b"http"),
+    (b":scheme", b"https"),
+    (b":status", b"200"),
+    (b":status", b"204"),
+    (b":status", b"206"),
+    (b":status", b"304"),
+    (b":status", b"400"),
+    (b":status", b"404"),
+    (b":status", b"500"),
+    (b"accept-charset", b""),
+    (b"accept-encoding", b"gzip, deflate"),
+    (b"accept-language", b""),
+    (b"accept-ranges", b""),
+    (b"accept", b""),
+    (b"access-control-allow-origin", b""),
+    (b"age", b""),
+    (b"allow", b""),
+    (b"authorization", b""),
+    (b"cache-control", b""),
+    (b"content-disposition", b""),
+    (b"content-encoding", b""),
+    (b"content-language", b""),
+    (b"content-length", b""),
+    (b"content-location", b""),
+    (b"content-range", b""),
+    (b"content-type", b""),
+    (b"cookie", b""),
+    (b"date", b""),
+    (b"etag", b""),
+    (b"expect", b""),
+    (b"expires", b""),
+    (b"from", b""),
+    (b"host", b""),
+    (b"if-match", b""),
+    (b"if-modified-since", b""),
+    (b"if-none-match", b""),
+    (b"if-range", b""),
+    (b"if-unmodified-since", b""),
+    (b"last-modified", b""),
+    (b"link", b""),
+    (b"location", b""),
+    (b"max-forwards", b""),
+    (b"proxy-authenticate", b""),
+    (b"proxy-authorization", b""),
+    (b"range", b""),
+    (b"referer", b""),
+    (b"refresh", b""),
+    (b"retry-after", b""),
+    (b"server", b""),
+    (b"set-cookie", b""),
+    (b"strict-transport-security", b""),
+    (b"transfer-encoding", b""),
+    (b"user-agent", b""),
+    (b"vary", b""),
+    (b"via", b""),
+    (b"www-authenticate", b"")
+];
+
+#[cfg(test)]
+mod test {
+    use super::{get, find, Match, LEN};
+
+    #[test]
+    fn test_get() {
+        assert_eq!(get(0), None);
+        assert_eq!(get(1), Some((&b":authority"[..], &b""[..])));
+        assert_eq!(get(2), Some((&b":method"[..], &b"GET"[..])));
+        assert_eq!(get(LEN), Some((&b"www-authenticate"[..], &b""[..])));
+        assert_eq!(get(LEN + 1), None);
+    }
+
+    #[test]
+    fn test_find() {
+        assert_eq!(find(b":status", b"404"), Some(Match::Full(13)));
+        assert_eq!(find(b":status", b"418"), Some(Match::Name(8)));
+        assert_eq!(find(b"accept-encoding", b"gzip, deflate"), Some(Match::Full(16)));
+        assert_eq!(find(b"x-custom", b""), None);
+    }
+}
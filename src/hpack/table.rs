@@ -0,0 +1,175 @@
+use std::collections::VecDeque;
+
+use super::static_table::{self, Match};
+
+/// The default and initial maximum size of the dynamic table.
+pub const DEFAULT_TABLE_SIZE: usize = ::consts::DEFAULT_HEADER_TABLE_SIZE as usize;
+
+// Every dynamic table entry is charged 32 bytes of overhead.
+const ENTRY_OVERHEAD: usize = 32;
+
+/// The size a header field is charged in the dynamic table.
+#[inline]
+pub fn entry_size(name: &[u8], value: &[u8]) -> usize {
+    name.len() + value.len() + ENTRY_OVERHEAD
+}
+
+/// The HPACK dynamic table, newest entry first.
+///
+/// Entries are evicted oldest first whenever an insertion or a change to
+/// the maximum size would take the table over its maximum size.
+#[derive(Clone, Debug)]
+pub struct DynamicTable {
+    entries: VecDeque<(Vec<u8>, Vec<u8>)>,
+    size: usize,
+    max_size: usize
+}
+
+impl DynamicTable {
+    pub fn new(max_size: usize) -> DynamicTable {
+        DynamicTable {
+            entries: VecDeque::new(),
+            size: 0,
+            max_size: max_size
+        }
+    }
+
+    /// Add an entry, evicting the oldest entries to make room for it.
+    ///
+    /// An entry larger than the maximum size empties the table.
+    pub fn insert(&mut self, name: Vec<u8>, value: Vec<u8>) {
+        let size = entry_size(&name, &value);
+
+        if size > self.max_size {
+            self.entries.clear();
+            self.size = 0;
+            return
+        }
+
+        self.evict_to(self.max_size - size);
+        self.size += size;
+        self.entries.push_front((name, value));
+    }
+
+    /// Get an entry by its dynamic index, starting at 0 for the newest.
+    pub fn get(&self, index: usize) -> Option<(&[u8], &[u8])> {
+        self.entries.get(index).map(|&(ref name, ref value)| (&**name, &**value))
+    }
+
+    /// The number of entries in the table.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The current size of the table, as defined by RFC 7541 Section 4.1.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn max_size(&self) -> usize {
+        self.max_size
+    }
+
+    /// Change the maximum size, evicting entries until the table fits.
+    pub fn set_max_size(&mut self, max_size: usize) {
+        self.max_size = max_size;
+        self.evict_to(max_size);
+    }
+
+    fn evict_to(&mut self, target: usize) {
+        while self.size > target {
+            let (name, value) = self.entries.pop_back().unwrap();
+            self.size -= entry_size(&name, &value);
+        }
+    }
+}
+
+/// Get an entry by its index in the combined static and dynamic tables.
+pub fn get(dynamic: &DynamicTable, index: usize) -> Option<(&[u8], &[u8])> {
+    if index <= static_table::LEN {
+        static_table::get(index)
+    } else {
+        dynamic.get(index - static_table::LEN - 1)
+    }
+}
+
+/// Search the static table and then the dynamic table for a header,
+/// preferring a full match over a name-only match.
+///
+/// Indices are in the combined address space of both tables.
+pub fn find(dynamic: &DynamicTable, name: &[u8], value: &[u8]) -> Option<Match> {
+    let mut result = static_table::find(name, value);
+    if let Some(Match::Full(_)) = result { return result }
+
+    for (index, &(ref n, ref v)) in dynamic.entries.iter().enumerate() {
+        if &**n != name { continue }
+
+        let index = index + static_table::LEN + 1;
+        if &**v == value {
+            return Some(Match::Full(index))
+        } else if result.is_none() {
+            result = Some(Match::Name(index))
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use hpack::static_table::Match;
+    use super::{DynamicTable, find, get};
+
+    #[test]
+    fn test_dynamic_table_eviction() {
+        // Each entry is 1 + 1 + 32 = 34 bytes.
+        let mut table = DynamicTable::new(70);
+        table.insert(b"a".to_vec(), b"1".to_vec());
+        table.insert(b"b".to_vec(), b"2".to_vec());
+        assert_eq!(table.len(), 2);
+        assert_eq!(table.size(), 68);
+
+        table.insert(b"c".to_vec(), b"3".to_vec());
+        assert_eq!(table.len(), 2);
+        assert_eq!(table.get(0), Some((&b"c"[..], &b"3"[..])));
+        assert_eq!(table.get(1), Some((&b"b"[..], &b"2"[..])));
+
+        table.set_max_size(40);
+        assert_eq!(table.len(), 1);
+        assert_eq!(table.get(0), Some((&b"c"[..], &b"3"[..])));
+
+        table.insert(vec![b'x'; 100], vec![]);
+        assert_eq!(table.len(), 0);
+        assert_eq!(table.size(), 0);
+    }
+
+    #[test]
+    fn test_get() {
+        let mut table = DynamicTable::new(4096);
+        table.insert(b"custom-key".to_vec(), b"custom-value".to_vec());
+
+        assert_eq!(get(&table, 0), None);
+        assert_eq!(get(&table, 61), Some((&b"www-authenticate"[..], &b""[..])));
+        assert_eq!(get(&table, 62), Some((&b"custom-key"[..], &b"custom-value"[..])));
+        assert_eq!(get(&table, 63), None);
+    }
+
+    #[test]
+    fn test_find() {
+        let mut table = DynamicTable::new(4096);
+        table.insert(b"custom-key".to_vec(), b"custom-value".to_vec());
+
+        assert_eq!(find(&table, b":method", b"POST"), Some(Match::Full(3)));
+        assert_eq!(find(&table, b":method", b"PUT"), Some(Match::Name(2)));
+        assert_eq!(find(&table, b"custom-key", b"custom-value"), Some(Match::Full(62)));
+        assert_eq!(find(&table, b"custom-key", b"other"), Some(Match::Name(62)));
+        assert_eq!(find(&table, b"x-unknown", b""), None);
+
+        table.insert(b":method".to_vec(), b"PUT".to_vec());
+        assert_eq!(find(&table, b":method", b"PUT"), Some(Match::Full(62)));
+    }
+}
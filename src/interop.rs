@@ -0,0 +1,243 @@
+//! Conversions to and from the frames of the `h2` crate.
+//!
+//! `h2` keeps header blocks decoded, so HEADERS, PUSH_PROMISE and
+//! CONTINUATION frames do not convert, and neither do frame types `h2` does
+//! not know; both are an `InteropError::Unsupported`. `h2` does not expose
+//! the weight of a PRIORITY frame, so those only convert to `h2`.
+//!
+//! `h2` keeps a single value per setting, so converting a SETTINGS frame
+//! to `h2` drops unknown and repeated settings, and converting back yields
+//! the settings in identifier order.
+
+use std::convert::TryFrom;
+
+use bytes::{Bytes, BytesMut};
+use h2::frame as h2;
+
+use {Frame, Payload, Setting, Settings, SettingIdentifier, Kind, Flag, Error, ErrorCode,
+     StreamIdentifier, SizeIncrement, OwnedFrame, OwnedPayload};
+
+/// Errors produced while converting frames.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum InteropError {
+    /// The other crate cannot represent frames of this kind.
+    Unsupported(Kind),
+
+    /// `h2` rejected the frame as invalid.
+    Rejected,
+
+    /// This crate could not parse the frame `h2` encoded.
+    Invalid(Error)
+}
+
+impl<'a, 'b> TryFrom<&'b Frame<'a>> for h2::Frame<Bytes> {
+    type Error = InteropError;
+
+    fn try_from(frame: &'b Frame<'a>) -> Result<h2::Frame<Bytes>, InteropError> {
+        let header = frame.header;
+        let id = h2::StreamId::from(header.id.0);
+
+        Ok(match frame.payload {
+            Payload::Data { data } => {
+                let mut converted = h2::Data::new(id, Bytes::copy_from_slice(data));
+                converted.set_end_stream(header.flag.contains(Flag::end_stream()));
+                converted.into()
+            },
+            Payload::Reset(error) => h2::Reset::new(id, h2::Reason::from(error.0)).into(),
+            Payload::Ping(_) if frame.is_ping_ack() =>
+                h2::Ping::pong(frame.payload.ping_data().unwrap()).into(),
+            Payload::Ping(_) => h2::Ping::new(frame.payload.ping_data().unwrap()).into(),
+            Payload::GoAway { last, error, data } =>
+                h2::GoAway::with_debug_data(h2::StreamId::from(last.0),
+                                            h2::Reason::from(error.0),
+                                            Bytes::copy_from_slice(data)).into(),
+            Payload::WindowUpdate(increment) => h2::WindowUpdate::new(id, increment.0).into(),
+            Payload::Priority(_) | Payload::Settings(_) => {
+                // Neither can be built directly, so go through the wire.
+                let mut payload = vec![0; frame.payload.encoded_len()];
+                frame.payload.encode(&mut payload);
+
+                let kind = h2::Kind::new(header.kind.encode());
+                let head = h2::Head::new(kind, header.flag.bits(), id);
+                try!(match header.kind {
+                    Kind::Priority => h2::Priority::load(head, &payload).map(Into::into),
+                    _ => h2::Settings::load(head, &payload).map(Into::into)
+                }.map_err(|_| InteropError::Rejected))
+            },
+            _ => return Err(InteropError::Unsupported(header.kind))
+        })
+    }
+}
+
+impl<'a> TryFrom<&'a h2::Frame<Bytes>> for OwnedFrame {
+    type Error = InteropError;
+
+    fn try_from(frame: &'a h2::Frame<Bytes>) -> Result<OwnedFrame, InteropError> {
+        let mut buf = BytesMut::new();
+
+        match *frame {
+            h2::Frame::Data(ref data) => {
+                let flag = if data.is_end_stream() { Flag::end_stream() } else { Flag::empty() };
+                return Ok(OwnedFrame {
+                    header: ::FrameHeader {
+                        length: data.payload().len() as u32,
+                        kind: Kind::Data,
+                        flag: flag,
+                        id: StreamIdentifier(u32::from(data.stream_id()))
+                    },
+                    payload: OwnedPayload::Data { data: data.payload().to_vec() }
+                })
+            },
+            h2::Frame::Reset(ref reset) => {
+                let id = StreamIdentifier(u32::from(reset.stream_id()));
+                let error = ErrorCode(u32::from(reset.reason()));
+                return Ok(OwnedFrame::from(Frame {
+                    header: ::FrameHeader {
+                        length: 4,
+                        kind: Kind::Reset,
+                        flag: Flag::empty(),
+                        id: id
+                    },
+                    payload: Payload::Reset(error)
+                }))
+            },
+            h2::Frame::WindowUpdate(ref update) => {
+                let id = StreamIdentifier(u32::from(update.stream_id()));
+                return Ok(OwnedFrame::from(Frame {
+                    header: ::FrameHeader {
+                        length: 4,
+                        kind: Kind::WindowUpdate,
+                        flag: Flag::empty(),
+                        id: id
+                    },
+                    payload: Payload::WindowUpdate(SizeIncrement(update.size_increment()))
+                }))
+            },
+            h2::Frame::Settings(ref settings) => settings.encode(&mut buf),
+            h2::Frame::Ping(ref ping) => ping.encode(&mut buf),
+            h2::Frame::GoAway(ref goaway) => goaway.encode(&mut buf),
+            h2::Frame::Headers(_) => return Err(InteropError::Unsupported(Kind::Headers)),
+            h2::Frame::Priority(_) => return Err(InteropError::Unsupported(Kind::Priority)),
+            h2::Frame::PushPromise(_) =>
+                return Err(InteropError::Unsupported(Kind::PushPromise))
+        }
+
+        Frame::try_from(&buf[..]).map(OwnedFrame::from).map_err(InteropError::Invalid)
+    }
+}
+
+impl<'a> TryFrom<Settings<'a>> for h2::Settings {
+    type Error = InteropError;
+
+    /// The settings as `h2` keeps them, without unknown settings and with
+    /// the last value of any repeated setting.
+    fn try_from(settings: Settings<'a>) -> Result<h2::Settings, InteropError> {
+        let payload = Payload::Settings(settings);
+        let mut buf = vec![0; payload.encoded_len()];
+        payload.encode(&mut buf);
+
+        let head = h2::Head::new(h2::Kind::Settings, 0, h2::StreamId::zero());
+        h2::Settings::load(head, &buf).map_err(|_| InteropError::Rejected)
+    }
+}
+
+/// The settings of an `h2` SETTINGS frame, in identifier order.
+pub fn settings_from_h2(settings: &h2::Settings) -> Vec<Setting> {
+    let values = [
+        (SettingIdentifier::HeaderTableSize, settings.header_table_size()),
+        (SettingIdentifier::EnablePush, settings.is_push_enabled().map(|push| push as u32)),
+        (SettingIdentifier::MaxConcurrentStreams, settings.max_concurrent_streams()),
+        (SettingIdentifier::InitialWindowSize, settings.initial_window_size()),
+        (SettingIdentifier::MaxFrameSize, settings.max_frame_size()),
+        (SettingIdentifier::MaxHeaderListSize, settings.max_header_list_size()),
+        (SettingIdentifier::EnableConnectProtocol,
+         settings.is_extended_connect_protocol_enabled().map(|enabled| enabled as u32))
+    ];
+
+    values.iter()
+        .filter_map(|&(identifier, value)| value.map(|value| Setting::new(identifier, value)))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use std::convert::TryFrom;
+
+    use bytes::Bytes;
+    use h2::frame as h2;
+
+    use {Frame, Setting, SettingIdentifier, Settings, Payload, Kind, Flag, FrameHeader,
+         StreamIdentifier, HttpError, OwnedFrame};
+    use super::{InteropError, settings_from_h2};
+
+    fn round_trip(frame: Frame) {
+        let converted = h2::Frame::try_from(&frame).unwrap();
+        assert_eq!(OwnedFrame::try_from(&converted), Ok(OwnedFrame::from(frame)));
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let settings = [Setting::new(SettingIdentifier::EnablePush, 0),
+                        Setting::new(SettingIdentifier::MaxFrameSize, 20000)];
+        round_trip(Frame {
+            header: FrameHeader {
+                length: 12,
+                kind: Kind::Settings,
+                flag: Flag::empty(),
+                id: StreamIdentifier(0)
+            },
+            payload: Payload::Settings(Settings::new(&settings))
+        });
+        round_trip(Frame::settings_ack());
+        round_trip(Frame::ping([1; 8]));
+        round_trip(Frame::ping([1; 8]).ping_ack().unwrap());
+        round_trip(Frame::goaway(StreamIdentifier(7), HttpError::Cancel, Some("bye")));
+        round_trip(Frame {
+            header: FrameHeader {
+                length: 5,
+                kind: Kind::Data,
+                flag: Flag::end_stream(),
+                id: StreamIdentifier(3)
+            },
+            payload: Payload::Data { data: b"hello" }
+        });
+        round_trip(Frame {
+            header: FrameHeader {
+                length: 4,
+                kind: Kind::Reset,
+                flag: Flag::empty(),
+                id: StreamIdentifier(3)
+            },
+            payload: Payload::reset(HttpError::RefusedStream)
+        });
+    }
+
+    #[test]
+    fn test_unsupported() {
+        let headers = Frame {
+            header: FrameHeader {
+                length: 0,
+                kind: Kind::Headers,
+                flag: Flag::end_headers(),
+                id: StreamIdentifier(1)
+            },
+            payload: Payload::Headers { priority: None, block: &[] }
+        };
+        assert_eq!(h2::Frame::try_from(&headers).err(),
+                   Some(InteropError::Unsupported(Kind::Headers)));
+
+        let settings = [Setting::new(SettingIdentifier::InitialWindowSize, 10),
+                        Setting::new(SettingIdentifier::EnablePush, 0),
+                        Setting::new(SettingIdentifier::InitialWindowSize, 20)];
+        let converted = h2::Settings::try_from(Settings::new(&settings)).unwrap();
+        assert_eq!(settings_from_h2(&converted), [settings[1], settings[2]]);
+        let invalid = [Setting::new(SettingIdentifier::EnablePush, 2)];
+        assert_eq!(h2::Settings::try_from(Settings::new(&invalid)).err(),
+                   Some(InteropError::Rejected));
+
+        let goaway = h2::Frame::GoAway(h2::GoAway::with_debug_data(
+            h2::StreamId::from(1), h2::Reason::from(2), Bytes::from_static(b"x")));
+        assert!(OwnedFrame::try_from(&goaway).is_ok());
+    }
+}
@@ -1,21 +1,33 @@
-#[repr(u8)]
+use std::fmt;
+
+use Error;
+
+/// The type of a frame.
+///
+/// More frame types may be defined in future, so matches outside this
+/// crate need a wildcard arm; `encode` gives the type byte of any kind.
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[non_exhaustive]
 pub enum Kind {
-    Data = 0,
-    Headers = 1,
-    Priority = 2,
-    Reset = 3,
-    Settings = 4,
-    PushPromise = 5,
-    Ping = 6,
-    GoAway = 7,
-    WindowUpdate = 8,
-    Continuation = 9,
-    Unregistered
+    Data,
+    Headers,
+    Priority,
+    Reset,
+    Settings,
+    PushPromise,
+    Ping,
+    GoAway,
+    WindowUpdate,
+    Continuation,
+
+    /// A frame type not defined by RFC 7540, with its raw type byte so
+    /// it can be forwarded unmodified.
+    Unregistered(u8)
 }
 
 impl Kind {
-    pub fn new(byte: u8) -> Kind {
+    pub const fn new(byte: u8) -> Kind {
         return match byte {
             0 => Kind::Data,
             1 => Kind::Headers,
@@ -27,11 +39,20 @@ impl Kind {
             7 => Kind::GoAway,
             8 => Kind::WindowUpdate,
             9 => Kind::Continuation,
-            _ => Kind::Unregistered
+            _ => Kind::Unregistered(byte)
         }
     }
 
-    pub fn encode(&self) -> u8 {
+    /// Like `new`, but unknown frame types are an `Error::BadKind`
+    /// instead of `Kind::Unregistered`.
+    pub fn new_strict(byte: u8) -> Result<Kind, Error> {
+        match Kind::new(byte) {
+            Kind::Unregistered(byte) => Err(Error::BadKind(byte)),
+            kind => Ok(kind)
+        }
+    }
+
+    pub const fn encode(&self) -> u8 {
         match *self {
             Kind::Data => 0,
             Kind::Headers => 1,
@@ -43,15 +64,139 @@ impl Kind {
             Kind::GoAway => 7,
             Kind::WindowUpdate => 8,
             Kind::Continuation => 9,
-            Kind::Unregistered => 255
+            Kind::Unregistered(byte) => byte
+        }
+    }
+
+    /// Whether frames of this kind may have the PADDED flag: DATA, HEADERS
+    /// and PUSH_PROMISE.
+    pub const fn allows_padding(&self) -> bool {
+        match *self {
+            Kind::Data | Kind::Headers | Kind::PushPromise => true,
+            _ => false
+        }
+    }
+
+    /// Whether frames of this kind may carry a stream priority. PRIORITY
+    /// frames always do, and HEADERS frames do with the PRIORITY flag.
+    pub const fn allows_priority(&self) -> bool {
+        match *self {
+            Kind::Headers | Kind::Priority => true,
+            _ => false
+        }
+    }
+
+    /// Whether frames of this kind apply to the whole connection, and so
+    /// must be sent on stream 0: SETTINGS, PING and GOAWAY.
+    ///
+    /// WINDOW_UPDATE frames may be sent on any stream, and are neither
+    /// connection nor stream level.
+    pub const fn is_connection_level(&self) -> bool {
+        match *self {
+            Kind::Settings | Kind::Ping | Kind::GoAway => true,
+            _ => false
+        }
+    }
+
+    /// Whether frames of this kind apply to a single stream, and so must
+    /// not be sent on stream 0.
+    pub const fn is_stream_level(&self) -> bool {
+        match *self {
+            Kind::Data | Kind::Headers | Kind::Priority | Kind::Reset |
+            Kind::PushPromise | Kind::Continuation => true,
+            _ => false
+        }
+    }
+
+    /// The payload length every frame of this kind must have, if there is
+    /// one.
+    ///
+    /// SETTINGS frames are not included: they must be empty only when they
+    /// are an ACK, and are otherwise any multiple of 6 bytes.
+    pub const fn has_fixed_length(&self) -> Option<u32> {
+        match *self {
+            Kind::Priority => Some(5),
+            Kind::Reset | Kind::WindowUpdate => Some(4),
+            Kind::Ping => Some(8),
+            _ => None
+        }
+    }
+}
+
+impl Kind {
+    // The name of a registered frame type as written in RFC 7540.
+    fn name(&self) -> Option<&'static str> {
+        Some(match *self {
+            Kind::Data => "DATA",
+            Kind::Headers => "HEADERS",
+            Kind::Priority => "PRIORITY",
+            Kind::Reset => "RST_STREAM",
+            Kind::Settings => "SETTINGS",
+            Kind::PushPromise => "PUSH_PROMISE",
+            Kind::Ping => "PING",
+            Kind::GoAway => "GOAWAY",
+            Kind::WindowUpdate => "WINDOW_UPDATE",
+            Kind::Continuation => "CONTINUATION",
+            Kind::Unregistered(_) => return None
+        })
+    }
+}
+
+impl fmt::Display for Kind {
+    /// The name of the frame type as written in RFC 7540.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.name() {
+            Some(name) => f.write_str(name),
+            None => write!(f, "UNKNOWN(0x{:02x})", self.encode())
+        }
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl ::defmt::Format for Kind {
+    fn format(&self, f: ::defmt::Formatter) {
+        match self.name() {
+            Some(name) => ::defmt::write!(f, "{=str}", name),
+            None => ::defmt::write!(f, "UNKNOWN({=u8:#04x})", self.encode())
         }
     }
 }
 
 #[test]
 fn test_encode() {
-    for n in 0..10 {
-        assert_eq!(Kind::new(n), Kind::new(Kind::new(n).encode()));
+    for n in 0..256 {
+        assert_eq!(Kind::new(n as u8).encode(), n as u8);
     }
 }
 
+#[test]
+fn test_new_strict() {
+    assert_eq!(Kind::new_strict(0x9), Ok(Kind::Continuation));
+    assert_eq!(Kind::new_strict(0xa), Err(Error::BadKind(0xa)));
+}
+
+#[test]
+fn test_properties() {
+    assert!(Kind::Data.allows_padding());
+    assert!(!Kind::Continuation.allows_padding());
+    assert!(Kind::Headers.allows_priority());
+    assert!(!Kind::PushPromise.allows_priority());
+
+    for n in 0..256 {
+        let kind = Kind::new(n as u8);
+        assert!(!(kind.is_connection_level() && kind.is_stream_level()));
+    }
+    assert!(!Kind::WindowUpdate.is_connection_level());
+    assert!(!Kind::WindowUpdate.is_stream_level());
+    assert!(!Kind::Unregistered(0xa).is_stream_level());
+
+    assert_eq!(Kind::Ping.has_fixed_length(), Some(8));
+    assert_eq!(Kind::Settings.has_fixed_length(), None);
+}
+
+#[test]
+fn test_unregistered_keeps_type() {
+    assert_eq!(Kind::new(0xa), Kind::Unregistered(0xa));
+    assert_eq!(Kind::new(0xff), Kind::Unregistered(0xff));
+}
+
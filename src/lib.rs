@@ -1,5 +1,4 @@
 #![cfg_attr(test, deny(warnings))]
-#![cfg_attr(test, feature(test))]
 #![allow(non_upper_case_globals)]
 // #![deny(missing_docs)]
 
@@ -12,30 +11,151 @@
 extern crate bitflags;
 extern crate byteorder;
 
-#[cfg(test)]
-extern crate test;
 #[cfg(any(test, feature = "random"))]
 extern crate rand;
+#[cfg(feature = "serde")]
+#[macro_use]
+extern crate serde;
+#[cfg(any(feature = "json", all(test, feature = "serde")))]
+extern crate serde_json;
+#[cfg(feature = "arbitrary")]
+extern crate arbitrary;
+#[cfg(feature = "proptest")]
+extern crate proptest;
+#[cfg(feature = "quickcheck")]
+extern crate quickcheck;
+#[cfg(feature = "defmt")]
+extern crate defmt;
+#[cfg(feature = "heapless")]
+extern crate heapless;
+#[cfg(feature = "wasm")]
+extern crate wasm_bindgen;
+#[cfg(feature = "python")]
+extern crate pyo3;
+#[cfg(feature = "h2-interop")]
+extern crate h2;
+#[cfg(feature = "h2-interop")]
+extern crate bytes;
+// `pyo3`'s macros name `::core`, which needs declaring before 2018.
+#[cfg(feature = "python")]
+extern crate core;
 
 const FRAME_HEADER_BYTES: usize = 9;
 
 pub use kind::Kind;
-pub use flag::Flag;
-pub use frame::{Frame, FrameHeader};
-pub use payload::{Payload, Priority, Setting, SettingIdentifier};
+pub use flag::{Flag, FlagIter, NamedFlags, FrameFlags, DataFlags, HeadersFlags,
+               PushPromiseFlags, ContinuationFlags, SettingsFlags, PingFlags};
+pub use frame::{Frame, FrameHeader, Frames, ConsistencyError, parse_many, encode_data_header};
+pub use visit::{FrameVisitor, EventParser, visit};
+pub use options::ParserOptions;
+#[cfg(feature = "random")]
+pub use frame::random_frame;
+#[cfg(feature = "random")]
+pub use mutate::{mutate, Mutation};
+pub use payload::{Payload, Priority, Setting, Settings, SettingsIter, SettingIdentifier};
+pub use collector::{HeaderBlockCollector, HeaderBlock, CollectorError};
+pub use allocator::{StreamIdAllocator, StreamIdError, Role};
+pub use mapper::{StreamIdMapper, MapError};
+pub use priority::{PriorityTree, PriorityError};
+pub use extension::{ExtensionFrame, ExtensionRegistry, ExtendedFrame, ExtendedPayload};
+pub use validator::{Validator, ValidationError};
+pub use sequencer::{Sequencer, SequenceError};
+pub use trailers::TrailersTracker;
+pub use ping::{Keepalive, PingTracker};
+pub use goaway::{GoAwayTracker, GoAwayError};
+pub use window::{WindowSize, WindowUpdateStrategy, RecvWindow};
+pub use preface::{PREFACE, parse_preface, write_preface};
+pub use upgrade::{encode_http2_settings, decode_http2_settings, UpgradeError};
+pub use settings::{SettingsState, SettingsSync};
+pub use flood::{RapidResetDetector, ControlFloodDetector, Flood};
+pub use owned::{OwnedFrame, OwnedPayload};
+pub use redact::RedactedFrame;
+pub use ranged::{FrameRef, PayloadRef};
+pub use raw::RawFrame;
+pub use split::{Split, SplitFrame, SplitPayload};
+pub use body::DataFrames;
+pub use batch::{BatchWriter, EncodeError, encode_all};
+pub use scheduler::{WriteScheduler, WeightedScheduler};
+pub use hex::HexError;
+
+use std::fmt;
 
 use byteorder::ByteOrder;
 
+pub mod hpack;
+pub mod consts;
+pub mod capture;
+pub mod script;
+pub mod mangle;
+pub mod headers;
+pub mod grpc;
+#[cfg(feature = "json")]
+pub mod log;
+#[cfg(feature = "pcap")]
+pub mod pcap;
+#[cfg(feature = "test-vectors")]
+pub mod vectors;
+#[cfg(feature = "testing")]
+pub mod testing;
+#[cfg(feature = "proptest")]
+pub mod strategy;
+#[cfg(feature = "heapless")]
+pub mod fixed;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+#[cfg(feature = "python")]
+pub mod python;
+#[cfg(feature = "h2-interop")]
+pub mod interop;
+
 mod kind;
 mod flag;
 mod payload;
 mod frame;
+mod visit;
+mod options;
+mod collector;
+mod allocator;
+mod mapper;
+mod priority;
+mod extension;
+mod validator;
+mod sequencer;
+mod trailers;
+mod ping;
+mod goaway;
+mod window;
+mod preface;
+mod upgrade;
+mod settings;
+mod flood;
+mod owned;
+mod redact;
+mod ranged;
+mod raw;
+mod split;
+mod body;
+mod batch;
+mod scheduler;
+mod hex;
+#[cfg(feature = "random")]
+mod mutate;
 
 /// Errors that can occur during parsing an HTTP/2 frame.
+///
+/// More errors may be added in future, so matches outside this crate
+/// need a wildcard arm; `h2_error_code` gives the code to report any
+/// error with.
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[non_exhaustive]
 pub enum Error {
-    /// A full frame header was not passed.
-    Short,
+    /// The buffer ended before the full frame header or payload.
+    ///
+    /// Holds the number of additional bytes required before parsing
+    /// can succeed.
+    Short(usize),
 
     /// An unsupported value was set for the flag value.
     BadFlag(u8),
@@ -43,8 +163,8 @@ pub enum Error {
     /// An unsupported value was set for the frame kind.
     BadKind(u8),
 
-    /// The padding length was larger than the frame-header-specified
-    /// length of the payload.
+    /// The padding, together with the byte holding its length, did not
+    /// fit in the frame-header-specified length of the payload.
     TooMuchPadding(u8),
 
     /// The payload length specified by the frame header was shorter than
@@ -53,7 +173,7 @@ pub enum Error {
     /// This happens if, for instance, the priority flag is set and the
     /// header length is shorter than a stream dependency.
     ///
-    /// `PayloadLengthTooShort` should be treated as a protocol error.
+    /// `PayloadLengthTooShort` should be treated as a frame size error.
     PayloadLengthTooShort,
 
     /// The payload length specified by the frame header of a settings frame
@@ -62,7 +182,127 @@ pub enum Error {
 
     /// The payload length specified by the frame header was not the
     /// value necessary for the specific frame type.
-    InvalidPayloadLength
+    InvalidPayloadLength,
+
+    /// A frame that applies to a single stream was sent on stream 0.
+    ZeroStreamId,
+
+    /// The client connection preface was malformed.
+    BadPreface,
+
+    /// The frame was longer than the maximum frame size of the
+    /// `ParserOptions` used. Holds the length from the frame header.
+    FrameTooLarge(u32),
+
+    /// Padding was not all zero, and the `ParserOptions` used require it
+    /// to be.
+    NonZeroPadding
+}
+
+/// Whether an error affects the whole connection or a single stream.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum ErrorScope {
+    /// The error must be answered with GOAWAY.
+    Connection,
+
+    /// The error can be answered with RST_STREAM on the given stream.
+    Stream(StreamIdentifier)
+}
+
+impl Error {
+    /// The number of additional bytes needed, if the buffer was short.
+    pub fn needed(&self) -> Option<usize> {
+        match *self {
+            Error::Short(needed) => Some(needed),
+            _ => None
+        }
+    }
+
+    /// The HTTP/2 error code to report the error with.
+    ///
+    /// `Short` is not an error on the peer's part and should normally be
+    /// handled by reading more data; if the connection ends mid-frame it is
+    /// reported as a frame size error.
+    pub fn h2_error_code(&self) -> ErrorCode {
+        match *self {
+            Error::BadFlag(_) | Error::BadKind(_) | Error::TooMuchPadding(_) |
+            Error::ZeroStreamId | Error::BadPreface | Error::NonZeroPadding =>
+                HttpError::Protocol,
+            Error::Short(_) | Error::PayloadLengthTooShort | Error::FrameTooLarge(_) |
+            Error::PartialSettingLength | Error::InvalidPayloadLength =>
+                HttpError::FrameSizeError
+        }.into()
+    }
+
+    /// Classify an error in a frame of the given kind and stream.
+    ///
+    /// Only size errors in frames that cannot alter connection state
+    /// (DATA, PRIORITY and frames of unregistered kinds, which are ignored,
+    /// on a non-zero stream) are stream errors; everything else is a
+    /// connection error. `Validator` classifies its errors the same way.
+    pub fn scope(&self, kind: Kind, id: StreamIdentifier) -> ErrorScope {
+        match (*self, kind) {
+            _ if id.0 == 0 => ErrorScope::Connection,
+            (Error::PayloadLengthTooShort, Kind::Data) |
+            (Error::PayloadLengthTooShort, Kind::Priority) |
+            (Error::FrameTooLarge(_), Kind::Data) |
+            (Error::FrameTooLarge(_), Kind::Priority) |
+            (Error::FrameTooLarge(_), Kind::Unregistered(_)) |
+            (Error::InvalidPayloadLength, Kind::Priority) => ErrorScope::Stream(id),
+            _ => ErrorScope::Connection
+        }
+    }
+}
+
+/// An `Error` with context about where it occurred, for diagnostics.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ParseError {
+    /// What went wrong.
+    pub error: Error,
+
+    /// The kind of the frame being parsed, if its header was parsed.
+    pub kind: Option<Kind>,
+
+    /// The stream of the frame being parsed, if its header was parsed.
+    pub id: Option<StreamIdentifier>,
+
+    /// The byte offset in the input of the start of the frame.
+    pub frame_offset: usize,
+
+    /// The byte offset in the input of the failure: the header field or
+    /// pad length byte at fault, the first non-zero padding byte, the
+    /// partial setting at the end of a SETTINGS frame, or where the input
+    /// ended. Errors about the length of a frame are at its start.
+    pub offset: usize
+}
+
+impl ParseError {
+    /// Classify the error, treating it as a connection error if the frame
+    /// header was never parsed.
+    pub fn scope(&self) -> ErrorScope {
+        match (self.kind, self.id) {
+            (Some(kind), Some(id)) => self.error.scope(kind, id),
+            _ => ErrorScope::Connection
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        try!(write!(f, "{:?} at offset {}", self.error, self.offset));
+
+        if let Some(kind) = self.kind {
+            try!(write!(f, " in {:?} frame", kind));
+        }
+
+        if let Some(id) = self.id {
+            try!(write!(f, " on stream {}", id.0));
+        }
+
+        Ok(())
+    }
 }
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
@@ -71,29 +311,182 @@ struct ParserSettings {
     priority: bool
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct StreamIdentifier(pub u32);
 
 impl StreamIdentifier {
-    pub fn parse(buf: &[u8]) -> StreamIdentifier {
-        StreamIdentifier(
-            byteorder::BigEndian::read_u32(buf) & ((1 << 31) - 1)
-        )
+    /// Stream 0, used for frames that apply to the whole connection.
+    #[inline]
+    pub const fn zero() -> StreamIdentifier {
+        StreamIdentifier(0)
     }
 
-    pub fn encode(&self, buf: &mut [u8]) -> usize {
+    /// Is this stream 0?
+    #[inline]
+    pub fn is_connection(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// Was this stream opened by the client, which uses odd identifiers?
+    #[inline]
+    pub fn is_client_initiated(&self) -> bool {
+        self.0 % 2 == 1
+    }
+
+    /// Was this stream opened by the server, which uses even identifiers
+    /// other than 0?
+    #[inline]
+    pub fn is_server_initiated(&self) -> bool {
+        self.0 != 0 && self.0 % 2 == 0
+    }
+
+    /// The next identifier the same endpoint could use, or `None` if
+    /// identifiers have run out.
+    #[inline]
+    pub fn checked_next(&self) -> Option<StreamIdentifier> {
+        match self.0.checked_add(2) {
+            Some(next) if next <= consts::MAX_STREAM_ID => Some(StreamIdentifier(next)),
+            _ => None
+        }
+    }
+
+    pub const fn parse(buf: &[u8]) -> StreamIdentifier {
+        StreamIdentifier(read_u32(buf) & consts::MAX_STREAM_ID)
+    }
+
+    pub const fn encode(&self, buf: &mut [u8]) -> usize {
         encode_u32(buf, self.0)
     }
 }
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct ErrorCode(pub u32);
 
+/// The error codes defined by RFC 7540, Section 7.
+///
+/// Codes registered later are `Unregistered` until a variant is added for
+/// them, so matches outside this crate need a wildcard arm.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[non_exhaustive]
 pub enum HttpError {
+    NoError,
     Protocol,
     Internal,
     FlowControlError,
     SettingsTimeout,
+    StreamClosed,
+    FrameSizeError,
+    RefusedStream,
+    Cancel,
+    CompressionError,
+    ConnectError,
+    EnhanceYourCalm,
+    InadequateSecurity,
+    Http11Required,
+
+    /// An error code with no defined meaning, which should be treated
+    /// as `Internal`.
+    Unregistered(u32)
+}
+
+impl fmt::Display for HttpError {
+    /// The name of the error code as written in RFC 7540.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.name() {
+            Some(name) => f.write_str(name),
+            None => write!(f, "0x{:x}", self.code())
+        }
+    }
+}
+
+impl From<ErrorCode> for HttpError {
+    fn from(code: ErrorCode) -> HttpError {
+        match code.0 {
+            0x0 => HttpError::NoError,
+            0x1 => HttpError::Protocol,
+            0x2 => HttpError::Internal,
+            0x3 => HttpError::FlowControlError,
+            0x4 => HttpError::SettingsTimeout,
+            0x5 => HttpError::StreamClosed,
+            0x6 => HttpError::FrameSizeError,
+            0x7 => HttpError::RefusedStream,
+            0x8 => HttpError::Cancel,
+            0x9 => HttpError::CompressionError,
+            0xa => HttpError::ConnectError,
+            0xb => HttpError::EnhanceYourCalm,
+            0xc => HttpError::InadequateSecurity,
+            0xd => HttpError::Http11Required,
+            code => HttpError::Unregistered(code)
+        }
+    }
+}
+
+impl From<HttpError> for ErrorCode {
+    fn from(error: HttpError) -> ErrorCode {
+        ErrorCode(match error {
+            HttpError::NoError => 0x0,
+            HttpError::Protocol => 0x1,
+            HttpError::Internal => 0x2,
+            HttpError::FlowControlError => 0x3,
+            HttpError::SettingsTimeout => 0x4,
+            HttpError::StreamClosed => 0x5,
+            HttpError::FrameSizeError => 0x6,
+            HttpError::RefusedStream => 0x7,
+            HttpError::Cancel => 0x8,
+            HttpError::CompressionError => 0x9,
+            HttpError::ConnectError => 0xa,
+            HttpError::EnhanceYourCalm => 0xb,
+            HttpError::InadequateSecurity => 0xc,
+            HttpError::Http11Required => 0xd,
+            HttpError::Unregistered(code) => code
+        })
+    }
+}
+
+// Every registered error, in order of code.
+const REGISTERED_ERRORS: &'static [HttpError] = &[
+    HttpError::NoError, HttpError::Protocol, HttpError::Internal,
+    HttpError::FlowControlError, HttpError::SettingsTimeout, HttpError::StreamClosed,
+    HttpError::FrameSizeError, HttpError::RefusedStream, HttpError::Cancel,
+    HttpError::CompressionError, HttpError::ConnectError, HttpError::EnhanceYourCalm,
+    HttpError::InadequateSecurity, HttpError::Http11Required
+];
+
+impl HttpError {
+    /// The error code sent on the wire.
+    pub fn code(&self) -> u32 {
+        ErrorCode::from(*self).0
+    }
+
+    /// The name of the error code as written in RFC 7540, if it is
+    /// registered.
+    pub fn name(&self) -> Option<&'static str> {
+        Some(match *self {
+            HttpError::NoError => "NO_ERROR",
+            HttpError::Protocol => "PROTOCOL_ERROR",
+            HttpError::Internal => "INTERNAL_ERROR",
+            HttpError::FlowControlError => "FLOW_CONTROL_ERROR",
+            HttpError::SettingsTimeout => "SETTINGS_TIMEOUT",
+            HttpError::StreamClosed => "STREAM_CLOSED",
+            HttpError::FrameSizeError => "FRAME_SIZE_ERROR",
+            HttpError::RefusedStream => "REFUSED_STREAM",
+            HttpError::Cancel => "CANCEL",
+            HttpError::CompressionError => "COMPRESSION_ERROR",
+            HttpError::ConnectError => "CONNECT_ERROR",
+            HttpError::EnhanceYourCalm => "ENHANCE_YOUR_CALM",
+            HttpError::InadequateSecurity => "INADEQUATE_SECURITY",
+            HttpError::Http11Required => "HTTP_1_1_REQUIRED",
+            HttpError::Unregistered(_) => return None
+        })
+    }
+
+    /// The registered error called `name`, as returned by `name`.
+    pub fn from_name(name: &str) -> Option<HttpError> {
+        REGISTERED_ERRORS.iter().find(|error| error.name() == Some(name)).cloned()
+    }
 }
 
 impl ErrorCode {
@@ -107,6 +500,7 @@ impl ErrorCode {
 }
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct SizeIncrement(pub u32);
 
 impl SizeIncrement {
@@ -120,7 +514,7 @@ impl SizeIncrement {
 }
 
 #[inline(always)]
-fn encode_u24(buf: &mut [u8], val: u32) -> usize {
+const fn encode_u24(buf: &mut [u8], val: u32) -> usize {
     buf[0] = (val >> 16) as u8;
     buf[1] = (val >> 8) as u8;
     buf[2] = val as u8;
@@ -129,11 +523,21 @@ fn encode_u24(buf: &mut [u8], val: u32) -> usize {
 }
 
 #[inline(always)]
-fn encode_u32(buf: &mut [u8], val: u32) -> usize {
-    byteorder::BigEndian::write_u32(buf, val);
+const fn encode_u32(buf: &mut [u8], val: u32) -> usize {
+    buf[0] = (val >> 24) as u8;
+    buf[1] = (val >> 16) as u8;
+    buf[2] = (val >> 8) as u8;
+    buf[3] = val as u8;
+
     4
 }
 
+// `byteorder` is not usable in `const fn`.
+#[inline(always)]
+const fn read_u32(buf: &[u8]) -> u32 {
+    (buf[0] as u32) << 24 | (buf[1] as u32) << 16 | (buf[2] as u32) << 8 | buf[3] as u32
+}
+
 #[inline(always)]
 fn encode_u64(buf: &mut [u8], val: u64) -> usize {
     byteorder::BigEndian::write_u64(buf, val);
@@ -150,3 +554,70 @@ fn test_stream_id_ignores_highest_bit() {
         StreamIdentifier::parse(&raw2));
 }
 
+
+#[test]
+fn test_stream_id_helpers() {
+    assert!(StreamIdentifier::zero().is_connection());
+    assert!(!StreamIdentifier::zero().is_client_initiated());
+    assert!(!StreamIdentifier::zero().is_server_initiated());
+    assert!(StreamIdentifier(1).is_client_initiated());
+    assert!(StreamIdentifier(2).is_server_initiated());
+
+    assert_eq!(StreamIdentifier(1).checked_next(), Some(StreamIdentifier(3)));
+    assert_eq!(StreamIdentifier((1 << 31) - 3).checked_next(),
+               Some(StreamIdentifier((1 << 31) - 1)));
+    assert_eq!(StreamIdentifier((1 << 31) - 1).checked_next(), None);
+
+    assert!(StreamIdentifier(3) > StreamIdentifier(1));
+}
+
+#[test]
+fn test_error_codes() {
+    assert_eq!(Error::BadFlag(0x2).h2_error_code(), ErrorCode(0x1));
+    assert_eq!(Error::TooMuchPadding(10).h2_error_code(), ErrorCode(0x1));
+    assert_eq!(Error::ZeroStreamId.h2_error_code(), ErrorCode(0x1));
+    assert_eq!(Error::PartialSettingLength.h2_error_code(), ErrorCode(0x6));
+    assert_eq!(Error::InvalidPayloadLength.h2_error_code(), ErrorCode(0x6));
+
+    assert_eq!(Error::Short(3).needed(), Some(3));
+    assert_eq!(Error::ZeroStreamId.needed(), None);
+}
+
+#[test]
+fn test_error_scope() {
+    let stream = StreamIdentifier(1);
+
+    assert_eq!(Error::InvalidPayloadLength.scope(Kind::Priority, stream),
+               ErrorScope::Stream(stream));
+    assert_eq!(Error::PayloadLengthTooShort.scope(Kind::Data, stream),
+               ErrorScope::Stream(stream));
+    assert_eq!(Error::InvalidPayloadLength.scope(Kind::Priority, StreamIdentifier(0)),
+               ErrorScope::Connection);
+    assert_eq!(Error::InvalidPayloadLength.scope(Kind::Reset, stream),
+               ErrorScope::Connection);
+    assert_eq!(Error::PayloadLengthTooShort.scope(Kind::Headers, stream),
+               ErrorScope::Connection);
+    assert_eq!(Error::TooMuchPadding(4).scope(Kind::Data, stream),
+               ErrorScope::Connection);
+    assert_eq!(Error::FrameTooLarge(1 << 16).scope(Kind::Unregistered(0xfa), stream),
+               ErrorScope::Stream(stream));
+    assert_eq!(Error::FrameTooLarge(1 << 16).scope(Kind::Headers, stream),
+               ErrorScope::Connection);
+
+    let unparsed = ParseError {
+        error: Error::InvalidPayloadLength, kind: None, id: None, frame_offset: 0, offset: 0
+    };
+    assert_eq!(unparsed.scope(), ErrorScope::Connection);
+}
+
+#[test]
+fn test_http_error_round_trip() {
+    for code in 0..0x20 {
+        let error = HttpError::from(ErrorCode(code));
+        assert_eq!(ErrorCode::from(error), ErrorCode(code));
+    }
+
+    assert_eq!(HttpError::from(ErrorCode(0xb)), HttpError::EnhanceYourCalm);
+    assert_eq!(HttpError::from(ErrorCode(0xe)), HttpError::Unregistered(0xe));
+    assert_eq!(HttpError::Http11Required.code(), 0xd);
+}
@@ -0,0 +1,134 @@
+//! Newline-delimited JSON logs of the frames sent and received on a
+//! connection.
+//!
+//! Each line holds one `LogRecord`, so logs can be streamed, grepped and
+//! read back with `JsonReader`.
+
+use std::io::{self, BufRead, Write};
+use std::time::SystemTime;
+
+use serde_json;
+
+use {Frame, OwnedFrame};
+
+pub use capture::Direction;
+
+/// A frame read back from a log.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct LogRecord {
+    pub direction: Direction,
+    pub timestamp: SystemTime,
+    pub frame: OwnedFrame
+}
+
+// Serializes identically to `LogRecord`, without copying the frame.
+#[derive(Serialize)]
+struct Record<'a, 'b: 'a> {
+    direction: Direction,
+    timestamp: SystemTime,
+    frame: &'a Frame<'b>
+}
+
+/// Writes frames to a log, one JSON object per line.
+#[derive(Debug)]
+pub struct JsonWriter<W> {
+    writer: W
+}
+
+impl<W: Write> JsonWriter<W> {
+    pub fn new(writer: W) -> JsonWriter<W> {
+        JsonWriter { writer: writer }
+    }
+
+    /// Append a frame seen at `timestamp`.
+    pub fn write(&mut self, direction: Direction, timestamp: SystemTime,
+                 frame: &Frame) -> io::Result<()> {
+        let record = Record { direction: direction, timestamp: timestamp, frame: frame };
+
+        try!(serde_json::to_writer(&mut self.writer, &record).map_err(io::Error::from));
+        self.writer.write_all(b"\n")
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+/// Reads the frames from a log written by `JsonWriter`.
+///
+/// Blank lines are skipped, and a malformed line is an error of kind
+/// `InvalidData`.
+#[derive(Debug)]
+pub struct JsonReader<R> {
+    reader: R,
+    line: String
+}
+
+impl<R: BufRead> JsonReader<R> {
+    pub fn new(reader: R) -> JsonReader<R> {
+        JsonReader { reader: reader, line: String::new() }
+    }
+}
+
+impl<R: BufRead> Iterator for JsonReader<R> {
+    type Item = io::Result<LogRecord>;
+
+    fn next(&mut self) -> Option<io::Result<LogRecord>> {
+        loop {
+            self.line.clear();
+
+            match self.reader.read_line(&mut self.line) {
+                Ok(0) => return None,
+                Ok(_) if self.line.trim().is_empty() => continue,
+                Ok(_) => return Some(serde_json::from_str(&self.line).map_err(|error| {
+                    io::Error::new(io::ErrorKind::InvalidData, error)
+                })),
+                Err(error) => return Some(Err(error))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::{self, Cursor};
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use {Frame, OwnedFrame, StreamIdentifier, HttpError};
+    use super::{JsonWriter, JsonReader, Direction, LogRecord};
+
+    #[test]
+    fn test_json_log_round_trip() {
+        let timestamp = UNIX_EPOCH + Duration::from_millis(1500);
+        let frames = [Frame::ping([7; 8]),
+                      Frame::goaway(StreamIdentifier(1), HttpError::NoError, Some("bye"))];
+
+        let mut writer = JsonWriter::new(Vec::new());
+        writer.write(Direction::Outbound, timestamp, &frames[0]).unwrap();
+        writer.write(Direction::Inbound, timestamp, &frames[1]).unwrap();
+
+        let log = writer.into_inner();
+        assert_eq!(log.iter().filter(|&&b| b == b'\n').count(), 2);
+
+        let records = JsonReader::new(Cursor::new(log)).collect::<io::Result<Vec<_>>>().unwrap();
+        assert_eq!(records, vec![
+            LogRecord {
+                direction: Direction::Outbound,
+                timestamp: timestamp,
+                frame: OwnedFrame::from(frames[0])
+            },
+            LogRecord {
+                direction: Direction::Inbound,
+                timestamp: timestamp,
+                frame: OwnedFrame::from(frames[1])
+            }
+        ]);
+    }
+
+    #[test]
+    fn test_json_log_errors() {
+        let mut reader = JsonReader::new(Cursor::new(&b"\n{\"direction\":\n"[..]));
+        assert_eq!(reader.next().unwrap().unwrap_err().kind(), io::ErrorKind::InvalidData);
+        assert!(reader.next().is_none());
+    }
+}
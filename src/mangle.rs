@@ -0,0 +1,175 @@
+//! Deterministic frame rewriting, for interop and chaos testing proxies.
+//!
+//! A frame is encoded and then each `Mangle` is applied to the encoding
+//! in turn. Unlike `Frame::encode`, the result need not be valid: flags
+//! can contradict the payload and lengths can disagree with it.
+
+use {Frame, Flag, Kind, FRAME_HEADER_BYTES};
+use payload::SETTING_BYTES;
+
+/// A change to an encoded frame.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Mangle {
+    /// Set these flags.
+    SetFlags(Flag),
+
+    /// Clear these flags.
+    UnsetFlags(Flag),
+
+    /// Pad the payload with this many zero bytes, setting PADDED and
+    /// adding the pad length byte if they are not already there.
+    ///
+    /// Padding is added to frames of any kind, even those which cannot
+    /// carry it.
+    Pad(u8),
+
+    /// Declare this payload length without changing the payload.
+    SetLength(u32),
+
+    /// Reverse the order of the entries of a SETTINGS frame.
+    ReverseSettings,
+
+    /// Rotate the entries of a SETTINGS frame left by this many places.
+    RotateSettings(usize)
+}
+
+impl Mangle {
+    /// Apply this change to an encoded frame.
+    ///
+    /// Settings changes leave frames of other kinds, and any partial
+    /// trailing entry, untouched.
+    pub fn apply(&self, frame: &mut Vec<u8>) {
+        if frame.len() < FRAME_HEADER_BYTES {
+            frame.resize(FRAME_HEADER_BYTES, 0);
+        }
+
+        match *self {
+            Mangle::SetFlags(flag) => frame[4] |= flag.bits(),
+            Mangle::UnsetFlags(flag) => frame[4] &= !flag.bits(),
+            Mangle::Pad(len) => {
+                if frame[4] & Flag::padded().bits() == 0 || frame.len() == FRAME_HEADER_BYTES {
+                    frame[4] |= Flag::padded().bits();
+                    frame.insert(FRAME_HEADER_BYTES, 0);
+                }
+
+                let total = frame[FRAME_HEADER_BYTES].saturating_add(len);
+                frame[FRAME_HEADER_BYTES] = total;
+                let padded_len = frame.len() + len as usize;
+                frame.resize(padded_len, 0);
+                set_length(frame);
+            },
+            Mangle::SetLength(length) => { ::encode_u24(frame, length); },
+            Mangle::ReverseSettings => with_settings(frame, |settings| settings.reverse()),
+            Mangle::RotateSettings(by) => with_settings(frame, |settings| {
+                if !settings.is_empty() {
+                    let by = by % settings.len();
+                    settings.rotate_left(by)
+                }
+            })
+        }
+    }
+}
+
+/// Encode `frame` and apply each change to it in order.
+pub fn mangle(frame: &Frame, mangles: &[Mangle]) -> Vec<u8> {
+    let mut buf = vec![0; frame.encoded_len()];
+    frame.encode(&mut buf);
+
+    for mangle in mangles {
+        mangle.apply(&mut buf);
+    }
+
+    buf
+}
+
+// Declare the length of everything after the header.
+fn set_length(frame: &mut Vec<u8>) {
+    let length = (frame.len() - FRAME_HEADER_BYTES) as u32;
+    ::encode_u24(frame, length);
+}
+
+fn with_settings<F: FnOnce(&mut Vec<[u8; SETTING_BYTES]>)>(frame: &mut Vec<u8>, change: F) {
+    if frame[3] != Kind::Settings.encode() { return }
+
+    let payload_len = frame.len() - FRAME_HEADER_BYTES;
+    let end = FRAME_HEADER_BYTES + payload_len - payload_len % SETTING_BYTES;
+
+    let mut settings = frame[FRAME_HEADER_BYTES..end].chunks(SETTING_BYTES).map(|chunk| {
+        let mut setting = [0; SETTING_BYTES];
+        setting.copy_from_slice(chunk);
+        setting
+    }).collect::<Vec<_>>();
+    change(&mut settings);
+
+    for (at, setting) in settings.iter().enumerate() {
+        let start = FRAME_HEADER_BYTES + at * SETTING_BYTES;
+        frame[start..start + SETTING_BYTES].copy_from_slice(setting);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use {Frame, FrameHeader, Payload, Priority, Flag, Kind, StreamIdentifier, Setting,
+         SettingIdentifier, Settings};
+    use super::{mangle, Mangle};
+
+    #[test]
+    fn test_pad_keeps_payload() {
+        let frame = Frame {
+            header: FrameHeader {
+                length: 7,
+                kind: Kind::Headers,
+                flag: Flag::end_headers() | Flag::priority(),
+                id: StreamIdentifier(1)
+            },
+            payload: Payload::Headers {
+                priority: Some(Priority::new(false, StreamIdentifier(0), 15)),
+                block: &[0x82, 0x86]
+            }
+        };
+
+        let buf = mangle(&frame, &[Mangle::Pad(3), Mangle::Pad(2)]);
+        let header = FrameHeader::parse(&buf).unwrap();
+        assert_eq!(header.length, 13);
+        assert!(header.flag.contains(Flag::padded()));
+        assert_eq!(buf[9], 5);
+        assert_eq!(Frame::parse(header, &buf[9..]).unwrap().payload, frame.payload);
+    }
+
+    #[test]
+    fn test_flags_and_length() {
+        let buf = mangle(&Frame::settings_ack(),
+                         &[Mangle::UnsetFlags(Flag::ack()), Mangle::SetFlags(Flag::end_headers()),
+                           Mangle::SetLength(6)]);
+        assert_eq!(buf, [0x0, 0x0, 0x6, 0x4, 0x4, 0x0, 0x0, 0x0, 0x0]);
+    }
+
+    #[test]
+    fn test_reorder_settings() {
+        let settings = [Setting::new(SettingIdentifier::HeaderTableSize, 1),
+                        Setting::new(SettingIdentifier::EnablePush, 0),
+                        Setting::new(SettingIdentifier::MaxFrameSize, 16384)];
+        let frame = Frame {
+            header: FrameHeader {
+                length: 18,
+                kind: Kind::Settings,
+                flag: Flag::empty(),
+                id: StreamIdentifier(0)
+            },
+            payload: Payload::Settings(Settings::new(&settings))
+        };
+        let identifiers = |buf: &[u8]| {
+            Settings::parse(&buf[9..]).unwrap().iter()
+                .map(|setting| setting.identifier().unwrap()).collect::<Vec<_>>()
+        };
+
+        assert_eq!(identifiers(&mangle(&frame, &[Mangle::ReverseSettings])),
+                   vec![SettingIdentifier::MaxFrameSize, SettingIdentifier::EnablePush,
+                        SettingIdentifier::HeaderTableSize]);
+        assert_eq!(identifiers(&mangle(&frame, &[Mangle::RotateSettings(4)])),
+                   vec![SettingIdentifier::EnablePush, SettingIdentifier::MaxFrameSize,
+                        SettingIdentifier::HeaderTableSize]);
+        assert_eq!(mangle(&Frame::ping([0; 8]), &[Mangle::ReverseSettings]),
+                   mangle(&Frame::ping([0; 8]), &[]));
+    }
+}
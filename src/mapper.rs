@@ -0,0 +1,266 @@
+use std::cmp;
+use std::collections::HashMap;
+
+use {Frame, Payload, Priority, Kind, StreamIdentifier};
+use consts::MAX_STREAM_ID;
+
+/// Errors produced by a `StreamIdMapper`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum MapError {
+    /// A frame arrived for a stream with no translation, which was not
+    /// opened by HEADERS, promised by PUSH_PROMISE or named by PRIORITY.
+    Unmapped(StreamIdentifier),
+
+    /// A translation would join stream 0 to a stream, or streams of
+    /// different parity, and so initiated by different ends.
+    WrongParity(StreamIdentifier),
+
+    /// The stream already has a translation.
+    AlreadyMapped(StreamIdentifier),
+
+    /// Every stream identifier of the needed parity has been used.
+    Exhausted
+}
+
+/// Translates stream identifiers between two connections spliced together
+/// by a frame-level proxy.
+///
+/// Frames travelling `forward` have their identifiers translated from the
+/// near connection's to the far connection's, and frames travelling
+/// `backward` the other way. Streams opened by HEADERS or promised by
+/// PUSH_PROMISE are given the next unused identifier of the same parity on
+/// the other connection, so streams keep the same initiating end.
+///
+/// PRIORITY frames may name idle streams, which are given a translation in
+/// the same way. A priority dependency on a stream with no translation
+/// becomes a dependency on stream 0, as the peer would treat a stream
+/// missing from its tree. The last stream of GOAWAY is a bound rather than
+/// a stream, and since closed streams are forgotten it is left as it is;
+/// a proxy should send its own GOAWAY on the other connection instead.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StreamIdMapper {
+    forward: HashMap<StreamIdentifier, StreamIdentifier>,
+    backward: HashMap<StreamIdentifier, StreamIdentifier>,
+
+    // The next unused identifiers on each connection, indexed by parity.
+    next_near: [u32; 2],
+    next_far: [u32; 2]
+}
+
+impl StreamIdMapper {
+    pub fn new() -> StreamIdMapper {
+        StreamIdMapper {
+            forward: HashMap::new(),
+            backward: HashMap::new(),
+            next_near: [2, 1],
+            next_far: [2, 1]
+        }
+    }
+
+    /// Translate the near stream `near` to the far stream `far`.
+    pub fn insert(&mut self, near: StreamIdentifier, far: StreamIdentifier)
+                  -> Result<(), MapError> {
+        if near.0 == 0 || near.0 % 2 != far.0 % 2 {
+            return Err(MapError::WrongParity(far))
+        }
+        if self.forward.contains_key(&near) {
+            return Err(MapError::AlreadyMapped(near))
+        }
+        if self.backward.contains_key(&far) {
+            return Err(MapError::AlreadyMapped(far))
+        }
+
+        let parity = (near.0 % 2) as usize;
+        self.next_near[parity] = cmp::max(self.next_near[parity], near.0.saturating_add(2));
+        self.next_far[parity] = cmp::max(self.next_far[parity], far.0.saturating_add(2));
+
+        self.forward.insert(near, far);
+        self.backward.insert(far, near);
+        Ok(())
+    }
+
+    /// The far stream translating the near stream `near`.
+    pub fn get(&self, near: StreamIdentifier) -> Option<StreamIdentifier> {
+        self.forward.get(&near).cloned()
+    }
+
+    /// The near stream translating the far stream `far`.
+    pub fn get_backward(&self, far: StreamIdentifier) -> Option<StreamIdentifier> {
+        self.backward.get(&far).cloned()
+    }
+
+    /// Forget the translation of the near stream `near`, once it closes.
+    pub fn remove(&mut self, near: StreamIdentifier) -> Option<StreamIdentifier> {
+        let far = self.forward.remove(&near);
+        if let Some(far) = far {
+            self.backward.remove(&far);
+        }
+        far
+    }
+
+    /// Translate a frame from the near connection for the far connection.
+    pub fn forward(&mut self, frame: &mut Frame) -> Result<(), MapError> {
+        self.rewrite(frame, true)
+    }
+
+    /// Translate a frame from the far connection for the near connection.
+    pub fn backward(&mut self, frame: &mut Frame) -> Result<(), MapError> {
+        self.rewrite(frame, false)
+    }
+
+    fn rewrite(&mut self, frame: &mut Frame, forward: bool) -> Result<(), MapError> {
+        let opens = frame.header.kind == Kind::Headers || frame.header.kind == Kind::Priority;
+        let id = try!(self.translate(frame.header.id, opens, forward));
+
+        match frame.payload {
+            Payload::PushPromise { ref mut promised, .. } =>
+                *promised = try!(self.translate(*promised, true, forward)),
+            Payload::Headers { priority: Some(ref mut priority), .. } |
+            Payload::Priority(ref mut priority) => {
+                let dependency = priority.dependency();
+                let translated = if forward {
+                    self.get(dependency)
+                } else {
+                    self.get_backward(dependency)
+                };
+                *priority = Priority::new(priority.exclusive(),
+                                          translated.unwrap_or(StreamIdentifier(0)),
+                                          priority.weight());
+            },
+            _ => {}
+        }
+
+        frame.header.id = id;
+        Ok(())
+    }
+
+    fn translate(&mut self, id: StreamIdentifier, open: bool, forward: bool)
+                 -> Result<StreamIdentifier, MapError> {
+        if id.0 == 0 { return Ok(id) }
+
+        let existing = if forward { self.get(id) } else { self.get_backward(id) };
+        match existing {
+            Some(translated) => return Ok(translated),
+            None if !open => return Err(MapError::Unmapped(id)),
+            None => {}
+        }
+
+        let parity = (id.0 % 2) as usize;
+        let next = if forward { self.next_far[parity] } else { self.next_near[parity] };
+        if next > MAX_STREAM_ID {
+            return Err(MapError::Exhausted)
+        }
+
+        let translated = StreamIdentifier(next);
+        try!(if forward { self.insert(id, translated) } else { self.insert(translated, id) });
+        Ok(translated)
+    }
+}
+
+impl Default for StreamIdMapper {
+    fn default() -> StreamIdMapper {
+        StreamIdMapper::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use {Frame, FrameHeader, Payload, Priority, Kind, Flag, StreamIdentifier, SizeIncrement,
+         HttpError};
+    use super::{StreamIdMapper, MapError};
+
+    fn frame<'a>(kind: Kind, id: u32, payload: Payload<'a>) -> Frame<'a> {
+        Frame {
+            header: FrameHeader {
+                length: payload.encoded_len() as u32,
+                kind: kind,
+                flag: Flag::empty(),
+                id: StreamIdentifier(id)
+            },
+            payload: payload
+        }
+    }
+
+    #[test]
+    fn test_map_streams() {
+        let mut mapper = StreamIdMapper::new();
+        mapper.insert(StreamIdentifier(1), StreamIdentifier(11)).unwrap();
+
+        let mut headers = frame(Kind::Headers, 5, Payload::Headers { priority: None, block: &[] });
+        mapper.forward(&mut headers).unwrap();
+        assert_eq!(headers.header.id, StreamIdentifier(13));
+
+        let mut update = frame(Kind::WindowUpdate, 13, Payload::WindowUpdate(SizeIncrement(1)));
+        mapper.backward(&mut update).unwrap();
+        assert_eq!(update.header.id, StreamIdentifier(5));
+
+        let mut promise = frame(Kind::PushPromise, 13, Payload::PushPromise {
+            promised: StreamIdentifier(2),
+            block: &[]
+        });
+        mapper.backward(&mut promise).unwrap();
+        assert_eq!(promise.header.id, StreamIdentifier(5));
+        assert_eq!(promise.payload, Payload::PushPromise { promised: StreamIdentifier(2), block: &[] });
+        assert_eq!(mapper.get(StreamIdentifier(2)), Some(StreamIdentifier(2)));
+
+        let mut ping = Frame::ping([0; 8]);
+        mapper.forward(&mut ping).unwrap();
+        assert_eq!(ping.header.id, StreamIdentifier(0));
+
+        assert_eq!(mapper.remove(StreamIdentifier(5)), Some(StreamIdentifier(13)));
+        assert_eq!(mapper.get_backward(StreamIdentifier(13)), None);
+    }
+
+    #[test]
+    fn test_map_priorities() {
+        let mut mapper = StreamIdMapper::new();
+        mapper.insert(StreamIdentifier(1), StreamIdentifier(11)).unwrap();
+
+        let mut headers = frame(Kind::Headers, 3, Payload::Headers {
+            priority: Some(Priority::new(true, StreamIdentifier(1), 7)),
+            block: &[]
+        });
+        mapper.forward(&mut headers).unwrap();
+        assert_eq!(headers.header.id, StreamIdentifier(13));
+        assert_eq!(headers.payload, Payload::Headers {
+            priority: Some(Priority::new(true, StreamIdentifier(11), 7)),
+            block: &[]
+        });
+
+        // An idle stream is given a translation, and an unknown dependency
+        // becomes stream 0.
+        let mut priority = frame(Kind::Priority, 9, Payload::Priority(
+            Priority::new(false, StreamIdentifier(21), 3)));
+        mapper.forward(&mut priority).unwrap();
+        assert_eq!(priority.header.id, StreamIdentifier(15));
+        assert_eq!(priority.payload,
+                   Payload::Priority(Priority::new(false, StreamIdentifier(0), 3)));
+        assert_eq!(mapper.get(StreamIdentifier(9)), Some(StreamIdentifier(15)));
+
+        let mut goaway = Frame::goaway(StreamIdentifier(13), HttpError::NoError, None);
+        mapper.backward(&mut goaway).unwrap();
+        assert_eq!(goaway.payload, Payload::GoAway {
+            last: StreamIdentifier(13),
+            error: HttpError::NoError.into(),
+            data: &[]
+        });
+    }
+
+    #[test]
+    fn test_map_errors() {
+        let mut mapper = StreamIdMapper::new();
+
+        assert_eq!(mapper.insert(StreamIdentifier(1), StreamIdentifier(2)),
+                   Err(MapError::WrongParity(StreamIdentifier(2))));
+        assert_eq!(mapper.insert(StreamIdentifier(0), StreamIdentifier(0)),
+                   Err(MapError::WrongParity(StreamIdentifier(0))));
+
+        mapper.insert(StreamIdentifier(1), StreamIdentifier(3)).unwrap();
+        assert_eq!(mapper.insert(StreamIdentifier(3), StreamIdentifier(3)),
+                   Err(MapError::AlreadyMapped(StreamIdentifier(3))));
+
+        let mut data = frame(Kind::Data, 7, Payload::Data { data: &[] });
+        assert_eq!(mapper.forward(&mut data), Err(MapError::Unmapped(StreamIdentifier(7))));
+    }
+}
@@ -0,0 +1,166 @@
+use rand::Rng;
+
+use byteorder::{BigEndian, ByteOrder};
+
+use {Flag, FRAME_HEADER_BYTES};
+use consts::{MAX_MAX_FRAME_SIZE as MAX_LENGTH, MAX_STREAM_ID};
+
+/// A protocol-aware change to an encoded frame, for fuzzers which would
+/// otherwise only flip bytes.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Mutation {
+    /// Toggle one bit of the flags.
+    FlipFlag,
+
+    /// Increase the length in the header without adding payload.
+    GrowLength,
+
+    /// Decrease the length in the header without removing payload.
+    ShrinkLength,
+
+    /// Set the PADDED flag and overwrite the pad length, inserting a pad
+    /// length byte if the frame was not padded.
+    CorruptPadding,
+
+    /// Replace the kind with a different one.
+    SwapKind,
+
+    /// Move the frame to another stream, often the connection.
+    SwapStream
+}
+
+impl Mutation {
+    /// Every mutation, in declaration order.
+    pub fn all() -> &'static [Mutation] {
+        static ALL: [Mutation; 6] = [
+            Mutation::FlipFlag, Mutation::GrowLength, Mutation::ShrinkLength,
+            Mutation::CorruptPadding, Mutation::SwapKind, Mutation::SwapStream
+        ];
+        &ALL
+    }
+
+    /// Apply this mutation to an encoded frame.
+    ///
+    /// Input shorter than a frame header is first padded with zeros to a
+    /// full header.
+    pub fn apply<R: Rng + ?Sized>(self, frame: &mut Vec<u8>, rng: &mut R) {
+        if frame.len() < FRAME_HEADER_BYTES {
+            frame.resize(FRAME_HEADER_BYTES, 0);
+        }
+
+        let length = BigEndian::read_uint(frame, 3) as u32;
+
+        match self {
+            Mutation::FlipFlag => frame[4] ^= 1 << rng.gen_range(0..8),
+            Mutation::GrowLength => {
+                let length = length.saturating_add(rng.gen_range(1..=16)).min(MAX_LENGTH);
+                ::encode_u24(frame, length);
+            },
+            Mutation::ShrinkLength => {
+                ::encode_u24(frame, length.saturating_sub(rng.gen_range(1..=16)));
+            },
+            Mutation::CorruptPadding => {
+                if frame[4] & Flag::padded().bits() == 0 || frame.len() == FRAME_HEADER_BYTES {
+                    frame[4] |= Flag::padded().bits();
+                    frame.insert(FRAME_HEADER_BYTES, 0);
+                    ::encode_u24(frame, (length + 1).min(MAX_LENGTH));
+                }
+
+                frame[FRAME_HEADER_BYTES] = rng.gen();
+            },
+            Mutation::SwapKind => {
+                // Any of the registered kinds and one unregistered kind.
+                let kind = frame[3] % 11;
+                frame[3] = (kind + rng.gen_range(1..=10)) % 11;
+            },
+            Mutation::SwapStream => {
+                let id = if rng.gen() { 0 } else { rng.gen_range(0..=MAX_STREAM_ID) };
+                ::encode_u32(&mut frame[5..], id);
+            }
+        }
+    }
+}
+
+/// Apply a randomly chosen `Mutation` to an encoded frame, returning the
+/// mutation applied.
+pub fn mutate<R: Rng + ?Sized>(frame: &mut Vec<u8>, rng: &mut R) -> Mutation {
+    let mutation = Mutation::all()[rng.gen_range(0..Mutation::all().len())];
+    mutation.apply(frame, rng);
+    mutation
+}
+
+#[cfg(test)]
+mod test {
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    use {Frame, FrameHeader, Flag, Kind, Payload, StreamIdentifier};
+    use super::{mutate, Mutation};
+
+    fn encode(frame: Frame) -> Vec<u8> {
+        let mut buf = vec![0; frame.encoded_len()];
+        frame.encode(&mut buf);
+        buf
+    }
+
+    #[test]
+    fn test_mutations() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let data = encode(Frame {
+            header: FrameHeader {
+                length: 3,
+                kind: Kind::Data,
+                flag: Flag::empty(),
+                id: StreamIdentifier(1)
+            },
+            payload: Payload::Data { data: b"abc" }
+        });
+        let header = |buf: &[u8]| FrameHeader::parse(buf).unwrap();
+
+        let mut buf = data.clone();
+        Mutation::FlipFlag.apply(&mut buf, &mut rng);
+        assert_eq!((buf[4] as u32).count_ones(), 1);
+
+        let mut buf = data.clone();
+        Mutation::GrowLength.apply(&mut buf, &mut rng);
+        assert!(header(&buf).length > 3);
+        assert_eq!(buf.len(), data.len());
+
+        let mut buf = data.clone();
+        Mutation::ShrinkLength.apply(&mut buf, &mut rng);
+        assert!(header(&buf).length < 3);
+
+        let mut buf = data.clone();
+        Mutation::CorruptPadding.apply(&mut buf, &mut rng);
+        assert!(header(&buf).flag.contains(Flag::padded()));
+        assert_eq!(header(&buf).length, 4);
+        assert_eq!(&buf[10..], b"abc");
+
+        let mut buf = data.clone();
+        Mutation::SwapKind.apply(&mut buf, &mut rng);
+        assert!(header(&buf).kind != Kind::Data);
+        assert!(buf[3] <= 10);
+
+        let mut buf = vec![];
+        Mutation::SwapStream.apply(&mut buf, &mut rng);
+        assert_eq!(buf.len(), 9);
+    }
+
+    #[test]
+    fn test_mutated_frames_parse() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut seen = Vec::new();
+
+        for _ in 0..1000 {
+            let mut buf = encode(::random_frame(&mut rng).as_frame());
+            let mutation = mutate(&mut buf, &mut rng);
+            if !seen.contains(&mutation) { seen.push(mutation) }
+
+            if let Ok(header) = FrameHeader::parse(&buf) {
+                let _ = Frame::parse(header, &buf[9..]);
+            }
+        }
+
+        assert_eq!(seen.len(), Mutation::all().len());
+    }
+}
@@ -0,0 +1,160 @@
+use {FrameHeader, Kind, Flag, Error};
+use consts::MAX_MAX_FRAME_SIZE;
+
+/// How strictly frames are parsed, beyond the checks `Frame::parse` always
+/// makes.
+///
+/// By default every check here is off and any length a header can hold is
+/// accepted, which is exactly the behaviour of `Frame::parse`. Receivers
+/// enforcing RFC 7540 fully will want to limit the frame size and turn on
+/// the stream identifier checks.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ParserOptions {
+    max_frame_size: u32,
+    strict_flags: bool,
+    strict_padding: bool,
+    strict_stream_ids: bool,
+    reject_unregistered: bool
+}
+
+impl ParserOptions {
+    pub fn new() -> ParserOptions {
+        ParserOptions {
+            max_frame_size: MAX_MAX_FRAME_SIZE,
+            strict_flags: false,
+            strict_padding: false,
+            strict_stream_ids: false,
+            reject_unregistered: false
+        }
+    }
+
+    /// Reject frames longer than `size` with an `Error::FrameTooLarge`, as
+    /// advertised in our SETTINGS_MAX_FRAME_SIZE.
+    pub fn set_max_frame_size(&mut self, size: u32) {
+        self.max_frame_size = size;
+    }
+
+    /// Reject flags that are not defined for the frame kind with an
+    /// `Error::BadFlag`, instead of ignoring them.
+    pub fn set_strict_flags(&mut self, strict: bool) {
+        self.strict_flags = strict;
+    }
+
+    /// Reject padding that is not all zero with an `Error::NonZeroPadding`.
+    pub fn set_strict_padding(&mut self, strict: bool) {
+        self.strict_padding = strict;
+    }
+
+    /// Reject frames for a single stream that are sent on stream 0 with an
+    /// `Error::ZeroStreamId`.
+    pub fn set_strict_stream_ids(&mut self, strict: bool) {
+        self.strict_stream_ids = strict;
+    }
+
+    /// Reject frame types not defined by RFC 7540 with an `Error::BadKind`,
+    /// instead of parsing them as `Payload::Unregistered`.
+    pub fn set_reject_unregistered(&mut self, reject: bool) {
+        self.reject_unregistered = reject;
+    }
+
+    /// The largest frame accepted.
+    pub fn max_frame_size(&self) -> u32 {
+        self.max_frame_size
+    }
+
+    /// Apply every check that needs only the frame header, so streaming
+    /// readers can reject a frame before reading its payload.
+    pub fn check_header(&self, header: &FrameHeader) -> Result<(), Error> {
+        if header.length > self.max_frame_size {
+            return Err(Error::FrameTooLarge(header.length))
+        }
+
+        if self.strict_flags {
+            try!(header.validate_flags());
+        }
+
+        if self.strict_stream_ids {
+            try!(header.validate_stream_id());
+        }
+
+        match header.kind {
+            Kind::Unregistered(kind) if self.reject_unregistered => Err(Error::BadKind(kind)),
+            _ => Ok(())
+        }
+    }
+
+    /// Check the padding at the end of `payload`, if it is required to be
+    /// zero. Padding that does not fit is left for `Payload::parse` to
+    /// report.
+    pub fn check_padding(&self, header: &FrameHeader, payload: &[u8]) -> Result<(), Error> {
+        if !self.strict_padding || !header.kind.allows_padding() ||
+           !header.flag.contains(Flag::padded()) {
+            return Ok(())
+        }
+
+        let length = header.length as usize;
+        match payload.first() {
+            Some(&pad_length) if (pad_length as usize) < length && length <= payload.len() =>
+                check_padding_bytes(self, &payload[length - pad_length as usize..length]),
+            _ => Ok(())
+        }
+    }
+}
+
+impl Default for ParserOptions {
+    fn default() -> ParserOptions {
+        ParserOptions::new()
+    }
+}
+
+/// Check that padding bytes are all zero, if the options require it.
+pub fn check_padding_bytes(options: &ParserOptions, padding: &[u8]) -> Result<(), Error> {
+    if options.strict_padding && padding.iter().any(|&b| b != 0) {
+        Err(Error::NonZeroPadding)
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use {Frame, FrameHeader, Kind, Flag, StreamIdentifier, Error};
+    use super::ParserOptions;
+
+    #[test]
+    fn test_parser_options() {
+        let header = |length, kind, flag, id| FrameHeader {
+            length: length,
+            kind: kind,
+            flag: flag,
+            id: StreamIdentifier(id)
+        };
+        let mut options = ParserOptions::new();
+
+        let data = header(6, Kind::Data, Flag::padded() | Flag::ack(), 0);
+        let payload = [2, b'h', b'i', b'!', 0, 1];
+        assert!(Frame::parse_with_options(data, &payload, &options).is_ok());
+
+        options.set_strict_padding(true);
+        assert_eq!(Frame::parse_with_options(data, &payload, &options),
+                   Err(Error::NonZeroPadding));
+        assert!(Frame::parse_with_options(data, &[2, b'h', b'i', b'!', 0, 0], &options).is_ok());
+
+        options.set_strict_stream_ids(true);
+        assert_eq!(options.check_header(&data), Err(Error::ZeroStreamId));
+
+        let headers = header(0, Kind::Headers, Flag::end_stream() | Flag::padded(), 1);
+        assert_eq!(options.check_header(&headers), Ok(()));
+        options.set_strict_flags(true);
+        assert_eq!(options.check_header(&headers), Ok(()));
+        assert_eq!(options.check_header(&header(0, Kind::Continuation, Flag::padded(), 1)),
+                   Err(Error::BadFlag(0x8)));
+
+        let unregistered = header(20000, Kind::Unregistered(0xb), Flag::empty(), 1);
+        assert_eq!(options.check_header(&unregistered), Ok(()));
+        options.set_reject_unregistered(true);
+        assert_eq!(options.check_header(&unregistered), Err(Error::BadKind(0xb)));
+        options.set_max_frame_size(16384);
+        assert_eq!(options.check_header(&unregistered), Err(Error::FrameTooLarge(20000)));
+    }
+}
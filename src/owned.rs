@@ -0,0 +1,351 @@
+#[cfg(feature = "arbitrary")]
+use arbitrary::{self, Unstructured};
+#[cfg(feature = "quickcheck")]
+use quickcheck::{self, Gen};
+#[cfg(feature = "random")]
+use rand::Rng;
+#[cfg(feature = "random")]
+use rand::distributions::{Distribution, Standard};
+#[cfg(feature = "random")]
+use rand::seq::SliceRandom;
+
+use {Frame, FrameHeader, Payload, Priority, Setting, Settings, StreamIdentifier,
+     ErrorCode, SizeIncrement};
+#[cfg(any(feature = "random", feature = "quickcheck"))]
+use SettingIdentifier;
+#[cfg(feature = "quickcheck")]
+use Flag;
+
+/// A `Payload` which owns its data, so it can outlive the buffer it was
+/// parsed from.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum OwnedPayload {
+    Data {
+        data: Vec<u8>
+    },
+    Headers {
+        priority: Option<Priority>,
+        block: Vec<u8>
+    },
+    Priority(Priority),
+    Reset(ErrorCode),
+    Settings(Vec<Setting>),
+    PushPromise {
+        promised: StreamIdentifier,
+        block: Vec<u8>
+    },
+    Ping(u64),
+    GoAway {
+        last: StreamIdentifier,
+        error: ErrorCode,
+        data: Vec<u8>
+    },
+    WindowUpdate(SizeIncrement),
+    Continuation(Vec<u8>),
+    Unregistered {
+        kind: u8,
+        data: Vec<u8>
+    }
+}
+
+impl OwnedPayload {
+    /// Borrow this payload as a `Payload`.
+    pub fn as_payload(&self) -> Payload {
+        match *self {
+            OwnedPayload::Data { ref data } => Payload::Data { data: data },
+            OwnedPayload::Headers { priority, ref block } =>
+                Payload::Headers { priority: priority, block: block },
+            OwnedPayload::Priority(priority) => Payload::Priority(priority),
+            OwnedPayload::Reset(error) => Payload::Reset(error),
+            OwnedPayload::Settings(ref settings) => Payload::Settings(Settings::new(settings)),
+            OwnedPayload::PushPromise { promised, ref block } =>
+                Payload::PushPromise { promised: promised, block: block },
+            OwnedPayload::Ping(data) => Payload::Ping(data),
+            OwnedPayload::GoAway { last, error, ref data } =>
+                Payload::GoAway { last: last, error: error, data: data },
+            OwnedPayload::WindowUpdate(increment) => Payload::WindowUpdate(increment),
+            OwnedPayload::Continuation(ref block) => Payload::Continuation(block),
+            OwnedPayload::Unregistered { kind, ref data } =>
+                Payload::Unregistered { kind: kind, data: data }
+        }
+    }
+}
+
+impl<'a> From<Payload<'a>> for OwnedPayload {
+    fn from(payload: Payload<'a>) -> OwnedPayload {
+        match payload {
+            Payload::Data { data } => OwnedPayload::Data { data: data.to_vec() },
+            Payload::Headers { priority, block } =>
+                OwnedPayload::Headers { priority: priority, block: block.to_vec() },
+            Payload::Priority(priority) => OwnedPayload::Priority(priority),
+            Payload::Reset(error) => OwnedPayload::Reset(error),
+            Payload::Settings(settings) => OwnedPayload::Settings(settings.iter().collect()),
+            Payload::PushPromise { promised, block } =>
+                OwnedPayload::PushPromise { promised: promised, block: block.to_vec() },
+            Payload::Ping(data) => OwnedPayload::Ping(data),
+            Payload::GoAway { last, error, data } =>
+                OwnedPayload::GoAway { last: last, error: error, data: data.to_vec() },
+            Payload::WindowUpdate(increment) => OwnedPayload::WindowUpdate(increment),
+            Payload::Continuation(block) => OwnedPayload::Continuation(block.to_vec()),
+            Payload::Unregistered { kind, data } =>
+                OwnedPayload::Unregistered { kind: kind, data: data.to_vec() }
+        }
+    }
+}
+
+/// A `Frame` which owns its payload.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct OwnedFrame {
+    pub header: FrameHeader,
+    pub payload: OwnedPayload
+}
+
+impl OwnedFrame {
+    /// Borrow this frame as a `Frame`.
+    pub fn as_frame(&self) -> Frame {
+        Frame {
+            header: self.header,
+            payload: self.payload.as_payload()
+        }
+    }
+}
+
+impl<'a> From<Frame<'a>> for OwnedFrame {
+    fn from(frame: Frame<'a>) -> OwnedFrame {
+        OwnedFrame {
+            header: frame.header,
+            payload: frame.payload.into()
+        }
+    }
+}
+
+#[cfg(feature = "random")]
+impl Distribution<OwnedPayload> for Standard {
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> OwnedPayload {
+        fn buf<R: Rng + ?Sized>(rng: &mut R) -> Vec<u8> {
+            let mut buf = vec![0; rng.gen_range(0..200)];
+            rng.fill_bytes(&mut buf);
+            buf
+        }
+
+        match rng.gen_range(0..11) {
+            0 => OwnedPayload::Data { data: buf(rng) },
+            1 => OwnedPayload::Headers { priority: rng.gen(), block: buf(rng) },
+            2 => OwnedPayload::Priority(rng.gen()),
+            3 => OwnedPayload::Reset(ErrorCode(rng.gen())),
+            4 => {
+                let len = rng.gen_range(0..200);
+                OwnedPayload::Settings((0..len).map(|_| {
+                    let identifier = *[
+                        SettingIdentifier::HeaderTableSize,
+                        SettingIdentifier::EnablePush,
+                        SettingIdentifier::MaxConcurrentStreams,
+                        SettingIdentifier::InitialWindowSize,
+                        SettingIdentifier::MaxFrameSize,
+                        SettingIdentifier::MaxHeaderListSize,
+                        SettingIdentifier::EnableConnectProtocol
+                    ].choose(rng).unwrap();
+                    Setting::new(identifier, rng.gen())
+                }).collect())
+            },
+            5 => OwnedPayload::PushPromise {
+                promised: StreamIdentifier(rng.gen_range(0..=::consts::MAX_STREAM_ID)),
+                block: buf(rng)
+            },
+            6 => OwnedPayload::Ping(rng.gen()),
+            7 => OwnedPayload::GoAway {
+                last: StreamIdentifier(rng.gen_range(0..=::consts::MAX_STREAM_ID)),
+                error: ErrorCode(rng.gen()),
+                data: buf(rng)
+            },
+            8 => OwnedPayload::WindowUpdate(SizeIncrement(rng.gen())),
+            9 => OwnedPayload::Continuation(buf(rng)),
+            _ => OwnedPayload::Unregistered { kind: rng.gen_range(10..=255), data: buf(rng) }
+        }
+    }
+}
+
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for OwnedPayload {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        u.arbitrary::<Payload>().map(OwnedPayload::from)
+    }
+}
+
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for OwnedFrame {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        u.arbitrary::<Frame>().map(OwnedFrame::from)
+    }
+}
+
+#[cfg(feature = "quickcheck")]
+impl quickcheck::Arbitrary for OwnedPayload {
+    fn arbitrary(g: &mut Gen) -> OwnedPayload {
+        use quickcheck::Arbitrary;
+
+        let id = |g: &mut Gen| StreamIdentifier(u32::arbitrary(g) & ::consts::MAX_STREAM_ID);
+
+        match u8::arbitrary(g) % 11 {
+            0 => OwnedPayload::Data { data: Arbitrary::arbitrary(g) },
+            1 => OwnedPayload::Headers {
+                priority: Arbitrary::arbitrary(g),
+                block: Arbitrary::arbitrary(g)
+            },
+            2 => OwnedPayload::Priority(Arbitrary::arbitrary(g)),
+            3 => OwnedPayload::Reset(ErrorCode(Arbitrary::arbitrary(g))),
+            4 => {
+                let len = usize::arbitrary(g) % (g.size() + 1);
+                OwnedPayload::Settings((0..len).map(|_| {
+                    let identifier = *g.choose(&[
+                        SettingIdentifier::HeaderTableSize,
+                        SettingIdentifier::EnablePush,
+                        SettingIdentifier::MaxConcurrentStreams,
+                        SettingIdentifier::InitialWindowSize,
+                        SettingIdentifier::MaxFrameSize,
+                        SettingIdentifier::MaxHeaderListSize,
+                        SettingIdentifier::EnableConnectProtocol
+                    ]).unwrap();
+                    Setting::new(identifier, Arbitrary::arbitrary(g))
+                }).collect())
+            },
+            5 => OwnedPayload::PushPromise { promised: id(g), block: Arbitrary::arbitrary(g) },
+            6 => OwnedPayload::Ping(Arbitrary::arbitrary(g)),
+            7 => OwnedPayload::GoAway {
+                last: id(g),
+                error: ErrorCode(Arbitrary::arbitrary(g)),
+                data: Arbitrary::arbitrary(g)
+            },
+            8 => OwnedPayload::WindowUpdate(SizeIncrement(Arbitrary::arbitrary(g))),
+            9 => OwnedPayload::Continuation(Arbitrary::arbitrary(g)),
+            _ => OwnedPayload::Unregistered {
+                kind: 10 + u8::arbitrary(g) % 246,
+                data: Arbitrary::arbitrary(g)
+            }
+        }
+    }
+
+    /// Shrinks the variable-length data of the payload.
+    fn shrink(&self) -> Box<dyn Iterator<Item=OwnedPayload>> {
+        match self.clone() {
+            OwnedPayload::Data { data } =>
+                Box::new(data.shrink().map(|data| OwnedPayload::Data { data: data })),
+            OwnedPayload::Headers { priority, block } =>
+                Box::new(block.shrink().map(move |block| {
+                    OwnedPayload::Headers { priority: priority, block: block }
+                })),
+            OwnedPayload::Settings(settings) =>
+                Box::new((0..settings.len()).rev().map(move |len| {
+                    OwnedPayload::Settings(settings[..len].to_vec())
+                })),
+            OwnedPayload::PushPromise { promised, block } =>
+                Box::new(block.shrink().map(move |block| {
+                    OwnedPayload::PushPromise { promised: promised, block: block }
+                })),
+            OwnedPayload::GoAway { last, error, data } =>
+                Box::new(data.shrink().map(move |data| {
+                    OwnedPayload::GoAway { last: last, error: error, data: data }
+                })),
+            OwnedPayload::Continuation(block) =>
+                Box::new(block.shrink().map(OwnedPayload::Continuation)),
+            OwnedPayload::Unregistered { kind, data } =>
+                Box::new(data.shrink().map(move |data| {
+                    OwnedPayload::Unregistered { kind: kind, data: data }
+                })),
+            _ => quickcheck::empty_shrinker()
+        }
+    }
+}
+
+/// Frames whose header matches their payload, as with `Rand`.
+#[cfg(feature = "quickcheck")]
+impl quickcheck::Arbitrary for OwnedFrame {
+    fn arbitrary(g: &mut Gen) -> OwnedFrame {
+        frame_for_payload(quickcheck::Arbitrary::arbitrary(g),
+                          StreamIdentifier(u32::arbitrary(g) & ::consts::MAX_STREAM_ID))
+    }
+
+    fn shrink(&self) -> Box<dyn Iterator<Item=OwnedFrame>> {
+        let id = self.header.id;
+        Box::new(self.payload.shrink().map(move |payload| frame_for_payload(payload, id)))
+    }
+}
+
+#[cfg(feature = "quickcheck")]
+fn frame_for_payload(payload: OwnedPayload, id: StreamIdentifier) -> OwnedFrame {
+    let flag = if payload.as_payload().priority().is_some() {
+        Flag::priority()
+    } else {
+        Flag::empty()
+    };
+
+    OwnedFrame {
+        header: FrameHeader {
+            length: payload.as_payload().encoded_len() as u32,
+            kind: payload.as_payload().kind(),
+            flag: flag,
+            id: id
+        },
+        payload: payload
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use {Frame, HttpError, StreamIdentifier, Setting, Settings, SettingIdentifier, Payload,
+         FrameHeader, Kind, Flag};
+    use super::{OwnedFrame, OwnedPayload};
+
+    #[test]
+    fn test_owned_round_trip() {
+        let settings = [Setting::new(SettingIdentifier::EnablePush, 0)];
+        let frames = [
+            Frame::goaway(StreamIdentifier(1), HttpError::NoError, Some("bye")),
+            Frame::ping([1; 8]),
+            Frame {
+                header: FrameHeader {
+                    length: 6,
+                    kind: Kind::Settings,
+                    flag: Flag::empty(),
+                    id: StreamIdentifier(0)
+                },
+                payload: Payload::Settings(Settings::new(&settings))
+            }
+        ];
+
+        for frame in &frames {
+            let owned = OwnedFrame::from(*frame);
+            assert_eq!(owned.as_frame(), *frame);
+        }
+
+        let owned = OwnedFrame::from(frames[2]);
+        assert_eq!(owned.payload, OwnedPayload::Settings(settings.to_vec()));
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_serde_round_trip() {
+        let frame = Frame::goaway(StreamIdentifier(7), HttpError::Cancel, Some("done"));
+
+        let json = ::serde_json::to_string(&frame).unwrap();
+        let owned: OwnedFrame = ::serde_json::from_str(&json).unwrap();
+        assert_eq!(owned.as_frame(), frame);
+        assert_eq!(::serde_json::to_string(&owned).unwrap(), json);
+    }
+
+    #[test]
+    #[cfg(feature = "quickcheck")]
+    fn test_quickcheck_round_trip() {
+        fn round_trip(frame: OwnedFrame) -> bool {
+            let mut buf = vec![0; frame.as_frame().encoded_len()];
+            frame.as_frame().encode(&mut buf);
+
+            let header = FrameHeader::parse(&buf).unwrap();
+            Frame::parse(header, &buf[9..]) == Ok(frame.as_frame())
+        }
+
+        ::quickcheck::quickcheck(round_trip as fn(OwnedFrame) -> bool);
+    }
+}
@@ -1,13 +1,26 @@
-use std::{slice, mem, fmt};
-use {FrameHeader, StreamIdentifier, Error, Kind,
+use std::borrow::Cow;
+use std::cmp;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use {FrameHeader, StreamIdentifier, Error, Kind, HttpError,
      ParserSettings, ErrorCode, SizeIncrement, Flag};
+use consts::{MAX_WINDOW_SIZE, MAX_MAX_FRAME_SIZE, DEFAULT_WEIGHT};
 
 use byteorder::ByteOrder;
 
 #[cfg(feature = "random")]
-use rand::{Rand, Rng};
+use rand::Rng;
+#[cfg(feature = "random")]
+use rand::distributions::{Distribution, Standard};
+#[cfg(feature = "random")]
+use OwnedPayload;
+#[cfg(feature = "arbitrary")]
+use arbitrary::{self, Unstructured};
+#[cfg(feature = "quickcheck")]
+use quickcheck::{self, Gen};
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
 pub enum Payload<'a> {
     Data {
         data: &'a [u8]
@@ -18,11 +31,13 @@ pub enum Payload<'a> {
     },
     Priority(Priority),
     Reset(ErrorCode),
-    Settings(&'a [Setting]),
+    Settings(Settings<'a>),
     PushPromise {
         promised: StreamIdentifier,
         block: &'a [u8]
     },
+    /// The opaque PING data, read as a big-endian integer. See
+    /// `Payload::ping` and `Payload::ping_data` for the octets themselves.
     Ping(u64),
     GoAway {
         last: StreamIdentifier,
@@ -31,10 +46,13 @@ pub enum Payload<'a> {
     },
     WindowUpdate(SizeIncrement),
     Continuation(&'a [u8]),
-    Unregistered(&'a [u8])
+    Unregistered {
+        kind: u8,
+        data: &'a [u8]
+    }
 }
 
-const PRIORITY_BYTES: u32 = 5;
+pub const PRIORITY_BYTES: u32 = 5;
 const PADDING_BYTES: u32 = 1;
 
 impl<'a> Payload<'a> {
@@ -53,21 +71,98 @@ impl<'a> Payload<'a> {
             GoAway { .. } => Kind::GoAway,
             WindowUpdate(_) => Kind::WindowUpdate,
             Continuation(_) => Kind::Continuation,
-            Unregistered(_) => Kind::Unregistered
+            Unregistered { kind, .. } => Kind::Unregistered(kind)
         }
     }
 
+    /// A PING payload carrying 8 opaque octets.
     #[inline]
-    pub fn parse(header: FrameHeader, mut buf: &'a [u8]) -> Result<Payload<'a>, Error> {
-        let settings = ParserSettings {
-            padding: header.flag.contains(Flag::padded()),
-            priority: header.flag.contains(Flag::priority())
-        };
+    pub const fn ping(data: [u8; 8]) -> Payload<'static> {
+        Payload::Ping(u64::from_be_bytes(data))
+    }
+
+    /// The opaque octets of a PING payload, in wire order.
+    #[inline]
+    pub fn ping_data(&self) -> Option<[u8; 8]> {
+        match *self {
+            Payload::Ping(data) => {
+                let mut buf = [0; 8];
+                ::encode_u64(&mut buf, data);
+                Some(buf)
+            },
+            _ => None
+        }
+    }
 
+    /// A RST_STREAM payload carrying `error`.
+    #[inline]
+    pub fn reset(error: HttpError) -> Payload<'static> {
+        Payload::Reset(error.into())
+    }
+
+    /// A GOAWAY payload carrying `error`, with optional debug data.
+    #[inline]
+    pub fn goaway(last: StreamIdentifier, error: HttpError,
+                  data: &'a [u8]) -> Payload<'a> {
+        Payload::GoAway {
+            last: last,
+            error: error.into(),
+            data: data
+        }
+    }
+
+    /// The debug data of a GOAWAY payload, as lossy UTF-8 for logging.
+    #[inline]
+    pub fn debug_data(&self) -> Option<Cow<'a, str>> {
+        match *self {
+            Payload::GoAway { data, .. } => Some(String::from_utf8_lossy(data)),
+            _ => None
+        }
+    }
+
+    /// The error carried by a RST_STREAM or GOAWAY payload.
+    #[inline]
+    pub fn error(&self) -> Option<HttpError> {
+        match *self {
+            Payload::Reset(code) => Some(code.into()),
+            Payload::GoAway { error, .. } => Some(error.into()),
+            _ => None
+        }
+    }
+
+    #[inline]
+    pub fn parse(header: FrameHeader, buf: &'a [u8]) -> Result<Payload<'a>, Error> {
         if buf.len() < header.length as usize {
-            return Err(Error::Short)
+            return Err(Error::Short(header.length as usize - buf.len()))
         }
 
+        Payload::parse_exact(header, &buf[..header.length as usize])
+    }
+
+    /// Like `parse`, but without checking that `buf` holds the whole
+    /// payload. The payload itself is still validated.
+    ///
+    /// # Safety
+    ///
+    /// `buf` must be at least `header.length` bytes long.
+    #[cfg(feature = "unchecked")]
+    #[inline]
+    pub unsafe fn parse_unchecked(header: FrameHeader, buf: &'a [u8])
+                                  -> Result<Payload<'a>, Error> {
+        debug_assert!(buf.len() >= header.length as usize);
+        Payload::parse_exact(header, buf.get_unchecked(..header.length as usize))
+    }
+
+    // Parse a payload from exactly `header.length` bytes.
+    #[inline]
+    fn parse_exact(header: FrameHeader, buf: &'a [u8]) -> Result<Payload<'a>, Error> {
+        // Flags not defined for the frame's kind must be ignored.
+        let flag = header.flag & Flag::allowed_for(header.kind);
+        let settings = ParserSettings {
+            padding: flag.contains(Flag::padded()),
+            priority: flag.contains(Flag::priority())
+        };
+
         let min_payload_length =
             if settings.priority && settings.padding {
                 PRIORITY_BYTES + PADDING_BYTES
@@ -83,8 +178,6 @@ impl<'a> Payload<'a> {
             return Err(Error::PayloadLengthTooShort)
         }
 
-        buf = &buf[..header.length as usize];
-
         match header.kind {
             Kind::Data => Payload::parse_data(header, buf, settings),
             Kind::Headers => Payload::parse_headers(header, buf, settings),
@@ -99,7 +192,7 @@ impl<'a> Payload<'a> {
             Kind::WindowUpdate => Payload::parse_window_update(header, buf),
             Kind::PushPromise => Payload::parse_push_promise(header, buf, settings),
             Kind::Continuation => Ok(Payload::Continuation(buf)),
-            Kind::Unregistered => Ok(Payload::Unregistered(buf))
+            Kind::Unregistered(kind) => Ok(Payload::Unregistered { kind: kind, data: buf })
         }
     }
 
@@ -113,9 +206,7 @@ impl<'a> Payload<'a> {
                 priority_wrote + block_wrote
             },
             Payload::Reset(ref err) => { err.encode(buf) },
-            Payload::Settings(ref settings) => {
-                encode_memory(Setting::to_bytes(settings), buf)
-            },
+            Payload::Settings(ref settings) => { settings.encode(buf) },
             Payload::Ping(data) => { ::encode_u64(buf, data) },
             Payload::GoAway { ref data, ref last, ref error } => {
                 let last_wrote = last.encode(buf);
@@ -133,7 +224,7 @@ impl<'a> Payload<'a> {
             },
             Payload::Priority(ref priority) => { priority.encode(buf) },
             Payload::Continuation(ref block) => { encode_memory(block, buf) },
-            Payload::Unregistered(ref block) => { encode_memory(block, buf) }
+            Payload::Unregistered { ref data, .. } => { encode_memory(data, buf) }
         }
     }
 
@@ -149,14 +240,14 @@ impl<'a> Payload<'a> {
                 priority_len + block.len()
             },
             Reset(_) => 4,
-            Settings(ref settings) => settings.len() * mem::size_of::<Setting>(),
+            Settings(ref settings) => settings.encoded_len(),
             Ping(_) => 8,
             GoAway { ref data, .. } => 4 + 4 + data.len(),
             WindowUpdate(_) => 4,
             PushPromise { ref block, .. } => 4 + block.len(),
             Priority(_) => 5,
             Continuation(ref block) => block.len(),
-            Unregistered(ref block) => block.len()
+            Unregistered { ref data, .. } => data.len()
         }
     }
 
@@ -206,11 +297,14 @@ impl<'a> Payload<'a> {
     #[inline]
     fn parse_settings(header: FrameHeader,
                       buf: &'a [u8]) -> Result<Payload<'a>, Error> {
-        if header.length % mem::size_of::<Setting>() as u32 != 0 {
+        if header.flag.contains(Flag::ack()) && header.length != 0 {
+            return Err(Error::InvalidPayloadLength)
+        }
+        if header.length as usize % SETTING_BYTES != 0 {
             return Err(Error::PartialSettingLength)
         }
 
-        Ok(Payload::Settings(Setting::from_bytes(&buf[..header.length as usize])))
+        Ok(Payload::Settings(try!(Settings::parse(buf))))
     }
 
     #[inline]
@@ -271,7 +365,62 @@ impl<'a> Payload<'a> {
     }
 }
 
+impl<'a> fmt::Display for Payload<'a> {
+    /// A one-line summary, such as `WINDOW_UPDATE increment=1024`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        try!(self.kind().fmt(f));
+        fmt_details(self, f)
+    }
+}
+
+/// Parse a payload from `buf`, which holds only its fixed-size fields: the
+/// unpadded payload with any variable-size data cut from the end.
+///
+/// Lengths are still checked against `header.length`, which must not count
+/// padding, and any data in the result is whatever `buf` holds past the
+/// fields. This lets payloads which cannot be borrowed whole be parsed from
+/// a copy of their fields.
+pub fn parse_fields(header: FrameHeader, buf: &[u8]) -> Result<Payload, Error> {
+    Payload::parse_exact(header, buf)
+}
+
+/// Write the parts of the summary following the frame kind, each
+/// preceded by a space.
+pub fn fmt_details(payload: &Payload, f: &mut fmt::Formatter) -> fmt::Result {
+    match *payload {
+        Payload::Data { data } => write!(f, " data={}", data.len()),
+        Payload::Headers { priority, block } => {
+            if let Some(priority) = priority {
+                try!(write!(f, " {}", priority));
+            }
+            write!(f, " block={}", block.len())
+        },
+        Payload::Priority(priority) => write!(f, " {}", priority),
+        Payload::Reset(error) => write!(f, " error={}", HttpError::from(error)),
+        Payload::Settings(settings) => {
+            for setting in settings.iter() {
+                try!(write!(f, " {}", setting));
+            }
+            Ok(())
+        },
+        Payload::PushPromise { promised, block } =>
+            write!(f, " promised={} block={}", promised.0, block.len()),
+        Payload::Ping(data) => write!(f, " data={:016x}", data),
+        Payload::GoAway { last, error, .. } => {
+            try!(write!(f, " last={} error={}", last.0, HttpError::from(error)));
+            match payload.debug_data() {
+                Some(ref debug) if !debug.is_empty() => write!(f, " debug={:?}", debug),
+                _ => Ok(())
+            }
+        },
+        Payload::WindowUpdate(increment) => write!(f, " increment={}", increment.0),
+        Payload::Continuation(block) => write!(f, " block={}", block.len()),
+        Payload::Unregistered { data, .. } => write!(f, " data={}", data.len())
+    }
+}
+
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Priority {
     exclusive: bool,
     dependency: StreamIdentifier,
@@ -279,9 +428,24 @@ pub struct Priority {
 }
 
 impl Priority {
+    /// A priority with `weight` as sent on the wire, one less than the
+    /// actual weight.
+    #[inline]
+    pub fn new(exclusive: bool, dependency: StreamIdentifier, weight: u8) -> Priority {
+        Priority {
+            exclusive: exclusive,
+            dependency: dependency,
+            weight: weight
+        }
+    }
+
     #[inline]
     pub fn parse(present: bool, buf: &[u8]) -> Result<(&[u8], Option<Priority>), Error> {
         if present {
+            if buf.len() < PRIORITY_BYTES as usize {
+                return Err(Error::PayloadLengthTooShort)
+            }
+
             Ok((&buf[5..], Some(Priority {
                 // Most significant bit.
                 exclusive: buf[0] & 0x7F != buf[0],
@@ -293,6 +457,30 @@ impl Priority {
         }
     }
 
+    /// Is this an exclusive dependency?
+    #[inline]
+    pub fn exclusive(&self) -> bool {
+        self.exclusive
+    }
+
+    /// The stream this stream depends on.
+    #[inline]
+    pub fn dependency(&self) -> StreamIdentifier {
+        self.dependency
+    }
+
+    /// The weight as sent on the wire, one less than the actual weight.
+    #[inline]
+    pub fn weight(&self) -> u8 {
+        self.weight
+    }
+
+    /// The actual weight, between 1 and 256.
+    #[inline]
+    pub fn effective_weight(&self) -> u16 {
+        self.weight as u16 + 1
+    }
+
     #[inline]
     pub fn encode(&self, buf: &mut [u8]) -> usize {
         let mut dependency = self.dependency;
@@ -305,9 +493,29 @@ impl Priority {
     }
 }
 
-// Settings are (u16, u32) in memory.
-#[repr(packed)]
+impl fmt::Display for Priority {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        try!(write!(f, "dependency={} weight={}", self.dependency.0, self.effective_weight()));
+        if self.exclusive { try!(f.write_str(" exclusive")) }
+        Ok(())
+    }
+}
+
+impl Default for Priority {
+    /// The priority streams are given when none is specified, a
+    /// non-exclusive dependency on stream 0 with a weight of 16.
+    fn default() -> Priority {
+        Priority::new(false, StreamIdentifier(0), (DEFAULT_WEIGHT - 1) as u8)
+    }
+}
+
+// Settings are (u16, u32) on the wire, both big-endian.
+pub const SETTING_BYTES: usize = 6;
+
+const MIN_MAX_FRAME_SIZE: u32 = ::consts::DEFAULT_MAX_FRAME_SIZE;
+
 #[derive(Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Setting {
     identifier: u16,
     value: u32
@@ -320,6 +528,16 @@ impl fmt::Debug for Setting {
     }
 }
 
+impl fmt::Display for Setting {
+    /// The setting as `NAME=value`, using the names from RFC 7540.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.identifier() {
+            Some(identifier) => write!(f, "{}={}", identifier.name(), self.value),
+            None => write!(f, "0x{:x}={}", self.identifier, self.value)
+        }
+    }
+}
+
 impl Setting {
     #[inline]
     pub fn new(identifier: SettingIdentifier, value: u32) -> Setting {
@@ -331,14 +549,13 @@ impl Setting {
 
     #[inline]
     pub fn identifier(&self) -> Option<SettingIdentifier> {
-        match self.identifier {
-            0x1 => Some(SettingIdentifier::HeaderTableSize),
-            0x2 => Some(SettingIdentifier::EnablePush),
-            0x3 => Some(SettingIdentifier::MaxConcurrentStreams),
-            0x4 => Some(SettingIdentifier::InitialWindowSize),
-            0x5 => Some(SettingIdentifier::MaxFrameSize),
-            _ => None
-        }
+        SettingIdentifier::from_code(self.identifier)
+    }
+
+    /// The raw identifier, which may not be a known setting.
+    #[inline]
+    pub fn code(&self) -> u16 {
+        self.identifier
     }
 
     #[inline]
@@ -346,121 +563,387 @@ impl Setting {
         self.value
     }
 
+    /// Check the value against the range the RFC mandates for this setting.
+    ///
+    /// Unknown settings are always valid, as they must be ignored.
     #[inline]
-    fn to_bytes(settings: &[Setting]) -> &[u8] {
-        unsafe {
-            slice::from_raw_parts(
-                settings.as_ptr() as *const u8,
-                settings.len() * mem::size_of::<Setting>())
+    pub fn validate(&self) -> Result<(), HttpError> {
+        match self.identifier() {
+            Some(SettingIdentifier::EnablePush) if self.value > 1 =>
+                Err(HttpError::Protocol),
+            Some(SettingIdentifier::EnableConnectProtocol) if self.value > 1 =>
+                Err(HttpError::Protocol),
+            Some(SettingIdentifier::InitialWindowSize) if self.value > MAX_WINDOW_SIZE =>
+                Err(HttpError::FlowControlError),
+            Some(SettingIdentifier::MaxFrameSize)
+                if self.value < MIN_MAX_FRAME_SIZE || self.value > MAX_MAX_FRAME_SIZE =>
+                    Err(HttpError::Protocol),
+            _ => Ok(())
         }
     }
 
+    /// Parse a single setting from the first 6 bytes of `buf`.
     #[inline]
-    fn from_bytes(bytes: &[u8]) -> &[Setting] {
-        unsafe {
-            slice::from_raw_parts(
-                bytes.as_ptr() as *const Setting,
-                bytes.len() / mem::size_of::<Setting>())
+    pub fn parse(buf: &[u8]) -> Setting {
+        Setting {
+            identifier: ::byteorder::BigEndian::read_u16(buf),
+            value: ::byteorder::BigEndian::read_u32(&buf[2..])
         }
     }
+
+    #[inline]
+    pub fn encode(&self, buf: &mut [u8]) -> usize {
+        ::byteorder::BigEndian::write_u16(buf, self.identifier);
+        ::encode_u32(&mut buf[2..], self.value) + 2
+    }
+}
+
+/// A lazily decoded list of settings.
+///
+/// Parsed settings borrow the raw payload and are decoded on access,
+/// settings constructed for encoding borrow a slice of `Setting`.
+#[derive(Copy, Clone)]
+pub struct Settings<'a> {
+    repr: SettingsRepr<'a>
+}
+
+#[derive(Copy, Clone)]
+enum SettingsRepr<'a> {
+    Raw(&'a [u8]),
+    Decoded(&'a [Setting])
 }
 
+impl<'a> Settings<'a> {
+    #[inline]
+    pub const fn new(settings: &'a [Setting]) -> Settings<'a> {
+        Settings { repr: SettingsRepr::Decoded(settings) }
+    }
+
+    /// Wrap a raw SETTINGS payload, which must be a multiple of 6 bytes.
+    #[inline]
+    pub fn parse(buf: &'a [u8]) -> Result<Settings<'a>, Error> {
+        if buf.len() % SETTING_BYTES != 0 {
+            return Err(Error::PartialSettingLength)
+        }
+
+        Ok(Settings { repr: SettingsRepr::Raw(buf) })
+    }
+
+    /// The raw payload, if these settings were parsed rather than
+    /// constructed.
+    #[inline]
+    pub fn raw(&self) -> Option<&'a [u8]> {
+        match self.repr {
+            SettingsRepr::Raw(buf) => Some(buf),
+            SettingsRepr::Decoded(_) => None
+        }
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        match self.repr {
+            SettingsRepr::Raw(buf) => buf.len() / SETTING_BYTES,
+            SettingsRepr::Decoded(settings) => settings.len()
+        }
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    #[inline]
+    pub fn get(&self, index: usize) -> Option<Setting> {
+        if index >= self.len() { return None }
+
+        match self.repr {
+            SettingsRepr::Raw(buf) => Some(Setting::parse(&buf[index * SETTING_BYTES..])),
+            SettingsRepr::Decoded(settings) => Some(settings[index])
+        }
+    }
+
+    #[inline]
+    pub fn encode(&self, buf: &mut [u8]) -> usize {
+        match self.repr {
+            SettingsRepr::Raw(raw) => encode_memory(raw, buf),
+            SettingsRepr::Decoded(settings) => {
+                settings.iter().fold(0, |wrote, setting| {
+                    wrote + setting.encode(&mut buf[wrote..])
+                })
+            }
+        }
+    }
+
+    #[inline]
+    pub fn encoded_len(&self) -> usize {
+        self.len() * SETTING_BYTES
+    }
+
+    /// Validate every setting, returning the first error found.
+    #[inline]
+    pub fn validate(&self) -> Result<(), HttpError> {
+        for setting in self.iter() {
+            try!(setting.validate());
+        }
+
+        Ok(())
+    }
+
+    #[inline]
+    pub fn iter(&self) -> SettingsIter<'a> {
+        SettingsIter { settings: *self, index: 0 }
+    }
+}
+
+impl<'a> IntoIterator for Settings<'a> {
+    type Item = Setting;
+    type IntoIter = SettingsIter<'a>;
+
+    #[inline]
+    fn into_iter(self) -> SettingsIter<'a> {
+        self.iter()
+    }
+}
+
+/// An iterator over the settings of a SETTINGS payload, decoding
+/// each 6-byte entry as it is reached.
+#[derive(Copy, Clone, Debug)]
+pub struct SettingsIter<'a> {
+    settings: Settings<'a>,
+    index: usize
+}
+
+impl<'a> SettingsIter<'a> {
+    /// Iterate a raw SETTINGS payload.
+    #[inline]
+    pub fn new(buf: &'a [u8]) -> Result<SettingsIter<'a>, Error> {
+        Settings::parse(buf).map(|settings| settings.iter())
+    }
+}
+
+impl<'a> Iterator for SettingsIter<'a> {
+    type Item = Setting;
+
+    #[inline]
+    fn next(&mut self) -> Option<Setting> {
+        let setting = self.settings.get(self.index);
+        if setting.is_some() { self.index += 1 }
+        setting
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.settings.len() - self.index;
+        (remaining, Some(remaining))
+    }
+}
+
+impl<'a> ExactSizeIterator for SettingsIter<'a> {}
+
+impl<'a, 'b> PartialEq<Settings<'b>> for Settings<'a> {
+    fn eq(&self, other: &Settings<'b>) -> bool {
+        self.len() == other.len() && self.iter().zip(other.iter()).all(|(a, b)| a == b)
+    }
+}
+
+impl<'a> Eq for Settings<'a> {}
+
+impl<'a> Hash for Settings<'a> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.len().hash(state);
+        for setting in self.iter() {
+            setting.hash(state);
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'a> ::serde::Serialize for Settings<'a> {
+    /// Settings are serialized as a sequence of `Setting`s, whether or
+    /// not they have been decoded.
+    fn serialize<S: ::serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.iter())
+    }
+}
+
+impl<'a> fmt::Debug for Settings<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+/// The settings this crate knows by name.
+///
+/// More settings may be added in future, so matches outside this crate
+/// need a wildcard arm.
 #[repr(u16)]
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
 pub enum SettingIdentifier {
     HeaderTableSize = 0x1,
     EnablePush = 0x2,
     MaxConcurrentStreams = 0x3,
     InitialWindowSize = 0x4,
-    MaxFrameSize = 0x5
+    MaxFrameSize = 0x5,
+    MaxHeaderListSize = 0x6,
+
+    /// SETTINGS_ENABLE_CONNECT_PROTOCOL, from RFC 8441.
+    EnableConnectProtocol = 0x8
 }
 
-#[cfg(feature = "random")]
-impl Rand for Payload<'static> {
-    fn rand<R: Rng>(rng: &mut R) -> Self {
-        use self::Payload::*;
+impl SettingIdentifier {
+    /// The setting with the identifier `code`, if it is known.
+    pub fn from_code(code: u16) -> Option<SettingIdentifier> {
+        match code {
+            0x1 => Some(SettingIdentifier::HeaderTableSize),
+            0x2 => Some(SettingIdentifier::EnablePush),
+            0x3 => Some(SettingIdentifier::MaxConcurrentStreams),
+            0x4 => Some(SettingIdentifier::InitialWindowSize),
+            0x5 => Some(SettingIdentifier::MaxFrameSize),
+            0x6 => Some(SettingIdentifier::MaxHeaderListSize),
+            0x8 => Some(SettingIdentifier::EnableConnectProtocol),
+            _ => None
+        }
+    }
 
-        let choices = &[
-            Data {
-                data: rand_buf(rng)
-            },
-            Headers {
-                priority: rng.gen(),
-                block: rand_buf(rng),
-            },
-            Priority(rng.gen()),
-            Reset(ErrorCode(rng.gen())),
-            Settings(leak({
-                let len = rng.gen_range(0, 200);
-
-                (0..len).map(|_| Setting {
-                    identifier: *rng.choose(&[
-                        SettingIdentifier::HeaderTableSize,
-                        SettingIdentifier::EnablePush,
-                        SettingIdentifier::MaxConcurrentStreams,
-                        SettingIdentifier::InitialWindowSize,
-                        SettingIdentifier::MaxFrameSize
-                    ]).unwrap() as u16,
-                    value: rng.gen()
-                }).collect::<Vec<Setting>>()})),
-            PushPromise {
-                promised: StreamIdentifier(rng.gen_range(0, 1 << 31)),
-                block: rand_buf(rng)
-            },
-            Ping(rng.gen()),
-            GoAway {
-                last: StreamIdentifier(rng.gen_range(0, 1 << 31)),
-                error: ErrorCode(rng.gen()),
-                data: rand_buf(rng)
-            },
-            WindowUpdate(SizeIncrement(rng.gen())),
-            Continuation(rand_buf(rng)),
-            Unregistered(rand_buf(rng))
-        ];
+    /// The identifier sent on the wire.
+    pub fn code(&self) -> u16 {
+        *self as u16
+    }
 
-        *rng.choose(choices).unwrap()
+    /// The setting's name from RFC 7540, without the `SETTINGS_` prefix.
+    pub fn name(&self) -> &'static str {
+        match *self {
+            SettingIdentifier::HeaderTableSize => "HEADER_TABLE_SIZE",
+            SettingIdentifier::EnablePush => "ENABLE_PUSH",
+            SettingIdentifier::MaxConcurrentStreams => "MAX_CONCURRENT_STREAMS",
+            SettingIdentifier::InitialWindowSize => "INITIAL_WINDOW_SIZE",
+            SettingIdentifier::MaxFrameSize => "MAX_FRAME_SIZE",
+            SettingIdentifier::MaxHeaderListSize => "MAX_HEADER_LIST_SIZE",
+            SettingIdentifier::EnableConnectProtocol => "ENABLE_CONNECT_PROTOCOL"
+        }
+    }
+
+    /// The known setting called `name`, as returned by `name`.
+    pub fn from_name(name: &str) -> Option<SettingIdentifier> {
+        SETTING_IDENTIFIERS.iter().find(|identifier| identifier.name() == name).cloned()
+    }
+}
+
+// Every known setting, in order of identifier.
+const SETTING_IDENTIFIERS: &'static [SettingIdentifier] = &[
+    SettingIdentifier::HeaderTableSize,
+    SettingIdentifier::EnablePush,
+    SettingIdentifier::MaxConcurrentStreams,
+    SettingIdentifier::InitialWindowSize,
+    SettingIdentifier::MaxFrameSize,
+    SettingIdentifier::MaxHeaderListSize,
+    SettingIdentifier::EnableConnectProtocol
+];
+
+/// Each payload is leaked to give it a `'static` lifetime, which suits
+/// tests but not long-running fuzzers; see `random_frame`.
+#[cfg(feature = "random")]
+impl Distribution<Payload<'static>> for Standard {
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> Payload<'static> {
+        let payload: &'static OwnedPayload = Box::leak(Box::new(rng.gen()));
+        payload.as_payload()
     }
 }
 
 #[cfg(feature = "random")]
-impl Rand for Priority {
-    fn rand<R: Rng>(rng: &mut R) -> Self {
+impl Distribution<Priority> for Standard {
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> Priority {
         Priority {
             exclusive: rng.gen(),
-            dependency: StreamIdentifier(rng.gen_range(0, 1 << 31)),
+            dependency: StreamIdentifier(rng.gen_range(0..=::consts::MAX_STREAM_ID)),
             weight: rng.gen()
         }
     }
 }
 
-#[cfg(feature = "random")]
-fn rand_buf<R: Rng>(rng: &mut R) -> &'static [u8] {
-    let len = rng.gen_range(0, 200);
-    let mut buf = vec![0; len];
-    rng.fill_bytes(&mut *buf);
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for Payload<'a> {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        use self::Payload::*;
+
+        Ok(match try!(u.int_in_range(0..=10u8)) {
+            0 => Data {
+                data: try!(u.arbitrary())
+            },
+            1 => Headers {
+                priority: try!(u.arbitrary()),
+                block: try!(u.arbitrary())
+            },
+            2 => Priority(try!(u.arbitrary())),
+            3 => Reset(ErrorCode(try!(u.arbitrary()))),
+            4 => {
+                let len = try!(u.arbitrary_len::<[u8; SETTING_BYTES]>());
+                Settings(::payload::Settings {
+                    repr: SettingsRepr::Raw(try!(u.bytes(len * SETTING_BYTES)))
+                })
+            },
+            5 => PushPromise {
+                promised: StreamIdentifier(try!(u.arbitrary::<u32>()) & ::consts::MAX_STREAM_ID),
+                block: try!(u.arbitrary())
+            },
+            6 => Ping(try!(u.arbitrary())),
+            7 => GoAway {
+                last: StreamIdentifier(try!(u.arbitrary::<u32>()) & ::consts::MAX_STREAM_ID),
+                error: ErrorCode(try!(u.arbitrary())),
+                data: try!(u.arbitrary())
+            },
+            8 => WindowUpdate(SizeIncrement(try!(u.arbitrary()))),
+            9 => Continuation(try!(u.arbitrary())),
+            _ => Unregistered {
+                kind: try!(u.int_in_range(10..=255u8)),
+                data: try!(u.arbitrary())
+            }
+        })
+    }
+}
 
-    leak(buf)
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for Priority {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        Ok(Priority {
+            exclusive: try!(u.arbitrary()),
+            dependency: StreamIdentifier(try!(u.arbitrary::<u32>()) & ::consts::MAX_STREAM_ID),
+            weight: try!(u.arbitrary())
+        })
+    }
 }
 
-#[cfg(feature = "random")]
-fn leak<T>(buf: Vec<T>) -> &'static [T] {
-    let result = unsafe { mem::transmute::<&[T], &'static [T]>(&*buf) };
-    mem::forget(buf);
-    result
+#[cfg(feature = "quickcheck")]
+impl quickcheck::Arbitrary for Priority {
+    fn arbitrary(g: &mut Gen) -> Priority {
+        Priority {
+            exclusive: quickcheck::Arbitrary::arbitrary(g),
+            dependency: StreamIdentifier(u32::arbitrary(g) & ::consts::MAX_STREAM_ID),
+            weight: quickcheck::Arbitrary::arbitrary(g)
+        }
+    }
 }
 
 #[inline]
 fn trim_padding(settings: ParserSettings, header: FrameHeader,
                 buf: &[u8]) -> Result<&[u8], Error> {
-    if settings.padding {
-        let pad_length = buf[0];
-        if pad_length as u32 > header.length {
-            Err(Error::TooMuchPadding(pad_length))
-        } else {
-            Ok(&buf[1..header.length as usize - pad_length as usize])
-        }
+    if !settings.padding {
+        return Ok(buf)
+    }
+
+    // Never trust the header length alone to index the buffer.
+    let length = cmp::min(header.length as usize, buf.len());
+    if length == 0 {
+        return Err(Error::PayloadLengthTooShort)
+    }
+
+    // The padding and the byte holding its length must both fit.
+    let pad_length = buf[0];
+    if pad_length as usize >= length {
+        Err(Error::TooMuchPadding(pad_length))
     } else {
-        Ok(buf)
+        Ok(&buf[1..length - pad_length as usize])
     }
 }
 
@@ -470,6 +953,65 @@ fn encode_memory(src: &[u8], mut dst: &mut [u8]) -> usize {
     dst.write(src).unwrap()
 }
 
+#[test]
+fn test_settings_wire_format() {
+    let raw = [0x0, 0x3, 0x0, 0x0, 0x0, 0x64,
+               0x0, 0x5, 0x0, 0x0, 0x40, 0x0];
+    let settings = Settings::parse(&raw).unwrap();
+
+    assert_eq!(settings.len(), 2);
+    assert_eq!(settings.get(0), Some(Setting::new(SettingIdentifier::MaxConcurrentStreams, 100)));
+    assert_eq!(settings.get(1), Some(Setting::new(SettingIdentifier::MaxFrameSize, 16384)));
+    assert_eq!(settings.get(2), None);
+    assert_eq!(settings.get(1).unwrap().code(), SettingIdentifier::MaxFrameSize.code());
+    assert_eq!(SettingIdentifier::from_code(0x5), Some(SettingIdentifier::MaxFrameSize));
+    assert_eq!(SettingIdentifier::from_code(0x7), None);
+
+    let decoded = settings.iter().collect::<Vec<_>>();
+    let mut buf = [0; 12];
+    assert_eq!(Settings::new(&decoded).encode(&mut buf), 12);
+    assert_eq!(buf, raw);
+    assert_eq!(Settings::new(&decoded), settings);
+
+    assert_eq!(Settings::parse(&raw[..7]), Err(Error::PartialSettingLength));
+}
+
+#[test]
+fn test_setting_validate() {
+    use SettingIdentifier::*;
+
+    assert_eq!(Setting::new(EnablePush, 1).validate(), Ok(()));
+    assert_eq!(Setting::new(EnablePush, 2).validate(), Err(HttpError::Protocol));
+    assert_eq!(Setting::new(InitialWindowSize, (1 << 31) - 1).validate(), Ok(()));
+    assert_eq!(Setting::new(InitialWindowSize, 1 << 31).validate(),
+               Err(HttpError::FlowControlError));
+    assert_eq!(Setting::new(MaxFrameSize, 16384).validate(), Ok(()));
+    assert_eq!(Setting::new(MaxFrameSize, 16383).validate(), Err(HttpError::Protocol));
+    assert_eq!(Setting::new(MaxFrameSize, 16777215).validate(), Ok(()));
+    assert_eq!(Setting::new(MaxFrameSize, 16777216).validate(), Err(HttpError::Protocol));
+    assert_eq!(Setting::new(HeaderTableSize, 0xFFFFFFFF).validate(), Ok(()));
+
+    let settings = [Setting::new(HeaderTableSize, 0), Setting::new(EnablePush, 5)];
+    assert_eq!(Settings::new(&settings).validate(), Err(HttpError::Protocol));
+}
+
+#[test]
+fn test_settings_iter() {
+    let raw = [0x0, 0x2, 0x0, 0x0, 0x0, 0x1,
+               0x0, 0x9, 0x0, 0x0, 0x0, 0x7];
+    let mut iter = SettingsIter::new(&raw).unwrap();
+
+    assert_eq!(iter.len(), 2);
+    assert_eq!(iter.next(), Some(Setting::new(SettingIdentifier::EnablePush, 1)));
+
+    let unknown = iter.next().unwrap();
+    assert_eq!(unknown.identifier(), None);
+    assert_eq!(unknown.value(), 7);
+
+    assert_eq!(iter.next(), None);
+    assert!(SettingsIter::new(&raw[..5]).is_err());
+}
+
 #[test]
 #[cfg(feature = "random")]
 fn test_specific_encode() {
@@ -524,3 +1066,157 @@ fn no_test_encoded_len_because_no_rand() {}
 #[cfg(not(feature = "random"))]
 fn no_test_encode_because_no_rand() {}
 
+
+#[test]
+fn test_http_error_payloads() {
+    use {Frame, FrameHeader, Kind, Flag};
+
+    let payloads = [
+        Payload::reset(HttpError::Cancel),
+        Payload::goaway(StreamIdentifier(7), HttpError::EnhanceYourCalm, b"slow down")
+    ];
+
+    for payload in &payloads {
+        let frame = Frame {
+            header: FrameHeader {
+                length: payload.encoded_len() as u32,
+                kind: payload.kind(),
+                flag: Flag::empty(),
+                id: StreamIdentifier(if payload.kind() == Kind::Reset { 1 } else { 0 })
+            },
+            payload: *payload
+        };
+
+        let mut buf = vec![0; frame.encoded_len()];
+        frame.encode(&mut buf);
+
+        let decoded = Frame::parse(FrameHeader::parse(&buf).unwrap(), &buf[9..]).unwrap();
+        assert_eq!(decoded.payload.error(), payload.error());
+    }
+
+    assert_eq!(payloads[0].error(), Some(HttpError::Cancel));
+    assert_eq!(Payload::Reset(ErrorCode(0x1)).error(), Some(HttpError::Protocol));
+    assert_eq!(Payload::Ping(0).error(), None);
+}
+
+#[test]
+fn test_ping_data() {
+    let data = [1, 2, 3, 4, 5, 6, 7, 8];
+    let payload = Payload::ping(data);
+
+    assert_eq!(payload.ping_data(), Some(data));
+    assert_eq!(payload, Payload::Ping(0x0102030405060708));
+
+    let mut buf = [0; 8];
+    payload.encode(&mut buf);
+    assert_eq!(buf, data);
+
+    assert_eq!(Payload::reset(HttpError::Cancel).ping_data(), None);
+}
+
+#[test]
+fn test_priority_accessors() {
+    let priority = Priority::new(true, StreamIdentifier(3), 255);
+    assert!(priority.exclusive());
+    assert_eq!(priority.dependency(), StreamIdentifier(3));
+    assert_eq!(priority.weight(), 255);
+    assert_eq!(priority.effective_weight(), 256);
+
+    let mut buf = [0; 5];
+    priority.encode(&mut buf);
+    assert_eq!(Priority::parse(true, &buf).unwrap().1, Some(priority));
+
+    let default = Priority::default();
+    assert!(!default.exclusive());
+    assert_eq!(default.dependency(), StreamIdentifier(0));
+    assert_eq!(default.effective_weight(), 16);
+}
+
+#[test]
+fn test_short_priority() {
+    use {Flag, Frame};
+
+    assert_eq!(Priority::parse(true, &[0, 0, 0, 1]), Err(Error::PayloadLengthTooShort));
+    assert_eq!(Priority::parse(false, &[]), Ok((&[][..], None)));
+
+    let priority = FrameHeader {
+        length: 3,
+        kind: Kind::Priority,
+        flag: Flag::empty(),
+        id: StreamIdentifier(1)
+    };
+    assert_eq!(Frame::parse(priority, &[0, 0, 0]), Err(Error::PayloadLengthTooShort));
+
+    // The padding leaves only one byte for the priority.
+    let headers = FrameHeader {
+        length: 6,
+        kind: Kind::Headers,
+        flag: Flag::padded() | Flag::priority(),
+        id: StreamIdentifier(1)
+    };
+    assert_eq!(Frame::parse(headers, &[4, 0, 0, 0, 0, 0]), Err(Error::PayloadLengthTooShort));
+}
+
+#[test]
+fn test_malformed_padding() {
+    use Flag;
+
+    let settings = ParserSettings { padding: true, priority: false };
+    let header = |length| FrameHeader {
+        length: length,
+        kind: Kind::Data,
+        flag: Flag::padded(),
+        id: StreamIdentifier(1)
+    };
+
+    assert_eq!(trim_padding(settings, header(3), &[2, 0, 0]), Ok(&[][..]));
+    assert_eq!(trim_padding(settings, header(3), &[1, 7, 0]), Ok(&[7][..]));
+    assert_eq!(trim_padding(settings, header(1), &[1]), Err(Error::TooMuchPadding(1)));
+    assert_eq!(trim_padding(settings, header(0), &[]), Err(Error::PayloadLengthTooShort));
+    assert_eq!(trim_padding(settings, header(8), &[]), Err(Error::PayloadLengthTooShort));
+    assert_eq!(trim_padding(settings, header(8), &[2, 0]), Err(Error::TooMuchPadding(2)));
+
+    assert_eq!(Payload::parse(header(1), &[1]), Err(Error::TooMuchPadding(1)));
+    assert_eq!(Payload::parse(header(255), &[255; 255]), Err(Error::TooMuchPadding(255)));
+}
+
+#[test]
+fn test_undefined_padded_and_priority_flags() {
+    use Flag;
+
+    let header = |length, kind, flag| FrameHeader {
+        length: length,
+        kind: kind,
+        flag: flag,
+        id: StreamIdentifier(0)
+    };
+
+    let update = header(4, Kind::WindowUpdate, Flag::priority());
+    assert_eq!(Payload::parse(update, &[0, 0, 0, 1]),
+               Ok(Payload::WindowUpdate(SizeIncrement(1))));
+
+    let ack = header(0, Kind::Settings, Flag::ack() | Flag::padded());
+    assert_eq!(Payload::parse(ack, &[]), Ok(Payload::Settings(Settings::new(&[]))));
+
+    let continuation = header(1, Kind::Continuation, Flag::padded() | Flag::priority());
+    assert_eq!(Payload::parse(continuation, &[9]), Ok(Payload::Continuation(&[9])));
+}
+
+#[test]
+fn test_payload_display() {
+    let settings = [Setting::new(SettingIdentifier::MaxConcurrentStreams, 100),
+                    Setting { identifier: 0x20, value: 1 }];
+    assert_eq!(Payload::Settings(Settings::new(&settings)).to_string(),
+               "SETTINGS MAX_CONCURRENT_STREAMS=100 0x20=1");
+
+    let headers = Payload::Headers {
+        priority: Some(Priority::new(true, StreamIdentifier(3), 15)),
+        block: &[0; 4]
+    };
+    assert_eq!(headers.to_string(), "HEADERS dependency=3 weight=16 exclusive block=4");
+
+    assert_eq!(Payload::reset(HttpError::Cancel).to_string(), "RST_STREAM error=CANCEL");
+    assert_eq!(Payload::Reset(ErrorCode(0x40)).to_string(), "RST_STREAM error=0x40");
+    assert_eq!(Payload::WindowUpdate(SizeIncrement(1024)).to_string(),
+               "WINDOW_UPDATE increment=1024");
+}
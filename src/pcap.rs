@@ -0,0 +1,508 @@
+//! Extraction of HTTP/2 streams from pcap and pcapng captures.
+//!
+//! Only plaintext (h2c) traffic can be read, over Ethernet, loopback,
+//! Linux cooked or raw IP links. IP fragments and IPv6 extension headers
+//! are skipped.
+
+use std::cmp;
+use std::collections::{BTreeMap, HashMap};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use byteorder::{BigEndian, LittleEndian, ByteOrder};
+
+use capture::Direction;
+use {Frames, Error};
+
+const PCAP_MICROS: u32 = 0xa1b2c3d4;
+const PCAP_NANOS: u32 = 0xa1b23c4d;
+const PCAPNG_SECTION: u32 = 0x0a0d0d0a;
+const PCAPNG_BYTE_ORDER: u32 = 0x1a2b3c4d;
+
+const LINKTYPE_NULL: u32 = 0;
+const LINKTYPE_ETHERNET: u32 = 1;
+const LINKTYPE_RAW: u32 = 101;
+const LINKTYPE_LINUX_SLL: u32 = 113;
+
+/// Errors produced while reading a capture.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum PcapError {
+    /// The capture is neither pcap nor pcapng.
+    BadMagic,
+
+    /// The capture ends partway through a header or block.
+    Truncated,
+
+    /// Packets were captured on a link type that cannot be decoded.
+    UnsupportedLinkType(u32)
+}
+
+/// One direction of a TCP connection, reassembled in sequence order.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Stream {
+    pub source: (IpAddr, u16),
+    pub destination: (IpAddr, u16),
+
+    /// `Inbound` if this is the client's side, sent to the server port.
+    pub direction: Direction,
+
+    /// The bytes sent, with gaps left by missing segments closed up, so
+    /// that the data after a lost segment directly follows the data before
+    /// it.
+    pub data: Vec<u8>
+}
+
+impl Stream {
+    /// Iterate over the frames sent, skipping the client's preface.
+    pub fn frames(&self) -> Result<Frames, Error> {
+        match self.direction {
+            Direction::Inbound => Frames::after_preface(&self.data),
+            Direction::Outbound => Ok(Frames::new(&self.data))
+        }
+    }
+}
+
+/// Reassemble every TCP stream to or from `port` in a pcap or pcapng
+/// capture, in the order they first appear.
+pub fn extract(capture: &[u8], port: u16) -> Result<Vec<Stream>, PcapError> {
+    let mut streams = Streams { port: port, map: HashMap::new(), streams: Vec::new() };
+
+    if capture.len() < 4 {
+        return Err(PcapError::Truncated)
+    }
+
+    if BigEndian::read_u32(capture) == PCAPNG_SECTION {
+        try!(read_pcapng(capture, &mut streams));
+    } else {
+        try!(read_pcap(capture, &mut streams));
+    }
+
+    Ok(streams.streams.into_iter().map(|(mut stream, reassembly)| {
+        reassembly.close_gaps(&mut stream);
+        stream
+    }).collect())
+}
+
+fn read_u16(buf: &[u8], little: bool) -> u16 {
+    if little { LittleEndian::read_u16(buf) } else { BigEndian::read_u16(buf) }
+}
+
+fn read_u32(buf: &[u8], little: bool) -> u32 {
+    if little { LittleEndian::read_u32(buf) } else { BigEndian::read_u32(buf) }
+}
+
+fn read_pcap(capture: &[u8], streams: &mut Streams) -> Result<(), PcapError> {
+    if capture.len() < 24 {
+        return Err(PcapError::Truncated)
+    }
+
+    let little = match (BigEndian::read_u32(capture), LittleEndian::read_u32(capture)) {
+        (PCAP_MICROS, _) | (PCAP_NANOS, _) => false,
+        (_, PCAP_MICROS) | (_, PCAP_NANOS) => true,
+        _ => return Err(PcapError::BadMagic)
+    };
+    let link = read_u32(&capture[20..], little);
+
+    let mut rest = &capture[24..];
+    while !rest.is_empty() {
+        if rest.len() < 16 {
+            return Err(PcapError::Truncated)
+        }
+
+        let length = read_u32(&rest[8..], little) as usize;
+        if rest.len() < 16 + length {
+            return Err(PcapError::Truncated)
+        }
+
+        try!(streams.push(link, &rest[16..16 + length]));
+        rest = &rest[16 + length..];
+    }
+
+    Ok(())
+}
+
+fn read_pcapng(capture: &[u8], streams: &mut Streams) -> Result<(), PcapError> {
+    let mut little = false;
+    let mut links = Vec::new();
+
+    let mut rest = capture;
+    while !rest.is_empty() {
+        if rest.len() < 12 {
+            return Err(PcapError::Truncated)
+        }
+
+        let kind = read_u32(rest, little);
+        if kind == PCAPNG_SECTION {
+            little = match (BigEndian::read_u32(&rest[8..]), LittleEndian::read_u32(&rest[8..])) {
+                (PCAPNG_BYTE_ORDER, _) => false,
+                (_, PCAPNG_BYTE_ORDER) => true,
+                _ => return Err(PcapError::BadMagic)
+            };
+            links.clear();
+        }
+
+        let length = read_u32(&rest[4..], little) as usize;
+        if length < 12 || rest.len() < length {
+            return Err(PcapError::Truncated)
+        }
+        let body = &rest[8..length - 4];
+
+        match kind {
+            // Interface Description Block
+            1 if body.len() >= 2 => links.push(read_u16(body, little) as u32),
+
+            // Enhanced Packet Block
+            6 if body.len() >= 20 => {
+                let interface = read_u32(body, little) as usize;
+                let captured = read_u32(&body[12..], little) as usize;
+                let link = *try!(links.get(interface).ok_or(PcapError::Truncated));
+                let packet = try!(body[20..].get(..captured).ok_or(PcapError::Truncated));
+                try!(streams.push(link, packet));
+            },
+
+            // Simple Packet Block, always from the first interface.
+            3 if body.len() >= 4 => {
+                let link = *try!(links.first().ok_or(PcapError::Truncated));
+                try!(streams.push(link, &body[4..]));
+            },
+
+            _ => {}
+        }
+
+        rest = &rest[length..];
+    }
+
+    Ok(())
+}
+
+struct Segment<'a> {
+    source: (IpAddr, u16),
+    destination: (IpAddr, u16),
+    sequence: u32,
+    syn: bool,
+    data: &'a [u8]
+}
+
+// The state of a stream being reassembled, alongside the stream itself.
+#[derive(Default)]
+struct Reassembly {
+    // The sequence number of the first byte of data.
+    start: Option<u32>,
+
+    // Segments which arrived ahead of a gap, keyed by offset.
+    pending: BTreeMap<usize, Vec<u8>>
+}
+
+impl Reassembly {
+    // Append the segments still waiting behind a gap, in offset order,
+    // skipping the bytes that never arrived.
+    fn close_gaps(self, stream: &mut Stream) {
+        // The offset, counted as if nothing was missing, of the end of
+        // `stream.data`.
+        let mut end = stream.data.len();
+
+        for (offset, data) in self.pending {
+            let overlap = end.saturating_sub(offset);
+            if overlap < data.len() {
+                stream.data.extend_from_slice(&data[overlap..]);
+            }
+            end = cmp::max(end, offset + data.len());
+        }
+    }
+}
+
+struct Streams {
+    port: u16,
+    map: HashMap<((IpAddr, u16), (IpAddr, u16)), usize>,
+    streams: Vec<(Stream, Reassembly)>
+}
+
+impl Streams {
+    fn push(&mut self, link: u32, packet: &[u8]) -> Result<(), PcapError> {
+        let segment = match try!(decode_link(link, packet)) {
+            Some(segment) => segment,
+            None => return Ok(())
+        };
+
+        let direction = if segment.destination.1 == self.port {
+            Direction::Inbound
+        } else if segment.source.1 == self.port {
+            Direction::Outbound
+        } else {
+            return Ok(())
+        };
+
+        let key = (segment.source, segment.destination);
+        let streams = &mut self.streams;
+        let index = *self.map.entry(key).or_insert_with(|| {
+            streams.push((Stream {
+                source: segment.source,
+                destination: segment.destination,
+                direction: direction,
+                data: Vec::new()
+            }, Reassembly::default()));
+            streams.len() - 1
+        });
+
+        let (ref mut stream, ref mut reassembly) = streams[index];
+
+        if segment.syn {
+            reassembly.start = Some(segment.sequence.wrapping_add(1));
+            return Ok(())
+        }
+
+        let start = *reassembly.start.get_or_insert(segment.sequence);
+        let offset = segment.sequence.wrapping_sub(start);
+
+        // Sequence numbers from before the start are retransmissions.
+        if offset >= 1 << 31 || segment.data.is_empty() {
+            return Ok(())
+        }
+
+        reassembly.pending.insert(offset as usize, segment.data.to_vec());
+
+        while let Some(offset) = reassembly.pending.keys().next().cloned() {
+            if offset > stream.data.len() {
+                break
+            }
+
+            let data = reassembly.pending.remove(&offset).unwrap();
+            let overlap = stream.data.len() - offset;
+            if overlap < data.len() {
+                stream.data.extend_from_slice(&data[overlap..]);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn decode_link(link: u32, packet: &[u8]) -> Result<Option<Segment>, PcapError> {
+    let ip = match link {
+        LINKTYPE_ETHERNET => {
+            let mut header = 14;
+            if packet.len() >= 16 && BigEndian::read_u16(&packet[12..]) == 0x8100 {
+                header += 4;
+            }
+            packet.get(header..)
+        },
+        LINKTYPE_NULL => packet.get(4..),
+        LINKTYPE_LINUX_SLL => packet.get(16..),
+        LINKTYPE_RAW => Some(packet),
+        link => return Err(PcapError::UnsupportedLinkType(link))
+    };
+
+    Ok(ip.and_then(decode_ip))
+}
+
+fn decode_ip(packet: &[u8]) -> Option<Segment> {
+    let (source, destination, tcp) = match packet.first().map(|byte| byte >> 4) {
+        Some(4) if packet.len() >= 20 => {
+            let header = (packet[0] & 0xf) as usize * 4;
+            let total = BigEndian::read_u16(&packet[2..]) as usize;
+            let fragment = BigEndian::read_u16(&packet[6..]);
+
+            if packet[9] != 6 || fragment & 0x3fff != 0 || header < 20 {
+                return None
+            }
+
+            let source = Ipv4Addr::new(packet[12], packet[13], packet[14], packet[15]);
+            let destination = Ipv4Addr::new(packet[16], packet[17], packet[18], packet[19]);
+            let end = cmp::min(total, packet.len());
+
+            (IpAddr::V4(source), IpAddr::V4(destination), packet.get(header..end))
+        },
+        Some(6) if packet.len() >= 40 => {
+            if packet[6] != 6 {
+                return None
+            }
+
+            let address = |buf: &[u8]| {
+                let mut segments = [0; 8];
+                for (i, segment) in segments.iter_mut().enumerate() {
+                    *segment = BigEndian::read_u16(&buf[i * 2..]);
+                }
+                Ipv6Addr::new(segments[0], segments[1], segments[2], segments[3],
+                              segments[4], segments[5], segments[6], segments[7])
+            };
+            let end = cmp::min(40 + BigEndian::read_u16(&packet[4..]) as usize, packet.len());
+
+            (IpAddr::V6(address(&packet[8..])), IpAddr::V6(address(&packet[24..])),
+             packet.get(40..end))
+        },
+        _ => return None
+    };
+
+    let tcp = match tcp {
+        Some(tcp) if tcp.len() >= 20 => tcp,
+        _ => return None
+    };
+    let data = match tcp.get((tcp[12] >> 4) as usize * 4..) {
+        Some(data) => data,
+        None => return None
+    };
+
+    Some(Segment {
+        source: (source, BigEndian::read_u16(tcp)),
+        destination: (destination, BigEndian::read_u16(&tcp[2..])),
+        sequence: BigEndian::read_u32(&tcp[4..]),
+        syn: tcp[13] & 0x02 != 0,
+        data: data
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use byteorder::{BigEndian, LittleEndian, ByteOrder};
+
+    use capture::Direction;
+    use {Frame, PREFACE};
+    use super::{extract, PcapError};
+
+    // An Ethernet frame carrying an IPv4 TCP segment.
+    fn packet(source: u16, destination: u16, sequence: u32, syn: bool, data: &[u8]) -> Vec<u8> {
+        let mut packet = vec![0; 14 + 20 + 20];
+        BigEndian::write_u16(&mut packet[12..], 0x0800);
+
+        {
+            let ip = &mut packet[14..];
+            ip[0] = 0x45;
+            BigEndian::write_u16(&mut ip[2..], (40 + data.len()) as u16);
+            ip[9] = 6;
+            ip[12..16].copy_from_slice(&[127, 0, 0, 1]);
+            ip[16..20].copy_from_slice(&[127, 0, 0, 1]);
+        }
+
+        {
+            let tcp = &mut packet[34..];
+            BigEndian::write_u16(tcp, source);
+            BigEndian::write_u16(&mut tcp[2..], destination);
+            BigEndian::write_u32(&mut tcp[4..], sequence);
+            tcp[12] = 5 << 4;
+            tcp[13] = if syn { 0x02 } else { 0x10 };
+        }
+
+        packet.extend_from_slice(data);
+        packet
+    }
+
+    fn encode(frame: Frame) -> Vec<u8> {
+        let mut buf = vec![0; frame.encoded_len()];
+        frame.encode(&mut buf);
+        buf
+    }
+
+    fn pcap(packets: &[Vec<u8>]) -> Vec<u8> {
+        let mut capture = vec![0; 24];
+        LittleEndian::write_u32(&mut capture, 0xa1b2c3d4);
+        LittleEndian::write_u32(&mut capture[20..], 1);
+
+        for packet in packets {
+            let mut record = vec![0; 16];
+            LittleEndian::write_u32(&mut record[8..], packet.len() as u32);
+            LittleEndian::write_u32(&mut record[12..], packet.len() as u32);
+            capture.extend_from_slice(&record);
+            capture.extend_from_slice(packet);
+        }
+
+        capture
+    }
+
+    fn pcapng(packets: &[Vec<u8>]) -> Vec<u8> {
+        fn block(capture: &mut Vec<u8>, kind: u32, body: &[u8]) {
+            let padded = (body.len() + 3) / 4 * 4;
+            let mut header = [0; 8];
+            BigEndian::write_u32(&mut header, kind);
+            BigEndian::write_u32(&mut header[4..], (padded + 12) as u32);
+
+            capture.extend_from_slice(&header);
+            capture.extend_from_slice(body);
+            capture.extend_from_slice(&vec![0; padded - body.len()]);
+            capture.extend_from_slice(&header[4..]);
+        }
+
+        let mut capture = Vec::new();
+        block(&mut capture, 0x0a0d0d0a, &[0x1a, 0x2b, 0x3c, 0x4d, 0, 1, 0, 0,
+                                          0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]);
+        block(&mut capture, 1, &[0, 1, 0, 0, 0, 0, 0xff, 0xff]);
+
+        for packet in packets {
+            let mut body = vec![0; 20];
+            BigEndian::write_u32(&mut body[12..], packet.len() as u32);
+            BigEndian::write_u32(&mut body[16..], packet.len() as u32);
+            body.extend_from_slice(packet);
+            block(&mut capture, 6, &body);
+        }
+
+        capture
+    }
+
+    fn exchange() -> Vec<Vec<u8>> {
+        let mut client = PREFACE.to_vec();
+        client.extend(encode(Frame::ping([1; 8])));
+        let server = encode(Frame::settings_ack());
+
+        vec![
+            packet(50000, 80, 99, true, &[]),
+            packet(50000, 80, 110, false, &client[10..]),
+            packet(80, 50000, 7, false, &server),
+            // Retransmitted and out of order.
+            packet(50000, 80, 100, false, &client[..12]),
+            packet(1234, 5678, 0, false, b"unrelated")
+        ]
+    }
+
+    fn check(capture: &[u8]) {
+        let streams = extract(capture, 80).unwrap();
+        assert_eq!(streams.len(), 2);
+
+        assert_eq!(streams[0].direction, Direction::Inbound);
+        assert_eq!(streams[0].source.1, 50000);
+        let frames = streams[0].frames().unwrap().collect::<Vec<_>>();
+        assert_eq!(frames, vec![Ok(Frame::ping([1; 8]))]);
+
+        assert_eq!(streams[1].direction, Direction::Outbound);
+        let frames = streams[1].frames().unwrap().collect::<Vec<_>>();
+        assert_eq!(frames, vec![Ok(Frame::settings_ack())]);
+    }
+
+    #[test]
+    fn test_extract_pcap() {
+        check(&pcap(&exchange()));
+    }
+
+    #[test]
+    fn test_extract_pcapng() {
+        check(&pcapng(&exchange()));
+    }
+
+    #[test]
+    fn test_missing_segment() {
+        let ping = encode(Frame::ping([2; 8]));
+        let settings = encode(Frame::settings_ack());
+        let mut data = ping.clone();
+        data.extend_from_slice(&settings);
+
+        // The middle of the PING frame is never captured.
+        let capture = pcap(&[
+            packet(80, 50000, 0, false, &data[..5]),
+            packet(80, 50000, 10, false, &data[10..15]),
+            packet(80, 50000, 15, false, &data[15..])
+        ]);
+
+        let streams = extract(&capture, 80).unwrap();
+        let mut expected = data[..5].to_vec();
+        expected.extend_from_slice(&data[10..]);
+        assert_eq!(streams[0].data, expected);
+        assert!(streams[0].data.ends_with(&settings));
+    }
+
+    #[test]
+    fn test_bad_captures() {
+        assert_eq!(extract(&[0; 24], 80), Err(PcapError::BadMagic));
+
+        let mut capture = pcap(&exchange());
+        capture.pop();
+        assert_eq!(extract(&capture, 80), Err(PcapError::Truncated));
+    }
+}
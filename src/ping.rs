@@ -0,0 +1,158 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use {Frame, Payload};
+
+/// Produces PING frames with distinct payloads, for keeping a connection
+/// alive or measuring round trip time.
+///
+/// Payloads count up from the seed, so acknowledgements can be matched
+/// to the ping that caused them.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Keepalive {
+    next: u64
+}
+
+impl Keepalive {
+    pub fn new(seed: u64) -> Keepalive {
+        Keepalive { next: seed }
+    }
+}
+
+impl Iterator for Keepalive {
+    type Item = Frame<'static>;
+
+    fn next(&mut self) -> Option<Frame<'static>> {
+        let mut data = [0; 8];
+        ::encode_u64(&mut data, self.next);
+        self.next = self.next.wrapping_add(1);
+
+        Some(Frame::ping(data))
+    }
+}
+
+/// Measures round trip times with PING frames.
+///
+/// Each ping sent is remembered until its ACK arrives, giving a round
+/// trip time, or until it has been outstanding for longer than `timeout`,
+/// when it is counted as lost.
+#[derive(Clone, Debug)]
+pub struct PingTracker {
+    keepalive: Keepalive,
+    timeout: Duration,
+    outstanding: VecDeque<(u64, Instant)>,
+    last_rtt: Option<Duration>,
+    lost: u64
+}
+
+impl PingTracker {
+    pub fn new(seed: u64, timeout: Duration) -> PingTracker {
+        PingTracker {
+            keepalive: Keepalive::new(seed),
+            timeout: timeout,
+            outstanding: VecDeque::new(),
+            last_rtt: None,
+            lost: 0
+        }
+    }
+
+    /// A PING frame with a fresh payload, recorded as sent at `now`.
+    pub fn send(&mut self, now: Instant) -> Frame<'static> {
+        let ping = self.keepalive.next().unwrap();
+        if let Payload::Ping(data) = ping.payload {
+            self.outstanding.push_back((data, now));
+        }
+        ping
+    }
+
+    /// Match a frame received at `now` against the outstanding pings,
+    /// returning the round trip time if it acknowledges one of them.
+    ///
+    /// Frames that are not PING ACKs, and ACKs of pings that were never
+    /// sent or were already counted as lost, are ignored.
+    pub fn recv(&mut self, frame: &Frame, now: Instant) -> Option<Duration> {
+        let data = match frame.payload {
+            Payload::Ping(data) if frame.is_ping_ack() => data,
+            _ => return None
+        };
+
+        let index = match self.outstanding.iter().position(|&(sent, _)| sent == data) {
+            Some(index) => index,
+            None => return None
+        };
+
+        let (_, sent) = self.outstanding.remove(index).unwrap();
+        let rtt = now.saturating_duration_since(sent);
+        self.last_rtt = Some(rtt);
+        Some(rtt)
+    }
+
+    /// Count pings outstanding for longer than the timeout at `now` as
+    /// lost, returning how many were.
+    pub fn expire(&mut self, now: Instant) -> usize {
+        let timeout = self.timeout;
+        let before = self.outstanding.len();
+        self.outstanding.retain(|&(_, sent)| now.saturating_duration_since(sent) <= timeout);
+
+        let expired = before - self.outstanding.len();
+        self.lost += expired as u64;
+        expired
+    }
+
+    /// The number of pings awaiting acknowledgement.
+    pub fn outstanding(&self) -> usize {
+        self.outstanding.len()
+    }
+
+    /// The round trip time of the last acknowledged ping.
+    pub fn last_rtt(&self) -> Option<Duration> {
+        self.last_rtt
+    }
+
+    /// The number of pings counted as lost so far.
+    pub fn lost(&self) -> u64 {
+        self.lost
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::{Duration, Instant};
+
+    use Frame;
+    use super::{Keepalive, PingTracker};
+
+    #[test]
+    fn test_keepalive_pings_are_unique() {
+        let pings = Keepalive::new(u64::max_value() - 1).take(3).collect::<Vec<Frame>>();
+
+        assert_eq!(pings[0].payload.ping_data(),
+                   Some([0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xfe]));
+        assert_eq!(pings[1].payload.ping_data(), Some([0xff; 8]));
+        assert_eq!(pings[2].payload.ping_data(), Some([0; 8]));
+        assert!(pings.iter().all(|ping| ping.ping_ack().is_some()));
+    }
+
+    #[test]
+    fn test_ping_tracker() {
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+        let mut tracker = PingTracker::new(0, Duration::from_millis(100));
+
+        let first = tracker.send(at(0));
+        let second = tracker.send(at(10));
+        assert_eq!(tracker.outstanding(), 2);
+
+        // The ping itself, rather than its ACK, is ignored.
+        assert_eq!(tracker.recv(&second, at(20)), None);
+        assert_eq!(tracker.recv(&second.ping_ack().unwrap(), at(40)),
+                   Some(Duration::from_millis(30)));
+        assert_eq!(tracker.recv(&second.ping_ack().unwrap(), at(40)), None);
+        assert_eq!(tracker.last_rtt(), Some(Duration::from_millis(30)));
+
+        assert_eq!(tracker.expire(at(100)), 0);
+        assert_eq!(tracker.expire(at(101)), 1);
+        assert_eq!((tracker.outstanding(), tracker.lost()), (0, 1));
+        assert_eq!(tracker.recv(&first.ping_ack().unwrap(), at(120)), None);
+    }
+}
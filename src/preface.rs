@@ -0,0 +1,50 @@
+use Error;
+
+/// The connection preface every client sends before its first frame.
+pub const PREFACE: &'static [u8; 24] = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
+
+/// Check for the client connection preface at the start of `buf`,
+/// returning the number of bytes it used.
+///
+/// A buffer holding only the start of the preface is an `Error::Short`,
+/// and anything else is an `Error::BadPreface`.
+pub fn parse_preface(buf: &[u8]) -> Result<usize, Error> {
+    let len = ::std::cmp::min(buf.len(), PREFACE.len());
+
+    if buf[..len] != PREFACE[..len] {
+        Err(Error::BadPreface)
+    } else if len < PREFACE.len() {
+        Err(Error::Short(PREFACE.len() - len))
+    } else {
+        Ok(PREFACE.len())
+    }
+}
+
+/// Write the client connection preface into `buf`, returning the number
+/// of bytes written.
+pub fn write_preface(buf: &mut [u8]) -> usize {
+    buf[..PREFACE.len()].copy_from_slice(PREFACE);
+    PREFACE.len()
+}
+
+#[cfg(test)]
+mod test {
+    use Error;
+    use super::{PREFACE, parse_preface, write_preface};
+
+    #[test]
+    fn test_preface_round_trip() {
+        let mut buf = [0; 30];
+        assert_eq!(write_preface(&mut buf), 24);
+        assert_eq!(&buf[..24], &PREFACE[..]);
+        assert_eq!(parse_preface(&buf), Ok(24));
+    }
+
+    #[test]
+    fn test_partial_and_bad_preface() {
+        assert_eq!(parse_preface(b""), Err(Error::Short(24)));
+        assert_eq!(parse_preface(b"PRI * HTTP/2.0\r\n"), Err(Error::Short(8)));
+        assert_eq!(parse_preface(b"GET / HTTP/1.1\r\n"), Err(Error::BadPreface));
+        assert_eq!(parse_preface(b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\r"), Err(Error::BadPreface));
+    }
+}
@@ -0,0 +1,279 @@
+use std::cmp;
+use std::collections::HashMap;
+
+use {Priority, StreamIdentifier, consts};
+
+// The weight, as sent on the wire, of streams with default priority.
+const DEFAULT_WEIGHT: u8 = (consts::DEFAULT_WEIGHT - 1) as u8;
+
+/// Errors produced by a `PriorityTree`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum PriorityError {
+    /// A stream was made to depend on itself, which is a stream error
+    /// of type PROTOCOL_ERROR.
+    SelfDependency(StreamIdentifier)
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Node {
+    parent: u32,
+    weight: u8,
+    children: Vec<u32>
+}
+
+/// The stream dependency tree of RFC 7540 Section 5.3.
+///
+/// Weights are stored as they are sent on the wire, from 0 to 255,
+/// representing actual weights from 1 to 256. Stream 0 is the root.
+#[derive(Clone, Debug)]
+pub struct PriorityTree {
+    nodes: HashMap<u32, Node>
+}
+
+impl PriorityTree {
+    pub fn new() -> PriorityTree {
+        let mut nodes = HashMap::new();
+        nodes.insert(0, Node { parent: 0, weight: DEFAULT_WEIGHT, children: Vec::new() });
+        PriorityTree { nodes: nodes }
+    }
+
+    /// Add a stream opened by a HEADERS frame, with its priority if the
+    /// frame carried one.
+    pub fn insert(&mut self, id: StreamIdentifier,
+                  priority: Option<&Priority>) -> Result<(), PriorityError> {
+        match priority {
+            Some(priority) => self.prioritize(id, priority),
+            None => self.set(id, 0, DEFAULT_WEIGHT, false)
+        }
+    }
+
+    /// Apply a PRIORITY frame, or the priority of a HEADERS frame.
+    ///
+    /// Streams not yet in the tree are added, and depending on a stream
+    /// not in the tree gives the stream default priority.
+    pub fn prioritize(&mut self, id: StreamIdentifier,
+                      priority: &Priority) -> Result<(), PriorityError> {
+        self.set(id, priority.dependency().0, priority.weight(), priority.exclusive())
+    }
+
+    /// Remove a closed stream, giving its children to its parent and
+    /// sharing its weight between them.
+    pub fn remove(&mut self, id: StreamIdentifier) {
+        if id.0 == 0 { return }
+
+        let node = match self.nodes.remove(&id.0) {
+            Some(node) => node,
+            None => return
+        };
+
+        self.detach(id.0, node.parent);
+
+        let total = node.children.iter()
+            .map(|child| self.nodes[child].weight as u32 + 1)
+            .fold(0, |a, b| a + b);
+
+        for &child in &node.children {
+            let child_node = self.nodes.get_mut(&child).unwrap();
+            // Shares are in 1..=256, stored on the wire one lower.
+            let share = (node.weight as u32 + 1) * (child_node.weight as u32 + 1) / total;
+            child_node.weight = (cmp::min(cmp::max(share, 1), 256) - 1) as u8;
+            child_node.parent = node.parent;
+        }
+
+        self.nodes.get_mut(&node.parent).unwrap().children.extend(node.children);
+    }
+
+    pub fn contains(&self, id: StreamIdentifier) -> bool {
+        id.0 != 0 && self.nodes.contains_key(&id.0)
+    }
+
+    /// The stream a stream depends on.
+    pub fn parent(&self, id: StreamIdentifier) -> Option<StreamIdentifier> {
+        if id.0 == 0 { return None }
+        self.nodes.get(&id.0).map(|node| StreamIdentifier(node.parent))
+    }
+
+    /// The streams depending on a stream, in the order they were added.
+    pub fn children(&self, id: StreamIdentifier) -> Vec<StreamIdentifier> {
+        self.nodes.get(&id.0).map(|node| {
+            node.children.iter().map(|&child| StreamIdentifier(child)).collect()
+        }).unwrap_or_default()
+    }
+
+    /// The weight of a stream, as sent on the wire.
+    pub fn weight(&self, id: StreamIdentifier) -> Option<u8> {
+        if id.0 == 0 { return None }
+        self.nodes.get(&id.0).map(|node| node.weight)
+    }
+
+    fn set(&mut self, id: StreamIdentifier, mut dependency: u32,
+           mut weight: u8, mut exclusive: bool) -> Result<(), PriorityError> {
+        if id.0 == dependency {
+            return Err(PriorityError::SelfDependency(id))
+        }
+
+        if !self.nodes.contains_key(&dependency) {
+            dependency = 0;
+            weight = DEFAULT_WEIGHT;
+            exclusive = false;
+        }
+
+        if !self.nodes.contains_key(&id.0) {
+            self.nodes.insert(id.0, Node { parent: 0, weight: weight, children: Vec::new() });
+            self.nodes.get_mut(&0).unwrap().children.push(id.0);
+        }
+
+        // Depending on a descendant first moves that descendant up to
+        // where this stream currently is, keeping its weight.
+        let parent = self.nodes[&id.0].parent;
+        if self.is_descendant(dependency, id.0) {
+            let old_parent = self.nodes[&dependency].parent;
+            self.detach(dependency, old_parent);
+            self.attach(dependency, parent);
+        }
+
+        self.detach(id.0, parent);
+
+        if exclusive {
+            let children = ::std::mem::replace(
+                &mut self.nodes.get_mut(&dependency).unwrap().children, Vec::new());
+
+            for &child in &children {
+                self.nodes.get_mut(&child).unwrap().parent = id.0;
+            }
+
+            self.nodes.get_mut(&id.0).unwrap().children.extend(children);
+        }
+
+        self.attach(id.0, dependency);
+        self.nodes.get_mut(&id.0).unwrap().weight = weight;
+
+        Ok(())
+    }
+
+    fn is_descendant(&self, mut id: u32, ancestor: u32) -> bool {
+        while id != 0 {
+            id = self.nodes[&id].parent;
+            if id == ancestor { return true }
+        }
+
+        false
+    }
+
+    fn detach(&mut self, id: u32, parent: u32) {
+        self.nodes.get_mut(&parent).unwrap().children.retain(|&child| child != id);
+    }
+
+    fn attach(&mut self, id: u32, parent: u32) {
+        self.nodes.get_mut(&id).unwrap().parent = parent;
+        self.nodes.get_mut(&parent).unwrap().children.push(id);
+    }
+}
+
+impl Default for PriorityTree {
+    fn default() -> PriorityTree { PriorityTree::new() }
+}
+
+#[cfg(test)]
+mod test {
+    use {Priority, StreamIdentifier};
+    use super::{PriorityTree, PriorityError};
+
+    fn priority(exclusive: bool, dependency: u32, weight: u8) -> Priority {
+        Priority::new(exclusive, StreamIdentifier(dependency), weight)
+    }
+
+    fn ids(ids: &[u32]) -> Vec<StreamIdentifier> {
+        ids.iter().map(|&id| StreamIdentifier(id)).collect()
+    }
+
+    #[test]
+    fn test_default_insertion() {
+        let mut tree = PriorityTree::new();
+        tree.insert(StreamIdentifier(1), None).unwrap();
+        tree.insert(StreamIdentifier(3), None).unwrap();
+
+        assert_eq!(tree.children(StreamIdentifier(0)), ids(&[1, 3]));
+        assert_eq!(tree.parent(StreamIdentifier(1)), Some(StreamIdentifier(0)));
+        assert_eq!(tree.weight(StreamIdentifier(3)), Some(15));
+    }
+
+    #[test]
+    fn test_exclusive_insertion() {
+        // RFC 7540 Figure 4: D is added exclusively on A, above B and C.
+        let mut tree = PriorityTree::new();
+        tree.insert(StreamIdentifier(1), None).unwrap();
+        tree.insert(StreamIdentifier(3), Some(&priority(false, 1, 15))).unwrap();
+        tree.insert(StreamIdentifier(5), Some(&priority(false, 1, 15))).unwrap();
+        tree.insert(StreamIdentifier(7), Some(&priority(true, 1, 31))).unwrap();
+
+        assert_eq!(tree.children(StreamIdentifier(1)), ids(&[7]));
+        assert_eq!(tree.children(StreamIdentifier(7)), ids(&[3, 5]));
+        assert_eq!(tree.parent(StreamIdentifier(5)), Some(StreamIdentifier(7)));
+        assert_eq!(tree.weight(StreamIdentifier(7)), Some(31));
+    }
+
+    #[test]
+    fn test_dependency_on_descendant() {
+        // RFC 7540 Figure 5, non-exclusive: A is made dependent on D.
+        let mut tree = PriorityTree::new();
+        tree.insert(StreamIdentifier(1), None).unwrap(); // A
+        tree.insert(StreamIdentifier(3), Some(&priority(false, 1, 15))).unwrap(); // B
+        tree.insert(StreamIdentifier(5), Some(&priority(false, 1, 15))).unwrap(); // C
+        tree.insert(StreamIdentifier(7), Some(&priority(false, 5, 15))).unwrap(); // D
+        tree.insert(StreamIdentifier(9), Some(&priority(false, 5, 15))).unwrap(); // E
+        tree.insert(StreamIdentifier(11), Some(&priority(false, 7, 15))).unwrap(); // F
+
+        tree.prioritize(StreamIdentifier(1), &priority(false, 7, 15)).unwrap();
+
+        assert_eq!(tree.children(StreamIdentifier(0)), ids(&[7]));
+        assert_eq!(tree.children(StreamIdentifier(7)), ids(&[11, 1]));
+        assert_eq!(tree.children(StreamIdentifier(1)), ids(&[3, 5]));
+        assert_eq!(tree.children(StreamIdentifier(5)), ids(&[9]));
+    }
+
+    #[test]
+    fn test_remove_redistributes_weight() {
+        let mut tree = PriorityTree::new();
+        tree.insert(StreamIdentifier(1), Some(&priority(false, 0, 15))).unwrap();
+        tree.insert(StreamIdentifier(3), Some(&priority(false, 1, 0))).unwrap();
+        tree.insert(StreamIdentifier(5), Some(&priority(false, 1, 2))).unwrap();
+
+        tree.remove(StreamIdentifier(1));
+
+        // 16 is split 1:3 between the children.
+        assert!(!tree.contains(StreamIdentifier(1)));
+        assert_eq!(tree.children(StreamIdentifier(0)), ids(&[3, 5]));
+        assert_eq!(tree.weight(StreamIdentifier(3)), Some(3));
+        assert_eq!(tree.weight(StreamIdentifier(5)), Some(11));
+    }
+
+    #[test]
+    fn test_remove_keeps_full_weight() {
+        let mut tree = PriorityTree::new();
+        tree.insert(StreamIdentifier(1), Some(&priority(false, 0, 255))).unwrap();
+        tree.insert(StreamIdentifier(3), Some(&priority(false, 1, 255))).unwrap();
+
+        tree.remove(StreamIdentifier(1));
+
+        assert_eq!(tree.children(StreamIdentifier(0)), ids(&[3]));
+        assert_eq!(tree.weight(StreamIdentifier(3)), Some(255));
+    }
+
+    #[test]
+    fn test_self_dependency() {
+        let mut tree = PriorityTree::new();
+        assert_eq!(tree.prioritize(StreamIdentifier(1), &priority(false, 1, 0)),
+                   Err(PriorityError::SelfDependency(StreamIdentifier(1))));
+    }
+
+    #[test]
+    fn test_dependency_on_unknown_stream() {
+        let mut tree = PriorityTree::new();
+        tree.insert(StreamIdentifier(3), Some(&priority(true, 1, 200))).unwrap();
+
+        assert_eq!(tree.parent(StreamIdentifier(3)), Some(StreamIdentifier(0)));
+        assert_eq!(tree.weight(StreamIdentifier(3)), Some(15));
+    }
+}
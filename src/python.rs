@@ -0,0 +1,140 @@
+//! Python bindings, for test harnesses and tools written in Python.
+//!
+//! ```python
+//! import http2parse
+//!
+//! for frame in http2parse.parse(data):
+//!     print(frame.kind, frame.stream, frame.payload)
+//!
+//! data = http2parse.encode("SETTINGS ack\nPING hex=0102030405060708")
+//! ```
+//!
+//! Errors are raised as `ValueError`. Building an importable module also
+//! needs a `cdylib` crate type and `pyo3`'s `extension-module` feature, as
+//! `maturin` sets up.
+
+use pyo3::prelude::*;
+use pyo3::exceptions::PyValueError;
+use pyo3::types::PyBytes;
+
+use {Frames, OwnedFrame, PREFACE};
+use script;
+
+/// A parsed frame.
+#[pyclass(name = "Frame", module = "http2parse")]
+#[derive(Clone)]
+pub struct PyFrame {
+    frame: OwnedFrame
+}
+
+#[pymethods]
+impl PyFrame {
+    /// The frame type, as written in RFC 7540.
+    #[getter]
+    fn kind(&self) -> String {
+        self.frame.header.kind.to_string()
+    }
+
+    #[getter]
+    fn stream(&self) -> u32 {
+        self.frame.header.id.0
+    }
+
+    #[getter]
+    fn flags(&self) -> u8 {
+        self.frame.header.flag.encode()
+    }
+
+    #[getter]
+    fn length(&self) -> u32 {
+        self.frame.header.length
+    }
+
+    /// The payload, encoded again from the parsed frame. Padding is not
+    /// kept, so for padded frames this differs from the bytes received.
+    #[getter]
+    fn payload<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        PyBytes::new_bound(py, &encode(&self.frame)[::FRAME_HEADER_BYTES..])
+    }
+
+    /// The frame, encoded again from the parsed frame. The header is kept
+    /// as received but padding is not, so padded frames do not round-trip.
+    fn to_bytes<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        PyBytes::new_bound(py, &encode(&self.frame))
+    }
+
+    fn __str__(&self) -> String {
+        self.frame.as_frame().to_string()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("<Frame {}>", self.frame.as_frame())
+    }
+}
+
+/// Parse every complete frame in `data`, which may start with the client
+/// connection preface. Trailing partial frames are ignored.
+#[pyfunction]
+fn parse(data: &[u8]) -> PyResult<Vec<PyFrame>> {
+    let frames = try!(parse_frames(data).map_err(PyValueError::new_err));
+    Ok(frames.into_iter().map(|frame| PyFrame { frame: frame }).collect())
+}
+
+/// Encode the frames described by a `script`.
+#[pyfunction]
+fn encode_script<'py>(py: Python<'py>, text: &str) -> PyResult<Bound<'py, PyBytes>> {
+    let frames = try!(script::parse_script(text)
+        .map_err(|(line, error)| PyValueError::new_err(format!("line {}: {:?}", line, error))));
+
+    let buf = frames.iter().flat_map(encode).collect::<Vec<u8>>();
+    Ok(PyBytes::new_bound(py, &buf))
+}
+
+/// Describe the frames in `data`, one per line.
+#[pyfunction]
+fn describe(data: &[u8]) -> PyResult<String> {
+    let frames = try!(parse_frames(data).map_err(PyValueError::new_err));
+    Ok(frames.iter().map(|frame| frame.as_frame().to_string()).collect::<Vec<_>>().join("\n"))
+}
+
+#[pymodule]
+fn http2parse(module: &Bound<PyModule>) -> PyResult<()> {
+    try!(module.add_class::<PyFrame>());
+    // Paths are from the crate root, as `use` paths are before 2018.
+    try!(module.add_function(try!(wrap_pyfunction!(python::parse, module))));
+    try!(module.add("encode", try!(wrap_pyfunction!(python::encode_script, module))));
+    module.add_function(try!(wrap_pyfunction!(python::describe, module)))
+}
+
+fn parse_frames(data: &[u8]) -> Result<Vec<OwnedFrame>, String> {
+    let frames = if data.starts_with(PREFACE) {
+        Frames::after_preface(data).unwrap()
+    } else {
+        Frames::new(data)
+    };
+
+    frames.map(|frame| frame.map(OwnedFrame::from).map_err(|error| error.to_string())).collect()
+}
+
+fn encode(frame: &OwnedFrame) -> Vec<u8> {
+    let mut buf = vec![0; frame.as_frame().encoded_len()];
+    frame.as_frame().encode(&mut buf);
+    buf
+}
+
+#[cfg(test)]
+mod test {
+    use {Frame, OwnedFrame, PREFACE};
+    use super::parse_frames;
+
+    #[test]
+    fn test_parse_frames() {
+        let mut data = PREFACE.to_vec();
+        data.extend_from_slice(&[0x0, 0x0, 0x0, 0x4, 0x1, 0x0, 0x0, 0x0, 0x0, 0x0]);
+        assert_eq!(parse_frames(&data), Ok(vec![OwnedFrame::from(Frame::settings_ack())]));
+
+        assert_eq!(parse_frames(&[0x0, 0x0, 0x1, 0x4, 0x1, 0x0, 0x0, 0x0, 0x0, 0x0]),
+                   Err("InvalidPayloadLength at offset 0 in Settings frame on stream 0"
+                           .to_string()));
+    }
+}
@@ -0,0 +1,165 @@
+use std::ops::Range;
+
+use {Frame, FrameHeader, Payload, Priority, Settings, StreamIdentifier, ErrorCode,
+     SizeIncrement, Error, FRAME_HEADER_BYTES};
+
+/// A parsed frame which refers to its data by offsets into the buffer it
+/// was parsed from, instead of borrowing it.
+///
+/// A `FrameRef` can be stored next to the buffer that owns its bytes, and
+/// turned back into a `Frame` with `resolve` when the bytes are needed.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct FrameRef {
+    pub header: FrameHeader,
+    pub payload: PayloadRef,
+
+    /// Where the frame, header included, lies in the buffer.
+    pub range: Range<usize>
+}
+
+/// A `Payload` whose data is given as byte ranges into the buffer it was
+/// parsed from.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum PayloadRef {
+    Data {
+        data: Range<usize>
+    },
+    Headers {
+        priority: Option<Priority>,
+        block: Range<usize>
+    },
+    Priority(Priority),
+    Reset(ErrorCode),
+    /// The raw SETTINGS payload.
+    Settings(Range<usize>),
+    PushPromise {
+        promised: StreamIdentifier,
+        block: Range<usize>
+    },
+    Ping(u64),
+    GoAway {
+        last: StreamIdentifier,
+        error: ErrorCode,
+        data: Range<usize>
+    },
+    WindowUpdate(SizeIncrement),
+    Continuation(Range<usize>),
+    Unregistered {
+        kind: u8,
+        data: Range<usize>
+    }
+}
+
+impl FrameRef {
+    /// Parse the frame starting at `offset` in `buf`.
+    ///
+    /// Ranges in the result count from the start of `buf`, not `offset`.
+    pub fn parse(buf: &[u8], offset: usize) -> Result<FrameRef, Error> {
+        let rest = &buf[offset..];
+        let header = try!(FrameHeader::parse(rest));
+        let frame = try!(Frame::parse(header, &rest[FRAME_HEADER_BYTES..]));
+
+        Ok(FrameRef {
+            header: header,
+            payload: PayloadRef::new(buf, frame.payload),
+            range: offset..offset + FRAME_HEADER_BYTES + header.length as usize
+        })
+    }
+
+    /// Borrow the frame's data from `buf` again.
+    ///
+    /// `buf` must hold the same bytes this frame was parsed from, or the
+    /// resulting frame is meaningless. Panics if a range is out of bounds.
+    pub fn resolve<'a>(&self, buf: &'a [u8]) -> Frame<'a> {
+        Frame {
+            header: self.header,
+            payload: self.payload.resolve(buf)
+        }
+    }
+}
+
+impl PayloadRef {
+    // `payload` must borrow from `buf`.
+    fn new(buf: &[u8], payload: Payload) -> PayloadRef {
+        let range = |slice: &[u8]| {
+            let start = slice.as_ptr() as usize - buf.as_ptr() as usize;
+            start..start + slice.len()
+        };
+
+        match payload {
+            Payload::Data { data } => PayloadRef::Data { data: range(data) },
+            Payload::Headers { priority, block } =>
+                PayloadRef::Headers { priority: priority, block: range(block) },
+            Payload::Priority(priority) => PayloadRef::Priority(priority),
+            Payload::Reset(error) => PayloadRef::Reset(error),
+            Payload::Settings(settings) => PayloadRef::Settings(range(settings.raw().unwrap())),
+            Payload::PushPromise { promised, block } =>
+                PayloadRef::PushPromise { promised: promised, block: range(block) },
+            Payload::Ping(data) => PayloadRef::Ping(data),
+            Payload::GoAway { last, error, data } =>
+                PayloadRef::GoAway { last: last, error: error, data: range(data) },
+            Payload::WindowUpdate(increment) => PayloadRef::WindowUpdate(increment),
+            Payload::Continuation(block) => PayloadRef::Continuation(range(block)),
+            Payload::Unregistered { kind, data } =>
+                PayloadRef::Unregistered { kind: kind, data: range(data) }
+        }
+    }
+
+    /// Borrow the payload's data from `buf` again. See `FrameRef::resolve`.
+    pub fn resolve<'a>(&self, buf: &'a [u8]) -> Payload<'a> {
+        match *self {
+            PayloadRef::Data { ref data } => Payload::Data { data: &buf[data.clone()] },
+            PayloadRef::Headers { priority, ref block } =>
+                Payload::Headers { priority: priority, block: &buf[block.clone()] },
+            PayloadRef::Priority(priority) => Payload::Priority(priority),
+            PayloadRef::Reset(error) => Payload::Reset(error),
+            PayloadRef::Settings(ref raw) =>
+                Payload::Settings(Settings::parse(&buf[raw.clone()])
+                                      .expect("settings range is a multiple of 6 bytes")),
+            PayloadRef::PushPromise { promised, ref block } =>
+                Payload::PushPromise { promised: promised, block: &buf[block.clone()] },
+            PayloadRef::Ping(data) => Payload::Ping(data),
+            PayloadRef::GoAway { last, error, ref data } =>
+                Payload::GoAway { last: last, error: error, data: &buf[data.clone()] },
+            PayloadRef::WindowUpdate(increment) => Payload::WindowUpdate(increment),
+            PayloadRef::Continuation(ref block) => Payload::Continuation(&buf[block.clone()]),
+            PayloadRef::Unregistered { kind, ref data } =>
+                Payload::Unregistered { kind: kind, data: &buf[data.clone()] }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use {Frame, Frames, StreamIdentifier, HttpError, Error};
+    use super::{FrameRef, PayloadRef};
+
+    #[test]
+    fn test_frame_ref_round_trip() {
+        let mut buf = vec![0xff; 3];
+        for frame in &[Frame::settings_ack(), Frame::ping([1, 2, 3, 4, 5, 6, 7, 8]),
+                       Frame::goaway(StreamIdentifier(1), HttpError::Protocol, Some("x"))] {
+            let start = buf.len();
+            buf.resize(start + frame.encoded_len(), 0);
+            frame.encode(&mut buf[start..]);
+        }
+
+        let mut offset = 3;
+        for frame in Frames::new(&buf[3..]) {
+            let frame_ref = FrameRef::parse(&buf, offset).unwrap();
+            assert_eq!(frame_ref.range.start, offset);
+            assert_eq!(frame_ref.resolve(&buf), frame.unwrap());
+            offset = frame_ref.range.end;
+        }
+        assert_eq!(offset, buf.len());
+
+        let goaway = FrameRef::parse(&buf, 29).unwrap();
+        assert_eq!(goaway.payload, PayloadRef::GoAway {
+            last: StreamIdentifier(1),
+            error: HttpError::Protocol.into(),
+            data: 46..47
+        });
+
+        assert_eq!(FrameRef::parse(&buf[..40], 29), Err(Error::Short(7)));
+    }
+}
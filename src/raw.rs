@@ -0,0 +1,180 @@
+use {Frame, FrameHeader, Payload, Flag, Error, FRAME_HEADER_BYTES};
+
+// The reserved bit of a stream identifier.
+const RESERVED: u8 = 0x80;
+
+/// A frame together with the parts of its encoding which `Frame` drops, so
+/// that it can be re-encoded byte for byte, as transparent proxies and
+/// record and replay tools need.
+///
+/// The frame type byte is already kept by `Kind::Unregistered`; the rest is
+/// kept here: the flag byte with any undefined bits, the pad length and
+/// padding bytes, the reserved bits of the stream identifier and of the
+/// stream identifiers in PUSH_PROMISE and GOAWAY payloads, and any bytes
+/// after a fixed-size payload such as RST_STREAM's.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct RawFrame<'a> {
+    pub frame: Frame<'a>,
+
+    /// The flag byte as it was sent, including undefined bits.
+    pub flags: u8,
+
+    /// The reserved bit of the frame's stream identifier.
+    pub reserved: bool,
+
+    /// The reserved bit of the promised stream of a PUSH_PROMISE frame or
+    /// the last stream of a GOAWAY frame.
+    pub payload_reserved: bool,
+
+    /// The padding of a padded frame, whose length is the pad length.
+    pub padding: Option<&'a [u8]>,
+
+    /// The bytes between the end of the payload `Frame` keeps and the
+    /// padding, which only fixed-size payloads can leave.
+    pub trailing: &'a [u8]
+}
+
+impl<'a> RawFrame<'a> {
+    /// Parse the frame at the start of `buf`, ignoring any bytes after it.
+    pub fn parse(buf: &'a [u8]) -> Result<RawFrame<'a>, Error> {
+        let header = try!(FrameHeader::parse(buf));
+        let payload = &buf[FRAME_HEADER_BYTES..];
+        let frame = try!(Frame::parse(header, payload));
+
+        let length = header.length as usize;
+        let padding = if header.kind.allows_padding() && header.flag.contains(Flag::padded()) {
+            Some(&payload[length - payload[0] as usize..length])
+        } else {
+            None
+        };
+        let kept = padding.map_or(0, |_| 1) + frame.payload.encoded_len();
+        let trailing = &payload[kept..length - padding.map_or(0, |padding| padding.len())];
+
+        Ok(RawFrame {
+            frame: frame,
+            flags: buf[4],
+            reserved: buf[5] & RESERVED != 0,
+            payload_reserved: match frame.payload {
+                // The promised stream follows the pad length byte, if any.
+                Payload::PushPromise { .. } => payload[padding.is_some() as usize] & RESERVED != 0,
+                Payload::GoAway { .. } => payload[0] & RESERVED != 0,
+                _ => false
+            },
+            padding: padding,
+            trailing: trailing
+        })
+    }
+
+    /// A `RawFrame` for a frame with nothing beyond what `Frame` keeps.
+    pub fn new(frame: Frame<'a>) -> RawFrame<'a> {
+        RawFrame {
+            frame: frame,
+            flags: frame.header.flag.encode(),
+            reserved: false,
+            payload_reserved: false,
+            padding: None,
+            trailing: &[]
+        }
+    }
+
+    /// Encode this frame into a buffer, exactly as it was parsed.
+    ///
+    /// The header's length is that of the payload, trailing bytes and any
+    /// padding, and the pad length byte is written whenever there is padding.
+    pub fn encode(&self, buf: &mut [u8]) -> usize {
+        let len = self.encoded_len();
+        let header = FrameHeader {
+            length: (len - FRAME_HEADER_BYTES) as u32,
+            ..self.frame.header
+        };
+        header.encode(buf);
+        buf[4] = self.flags;
+        if self.reserved {
+            buf[5] |= RESERVED;
+        }
+
+        let mut position = FRAME_HEADER_BYTES;
+        if let Some(padding) = self.padding {
+            buf[position] = padding.len() as u8;
+            position += 1;
+        }
+
+        let wrote = self.frame.payload.encode(&mut buf[position..]);
+        match self.frame.payload {
+            Payload::PushPromise { .. } | Payload::GoAway { .. } if self.payload_reserved =>
+                buf[position] |= RESERVED,
+            _ => {}
+        }
+        position += wrote;
+
+        buf[position..position + self.trailing.len()].copy_from_slice(self.trailing);
+        position += self.trailing.len();
+
+        if let Some(padding) = self.padding {
+            buf[position..len].copy_from_slice(padding);
+        }
+
+        len
+    }
+
+    /// How many bytes this frame will use in a buffer when encoding.
+    pub fn encoded_len(&self) -> usize {
+        self.frame.encoded_len() + self.trailing.len() +
+            self.padding.map_or(0, |padding| padding.len() + 1)
+    }
+}
+
+impl<'a> From<Frame<'a>> for RawFrame<'a> {
+    fn from(frame: Frame<'a>) -> RawFrame<'a> {
+        RawFrame::new(frame)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use {Frame, Payload, Kind, StreamIdentifier, HttpError};
+    use super::RawFrame;
+
+    fn round_trip(bytes: &[u8]) -> RawFrame {
+        let raw = RawFrame::parse(bytes).unwrap();
+        let mut encoded = vec![0; raw.encoded_len()];
+        assert_eq!(raw.encode(&mut encoded), bytes.len());
+        assert_eq!(encoded, bytes);
+        raw
+    }
+
+    #[test]
+    fn test_raw_round_trip() {
+        // A padded DATA frame with an undefined flag and the reserved bit.
+        let data = [0, 0, 8, 0, 0x4b, 0x80, 0, 0, 1, 3, b'a', b'b', b'c', b'd', 1, 2, 3];
+        let raw = round_trip(&data);
+        assert_eq!(raw.frame.payload, Payload::Data { data: b"abcd" });
+        assert_eq!(raw.padding, Some(&[1, 2, 3][..]));
+        assert_eq!((raw.flags, raw.reserved), (0x4b, true));
+        assert_eq!(raw.frame.header.id, StreamIdentifier(1));
+
+        // A padded PUSH_PROMISE with the reserved bit of the promised stream.
+        let push = [0, 0, 8, 5, 0x0c, 0, 0, 0, 1, 1, 0x80, 0, 0, 2, 0x82, 0x84, 0];
+        let raw = round_trip(&push);
+        assert!(raw.payload_reserved);
+        assert_eq!(raw.frame.payload,
+                   Payload::PushPromise { promised: StreamIdentifier(2), block: &[0x82, 0x84] });
+
+        let unregistered = [0, 0, 2, 0xfa, 0x83, 0, 0, 0, 0, 7, 7];
+        assert_eq!(round_trip(&unregistered).frame.header.kind, Kind::Unregistered(0xfa));
+
+        // Over-long RST_STREAM and PRIORITY payloads keep their extra bytes.
+        let reset = [0, 0, 6, 3, 0, 0, 0, 0, 1, 0, 0, 0, 8, 0xaa, 0xbb];
+        let raw = round_trip(&reset);
+        assert_eq!(raw.frame.payload, Payload::Reset(HttpError::Cancel.into()));
+        assert_eq!(raw.trailing, &[0xaa, 0xbb]);
+        let priority = [0, 0, 7, 2, 0, 0, 0, 0, 3, 0, 0, 0, 1, 15, 0xcc, 0xdd];
+        assert_eq!(round_trip(&priority).trailing, &[0xcc, 0xdd]);
+
+        let goaway = Frame::goaway(StreamIdentifier(3), HttpError::NoError, Some("bye"));
+        let mut bytes = Vec::from(&goaway);
+        assert_eq!(round_trip(&bytes), RawFrame::from(goaway));
+        bytes[9] |= 0x80;
+        assert!(round_trip(&bytes).payload_reserved);
+    }
+}
@@ -0,0 +1,118 @@
+use std::fmt;
+
+use {Frame, Payload, HttpError};
+
+/// Formats a frame without the bytes that may carry secrets, printing
+/// only their lengths.
+///
+/// DATA bodies, header blocks, GOAWAY debug data and the payloads of
+/// unregistered frame types are masked by both `Debug` and `Display`;
+/// everything else is printed as for the frame itself.
+#[derive(Copy, Clone)]
+pub struct RedactedFrame<'a>(&'a Frame<'a>);
+
+impl<'a> Frame<'a> {
+    /// This frame, formatted without its data or header blocks.
+    pub fn redacted(&'a self) -> RedactedFrame<'a> {
+        RedactedFrame(self)
+    }
+}
+
+// Masked bytes, shown as their length.
+struct Masked(usize);
+
+impl fmt::Debug for Masked {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "<{} bytes>", self.0)
+    }
+}
+
+impl<'a> fmt::Debug for RedactedFrame<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Frame")
+            .field("header", &self.0.header)
+            .field("payload", &RedactedPayload(&self.0.payload))
+            .finish()
+    }
+}
+
+impl<'a> fmt::Display for RedactedFrame<'a> {
+    /// The one-line summary of `Frame`'s `Display`, with GOAWAY debug data
+    /// shown as a length.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0.payload {
+            Payload::GoAway { last, error, data } => {
+                write!(f, "{} last={} error={} debug={}", self.0.header, last.0,
+                       HttpError::from(error), data.len())
+            },
+            _ => fmt::Display::fmt(self.0, f)
+        }
+    }
+}
+
+struct RedactedPayload<'a>(&'a Payload<'a>);
+
+impl<'a> fmt::Debug for RedactedPayload<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self.0 {
+            Payload::Data { data } =>
+                f.debug_struct("Data").field("data", &Masked(data.len())).finish(),
+            Payload::Headers { priority, block } =>
+                f.debug_struct("Headers")
+                    .field("priority", &priority)
+                    .field("block", &Masked(block.len()))
+                    .finish(),
+            Payload::PushPromise { promised, block } =>
+                f.debug_struct("PushPromise")
+                    .field("promised", &promised)
+                    .field("block", &Masked(block.len()))
+                    .finish(),
+            Payload::GoAway { last, error, data } =>
+                f.debug_struct("GoAway")
+                    .field("last", &last)
+                    .field("error", &error)
+                    .field("data", &Masked(data.len()))
+                    .finish(),
+            Payload::Continuation(block) =>
+                f.debug_tuple("Continuation").field(&Masked(block.len())).finish(),
+            Payload::Unregistered { kind, data } =>
+                f.debug_struct("Unregistered")
+                    .field("kind", &kind)
+                    .field("data", &Masked(data.len()))
+                    .finish(),
+            ref payload => fmt::Debug::fmt(payload, f)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use {Frame, FrameHeader, Payload, Kind, Flag, StreamIdentifier, HttpError};
+
+    #[test]
+    fn test_redacted_frame() {
+        let frame = Frame {
+            header: FrameHeader {
+                length: 19,
+                kind: Kind::Headers,
+                flag: Flag::end_headers(),
+                id: StreamIdentifier(1)
+            },
+            payload: Payload::Headers { priority: None, block: b"authorization: xyz!" }
+        };
+
+        let debug = format!("{:?}", frame.redacted());
+        assert!(!debug.contains("120"));
+        assert!(debug.contains("block: <19 bytes>"));
+        assert_eq!(frame.redacted().to_string(), frame.to_string());
+
+        let goaway = Frame::goaway(StreamIdentifier(0), HttpError::Internal, Some("secret"));
+        assert_eq!(goaway.redacted().to_string(),
+                   "GOAWAY stream=0 len=14 last=0 error=INTERNAL_ERROR debug=6");
+        assert!(!format!("{:?}", goaway.redacted()).contains("115"));
+
+        let ping = Frame::ping([1; 8]);
+        assert_eq!(format!("{:?}", ping.redacted()),
+                   format!("Frame {{ header: {:?}, payload: {:?} }}", ping.header, ping.payload));
+    }
+}
@@ -0,0 +1,403 @@
+use std::cmp;
+use std::collections::BTreeMap;
+
+use {FrameHeader, Kind, Flag, StreamIdentifier, SizeIncrement, WindowSize, HttpError,
+     OwnedFrame, OwnedPayload};
+use consts::{DEFAULT_MAX_FRAME_SIZE, DEFAULT_WEIGHT};
+
+// The urgency of streams without one, as in RFC 9218.
+const DEFAULT_URGENCY: u8 = 3;
+
+#[derive(Clone, Debug)]
+struct Stream {
+    data: Vec<u8>,
+    end_stream: bool,
+    window: WindowSize,
+    urgency: u8,
+    incremental: bool
+}
+
+impl Stream {
+    // Whether a frame can be sent now, given the connection window.
+    fn ready(&self, connection: &WindowSize) -> bool {
+        (self.data.is_empty() && self.end_stream) ||
+            (!self.data.is_empty() && self.window.available() > 0 && connection.available() > 0)
+    }
+}
+
+/// Decides which DATA frames to send next, and how large, from the data
+/// queued on each stream, the flow-control windows and stream priorities.
+///
+/// Priorities follow RFC 9218: streams with a lower urgency are served
+/// first. Within an urgency, streams which are not incremental are sent
+/// one at a time in order of stream identifier, then incremental streams
+/// take turns a frame at a time.
+#[derive(Clone, Debug)]
+pub struct WriteScheduler {
+    streams: BTreeMap<u32, Stream>,
+    connection: WindowSize,
+    initial_window_size: u32,
+    max_frame_size: u32,
+
+    // The incremental stream last served, so the next turn goes to the
+    // stream after it.
+    last_incremental: u32
+}
+
+impl WriteScheduler {
+    /// A scheduler with the initial connection window and settings.
+    pub fn new() -> WriteScheduler {
+        WriteScheduler {
+            streams: BTreeMap::new(),
+            connection: WindowSize::default(),
+            initial_window_size: WindowSize::default().available(),
+            max_frame_size: DEFAULT_MAX_FRAME_SIZE,
+            last_incremental: 0
+        }
+    }
+
+    /// Apply the peer's SETTINGS_MAX_FRAME_SIZE.
+    pub fn set_max_frame_size(&mut self, size: u32) {
+        self.max_frame_size = size;
+    }
+
+    /// Apply the peer's SETTINGS_INITIAL_WINDOW_SIZE, adjusting the
+    /// window of every stream.
+    pub fn set_initial_window_size(&mut self, size: u32) -> Result<(), HttpError> {
+        try!(WindowSize::new(size));
+
+        let old = self.initial_window_size;
+        for stream in self.streams.values_mut() {
+            stream.window = try!(stream.window.adjust(old, size));
+        }
+
+        self.initial_window_size = size;
+        Ok(())
+    }
+
+    /// Apply a WINDOW_UPDATE from the peer, to the connection when `id`
+    /// is stream 0. Updates for streams with nothing queued are ignored.
+    pub fn window_update(&mut self, id: StreamIdentifier,
+                         increment: SizeIncrement) -> Result<(), HttpError> {
+        if id.0 == 0 {
+            self.connection = try!(self.connection.checked_add(increment));
+        } else if let Some(stream) = self.streams.get_mut(&id.0) {
+            stream.window = try!(stream.window.checked_add(increment));
+        }
+
+        Ok(())
+    }
+
+    /// Set a stream's urgency, from 0 (most urgent) to 7, and whether its
+    /// data is useful in pieces.
+    pub fn set_priority(&mut self, id: StreamIdentifier, urgency: u8, incremental: bool) {
+        let stream = self.stream(id);
+        stream.urgency = cmp::min(urgency, 7);
+        stream.incremental = incremental;
+    }
+
+    /// Queue data on a stream, ending it if `end_stream` is set.
+    pub fn push(&mut self, id: StreamIdentifier, data: &[u8], end_stream: bool) {
+        let stream = self.stream(id);
+        stream.data.extend_from_slice(data);
+        stream.end_stream |= end_stream;
+    }
+
+    /// The number of bytes queued on a stream.
+    pub fn queued(&self, id: StreamIdentifier) -> usize {
+        self.streams.get(&id.0).map_or(0, |stream| stream.data.len())
+    }
+
+    /// Forget a stream and its queued data, as when it is reset.
+    pub fn remove(&mut self, id: StreamIdentifier) {
+        self.streams.remove(&id.0);
+    }
+
+    /// The next DATA frame to send, or `None` if no stream can send.
+    ///
+    /// Windows are reduced by the frame's length. A stream is forgotten
+    /// once its END_STREAM frame is produced.
+    pub fn next_frame(&mut self) -> Option<OwnedFrame> {
+        let id = match self.pick() {
+            Some(id) => id,
+            None => return None
+        };
+
+        let (data, end_stream, incremental) = {
+            let stream = self.streams.get_mut(&id).unwrap();
+            let len = cmp::min(stream.data.len() as u32, self.max_frame_size);
+            let len = cmp::min(len, cmp::min(stream.window.available(),
+                                             self.connection.available())) as usize;
+
+            stream.window = stream.window.checked_sub(len as u32).unwrap();
+            self.connection = self.connection.checked_sub(len as u32).unwrap();
+
+            let data = stream.data.drain(..len).collect::<Vec<u8>>();
+            (data, stream.end_stream && stream.data.is_empty(), stream.incremental)
+        };
+
+        if incremental {
+            self.last_incremental = id;
+        }
+        if end_stream {
+            self.streams.remove(&id);
+        }
+
+        Some(OwnedFrame {
+            header: FrameHeader {
+                length: data.len() as u32,
+                kind: Kind::Data,
+                flag: if end_stream { Flag::end_stream() } else { Flag::empty() },
+                id: StreamIdentifier(id)
+            },
+            payload: OwnedPayload::Data { data: data }
+        })
+    }
+
+    fn stream(&mut self, id: StreamIdentifier) -> &mut Stream {
+        let window = WindowSize::new(self.initial_window_size).unwrap();
+        self.streams.entry(id.0).or_insert_with(|| Stream {
+            data: Vec::new(),
+            end_stream: false,
+            window: window,
+            urgency: DEFAULT_URGENCY,
+            incremental: false
+        })
+    }
+
+    fn pick(&self) -> Option<u32> {
+        let connection = &self.connection;
+        let urgency = match self.streams.values()
+                .filter(|stream| stream.ready(connection))
+                .map(|stream| stream.urgency).min() {
+            Some(urgency) => urgency,
+            None => return None
+        };
+
+        let mut candidates = self.streams.iter()
+            .filter(|&(_, stream)| stream.urgency == urgency && stream.ready(connection));
+
+        let sequential = candidates.clone().find(|&(_, stream)| !stream.incremental);
+        if let Some((&id, _)) = sequential {
+            return Some(id)
+        }
+
+        let after = self.last_incremental;
+        candidates.clone().find(|&(&id, _)| id > after)
+            .or_else(|| candidates.next())
+            .map(|(&id, _)| id)
+    }
+}
+
+impl Default for WriteScheduler {
+    fn default() -> WriteScheduler {
+        WriteScheduler::new()
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+struct Turn {
+    pending: u64,
+    weight: u32,
+    urgency: u8,
+    incremental: bool
+}
+
+/// Decides which stream writes next and how much, without holding any
+/// data, as an iterator of stream identifiers with byte budgets.
+///
+/// Streams are served in order of urgency, as in RFC 9218. Within an
+/// urgency, streams marked non-incremental with `set_urgency` are served
+/// one at a time in order of stream identifier, and the rest take turns,
+/// each turn's budget being `quantum` bytes scaled by the stream's RFC 7540
+/// weight over the default weight of 16. Streams are incremental with the
+/// default urgency until `set_urgency` is used, so weights alone apply.
+///
+/// The budget of each turn is charged as though it were written in full;
+/// bytes left unwritten should be added back with `push`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WeightedScheduler {
+    streams: BTreeMap<u32, Turn>,
+    quantum: u32,
+    last: u32
+}
+
+impl WeightedScheduler {
+    pub fn new(quantum: u32) -> WeightedScheduler {
+        WeightedScheduler {
+            streams: BTreeMap::new(),
+            quantum: quantum,
+            last: 0
+        }
+    }
+
+    /// Set a stream's weight, as sent on the wire, from 0 to 255.
+    pub fn set_weight(&mut self, id: StreamIdentifier, weight: u8) {
+        self.turn(id).weight = weight as u32 + 1;
+    }
+
+    /// Set a stream's urgency, from 0 (most urgent) to 7, and whether its
+    /// data is useful in pieces.
+    pub fn set_urgency(&mut self, id: StreamIdentifier, urgency: u8, incremental: bool) {
+        let turn = self.turn(id);
+        turn.urgency = cmp::min(urgency, 7);
+        turn.incremental = incremental;
+    }
+
+    /// Add `len` bytes to those waiting to be written on a stream.
+    pub fn push(&mut self, id: StreamIdentifier, len: u64) {
+        self.turn(id).pending += len;
+    }
+
+    /// The bytes waiting to be written on a stream.
+    pub fn pending(&self, id: StreamIdentifier) -> u64 {
+        self.streams.get(&id.0).map_or(0, |turn| turn.pending)
+    }
+
+    /// Forget a stream, as when it is closed.
+    pub fn remove(&mut self, id: StreamIdentifier) {
+        self.streams.remove(&id.0);
+    }
+
+    fn turn(&mut self, id: StreamIdentifier) -> &mut Turn {
+        self.streams.entry(id.0).or_insert(Turn {
+            pending: 0,
+            weight: DEFAULT_WEIGHT,
+            urgency: DEFAULT_URGENCY,
+            incremental: true
+        })
+    }
+
+    fn pick(&self) -> Option<u32> {
+        let urgency = match self.streams.values()
+                .filter(|turn| turn.pending > 0)
+                .map(|turn| turn.urgency).min() {
+            Some(urgency) => urgency,
+            None => return None
+        };
+
+        let mut candidates = self.streams.iter()
+            .filter(|&(_, turn)| turn.urgency == urgency && turn.pending > 0);
+
+        if let Some((&id, _)) = candidates.clone().find(|&(_, turn)| !turn.incremental) {
+            return Some(id)
+        }
+
+        let after = self.last;
+        candidates.clone().find(|&(&id, _)| id > after)
+            .or_else(|| candidates.next())
+            .map(|(&id, _)| id)
+    }
+}
+
+impl Iterator for WeightedScheduler {
+    type Item = (StreamIdentifier, u32);
+
+    /// The next stream to write and the most it should write, or `None`
+    /// if no stream has anything pending.
+    fn next(&mut self) -> Option<(StreamIdentifier, u32)> {
+        let id = match self.pick() {
+            Some(id) => id,
+            None => return None
+        };
+
+        let quantum = self.quantum as u64;
+        let turn = self.streams.get_mut(&id).unwrap();
+        let budget = if turn.incremental {
+            self.last = id;
+            cmp::max(quantum * turn.weight as u64 / DEFAULT_WEIGHT as u64, 1)
+        } else {
+            turn.pending
+        };
+
+        let budget = cmp::min(cmp::min(budget, turn.pending), u32::max_value() as u64);
+        turn.pending -= budget;
+        Some((StreamIdentifier(id), budget as u32))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use {StreamIdentifier, SizeIncrement, Flag};
+    use super::{WriteScheduler, WeightedScheduler};
+
+    fn next(scheduler: &mut WriteScheduler) -> Option<(u32, usize, bool)> {
+        scheduler.next_frame().map(|frame| {
+            (frame.header.id.0, frame.header.length as usize,
+             frame.header.flag.contains(Flag::end_stream()))
+        })
+    }
+
+    #[test]
+    fn test_frames_respect_limits() {
+        let mut scheduler = WriteScheduler::new();
+        scheduler.set_max_frame_size(20000);
+        scheduler.push(StreamIdentifier(1), &[0; 70000], true);
+
+        assert_eq!(next(&mut scheduler), Some((1, 20000, false)));
+        assert_eq!(next(&mut scheduler), Some((1, 20000, false)));
+        assert_eq!(next(&mut scheduler), Some((1, 20000, false)));
+        assert_eq!(next(&mut scheduler), Some((1, 5535, false)));
+        assert_eq!(next(&mut scheduler), None);
+
+        scheduler.window_update(StreamIdentifier(0), SizeIncrement(10000)).unwrap();
+        assert_eq!(next(&mut scheduler), None);
+        scheduler.window_update(StreamIdentifier(1), SizeIncrement(10000)).unwrap();
+        assert_eq!(next(&mut scheduler), Some((1, 4465, true)));
+        assert_eq!(scheduler.queued(StreamIdentifier(1)), 0);
+    }
+
+    #[test]
+    fn test_priorities() {
+        let mut scheduler = WriteScheduler::new();
+        scheduler.set_max_frame_size(10);
+
+        scheduler.push(StreamIdentifier(1), &[0; 20], true);
+        scheduler.push(StreamIdentifier(3), &[0; 10], true);
+        scheduler.set_priority(StreamIdentifier(3), 0, false);
+        scheduler.push(StreamIdentifier(5), &[0; 20], false);
+        scheduler.push(StreamIdentifier(7), &[0; 20], false);
+        scheduler.set_priority(StreamIdentifier(5), 3, true);
+        scheduler.set_priority(StreamIdentifier(7), 3, true);
+
+        assert_eq!(next(&mut scheduler), Some((3, 10, true)));
+        assert_eq!(next(&mut scheduler), Some((1, 10, false)));
+        assert_eq!(next(&mut scheduler), Some((1, 10, true)));
+        assert_eq!(next(&mut scheduler), Some((5, 10, false)));
+        assert_eq!(next(&mut scheduler), Some((7, 10, false)));
+        assert_eq!(next(&mut scheduler), Some((5, 10, false)));
+        assert_eq!(next(&mut scheduler), Some((7, 10, false)));
+        assert_eq!(next(&mut scheduler), None);
+
+        scheduler.push(StreamIdentifier(5), &[], true);
+        assert_eq!(next(&mut scheduler), Some((5, 0, true)));
+    }
+
+    #[test]
+    fn test_weighted_turns() {
+        let mut scheduler = WeightedScheduler::new(100);
+        scheduler.push(StreamIdentifier(1), 1000);
+        scheduler.push(StreamIdentifier(3), 1000);
+        scheduler.set_weight(StreamIdentifier(3), 31);
+        scheduler.push(StreamIdentifier(5), 150);
+        scheduler.set_weight(StreamIdentifier(5), 7);
+
+        let turns = scheduler.by_ref().take(6)
+            .map(|(id, budget)| (id.0, budget)).collect::<Vec<_>>();
+        assert_eq!(turns, [(1, 100), (3, 200), (5, 50), (1, 100), (3, 200), (5, 50)]);
+        assert_eq!(scheduler.pending(StreamIdentifier(5)), 50);
+
+        scheduler.push(StreamIdentifier(7), 30);
+        scheduler.push(StreamIdentifier(9), 10);
+        scheduler.set_urgency(StreamIdentifier(9), 0, false);
+        scheduler.set_urgency(StreamIdentifier(7), 0, false);
+        assert_eq!(scheduler.next(), Some((StreamIdentifier(7), 30)));
+        assert_eq!(scheduler.next(), Some((StreamIdentifier(9), 10)));
+        assert_eq!(scheduler.next(), Some((StreamIdentifier(1), 100)));
+
+        scheduler.remove(StreamIdentifier(1));
+        scheduler.remove(StreamIdentifier(3));
+        assert_eq!(scheduler.next(), Some((StreamIdentifier(5), 50)));
+        assert_eq!(scheduler.next(), None);
+    }
+}
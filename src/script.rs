@@ -0,0 +1,264 @@
+//! A small text language for describing frames, in the style of `h2i`.
+//!
+//! Each frame is written on one line as its kind followed by
+//! space-separated fields, using the same names as `Frame`'s `Display`:
+//!
+//! ```text
+//! SETTINGS INITIAL_WINDOW_SIZE=65535 ENABLE_PUSH=0
+//! HEADERS stream=1 end_stream end_headers weight=32 hex=828684
+//! DATA stream=1 text=hello
+//! GOAWAY last=1 error=PROTOCOL_ERROR text=bye
+//! ```
+//!
+//! Flags are given by name in either case, and must be defined for the
+//! frame's kind. PADDED is refused, since frames are built without padding,
+//! and PRIORITY given alone sends the default priority. Payload bytes come
+//! from `hex=` and `text=` fields, which may be repeated and are
+//! concatenated. Numbers may be decimal or prefixed with `0x`. The frame
+//! length is computed.
+
+use {Kind, Flag, FrameHeader, StreamIdentifier, ErrorCode, HttpError, SizeIncrement,
+     Payload, Priority, Setting, SettingIdentifier, OwnedFrame, OwnedPayload};
+
+/// Errors produced while reading a frame description.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum ScriptError {
+    /// The line had no frame kind.
+    MissingKind,
+
+    /// The frame kind was not recognized.
+    UnknownKind(String),
+
+    /// A field is not recognized or does not apply to the frame's kind.
+    UnknownField(String),
+
+    /// A field's value could not be parsed.
+    InvalidValue(String)
+}
+
+/// Build a frame from a one-line description.
+pub fn parse_frame(line: &str) -> Result<OwnedFrame, ScriptError> {
+    let mut tokens = line.split_whitespace();
+    let kind = match tokens.next() {
+        Some(name) => try!(parse_kind(name)),
+        None => return Err(ScriptError::MissingKind)
+    };
+
+    let mut id = 0;
+    let mut flag = Flag::empty();
+    let mut data = Vec::new();
+    let mut settings = Vec::new();
+    let mut exclusive = false;
+    let mut dependency = None;
+    let mut weight = None;
+    let mut error = HttpError::NoError;
+    let mut last = 0;
+    let mut promised = 0;
+    let mut increment = 0;
+
+    for token in tokens {
+        let (key, value) = match token.find('=') {
+            Some(at) => (&token[..at], &token[at + 1..]),
+            None => (token, "")
+        };
+        let invalid = || ScriptError::InvalidValue(token.to_string());
+        let unknown = || ScriptError::UnknownField(token.to_string());
+
+        match (key, kind) {
+            ("stream", _) => id = try!(parse_number(value).ok_or_else(invalid)),
+            ("hex", _) => data.extend(try!(::hex::decode(value).ok_or_else(invalid))),
+            ("text", _) => data.extend_from_slice(value.as_bytes()),
+            ("exclusive", Kind::Headers) | ("exclusive", Kind::Priority) if value.is_empty() =>
+                exclusive = true,
+            ("dependency", Kind::Headers) | ("dependency", Kind::Priority) =>
+                dependency = Some(try!(parse_number(value).ok_or_else(invalid))),
+            ("weight", Kind::Headers) | ("weight", Kind::Priority) => {
+                let value = try!(parse_number(value).ok_or_else(invalid));
+                if value < 1 || value > 256 { return Err(invalid()) }
+                weight = Some((value - 1) as u8);
+            },
+            ("error", Kind::Reset) | ("error", Kind::GoAway) =>
+                error = try!(parse_error(value).ok_or_else(invalid)),
+            ("last", Kind::GoAway) => last = try!(parse_number(value).ok_or_else(invalid)),
+            ("promised", Kind::PushPromise) =>
+                promised = try!(parse_number(value).ok_or_else(invalid)),
+            ("increment", Kind::WindowUpdate) =>
+                increment = try!(parse_number(value).ok_or_else(invalid)),
+            (_, Kind::Settings) if !value.is_empty() => {
+                let identifier = try!(SettingIdentifier::from_name(key).ok_or_else(unknown));
+                settings.push(Setting::new(identifier,
+                                           try!(parse_number(value).ok_or_else(invalid))));
+            },
+            (_, _) if value.is_empty() => match try!(parse_flag(key, kind).ok_or_else(unknown)) {
+                padded if padded == Flag::padded() => return Err(unknown()),
+                defined => flag = flag | defined
+            },
+            _ => return Err(unknown())
+        }
+    }
+
+    let priority = match (dependency, weight) {
+        (None, None) if !exclusive && !flag.contains(Flag::priority()) => None,
+        _ => Some(Priority::new(exclusive, StreamIdentifier(dependency.unwrap_or(0)),
+                                weight.unwrap_or(Priority::default().weight())))
+    };
+
+    let payload = match kind {
+        Kind::Data => OwnedPayload::Data { data: data },
+        Kind::Headers => {
+            if priority.is_some() { flag = flag | Flag::priority() }
+            OwnedPayload::Headers { priority: priority, block: data }
+        },
+        Kind::Priority => OwnedPayload::Priority(priority.unwrap_or_default()),
+        Kind::Reset => OwnedPayload::Reset(error.into()),
+        Kind::Settings => OwnedPayload::Settings(settings),
+        Kind::PushPromise => OwnedPayload::PushPromise {
+            promised: StreamIdentifier(promised),
+            block: data
+        },
+        Kind::Ping => {
+            if !data.is_empty() && data.len() != 8 {
+                return Err(ScriptError::InvalidValue(format!("{} bytes of ping data",
+                                                             data.len())))
+            }
+            let mut opaque = [0; 8];
+            opaque[..data.len()].copy_from_slice(&data);
+            Payload::ping(opaque).into()
+        },
+        Kind::GoAway => OwnedPayload::GoAway {
+            last: StreamIdentifier(last),
+            error: error.into(),
+            data: data
+        },
+        Kind::WindowUpdate => OwnedPayload::WindowUpdate(SizeIncrement(increment)),
+        Kind::Continuation => OwnedPayload::Continuation(data),
+        Kind::Unregistered(kind) => OwnedPayload::Unregistered { kind: kind, data: data }
+    };
+
+    Ok(OwnedFrame {
+        header: FrameHeader {
+            length: payload.as_payload().encoded_len() as u32,
+            kind: kind,
+            flag: flag,
+            id: StreamIdentifier(id)
+        },
+        payload: payload
+    })
+}
+
+/// Build the frames described by a script, one per line.
+///
+/// Blank lines and lines starting with `#` are skipped. Errors are
+/// returned with the line number they occurred on, counting from 1.
+pub fn parse_script(script: &str) -> Result<Vec<OwnedFrame>, (usize, ScriptError)> {
+    script.lines().enumerate()
+        .filter(|&(_, line)| {
+            let line = line.trim();
+            !line.is_empty() && !line.starts_with('#')
+        })
+        .map(|(number, line)| parse_frame(line).map_err(|error| (number + 1, error)))
+        .collect()
+}
+
+fn parse_kind(name: &str) -> Result<Kind, ScriptError> {
+    (0..256).map(|byte| Kind::new(byte as u8))
+        .find(|kind| kind.to_string() == name)
+        .ok_or_else(|| ScriptError::UnknownKind(name.to_string()))
+}
+
+fn parse_flag(name: &str, kind: Kind) -> Option<Flag> {
+    Flag::defined_for(kind).into_iter()
+        .find(|&(_, defined)| defined.eq_ignore_ascii_case(name))
+        .map(|(flag, _)| flag)
+}
+
+fn parse_error(name: &str) -> Option<HttpError> {
+    HttpError::from_name(name)
+        .or_else(|| parse_number(name).map(|code| HttpError::from(ErrorCode(code))))
+}
+
+fn parse_number(value: &str) -> Option<u32> {
+    if value.starts_with("0x") {
+        u32::from_str_radix(&value[2..], 16).ok()
+    } else {
+        value.parse().ok()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use {Frame, StreamIdentifier, HttpError, Setting, SettingIdentifier, Priority,
+         Payload, Flag, Kind};
+    use super::{parse_frame, parse_script, ScriptError};
+
+    #[test]
+    fn test_parse_frames() {
+        assert_eq!(parse_frame("SETTINGS ack").unwrap().as_frame(), Frame::settings_ack());
+        assert_eq!(parse_frame("PING hex=0102030405060708").unwrap().as_frame(),
+                   Frame::ping([1, 2, 3, 4, 5, 6, 7, 8]));
+        assert_eq!(parse_frame("GOAWAY last=3 error=PROTOCOL_ERROR text=oops").unwrap()
+                       .as_frame(),
+                   Frame::goaway(StreamIdentifier(3), HttpError::Protocol, Some("oops")));
+
+        let settings = parse_frame("SETTINGS ENABLE_PUSH=0 INITIAL_WINDOW_SIZE=0x10").unwrap();
+        assert_eq!(settings.header.length, 12);
+        assert_eq!(settings.as_frame().payload.to_string(),
+                   "SETTINGS ENABLE_PUSH=0 INITIAL_WINDOW_SIZE=16");
+        assert!(settings.as_frame().payload ==
+                Payload::Settings(::Settings::new(&[
+                    Setting::new(SettingIdentifier::EnablePush, 0),
+                    Setting::new(SettingIdentifier::InitialWindowSize, 16)])));
+
+        let headers = parse_frame("HEADERS stream=1 END_STREAM end_headers weight=32 \
+                                   hex=8286 hex=84").unwrap();
+        assert_eq!(headers.header.kind, Kind::Headers);
+        assert_eq!(headers.header.id, StreamIdentifier(1));
+        assert_eq!(headers.header.length, 8);
+        assert_eq!(headers.header.flag,
+                   Flag::end_stream() | Flag::end_headers() | Flag::priority());
+        assert_eq!(headers.as_frame().payload,
+                   Payload::Headers {
+                       priority: Some(Priority::new(false, StreamIdentifier(0), 31)),
+                       block: &[0x82, 0x86, 0x84]
+                   });
+
+        let headers = parse_frame("HEADERS stream=1 priority").unwrap();
+        assert_eq!(headers.header.length, 5);
+        assert_eq!(headers.as_frame().payload,
+                   Payload::Headers { priority: Some(Priority::default()), block: &[] });
+        assert_eq!(parse_frame("RST_STREAM stream=1 error=0x1f").unwrap().as_frame().payload,
+                   Payload::Reset(HttpError::Unregistered(0x1f).into()));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(parse_frame("  "), Err(ScriptError::MissingKind));
+        assert_eq!(parse_frame("FOO"), Err(ScriptError::UnknownKind("FOO".to_string())));
+        assert_eq!(parse_frame("DATA weight=2"),
+                   Err(ScriptError::UnknownField("weight=2".to_string())));
+        assert_eq!(parse_frame("DATA hex=abc"),
+                   Err(ScriptError::InvalidValue("hex=abc".to_string())));
+        assert_eq!(parse_frame("PRIORITY weight=0"),
+                   Err(ScriptError::InvalidValue("weight=0".to_string())));
+        assert_eq!(parse_frame("DATA stream=1 ack"),
+                   Err(ScriptError::UnknownField("ack".to_string())));
+        assert_eq!(parse_frame("DATA stream=1 padded"),
+                   Err(ScriptError::UnknownField("padded".to_string())));
+        assert_eq!(parse_frame("SETTINGS FOO=1"),
+                   Err(ScriptError::UnknownField("FOO=1".to_string())));
+    }
+
+    #[test]
+    fn test_parse_script() {
+        let frames = parse_script("# A client's opening frames.\n\
+                                   SETTINGS\n\
+                                   \n\
+                                   WINDOW_UPDATE increment=1000\n").unwrap();
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[1].as_frame().to_string(), "WINDOW_UPDATE stream=0 len=4 increment=1000");
+
+        assert_eq!(parse_script("SETTINGS\nPING\nPANG\n"),
+                   Err((3, ScriptError::UnknownKind("PANG".to_string()))));
+    }
+}
@@ -0,0 +1,147 @@
+use {FrameHeader, Kind, Flag, StreamIdentifier};
+
+/// Errors produced by a `Sequencer`.
+///
+/// All of these are connection errors of type PROTOCOL_ERROR.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum SequenceError {
+    /// The first frame on the connection was not a SETTINGS frame without
+    /// ACK. Holds the kind of the frame that arrived instead.
+    ExpectedSettings(Kind),
+
+    /// A CONTINUATION frame arrived while no header block was open.
+    UnexpectedContinuation,
+
+    /// A frame other than CONTINUATION arrived while a header block was open.
+    ExpectedContinuation(Kind),
+
+    /// A CONTINUATION frame arrived on a different stream than the
+    /// header block it should continue.
+    StreamMismatch(StreamIdentifier)
+}
+
+/// Enforces the ordering rules that span frames on a connection.
+///
+/// The peer's first frame must be SETTINGS, and once a HEADERS or
+/// PUSH_PROMISE frame without END_HEADERS arrives, only CONTINUATION
+/// frames on the same stream may follow until one carries END_HEADERS.
+///
+/// Headers are fed in the order they are received, after the connection
+/// preface.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Sequencer {
+    started: bool,
+    block: Option<StreamIdentifier>
+}
+
+impl Sequencer {
+    pub fn new() -> Sequencer {
+        Sequencer { started: false, block: None }
+    }
+
+    /// Has the peer's initial SETTINGS frame been seen?
+    pub fn is_started(&self) -> bool {
+        self.started
+    }
+
+    /// The stream of the header block awaiting CONTINUATION frames, if any.
+    pub fn open_block(&self) -> Option<StreamIdentifier> {
+        self.block
+    }
+
+    /// Check and record the next frame received.
+    pub fn push(&mut self, header: &FrameHeader) -> Result<(), SequenceError> {
+        if !self.started {
+            if header.kind != Kind::Settings || header.flag.contains(Flag::ack()) {
+                return Err(SequenceError::ExpectedSettings(header.kind))
+            }
+
+            self.started = true;
+            return Ok(())
+        }
+
+        let end_headers = header.flag.contains(Flag::end_headers());
+
+        match (self.block, header.kind) {
+            (Some(id), Kind::Continuation) => {
+                if header.id != id {
+                    return Err(SequenceError::StreamMismatch(header.id))
+                }
+
+                if end_headers { self.block = None }
+            },
+            (Some(_), kind) => return Err(SequenceError::ExpectedContinuation(kind)),
+            (None, Kind::Continuation) => return Err(SequenceError::UnexpectedContinuation),
+            (None, Kind::Headers) | (None, Kind::PushPromise) => {
+                if !end_headers { self.block = Some(header.id) }
+            },
+            (None, _) => {}
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use {FrameHeader, Kind, Flag, StreamIdentifier};
+    use super::{Sequencer, SequenceError};
+
+    fn header(kind: Kind, flag: Flag, id: u32) -> FrameHeader {
+        FrameHeader {
+            length: 0,
+            kind: kind,
+            flag: flag,
+            id: StreamIdentifier(id)
+        }
+    }
+
+    fn started() -> Sequencer {
+        let mut sequencer = Sequencer::new();
+        sequencer.push(&header(Kind::Settings, Flag::empty(), 0)).unwrap();
+        sequencer
+    }
+
+    #[test]
+    fn test_first_frame_must_be_settings() {
+        let mut sequencer = Sequencer::new();
+        assert_eq!(sequencer.push(&header(Kind::Ping, Flag::empty(), 0)),
+                   Err(SequenceError::ExpectedSettings(Kind::Ping)));
+        assert_eq!(sequencer.push(&header(Kind::Settings, Flag::ack(), 0)),
+                   Err(SequenceError::ExpectedSettings(Kind::Settings)));
+        assert!(!sequencer.is_started());
+
+        assert_eq!(sequencer.push(&header(Kind::Settings, Flag::empty(), 0)), Ok(()));
+        assert!(sequencer.is_started());
+        assert_eq!(sequencer.push(&header(Kind::Ping, Flag::empty(), 0)), Ok(()));
+    }
+
+    #[test]
+    fn test_continuations() {
+        let mut sequencer = started();
+
+        assert_eq!(sequencer.push(&header(Kind::Headers, Flag::empty(), 1)), Ok(()));
+        assert_eq!(sequencer.open_block(), Some(StreamIdentifier(1)));
+        assert_eq!(sequencer.push(&header(Kind::Continuation, Flag::empty(), 1)), Ok(()));
+        assert_eq!(sequencer.push(&header(Kind::Continuation, Flag::end_headers(), 1)), Ok(()));
+        assert_eq!(sequencer.open_block(), None);
+
+        assert_eq!(sequencer.push(&header(Kind::Continuation, Flag::end_headers(), 1)),
+                   Err(SequenceError::UnexpectedContinuation));
+        assert_eq!(sequencer.push(&header(Kind::Headers, Flag::end_headers(), 3)), Ok(()));
+        assert_eq!(sequencer.open_block(), None);
+    }
+
+    #[test]
+    fn test_no_interleaving() {
+        let mut sequencer = started();
+
+        assert_eq!(sequencer.push(&header(Kind::PushPromise, Flag::empty(), 1)), Ok(()));
+        assert_eq!(sequencer.push(&header(Kind::Data, Flag::empty(), 1)),
+                   Err(SequenceError::ExpectedContinuation(Kind::Data)));
+        assert_eq!(sequencer.push(&header(Kind::Continuation, Flag::end_headers(), 3)),
+                   Err(SequenceError::StreamMismatch(StreamIdentifier(3))));
+        assert_eq!(sequencer.push(&header(Kind::Continuation, Flag::end_headers(), 1)), Ok(()));
+    }
+}
@@ -0,0 +1,272 @@
+use std::collections::VecDeque;
+use std::ops::Deref;
+use std::time::{Duration, Instant};
+
+use {Frame, FrameHeader, Payload, Setting, Settings, SettingIdentifier, HttpError,
+     Kind, Flag, StreamIdentifier};
+use consts::{DEFAULT_HEADER_TABLE_SIZE, DEFAULT_INITIAL_WINDOW_SIZE, DEFAULT_MAX_FRAME_SIZE};
+
+/// The values of every setting for one side of a connection.
+///
+/// Settings that have never been sent have their initial values, and
+/// unlimited settings are `None`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct SettingsState {
+    pub header_table_size: u32,
+    pub enable_push: bool,
+    pub max_concurrent_streams: Option<u32>,
+    pub initial_window_size: u32,
+    pub max_frame_size: u32,
+    pub max_header_list_size: Option<u32>,
+    pub enable_connect_protocol: bool
+}
+
+impl SettingsState {
+    /// Validate and then apply every setting, leaving this state
+    /// untouched if any is invalid.
+    ///
+    /// SETTINGS_ENABLE_CONNECT_PROTOCOL cannot be turned off once it is
+    /// on, as RFC 8441 requires.
+    pub fn apply(&mut self, settings: &Settings) -> Result<(), HttpError> {
+        try!(settings.validate());
+
+        if self.enable_connect_protocol && settings.iter().any(|setting| {
+            setting.identifier() == Some(SettingIdentifier::EnableConnectProtocol) &&
+                setting.value() == 0
+        }) {
+            return Err(HttpError::Protocol)
+        }
+
+        for setting in settings.iter() {
+            let value = setting.value();
+
+            match setting.identifier() {
+                Some(SettingIdentifier::HeaderTableSize) => self.header_table_size = value,
+                Some(SettingIdentifier::EnablePush) => self.enable_push = value == 1,
+                Some(SettingIdentifier::MaxConcurrentStreams) =>
+                    self.max_concurrent_streams = Some(value),
+                Some(SettingIdentifier::InitialWindowSize) => self.initial_window_size = value,
+                Some(SettingIdentifier::MaxFrameSize) => self.max_frame_size = value,
+                Some(SettingIdentifier::MaxHeaderListSize) =>
+                    self.max_header_list_size = Some(value),
+                Some(SettingIdentifier::EnableConnectProtocol) =>
+                    self.enable_connect_protocol = value == 1,
+                None => {}
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for SettingsState {
+    fn default() -> SettingsState {
+        SettingsState {
+            header_table_size: DEFAULT_HEADER_TABLE_SIZE,
+            enable_push: true,
+            max_concurrent_streams: None,
+            initial_window_size: DEFAULT_INITIAL_WINDOW_SIZE,
+            max_frame_size: DEFAULT_MAX_FRAME_SIZE,
+            max_header_list_size: None,
+            enable_connect_protocol: false
+        }
+    }
+}
+
+/// Keeps both sides' settings in sync with the SETTINGS frames exchanged.
+///
+/// Our settings only take effect once the peer acknowledges them, while
+/// the peer's take effect as soon as they arrive and must be acknowledged.
+#[derive(Clone, Debug, Default)]
+pub struct SettingsSync {
+    state: SyncState<VecDeque<(Vec<Setting>, Instant)>>
+}
+
+impl SettingsSync {
+    pub fn new() -> SettingsSync {
+        SettingsSync::default()
+    }
+
+    /// Our settings, as acknowledged by the peer.
+    pub fn local(&self) -> &SettingsState {
+        &self.state.local
+    }
+
+    /// The peer's settings, which we must abide by.
+    pub fn remote(&self) -> &SettingsState {
+        &self.state.remote
+    }
+
+    /// How many of our SETTINGS frames are awaiting acknowledgement.
+    pub fn pending(&self) -> usize {
+        self.state.pending.len()
+    }
+
+    /// Record `settings` as sent at `now`, returning the SETTINGS frame
+    /// to send.
+    pub fn send<'a>(&mut self, settings: &'a [Setting], now: Instant) -> Frame<'a> {
+        self.state.pending.push_back((settings.to_vec(), now));
+        settings_frame(settings)
+    }
+
+    /// Process a frame received from the peer.
+    ///
+    /// An acknowledgement applies our oldest pending settings, and the
+    /// peer's settings are applied immediately, returning the ACK frame
+    /// to send. Other frames are ignored.
+    pub fn receive(&mut self, frame: &Frame) -> Result<Option<Frame<'static>>, HttpError> {
+        self.state.receive(frame)
+    }
+
+    /// Check whether the peer has taken longer than `timeout` to
+    /// acknowledge our settings, which should close the connection with
+    /// a GOAWAY carrying the returned `HttpError::SettingsTimeout`.
+    pub fn check_timeout(&self, now: Instant, timeout: Duration) -> Result<(), HttpError> {
+        self.state.check_timeout(now, timeout)
+    }
+}
+
+/// Our SETTINGS frames awaiting acknowledgement, oldest first, with the
+/// times they were sent, however they are stored.
+pub trait PendingSettings {
+    type Saved: Deref<Target = [Setting]>;
+
+    fn pop_oldest(&mut self) -> Option<(Self::Saved, Instant)>;
+
+    fn oldest_sent(&self) -> Option<Instant>;
+}
+
+impl PendingSettings for VecDeque<(Vec<Setting>, Instant)> {
+    type Saved = Vec<Setting>;
+
+    fn pop_oldest(&mut self) -> Option<(Vec<Setting>, Instant)> {
+        self.pop_front()
+    }
+
+    fn oldest_sent(&self) -> Option<Instant> {
+        self.front().map(|&(_, sent)| sent)
+    }
+}
+
+/// The state of a settings sync, shared by `SettingsSync` and
+/// `FixedSettingsSync`.
+#[derive(Clone, Debug, Default)]
+pub struct SyncState<Q> {
+    pub local: SettingsState,
+    pub remote: SettingsState,
+    pub pending: Q
+}
+
+impl<Q: PendingSettings> SyncState<Q> {
+    pub fn receive(&mut self, frame: &Frame) -> Result<Option<Frame<'static>>, HttpError> {
+        let settings = match frame.payload {
+            Payload::Settings(settings) => settings,
+            _ => return Ok(None)
+        };
+
+        if frame.is_settings_ack() {
+            let (settings, _) = try!(self.pending.pop_oldest().ok_or(HttpError::Protocol));
+            try!(self.local.apply(&Settings::new(&settings)));
+            Ok(None)
+        } else {
+            try!(self.remote.apply(&settings));
+            Ok(Some(Frame::settings_ack()))
+        }
+    }
+
+    pub fn check_timeout(&self, now: Instant, timeout: Duration) -> Result<(), HttpError> {
+        match self.pending.oldest_sent() {
+            Some(sent) if now.duration_since(sent) > timeout => Err(HttpError::SettingsTimeout),
+            _ => Ok(())
+        }
+    }
+}
+
+/// The SETTINGS frame carrying `settings`.
+pub fn settings_frame<'a>(settings: &'a [Setting]) -> Frame<'a> {
+    let payload = Payload::Settings(Settings::new(settings));
+    Frame {
+        header: FrameHeader {
+            length: payload.encoded_len() as u32,
+            kind: Kind::Settings,
+            flag: Flag::empty(),
+            id: StreamIdentifier(0)
+        },
+        payload: payload
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::{Duration, Instant};
+
+    use {Frame, Setting, Settings, SettingIdentifier, HttpError};
+    use super::{SettingsSync, SettingsState};
+
+    #[test]
+    fn test_local_settings_apply_on_ack() {
+        let mut sync = SettingsSync::new();
+        let now = Instant::now();
+
+        let settings = [Setting::new(SettingIdentifier::InitialWindowSize, 1000)];
+        let frame = sync.send(&settings, now);
+        assert_eq!(frame.header.length, 6);
+        assert_eq!(sync.pending(), 1);
+        assert_eq!(sync.local().initial_window_size, 65535);
+
+        assert_eq!(sync.receive(&Frame::settings_ack()), Ok(None));
+        assert_eq!(sync.pending(), 0);
+        assert_eq!(sync.local().initial_window_size, 1000);
+
+        assert_eq!(sync.receive(&Frame::settings_ack()), Err(HttpError::Protocol));
+    }
+
+    #[test]
+    fn test_remote_settings_apply_immediately() {
+        let mut sync = SettingsSync::new();
+        let mut peer = SettingsSync::new();
+
+        let settings = [Setting::new(SettingIdentifier::EnablePush, 0),
+                        Setting::new(SettingIdentifier::MaxConcurrentStreams, 10)];
+        let frame = peer.send(&settings, Instant::now());
+
+        assert_eq!(sync.receive(&frame), Ok(Some(Frame::settings_ack())));
+        assert_eq!(*sync.remote(), SettingsState {
+            enable_push: false,
+            max_concurrent_streams: Some(10),
+            ..SettingsState::default()
+        });
+
+        let bad = [Setting::new(SettingIdentifier::MaxConcurrentStreams, 1),
+                   Setting::new(SettingIdentifier::EnablePush, 2)];
+        assert_eq!(sync.receive(&peer.send(&bad, Instant::now())), Err(HttpError::Protocol));
+        assert_eq!(sync.remote().max_concurrent_streams, Some(10));
+    }
+
+    #[test]
+    fn test_connect_protocol_stays_enabled() {
+        let mut state = SettingsState::default();
+        let enable = [Setting::new(SettingIdentifier::EnableConnectProtocol, 1)];
+        let disable = [Setting::new(SettingIdentifier::EnableConnectProtocol, 0)];
+
+        assert_eq!(state.apply(&Settings::new(&disable)), Ok(()));
+        assert_eq!(state.apply(&Settings::new(&enable)), Ok(()));
+        assert!(state.enable_connect_protocol);
+        assert_eq!(state.apply(&Settings::new(&disable)), Err(HttpError::Protocol));
+        assert!(state.enable_connect_protocol);
+    }
+
+    #[test]
+    fn test_settings_timeout() {
+        let mut sync = SettingsSync::new();
+        let now = Instant::now();
+        let timeout = Duration::from_secs(5);
+
+        assert_eq!(sync.check_timeout(now + Duration::from_secs(60), timeout), Ok(()));
+
+        sync.send(&[], now);
+        assert_eq!(sync.check_timeout(now + Duration::from_secs(1), timeout), Ok(()));
+        assert_eq!(sync.check_timeout(now + Duration::from_secs(6), timeout),
+                   Err(HttpError::SettingsTimeout));
+    }
+}
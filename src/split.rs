@@ -0,0 +1,358 @@
+use std::cmp;
+
+use {Frame, FrameHeader, Payload, Priority, Settings, StreamIdentifier, ErrorCode,
+     SizeIncrement, Error, Kind, Flag, OwnedFrame, OwnedPayload, FRAME_HEADER_BYTES};
+use payload::PRIORITY_BYTES;
+
+/// Bytes which may be split in two, as when they wrap around the end of
+/// a ring buffer.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Split<'a> {
+    pub head: &'a [u8],
+    pub tail: &'a [u8]
+}
+
+impl<'a> Split<'a> {
+    pub fn new(head: &'a [u8], tail: &'a [u8]) -> Split<'a> {
+        Split { head: head, tail: tail }
+    }
+
+    pub fn len(&self) -> usize {
+        self.head.len() + self.tail.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The bytes as one slice, if they do not cross the split.
+    pub fn contiguous(&self) -> Option<&'a [u8]> {
+        match (self.head.is_empty(), self.tail.is_empty()) {
+            (_, true) => Some(self.head),
+            (true, false) => Some(self.tail),
+            (false, false) => None
+        }
+    }
+
+    pub fn to_vec(&self) -> Vec<u8> {
+        let mut buf = self.head.to_vec();
+        buf.extend_from_slice(self.tail);
+        buf
+    }
+
+    /// Split into the first `at` bytes and the rest.
+    ///
+    /// Panics if `at` is greater than `len`.
+    pub fn split_at(&self, at: usize) -> (Split<'a>, Split<'a>) {
+        if at <= self.head.len() {
+            (Split::new(&self.head[..at], &[]), Split::new(&self.head[at..], self.tail))
+        } else {
+            let (first, rest) = self.tail.split_at(at - self.head.len());
+            (Split::new(self.head, first), Split::new(rest, &[]))
+        }
+    }
+
+    // Copy the first `buf.len()` bytes into `buf`.
+    fn copy_to(&self, buf: &mut [u8]) {
+        let from_head = cmp::min(buf.len(), self.head.len());
+        buf[..from_head].copy_from_slice(&self.head[..from_head]);
+        let rest = buf.len() - from_head;
+        buf[from_head..].copy_from_slice(&self.tail[..rest]);
+    }
+}
+
+/// A `Frame` whose payload may be split in two.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct SplitFrame<'a> {
+    pub header: FrameHeader,
+    pub payload: SplitPayload<'a>
+}
+
+/// A `Payload` whose data may be split in two.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum SplitPayload<'a> {
+    Data {
+        data: Split<'a>
+    },
+    Headers {
+        priority: Option<Priority>,
+        block: Split<'a>
+    },
+    Priority(Priority),
+    Reset(ErrorCode),
+    /// The raw SETTINGS payload.
+    Settings(Split<'a>),
+    PushPromise {
+        promised: StreamIdentifier,
+        block: Split<'a>
+    },
+    Ping(u64),
+    GoAway {
+        last: StreamIdentifier,
+        error: ErrorCode,
+        data: Split<'a>
+    },
+    WindowUpdate(SizeIncrement),
+    Continuation(Split<'a>),
+    Unregistered {
+        kind: u8,
+        data: Split<'a>
+    }
+}
+
+impl FrameHeader {
+    /// Parse a frame header which may be split between `head` and `tail`.
+    pub fn parse_split(head: &[u8], tail: &[u8]) -> Result<FrameHeader, Error> {
+        let split = Split::new(head, tail);
+        if split.len() < FRAME_HEADER_BYTES {
+            return Err(Error::Short(FRAME_HEADER_BYTES - split.len()))
+        }
+
+        let mut buf = [0; FRAME_HEADER_BYTES];
+        split.copy_to(&mut buf);
+        FrameHeader::parse(&buf)
+    }
+}
+
+impl<'a> Frame<'a> {
+    /// Parse a payload which may be split between `head` and `tail`,
+    /// without copying it.
+    ///
+    /// The same errors as `Frame::parse` are produced.
+    pub fn parse_split(header: FrameHeader, head: &'a [u8], tail: &'a [u8])
+                       -> Result<SplitFrame<'a>, Error> {
+        Ok(SplitFrame {
+            header: header,
+            payload: try!(SplitPayload::parse(header, Split::new(head, tail)))
+        })
+    }
+}
+
+impl<'a> SplitFrame<'a> {
+    /// This frame as a `Frame`, if none of its data crosses the split.
+    pub fn contiguous(&self) -> Option<Frame<'a>> {
+        self.payload.contiguous().map(|payload| Frame { header: self.header, payload: payload })
+    }
+}
+
+impl<'a> SplitPayload<'a> {
+    pub fn parse(header: FrameHeader, buf: Split<'a>) -> Result<SplitPayload<'a>, Error> {
+        let length = header.length as usize;
+        if buf.len() < length {
+            return Err(Error::Short(length - buf.len()))
+        }
+        let buf = buf.split_at(length).0;
+
+        // Flags not defined for the frame's kind must be ignored.
+        let flag = header.flag & Flag::allowed_for(header.kind);
+        let padding = flag.contains(Flag::padded());
+        let priority = flag.contains(Flag::priority());
+        if length < padding as usize + if priority { PRIORITY_BYTES as usize } else { 0 } {
+            return Err(Error::PayloadLengthTooShort)
+        }
+
+        // Copy only the fixed-size fields, and parse them as `Payload` would.
+        let buf = try!(trim_padding(padding, buf));
+        let fields_length = cmp::min(buf.len(), fields_length(header.kind, priority));
+        let mut fields = [0; 8];
+        buf.copy_to(&mut fields[..fields_length]);
+        let rest = buf.split_at(fields_length).1;
+
+        let unpadded = FrameHeader {
+            length: buf.len() as u32,
+            flag: flag - Flag::padded(),
+            ..header
+        };
+        Ok(match try!(::payload::parse_fields(unpadded, &fields[..fields_length])) {
+            Payload::Data { .. } => SplitPayload::Data { data: rest },
+            Payload::Headers { priority, .. } =>
+                SplitPayload::Headers { priority: priority, block: rest },
+            Payload::Priority(priority) => SplitPayload::Priority(priority),
+            Payload::Reset(error) => SplitPayload::Reset(error),
+            Payload::Settings(_) => SplitPayload::Settings(rest),
+            Payload::PushPromise { promised, .. } =>
+                SplitPayload::PushPromise { promised: promised, block: rest },
+            Payload::Ping(data) => SplitPayload::Ping(data),
+            Payload::GoAway { last, error, .. } =>
+                SplitPayload::GoAway { last: last, error: error, data: rest },
+            Payload::WindowUpdate(increment) => SplitPayload::WindowUpdate(increment),
+            Payload::Continuation(_) => SplitPayload::Continuation(rest),
+            Payload::Unregistered { kind, .. } =>
+                SplitPayload::Unregistered { kind: kind, data: rest }
+        })
+    }
+
+    /// This payload as a `Payload`, if none of its data crosses the split.
+    pub fn contiguous(&self) -> Option<Payload<'a>> {
+        match *self {
+            SplitPayload::Data { data } =>
+                data.contiguous().map(|data| Payload::Data { data: data }),
+            SplitPayload::Headers { priority, block } => block.contiguous()
+                .map(|block| Payload::Headers { priority: priority, block: block }),
+            SplitPayload::Priority(priority) => Some(Payload::Priority(priority)),
+            SplitPayload::Reset(error) => Some(Payload::Reset(error)),
+            SplitPayload::Settings(raw) => raw.contiguous()
+                .map(|raw| Payload::Settings(Settings::parse(raw).unwrap())),
+            SplitPayload::PushPromise { promised, block } => block.contiguous()
+                .map(|block| Payload::PushPromise { promised: promised, block: block }),
+            SplitPayload::Ping(data) => Some(Payload::Ping(data)),
+            SplitPayload::GoAway { last, error, data } => data.contiguous()
+                .map(|data| Payload::GoAway { last: last, error: error, data: data }),
+            SplitPayload::WindowUpdate(increment) => Some(Payload::WindowUpdate(increment)),
+            SplitPayload::Continuation(block) => block.contiguous().map(Payload::Continuation),
+            SplitPayload::Unregistered { kind, data } => data.contiguous()
+                .map(|data| Payload::Unregistered { kind: kind, data: data })
+        }
+    }
+}
+
+impl<'a> From<SplitPayload<'a>> for OwnedPayload {
+    fn from(payload: SplitPayload<'a>) -> OwnedPayload {
+        match payload {
+            SplitPayload::Data { data } => OwnedPayload::Data { data: data.to_vec() },
+            SplitPayload::Headers { priority, block } =>
+                OwnedPayload::Headers { priority: priority, block: block.to_vec() },
+            SplitPayload::Priority(priority) => OwnedPayload::Priority(priority),
+            SplitPayload::Reset(error) => OwnedPayload::Reset(error),
+            SplitPayload::Settings(raw) => {
+                let raw = raw.to_vec();
+                OwnedPayload::Settings(Settings::parse(&raw).unwrap().iter().collect())
+            },
+            SplitPayload::PushPromise { promised, block } =>
+                OwnedPayload::PushPromise { promised: promised, block: block.to_vec() },
+            SplitPayload::Ping(data) => OwnedPayload::Ping(data),
+            SplitPayload::GoAway { last, error, data } =>
+                OwnedPayload::GoAway { last: last, error: error, data: data.to_vec() },
+            SplitPayload::WindowUpdate(increment) => OwnedPayload::WindowUpdate(increment),
+            SplitPayload::Continuation(block) => OwnedPayload::Continuation(block.to_vec()),
+            SplitPayload::Unregistered { kind, data } =>
+                OwnedPayload::Unregistered { kind: kind, data: data.to_vec() }
+        }
+    }
+}
+
+impl<'a> From<SplitFrame<'a>> for OwnedFrame {
+    fn from(frame: SplitFrame<'a>) -> OwnedFrame {
+        OwnedFrame {
+            header: frame.header,
+            payload: frame.payload.into()
+        }
+    }
+}
+
+// The length of the fixed-size fields at the start of a payload, after any
+// pad length.
+fn fields_length(kind: Kind, priority: bool) -> usize {
+    match kind {
+        Kind::Headers if priority => PRIORITY_BYTES as usize,
+        Kind::Priority => PRIORITY_BYTES as usize,
+        Kind::Reset | Kind::WindowUpdate | Kind::PushPromise => 4,
+        Kind::Ping | Kind::GoAway => 8,
+        _ => 0
+    }
+}
+
+// As `trim_padding` in the payload module.
+fn trim_padding(padding: bool, buf: Split) -> Result<Split, Error> {
+    if !padding {
+        return Ok(buf)
+    }
+
+    let length = buf.len();
+    if length == 0 {
+        return Err(Error::PayloadLengthTooShort)
+    }
+
+    let mut pad_length = [0];
+    buf.copy_to(&mut pad_length);
+    if pad_length[0] as usize >= length {
+        Err(Error::TooMuchPadding(pad_length[0]))
+    } else {
+        Ok(buf.split_at(length - pad_length[0] as usize).0.split_at(1).1)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use {Frame, FrameHeader, Payload, Priority, StreamIdentifier, Flag, Kind, HttpError,
+         OwnedFrame, Error};
+    use super::Split;
+
+    fn encode(frame: Frame) -> Vec<u8> {
+        let mut buf = vec![0; frame.encoded_len()];
+        frame.encode(&mut buf);
+        buf
+    }
+
+    #[test]
+    fn test_parse_split_at_every_offset() {
+        let mut padded = encode(Frame {
+            header: FrameHeader {
+                length: 3,
+                kind: Kind::Headers,
+                flag: Flag::end_headers() | Flag::priority(),
+                id: StreamIdentifier(1)
+            },
+            payload: Payload::Headers {
+                priority: Some(Priority::new(true, StreamIdentifier(3), 7)),
+                block: &[0x82, 0x86, 0x84]
+            }
+        });
+        // Add a pad length byte and two bytes of padding.
+        padded[2] = 11;
+        padded[4] |= Flag::padded().bits();
+        padded.insert(9, 2);
+        padded.extend_from_slice(&[0, 0]);
+
+        let buffers = vec![
+            padded,
+            encode(Frame::goaway(StreamIdentifier(1), HttpError::Protocol, Some("bye"))),
+            encode(Frame::ping([1, 2, 3, 4, 5, 6, 7, 8])),
+            ::hex::decode("000006040000000000000100001000").unwrap()
+        ];
+
+        for buf in buffers {
+            let header = FrameHeader::parse(&buf).unwrap();
+            let expected = OwnedFrame::from(Frame::parse(header, &buf[9..]).unwrap());
+
+            for at in 0..buf.len() + 1 {
+                let (head, tail) = buf.split_at(at);
+                let header = FrameHeader::parse_split(head, tail).unwrap();
+
+                let payload = Split::new(head, tail).split_at(9).1;
+                let frame = Frame::parse_split(header, payload.head, payload.tail).unwrap();
+                assert_eq!(OwnedFrame::from(frame), expected);
+
+                if at <= 9 {
+                    assert_eq!(frame.contiguous().map(OwnedFrame::from), Some(expected.clone()));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_split_errors() {
+        let buf = [0x0, 0x0, 0x5, 0x1, 0x8, 0x0, 0x0, 0x0, 0x1, 0x5, 0x0, 0x0, 0x0, 0x0];
+        let header = FrameHeader::parse(&buf).unwrap();
+
+        assert_eq!(FrameHeader::parse_split(&buf[..4], &buf[4..8]), Err(Error::Short(1)));
+        assert_eq!(Frame::parse_split(header, &buf[9..11], &buf[11..]).err(),
+                   Some(Error::TooMuchPadding(5)));
+        assert_eq!(Frame::parse_split(header, &buf[9..11], &[]).err(), Some(Error::Short(3)));
+
+        // PADDED and PRIORITY mean nothing on WINDOW_UPDATE frames.
+        let update = [0x0, 0x0, 0x4, 0x8, 0x28, 0x0, 0x0, 0x0, 0x1, 0x0, 0x0, 0x0, 0x1];
+        let header = FrameHeader::parse(&update).unwrap();
+        assert!(Frame::parse_split(header, &update[9..11], &update[11..]).is_ok());
+
+        // Length errors match those of `Frame::parse`.
+        for buf in &["000007040000000000000100001000ff", "000009060000000000000000000000000000",
+                     "000003030000000001000000"] {
+            let buf = ::hex::decode(buf).unwrap();
+            let header = FrameHeader::parse(&buf).unwrap();
+            let error = Frame::parse(header, &buf[9..]).err();
+            assert!(error.is_some());
+            assert_eq!(Frame::parse_split(header, &buf[9..12], &buf[12..]).err(), error);
+        }
+    }
+}
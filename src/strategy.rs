@@ -0,0 +1,176 @@
+//! `proptest` strategies for generating frames.
+//!
+//! Valid frames follow every rule `Validator` checks with strict flags,
+//! under the default SETTINGS_MAX_FRAME_SIZE. Frames never carry padding.
+
+use proptest::prelude::*;
+use proptest::collection::vec;
+
+use {Kind, Flag, FrameHeader, StreamIdentifier, ErrorCode, SizeIncrement, Priority,
+     Setting, SettingIdentifier, OwnedFrame, OwnedPayload};
+use consts::{MAX_STREAM_ID, MAX_WINDOW_SIZE};
+
+// Keeps generated frames well under the default maximum frame size.
+const MAX_DATA: usize = 256;
+const MAX_SETTINGS: usize = 16;
+
+/// Any valid frame.
+pub fn frame() -> BoxedStrategy<OwnedFrame> {
+    (0..11u8).prop_flat_map(|kind| match kind {
+        10 => (10..=255u8).prop_flat_map(|kind| frame_of_kind(Kind::new(kind))).boxed(),
+        kind => frame_of_kind(Kind::new(kind))
+    }).boxed()
+}
+
+/// Valid frames of one kind.
+pub fn frame_of_kind(kind: Kind) -> BoxedStrategy<OwnedFrame> {
+    let id = match kind {
+        Kind::Settings | Kind::Ping | Kind::GoAway => Just(StreamIdentifier(0)).boxed(),
+        Kind::WindowUpdate | Kind::Unregistered(_) => any_stream_id(),
+        _ => stream_id()
+    };
+
+    (payload(kind), id, any::<u8>()).prop_map(move |(payload, id, bits)| {
+        let mut flag = Flag::new_lenient(bits).0 & Flag::allowed_for(kind)
+            - Flag::padded() - Flag::priority();
+
+        match payload {
+            OwnedPayload::Headers { priority: Some(_), .. } => flag = flag | Flag::priority(),
+            OwnedPayload::Settings(ref settings) if !settings.is_empty() =>
+                flag = flag - Flag::ack(),
+            _ => {}
+        }
+
+        OwnedFrame {
+            header: FrameHeader {
+                length: payload.as_payload().encoded_len() as u32,
+                kind: kind,
+                flag: flag,
+                id: id
+            },
+            payload: payload
+        }
+    }).boxed()
+}
+
+/// Valid payloads of one kind.
+pub fn payload(kind: Kind) -> BoxedStrategy<OwnedPayload> {
+    match kind {
+        Kind::Data => data().prop_map(|data| OwnedPayload::Data { data: data }).boxed(),
+        Kind::Headers => (proptest::option::of(priority()), data())
+            .prop_map(|(priority, block)| OwnedPayload::Headers {
+                priority: priority,
+                block: block
+            }).boxed(),
+        Kind::Priority => priority().prop_map(OwnedPayload::Priority).boxed(),
+        Kind::Reset => any::<u32>()
+            .prop_map(|code| OwnedPayload::Reset(ErrorCode(code))).boxed(),
+        Kind::Settings => vec(setting(), 0..MAX_SETTINGS)
+            .prop_map(OwnedPayload::Settings).boxed(),
+        Kind::PushPromise => (stream_id(), data())
+            .prop_map(|(promised, block)| OwnedPayload::PushPromise {
+                promised: promised,
+                block: block
+            }).boxed(),
+        Kind::Ping => any::<u64>().prop_map(OwnedPayload::Ping).boxed(),
+        Kind::GoAway => (any_stream_id(), any::<u32>(), data())
+            .prop_map(|(last, code, data)| OwnedPayload::GoAway {
+                last: last,
+                error: ErrorCode(code),
+                data: data
+            }).boxed(),
+        Kind::WindowUpdate => (1..=MAX_STREAM_ID)
+            .prop_map(|increment| OwnedPayload::WindowUpdate(SizeIncrement(increment))).boxed(),
+        Kind::Continuation => data().prop_map(OwnedPayload::Continuation).boxed(),
+        Kind::Unregistered(kind) => data()
+            .prop_map(move |data| OwnedPayload::Unregistered { kind: kind, data: data }).boxed()
+    }
+}
+
+/// The encoding of a valid frame with one mutation applied: a flipped
+/// bit, a truncation, extra trailing bytes, a changed length or a changed
+/// stream identifier.
+pub fn mutated_frame() -> BoxedStrategy<Vec<u8>> {
+    (frame(), 0..5u8, any::<usize>(), any::<u32>()).prop_map(|(frame, mutation, at, value)| {
+        let mut buf = vec![0; frame.as_frame().encoded_len()];
+        frame.as_frame().encode(&mut buf);
+
+        let at = at % buf.len();
+        match mutation {
+            0 => buf[at] ^= 1 << (value % 8),
+            1 => buf.truncate(at),
+            2 => buf.extend((0..value % 16).map(|byte| byte as u8)),
+            3 => { ::encode_u24(&mut buf, value & 0xffffff); },
+            _ => { StreamIdentifier(value & MAX_STREAM_ID).encode(&mut buf[5..]); }
+        }
+
+        buf
+    }).boxed()
+}
+
+fn stream_id() -> BoxedStrategy<StreamIdentifier> {
+    (1..=MAX_STREAM_ID).prop_map(StreamIdentifier).boxed()
+}
+
+fn any_stream_id() -> BoxedStrategy<StreamIdentifier> {
+    (0..=MAX_STREAM_ID).prop_map(StreamIdentifier).boxed()
+}
+
+fn data() -> BoxedStrategy<Vec<u8>> {
+    vec(any::<u8>(), 0..MAX_DATA).boxed()
+}
+
+fn priority() -> BoxedStrategy<Priority> {
+    (any::<bool>(), any_stream_id(), any::<u8>())
+        .prop_map(|(exclusive, dependency, weight)| Priority::new(exclusive, dependency, weight))
+        .boxed()
+}
+
+fn setting() -> BoxedStrategy<Setting> {
+    let setting = |identifier| move |value| Setting::new(identifier, value);
+
+    prop_oneof![
+        any::<u32>().prop_map(setting(SettingIdentifier::HeaderTableSize)),
+        (0..=1u32).prop_map(setting(SettingIdentifier::EnablePush)),
+        any::<u32>().prop_map(setting(SettingIdentifier::MaxConcurrentStreams)),
+        (0..=MAX_WINDOW_SIZE).prop_map(setting(SettingIdentifier::InitialWindowSize)),
+        ((1u32 << 14)..=(1 << 24) - 1).prop_map(setting(SettingIdentifier::MaxFrameSize)),
+        any::<u32>().prop_map(setting(SettingIdentifier::MaxHeaderListSize)),
+        (0..=1u32).prop_map(setting(SettingIdentifier::EnableConnectProtocol))
+    ].boxed()
+}
+
+#[cfg(test)]
+mod test {
+    use proptest::prelude::*;
+
+    use {Frame, FrameHeader, Validator, Kind};
+    use super::{frame, frame_of_kind, mutated_frame};
+
+    proptest! {
+        #[test]
+        fn test_frames_are_valid(frame in frame()) {
+            let mut buf = vec![0; frame.as_frame().encoded_len()];
+            frame.as_frame().encode(&mut buf);
+
+            let mut validator = Validator::new();
+            validator.set_strict_flags(true);
+            prop_assert_eq!(validator.validate(&frame.header, &buf[9..]), Ok(()));
+
+            let header = FrameHeader::parse(&buf).unwrap();
+            prop_assert_eq!(Frame::parse(header, &buf[9..]), Ok(frame.as_frame()));
+        }
+
+        #[test]
+        fn test_frames_of_kind(frame in frame_of_kind(Kind::Headers)) {
+            prop_assert_eq!(frame.header.kind, Kind::Headers);
+        }
+
+        #[test]
+        fn test_mutated_frames_parse(buf in mutated_frame()) {
+            if let Ok(header) = FrameHeader::parse(&buf) {
+                let _ = Frame::parse(header, &buf[9..]);
+            }
+        }
+    }
+}
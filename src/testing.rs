@@ -0,0 +1,178 @@
+//! Helpers for testing code that produces frames.
+//!
+//! A `FrameRecorder` collects the frames written by the code under test,
+//! and its `assert_sequence` checks them against a list of
+//! `FrameMatcher`s, each of which only compares the fields it was given:
+//!
+//! ```ignore
+//! recorder.assert_sequence(&[
+//!     FrameMatcher::new().kind(Kind::Settings),
+//!     FrameMatcher::new().kind(Kind::Headers).stream(1).flags(Flag::end_headers()),
+//!     FrameMatcher::new().kind(Kind::Data).stream(1).payload_starts_with(b"hello")
+//! ]);
+//! ```
+
+use std::fmt;
+
+use {Frame, Kind, Flag, StreamIdentifier, OwnedFrame, ParseError, parse_many};
+
+/// Collects frames for later assertions.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FrameRecorder {
+    frames: Vec<OwnedFrame>
+}
+
+impl FrameRecorder {
+    pub fn new() -> FrameRecorder {
+        FrameRecorder::default()
+    }
+
+    /// Record a frame.
+    pub fn record(&mut self, frame: &Frame) {
+        self.frames.push(OwnedFrame::from(*frame));
+    }
+
+    /// Record every complete frame in `buf`, as written to a socket,
+    /// returning the number of bytes used.
+    pub fn record_bytes(&mut self, buf: &[u8]) -> Result<usize, ParseError> {
+        let mut frames = Vec::new();
+        let result = parse_many(buf, &mut frames);
+        self.frames.extend(frames.into_iter().map(OwnedFrame::from));
+        result
+    }
+
+    /// The frames recorded so far.
+    pub fn frames(&self) -> &[OwnedFrame] {
+        &self.frames
+    }
+
+    /// Forget the frames recorded so far.
+    pub fn clear(&mut self) {
+        self.frames.clear();
+    }
+
+    /// Check that the recorded frames match `expected`, one matcher per
+    /// frame, describing the first difference if they do not.
+    pub fn check(&self, expected: &[FrameMatcher]) -> Result<(), String> {
+        for (index, (frame, matcher)) in self.frames.iter().zip(expected).enumerate() {
+            if !matcher.matches(&frame.as_frame()) {
+                return Err(format!("frame {} is `{}`, expected {}",
+                                   index, frame.as_frame(), matcher))
+            }
+        }
+
+        if self.frames.len() != expected.len() {
+            return Err(format!("recorded {} frames, expected {}",
+                               self.frames.len(), expected.len()))
+        }
+
+        Ok(())
+    }
+
+    /// Like `check`, but panicking with the difference.
+    pub fn assert_sequence(&self, expected: &[FrameMatcher]) {
+        if let Err(difference) = self.check(expected) {
+            panic!("{}", difference)
+        }
+    }
+}
+
+/// A pattern for a frame, which matches any frame unless restricted.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct FrameMatcher {
+    kind: Option<Kind>,
+    id: Option<StreamIdentifier>,
+    flags: Option<Flag>,
+    payload: Option<Vec<u8>>
+}
+
+impl FrameMatcher {
+    pub fn new() -> FrameMatcher {
+        FrameMatcher::default()
+    }
+
+    /// Match only frames of this kind.
+    pub fn kind(mut self, kind: Kind) -> FrameMatcher {
+        self.kind = Some(kind);
+        self
+    }
+
+    /// Match only frames on this stream.
+    pub fn stream(mut self, id: u32) -> FrameMatcher {
+        self.id = Some(StreamIdentifier(id));
+        self
+    }
+
+    /// Match only frames with at least these flags set.
+    pub fn flags(mut self, flags: Flag) -> FrameMatcher {
+        self.flags = Some(flags);
+        self
+    }
+
+    /// Match only frames whose encoded payload, without padding, starts
+    /// with `prefix`.
+    pub fn payload_starts_with(mut self, prefix: &[u8]) -> FrameMatcher {
+        self.payload = Some(prefix.to_vec());
+        self
+    }
+
+    pub fn matches(&self, frame: &Frame) -> bool {
+        self.kind.map_or(true, |kind| frame.header.kind == kind) &&
+            self.id.map_or(true, |id| frame.header.id == id) &&
+            self.flags.map_or(true, |flags| frame.header.flag.contains(flags)) &&
+            self.payload.as_ref().map_or(true, |prefix| {
+                let mut payload = vec![0; frame.payload.encoded_len()];
+                frame.payload.encode(&mut payload);
+                payload.starts_with(prefix)
+            })
+    }
+}
+
+impl fmt::Display for FrameMatcher {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.kind {
+            Some(kind) => try!(write!(f, "{}", kind)),
+            None => try!(f.write_str("any frame"))
+        }
+        if let Some(id) = self.id {
+            try!(write!(f, " stream={}", id.0));
+        }
+        if let Some(flags) = self.flags {
+            try!(write!(f, " flags>={:#04x}", flags.bits()));
+        }
+        if let Some(ref prefix) = self.payload {
+            try!(write!(f, " payload={:?}..", prefix));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use {Frame, Kind, Flag, StreamIdentifier, HttpError};
+    use super::{FrameRecorder, FrameMatcher};
+
+    #[test]
+    fn test_assert_sequence() {
+        let mut recorder = FrameRecorder::new();
+        recorder.record(&Frame::settings_ack());
+        recorder.record(&Frame::ping([1, 2, 3, 4, 5, 6, 7, 8]));
+        let goaway = Frame::goaway(StreamIdentifier(3), HttpError::Protocol, None);
+        assert_eq!(recorder.record_bytes(&Vec::from(&goaway)), Ok(17));
+
+        recorder.assert_sequence(&[
+            FrameMatcher::new().kind(Kind::Settings).flags(Flag::ack()),
+            FrameMatcher::new().payload_starts_with(&[1, 2, 3]),
+            FrameMatcher::new().kind(Kind::GoAway).stream(0)
+        ]);
+
+        assert_eq!(recorder.check(&[FrameMatcher::new()]),
+                   Err("recorded 3 frames, expected 1".to_string()));
+        assert_eq!(recorder.check(&[FrameMatcher::new().kind(Kind::Ping).stream(1)]),
+                   Err("frame 0 is `SETTINGS stream=0 len=0 ACK`, \
+                        expected PING stream=1".to_string()));
+
+        recorder.clear();
+        assert_eq!(recorder.check(&[]), Ok(()));
+    }
+}
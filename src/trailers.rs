@@ -0,0 +1,169 @@
+use {FrameHeader, Kind, Flag, HttpError, ErrorScope, ValidationError};
+
+// The progress of the message sent in one direction of a stream.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+struct Message {
+    headers: bool,
+    data: bool,
+    ended: bool
+}
+
+impl Message {
+    fn push(&mut self, header: &FrameHeader) -> Result<(), ValidationError> {
+        let end_stream = header.flag.contains(Flag::end_stream());
+
+        if self.ended {
+            return match header.kind {
+                Kind::Priority | Kind::WindowUpdate | Kind::Reset
+                    | Kind::Continuation => Ok(()),
+                _ => Err(stream_error(header, HttpError::StreamClosed))
+            }
+        }
+
+        match header.kind {
+            Kind::Headers => {
+                // HEADERS after DATA can only be trailers, which end the
+                // stream. Before DATA, further HEADERS may follow
+                // informational responses.
+                if self.data && !end_stream {
+                    return Err(stream_error(header, HttpError::Protocol))
+                }
+
+                self.headers = true;
+            },
+            Kind::Data => {
+                if !self.headers {
+                    return Err(stream_error(header, HttpError::Protocol))
+                }
+
+                self.data = true;
+            },
+            Kind::Reset => self.ended = true,
+            _ => {}
+        }
+
+        if end_stream && (header.kind == Kind::Headers || header.kind == Kind::Data) {
+            self.ended = true;
+        }
+
+        Ok(())
+    }
+}
+
+/// Tracks END_STREAM in both directions of a single stream, and checks
+/// that each message is HEADERS, then DATA, then optionally a trailers
+/// HEADERS frame carrying END_STREAM.
+///
+/// Frames other than PRIORITY, WINDOW_UPDATE, RST_STREAM and CONTINUATION
+/// after END_STREAM are a STREAM_CLOSED stream error. DATA before HEADERS,
+/// and trailers without END_STREAM, are a PROTOCOL_ERROR stream error.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct TrailersTracker {
+    local: Message,
+    remote: Message
+}
+
+impl TrailersTracker {
+    pub fn new() -> TrailersTracker {
+        TrailersTracker::default()
+    }
+
+    /// Check and record a frame sent on this stream.
+    pub fn send(&mut self, header: &FrameHeader) -> Result<(), ValidationError> {
+        try!(self.local.push(header));
+        if header.kind == Kind::Reset { self.remote.ended = true }
+        Ok(())
+    }
+
+    /// Check and record a frame received on this stream.
+    pub fn recv(&mut self, header: &FrameHeader) -> Result<(), ValidationError> {
+        try!(self.remote.push(header));
+        if header.kind == Kind::Reset { self.local.ended = true }
+        Ok(())
+    }
+
+    /// Have we sent END_STREAM?
+    pub fn is_local_ended(&self) -> bool {
+        self.local.ended
+    }
+
+    /// Has the peer sent END_STREAM?
+    pub fn is_remote_ended(&self) -> bool {
+        self.remote.ended
+    }
+
+    /// Have both ends sent END_STREAM, or either reset the stream?
+    pub fn is_closed(&self) -> bool {
+        self.local.ended && self.remote.ended
+    }
+
+    /// Would a HEADERS frame received now be trailers?
+    pub fn expects_trailers(&self) -> bool {
+        self.remote.data && !self.remote.ended
+    }
+}
+
+fn stream_error(header: &FrameHeader, error: HttpError) -> ValidationError {
+    ValidationError { error: error, scope: ErrorScope::Stream(header.id) }
+}
+
+#[cfg(test)]
+mod test {
+    use {FrameHeader, Kind, Flag, StreamIdentifier, HttpError, ErrorScope, ValidationError};
+    use super::TrailersTracker;
+
+    fn header(kind: Kind, flag: Flag) -> FrameHeader {
+        FrameHeader {
+            length: 0,
+            kind: kind,
+            flag: flag,
+            id: StreamIdentifier(1)
+        }
+    }
+
+    fn error(error: HttpError) -> Result<(), ValidationError> {
+        Err(ValidationError { error: error, scope: ErrorScope::Stream(StreamIdentifier(1)) })
+    }
+
+    #[test]
+    fn test_trailers() {
+        let mut tracker = TrailersTracker::new();
+
+        assert_eq!(tracker.recv(&header(Kind::Headers, Flag::empty())), Ok(()));
+        assert_eq!(tracker.recv(&header(Kind::Headers, Flag::empty())), Ok(()));
+        assert!(!tracker.expects_trailers());
+        assert_eq!(tracker.recv(&header(Kind::Data, Flag::empty())), Ok(()));
+        assert!(tracker.expects_trailers());
+        assert_eq!(tracker.recv(&header(Kind::Headers, Flag::empty())),
+                   error(HttpError::Protocol));
+
+        assert_eq!(tracker.recv(&header(Kind::Headers, Flag::end_stream())), Ok(()));
+        assert_eq!(tracker.recv(&header(Kind::Continuation, Flag::end_headers())), Ok(()));
+        assert!(tracker.is_remote_ended());
+        assert!(!tracker.is_closed());
+
+        assert_eq!(tracker.recv(&header(Kind::Data, Flag::empty())),
+                   error(HttpError::StreamClosed));
+        assert_eq!(tracker.recv(&header(Kind::WindowUpdate, Flag::empty())), Ok(()));
+    }
+
+    #[test]
+    fn test_directions() {
+        let mut tracker = TrailersTracker::new();
+
+        assert_eq!(tracker.send(&header(Kind::Data, Flag::empty())), error(HttpError::Protocol));
+        assert_eq!(tracker.send(&header(Kind::Headers, Flag::end_stream())), Ok(()));
+        assert!(tracker.is_local_ended());
+        assert!(!tracker.is_remote_ended());
+
+        assert_eq!(tracker.recv(&header(Kind::Headers, Flag::empty())), Ok(()));
+        assert_eq!(tracker.recv(&header(Kind::Data, Flag::end_stream())), Ok(()));
+        assert!(tracker.is_closed());
+
+        let mut reset = TrailersTracker::new();
+        assert_eq!(reset.recv(&header(Kind::Reset, Flag::empty())), Ok(()));
+        assert!(reset.is_closed());
+        assert_eq!(reset.send(&header(Kind::Headers, Flag::empty())),
+                   error(HttpError::StreamClosed));
+    }
+}
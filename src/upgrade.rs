@@ -0,0 +1,109 @@
+use {Setting, Settings};
+
+static ALPHABET: &'static [u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+/// Errors decoding an `HTTP2-Settings` header value.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum UpgradeError {
+    /// The value was not valid base64url.
+    InvalidBase64,
+
+    /// The decoded payload was not a round multiple of the size of a
+    /// single setting.
+    PartialSettingLength
+}
+
+/// Encode settings as the value of the `HTTP2-Settings` header sent with
+/// an HTTP/1.1 request to upgrade to h2c.
+///
+/// This is the SETTINGS payload in base64url, without trailing `=`.
+pub fn encode_http2_settings(settings: &Settings) -> String {
+    let mut payload = vec![0; settings.encoded_len()];
+    settings.encode(&mut payload);
+
+    let mut value = String::with_capacity((payload.len() * 4 + 2) / 3);
+    for chunk in payload.chunks(3) {
+        let bits = chunk.iter().enumerate().fold(0u32, |bits, (i, &byte)| {
+            bits | (byte as u32) << (16 - 8 * i)
+        });
+
+        for i in 0..chunk.len() + 1 {
+            value.push(ALPHABET[(bits >> (18 - 6 * i) & 0x3f) as usize] as char);
+        }
+    }
+
+    value
+}
+
+/// Decode the value of an `HTTP2-Settings` header into the settings it
+/// carries.
+///
+/// Trailing `=` padding is accepted, although senders must omit it.
+pub fn decode_http2_settings(value: &[u8]) -> Result<Vec<Setting>, UpgradeError> {
+    let end = value.iter().rposition(|&c| c != b'=').map_or(0, |last| last + 1);
+    let value = &value[..end];
+
+    // A single leftover character cannot hold a whole byte.
+    if value.len() % 4 == 1 {
+        return Err(UpgradeError::InvalidBase64)
+    }
+
+    let mut payload = Vec::with_capacity(value.len() * 3 / 4);
+    for chunk in value.chunks(4) {
+        let mut bits = 0u32;
+        for (i, &c) in chunk.iter().enumerate() {
+            let sextet = match ALPHABET.iter().position(|&a| a == c) {
+                Some(sextet) => sextet as u32,
+                None => return Err(UpgradeError::InvalidBase64)
+            };
+            bits |= sextet << (18 - 6 * i);
+        }
+
+        for i in 0..chunk.len() - 1 {
+            payload.push((bits >> (16 - 8 * i)) as u8);
+        }
+    }
+
+    let settings = try!(Settings::parse(&payload)
+                        .map_err(|_| UpgradeError::PartialSettingLength));
+    Ok(settings.iter().collect())
+}
+
+#[cfg(test)]
+mod test {
+    use {Setting, Settings, SettingIdentifier};
+    use super::{encode_http2_settings, decode_http2_settings, UpgradeError};
+
+    #[test]
+    fn test_http2_settings_round_trip() {
+        let settings = [
+            Setting::new(SettingIdentifier::MaxConcurrentStreams, 100),
+            Setting::new(SettingIdentifier::InitialWindowSize, 65535)
+        ];
+
+        let value = encode_http2_settings(&Settings::new(&settings));
+        assert_eq!(value, "AAMAAABkAAQAAP__");
+        assert_eq!(decode_http2_settings(value.as_bytes()), Ok(settings.to_vec()));
+    }
+
+    #[test]
+    fn test_http2_settings_lengths() {
+        let one = [Setting::new(SettingIdentifier::EnablePush, 0)];
+        let value = encode_http2_settings(&Settings::new(&one));
+        assert_eq!(value, "AAIAAAAA");
+        assert_eq!(decode_http2_settings(b"AAIAAAAA"), Ok(one.to_vec()));
+
+        assert_eq!(encode_http2_settings(&Settings::new(&[])), "");
+        assert_eq!(decode_http2_settings(b""), Ok(vec![]));
+    }
+
+    #[test]
+    fn test_bad_http2_settings() {
+        assert_eq!(decode_http2_settings(b"AAMAAABk+AQA"), Err(UpgradeError::InvalidBase64));
+        assert_eq!(decode_http2_settings(b"AAMAA"), Err(UpgradeError::InvalidBase64));
+        assert_eq!(decode_http2_settings(b"AAMA"), Err(UpgradeError::PartialSettingLength));
+        assert_eq!(decode_http2_settings(b"AAMAAABkAA=="), Err(UpgradeError::PartialSettingLength));
+    }
+}
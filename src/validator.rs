@@ -0,0 +1,245 @@
+use {FrameHeader, Kind, Flag, Error, HttpError, ErrorScope, StreamIdentifier};
+use consts::{DEFAULT_MAX_FRAME_SIZE, MAX_STREAM_ID};
+
+use std::cmp;
+
+use byteorder::{BigEndian, ByteOrder};
+
+/// A structural rule violated by a frame, with the error the spec
+/// requires in response.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ValidationError {
+    pub error: HttpError,
+    pub scope: ErrorScope
+}
+
+/// Checks frames against the per-kind rules of RFC 7540, Section 6,
+/// beyond what `Payload::parse` enforces.
+///
+/// This covers stream identifiers, exact and minimum payload lengths,
+/// padding and zero window increments. Undefined flags and non-zero
+/// padding are allowed unless `set_strict_flags` or `set_strict_padding`
+/// is used.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Validator {
+    max_frame_size: u32,
+    strict_flags: bool,
+    strict_padding: bool
+}
+
+impl Validator {
+    pub fn new() -> Validator {
+        Validator {
+            max_frame_size: DEFAULT_MAX_FRAME_SIZE,
+            strict_flags: false,
+            strict_padding: false
+        }
+    }
+
+    /// Set the largest payload accepted, as advertised in our
+    /// SETTINGS_MAX_FRAME_SIZE.
+    pub fn set_max_frame_size(&mut self, size: u32) {
+        self.max_frame_size = size;
+    }
+
+    /// Reject flags that are not defined for the frame kind.
+    pub fn set_strict_flags(&mut self, strict: bool) {
+        self.strict_flags = strict;
+    }
+
+    /// Reject padding that is not all zero, which receivers may treat as
+    /// a connection error of type PROTOCOL_ERROR.
+    pub fn set_strict_padding(&mut self, strict: bool) {
+        self.strict_padding = strict;
+    }
+
+    /// Validate a frame from its header and complete payload.
+    pub fn validate(&self, header: &FrameHeader,
+                    payload: &[u8]) -> Result<(), ValidationError> {
+        let connection = |error| Err(ValidationError {
+            error: error,
+            scope: ErrorScope::Connection
+        });
+        let stream = |error| Err(ValidationError {
+            error: error,
+            scope: ErrorScope::Stream(header.id)
+        });
+
+        let kind = header.kind;
+        let length = header.length;
+        let on_zero = header.id == StreamIdentifier(0);
+
+        // Size errors are scoped as when parsing, by `Error::scope`.
+        let size_error = |error: Error| Err(ValidationError {
+            error: HttpError::FrameSizeError,
+            scope: error.scope(kind, header.id)
+        });
+
+        if length > self.max_frame_size {
+            return size_error(Error::FrameTooLarge(length))
+        }
+
+        if self.strict_flags && header.validate_flags().is_err() {
+            return connection(HttpError::Protocol)
+        }
+
+        if header.validate_stream_id().is_err() {
+            return connection(HttpError::Protocol)
+        }
+
+        if kind.is_connection_level() && !on_zero {
+            return connection(HttpError::Protocol)
+        }
+
+        if let Err(error) = header.validate() {
+            return size_error(error)
+        }
+
+        let padded = kind.allows_padding() && header.flag.contains(Flag::padded());
+        let minimum = header.min_length();
+
+        if padded {
+            let pad_length = payload.get(0).cloned().unwrap_or(0) as u32;
+            if pad_length > length - minimum {
+                return connection(HttpError::Protocol)
+            }
+
+            let end = cmp::min(length as usize, payload.len());
+            let start = end.saturating_sub(pad_length as usize);
+            if self.strict_padding && payload[start..end].iter().any(|&b| b != 0) {
+                return connection(HttpError::Protocol)
+            }
+        }
+
+        if kind == Kind::WindowUpdate && payload.len() >= 4 &&
+           BigEndian::read_u32(payload) & MAX_STREAM_ID == 0 {
+            return if on_zero {
+                connection(HttpError::Protocol)
+            } else {
+                stream(HttpError::Protocol)
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for Validator {
+    fn default() -> Validator {
+        Validator::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {FrameHeader, Kind, Flag, HttpError, ErrorScope, StreamIdentifier};
+    use super::{Validator, ValidationError};
+
+    fn header(kind: Kind, flag: Flag, id: u32, length: u32) -> FrameHeader {
+        FrameHeader {
+            length: length,
+            kind: kind,
+            flag: flag,
+            id: StreamIdentifier(id)
+        }
+    }
+
+    fn connection(error: HttpError) -> Result<(), ValidationError> {
+        Err(ValidationError { error: error, scope: ErrorScope::Connection })
+    }
+
+    fn stream(error: HttpError, id: u32) -> Result<(), ValidationError> {
+        Err(ValidationError { error: error, scope: ErrorScope::Stream(StreamIdentifier(id)) })
+    }
+
+    #[test]
+    fn test_stream_identifiers() {
+        let validator = Validator::new();
+
+        assert_eq!(validator.validate(&header(Kind::Data, Flag::empty(), 0, 0), &[]),
+                   connection(HttpError::Protocol));
+        assert_eq!(validator.validate(&header(Kind::Ping, Flag::empty(), 1, 8), &[0; 8]),
+                   connection(HttpError::Protocol));
+        assert_eq!(validator.validate(&header(Kind::Data, Flag::empty(), 1, 0), &[]), Ok(()));
+        assert_eq!(validator.validate(&header(Kind::Settings, Flag::empty(), 0, 0), &[]), Ok(()));
+    }
+
+    #[test]
+    fn test_lengths() {
+        let validator = Validator::new();
+
+        assert_eq!(validator.validate(&header(Kind::Priority, Flag::empty(), 3, 4), &[0; 4]),
+                   stream(HttpError::FrameSizeError, 3));
+        assert_eq!(validator.validate(&header(Kind::Reset, Flag::empty(), 3, 5), &[0; 5]),
+                   connection(HttpError::FrameSizeError));
+        assert_eq!(validator.validate(&header(Kind::Settings, Flag::ack(), 0, 6), &[0; 6]),
+                   connection(HttpError::FrameSizeError));
+        assert_eq!(validator.validate(&header(Kind::Settings, Flag::empty(), 0, 7), &[0; 7]),
+                   connection(HttpError::FrameSizeError));
+        assert_eq!(validator.validate(&header(Kind::GoAway, Flag::empty(), 0, 7), &[0; 7]),
+                   connection(HttpError::FrameSizeError));
+        assert_eq!(validator.validate(&header(Kind::Data, Flag::empty(), 1, 1 << 14), &[]),
+                   Ok(()));
+        assert_eq!(validator.validate(&header(Kind::Data, Flag::empty(), 1, (1 << 14) + 1), &[]),
+                   stream(HttpError::FrameSizeError, 1));
+        assert_eq!(validator.validate(&header(Kind::Headers, Flag::empty(), 1, (1 << 14) + 1), &[]),
+                   connection(HttpError::FrameSizeError));
+        assert_eq!(validator.validate(&header(Kind::Unregistered(0xfa), Flag::empty(), 1,
+                                              (1 << 14) + 1), &[]),
+                   stream(HttpError::FrameSizeError, 1));
+    }
+
+    #[test]
+    fn test_padding() {
+        let validator = Validator::new();
+
+        assert_eq!(validator.validate(&header(Kind::Data, Flag::padded(), 1, 4), &[3, 0, 0, 0]),
+                   Ok(()));
+        assert_eq!(validator.validate(&header(Kind::Data, Flag::padded(), 1, 4), &[4, 0, 0, 0]),
+                   connection(HttpError::Protocol));
+        assert_eq!(validator.validate(&header(Kind::Data, Flag::padded(), 1, 0), &[]),
+                   stream(HttpError::FrameSizeError, 1));
+
+        let flag = Flag::padded() | Flag::priority();
+        assert_eq!(validator.validate(&header(Kind::Headers, flag, 1, 7), &[1; 7]), Ok(()));
+        assert_eq!(validator.validate(&header(Kind::Headers, flag, 1, 7), &[2; 7]),
+                   connection(HttpError::Protocol));
+    }
+
+    #[test]
+    fn test_window_update() {
+        let validator = Validator::new();
+
+        assert_eq!(validator.validate(&header(Kind::WindowUpdate, Flag::empty(), 5, 4), &[0; 4]),
+                   stream(HttpError::Protocol, 5));
+        assert_eq!(validator.validate(&header(Kind::WindowUpdate, Flag::empty(), 0, 4), &[0; 4]),
+                   connection(HttpError::Protocol));
+        assert_eq!(validator.validate(&header(Kind::WindowUpdate, Flag::empty(), 0, 4),
+                                      &[0, 0, 0, 1]),
+                   Ok(()));
+    }
+
+    #[test]
+    fn test_strict_flags() {
+        let mut validator = Validator::new();
+        let ping = header(Kind::Ping, Flag::padded(), 0, 8);
+
+        assert_eq!(validator.validate(&ping, &[0; 8]), Ok(()));
+
+        validator.set_strict_flags(true);
+        assert_eq!(validator.validate(&ping, &[0; 8]), connection(HttpError::Protocol));
+    }
+
+    #[test]
+    fn test_strict_padding() {
+        let mut validator = Validator::new();
+        let data = header(Kind::Data, Flag::padded(), 1, 5);
+
+        assert_eq!(validator.validate(&data, &[2, 9, 9, 0, 1]), Ok(()));
+
+        validator.set_strict_padding(true);
+        assert_eq!(validator.validate(&data, &[2, 9, 9, 0, 1]), connection(HttpError::Protocol));
+        assert_eq!(validator.validate(&data, &[2, 9, 9, 0, 0]), Ok(()));
+    }
+}
@@ -0,0 +1,143 @@
+//! Known-good encoded frames and their parsed forms, for testing HTTP/2
+//! implementations against.
+//!
+//! Each `Vector` holds a frame as sent on the wire and the frame it should
+//! parse to, written in the language of the `script` module.
+
+use {OwnedFrame, Frame, HexError};
+use script::{self, ScriptError};
+
+/// An encoded frame and the frame it should parse to.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Vector {
+    /// A short, unique name.
+    pub name: &'static str,
+
+    /// Where the frame comes from.
+    pub source: &'static str,
+
+    /// The frame as sent on the wire, in hex.
+    pub hex: &'static str,
+
+    /// The parsed frame, as a `script` description.
+    pub expected: &'static str
+}
+
+impl Vector {
+    /// The frame as sent on the wire.
+    pub fn bytes(&self) -> Vec<u8> {
+        ::hex::decode(self.hex).unwrap()
+    }
+
+    /// Parse the encoded frame.
+    pub fn parse(&self) -> Result<OwnedFrame, HexError> {
+        let mut buf = Vec::new();
+        Frame::from_hex(self.hex, &mut buf).map(OwnedFrame::from)
+    }
+
+    /// The frame the encoded frame should parse to.
+    pub fn expected(&self) -> Result<OwnedFrame, ScriptError> {
+        script::parse_frame(self.expected)
+    }
+}
+
+pub static VECTORS: &'static [Vector] = &[
+    Vector {
+        name: "rfc7541-c3-1",
+        source: "RFC 7541 Appendix C.3.1, in a HEADERS frame",
+        hex: "00001401050000000182868441\
+              0f7777772e6578616d706c652e636f6d",
+        expected: "HEADERS stream=1 end_stream end_headers \
+                   hex=828684410f7777772e6578616d706c652e636f6d"
+    },
+    Vector {
+        name: "rfc7541-c3-2",
+        source: "RFC 7541 Appendix C.3.2, in a HEADERS frame",
+        hex: "00000e010500000003828684be58086e6f2d6361636865",
+        expected: "HEADERS stream=3 end_stream end_headers hex=828684be58086e6f2d6361636865"
+    },
+    Vector {
+        name: "rfc7541-c4-1",
+        source: "RFC 7541 Appendix C.4.1, in a HEADERS frame",
+        hex: "00001101050000000182868441\
+              8cf1e3c2e5f23a6ba0ab90f4ff",
+        expected: "HEADERS stream=1 end_stream end_headers \
+                   hex=828684418cf1e3c2e5f23a6ba0ab90f4ff"
+    },
+    Vector {
+        name: "chrome-settings",
+        source: "The SETTINGS frame Chrome opens connections with",
+        hex: "000018040000000000\
+              000100010000000200000000000400600000000600040000",
+        expected: "SETTINGS HEADER_TABLE_SIZE=65536 ENABLE_PUSH=0 \
+                   INITIAL_WINDOW_SIZE=6291456 MAX_HEADER_LIST_SIZE=262144"
+    },
+    Vector {
+        name: "chrome-window-update",
+        source: "The connection WINDOW_UPDATE Chrome sends after its SETTINGS",
+        hex: "00000408000000000000ef0001",
+        expected: "WINDOW_UPDATE increment=15663105"
+    },
+    Vector {
+        name: "settings-ack",
+        source: "Encoded by hand from RFC 7540 Section 6.5",
+        hex: "000000040100000000",
+        expected: "SETTINGS ack"
+    },
+    Vector {
+        name: "data-end-stream",
+        source: "Encoded by hand from RFC 7540 Section 6.1",
+        hex: "00000500010000000168656c6c6f",
+        expected: "DATA stream=1 end_stream text=hello"
+    },
+    Vector {
+        name: "priority-exclusive",
+        source: "Encoded by hand from RFC 7540 Section 6.3",
+        hex: "000005020000000003800000010f",
+        expected: "PRIORITY stream=3 dependency=1 weight=16 exclusive"
+    },
+    Vector {
+        name: "rst-stream-cancel",
+        source: "Encoded by hand from RFC 7540 Section 6.4",
+        hex: "00000403000000000100000008",
+        expected: "RST_STREAM stream=1 error=CANCEL"
+    },
+    Vector {
+        name: "push-promise",
+        source: "Encoded by hand from RFC 7540 Section 6.6",
+        hex: "0000050504000000010000000282",
+        expected: "PUSH_PROMISE stream=1 end_headers promised=2 hex=82"
+    },
+    Vector {
+        name: "ping",
+        source: "Encoded by hand from RFC 7540 Section 6.7",
+        hex: "0000080600000000000102030405060708",
+        expected: "PING hex=0102030405060708"
+    },
+    Vector {
+        name: "goaway-protocol-error",
+        source: "Encoded by hand from RFC 7540 Section 6.8",
+        hex: "0000080700000000000000000000000001",
+        expected: "GOAWAY last=0 error=PROTOCOL_ERROR"
+    }
+];
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashSet;
+
+    use super::VECTORS;
+
+    #[test]
+    fn test_vectors() {
+        let mut names = HashSet::new();
+
+        for vector in VECTORS {
+            assert!(names.insert(vector.name), "duplicate vector {}", vector.name);
+
+            let frame = vector.parse().unwrap();
+            assert_eq!(frame, vector.expected().unwrap(), "{}", vector.name);
+            assert_eq!(frame.to_hex(), ::hex::encode(&vector.bytes()), "{}", vector.name);
+        }
+    }
+}
@@ -0,0 +1,441 @@
+use std::cmp;
+
+use {FrameHeader, Payload, Priority, Setting, StreamIdentifier, ErrorCode, SizeIncrement,
+     ParseError, ParserOptions, Error, Kind, Flag, FRAME_HEADER_BYTES};
+use frame::parse_error;
+
+/// Callbacks for the parts of each frame, as they are parsed by `visit`.
+///
+/// Every method does nothing by default, so a visitor only implements the
+/// ones it cares about. Payloads are only parsed if `on_header` asks for
+/// them.
+pub trait FrameVisitor {
+    /// Called first for every frame. Return `false` to skip the payload,
+    /// which is then neither parsed nor checked.
+    fn on_header(&mut self, _header: &FrameHeader) -> bool { true }
+
+    /// Bytes of a DATA frame's body, with any padding removed.
+    ///
+    /// `visit` passes the whole body at once, but `EventParser` may split
+    /// it into several chunks as it arrives.
+    fn on_data_chunk(&mut self, _header: &FrameHeader, _chunk: &[u8]) {}
+
+    /// The header block fragment of a HEADERS, PUSH_PROMISE or
+    /// CONTINUATION frame.
+    fn on_header_block(&mut self, _header: &FrameHeader, _fragment: &[u8]) {}
+
+    /// The priority of a PRIORITY frame, or of a HEADERS frame with one.
+    fn on_priority(&mut self, _id: StreamIdentifier, _priority: Priority) {}
+
+    /// Each setting of a SETTINGS frame, in order.
+    fn on_settings_entry(&mut self, _setting: Setting) {}
+
+    fn on_reset(&mut self, _id: StreamIdentifier, _error: ErrorCode) {}
+
+    fn on_push_promise(&mut self, _id: StreamIdentifier, _promised: StreamIdentifier) {}
+
+    /// The opaque data of a PING frame, and whether it was an ACK.
+    fn on_ping(&mut self, _data: u64, _ack: bool) {}
+
+    fn on_goaway(&mut self, _last: StreamIdentifier, _error: ErrorCode, _debug: &[u8]) {}
+
+    fn on_window_update(&mut self, _id: StreamIdentifier, _increment: SizeIncrement) {}
+
+    /// The payload of a frame type not defined by RFC 7540.
+    fn on_unregistered(&mut self, _header: &FrameHeader, _data: &[u8]) {}
+
+    /// Called last for every frame, whether or not its payload was visited.
+    fn on_frame_end(&mut self, _header: &FrameHeader) {}
+}
+
+/// Visit every complete frame at the start of `buf`, returning the number
+/// of bytes consumed.
+///
+/// Like `parse_many`, a trailing partial frame is left unconsumed, and on
+/// error the frames before the bad one have already been visited.
+pub fn visit<V: FrameVisitor>(buf: &[u8], visitor: &mut V) -> Result<usize, ParseError> {
+    let mut offset = 0;
+
+    while let Ok(header) = FrameHeader::parse(&buf[offset..]) {
+        let end = offset + FRAME_HEADER_BYTES + header.length as usize;
+        if buf.len() < end { break }
+
+        let payload = &buf[offset + FRAME_HEADER_BYTES..end];
+        try!(visit_frame(header, payload, &ParserOptions::new(), visitor)
+             .map_err(|error| parse_error(error, header, offset, payload)));
+        offset = end;
+    }
+
+    Ok(offset)
+}
+
+/// A parser that is fed a byte stream in arbitrary pieces and reports its
+/// frames to a `FrameVisitor`.
+///
+/// Other frames are buffered until they are complete, but the body of a
+/// DATA frame is passed to `on_data_chunk` as soon as it arrives, so large
+/// DATA frames are never held in memory. Frame lengths are checked with
+/// `FrameHeader::validate` as soon as the header arrives, along with the
+/// header checks of the parser's `ParserOptions`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct EventParser {
+    buf: Vec<u8>,
+    data: Option<DataState>,
+    position: usize,
+    options: ParserOptions
+}
+
+// The rest of the DATA frame being streamed.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+struct DataState {
+    header: FrameHeader,
+    position: usize,
+    body: usize,
+    padding: usize,
+    visit: bool
+}
+
+impl EventParser {
+    pub fn new() -> EventParser {
+        EventParser::default()
+    }
+
+    /// A parser making the checks turned on in `options`.
+    pub fn with_options(options: ParserOptions) -> EventParser {
+        EventParser { options: options, ..EventParser::default() }
+    }
+
+    /// Parse the next bytes of the stream, visiting every frame or part of
+    /// a DATA body they complete.
+    ///
+    /// The offsets of errors are from the start of the stream. After an
+    /// error the state of the parser is unspecified.
+    pub fn feed<V: FrameVisitor>(&mut self, buf: &[u8], visitor: &mut V)
+                                 -> Result<(), ParseError> {
+        self.buf.extend_from_slice(buf);
+
+        let mut offset = 0;
+        let result = self.run(&mut offset, visitor);
+        self.buf.drain(..offset);
+        self.position += offset;
+        result
+    }
+
+    /// The number of bytes held for frames that are not yet complete.
+    pub fn buffered(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// Whether the parser is between frames, so the stream may end here.
+    pub fn is_idle(&self) -> bool {
+        self.buf.is_empty() && self.data.is_none()
+    }
+
+    fn run<V: FrameVisitor>(&mut self, offset: &mut usize, visitor: &mut V)
+                            -> Result<(), ParseError> {
+        loop {
+            if let Some(mut data) = self.data {
+                let rest = &self.buf[*offset..];
+                let body = cmp::min(data.body, rest.len());
+                if body > 0 && data.visit {
+                    visitor.on_data_chunk(&data.header, &rest[..body]);
+                }
+                let padding = cmp::min(data.padding, rest.len() - body);
+                let bytes = &rest[body..body + padding];
+                try!(::options::check_padding_bytes(&self.options, bytes).map_err(|error| {
+                    let dirty = bytes.iter().position(|&b| b != 0).unwrap_or(0);
+                    ParseError {
+                        offset: self.position + *offset + body + dirty,
+                        ..parse_error(error, data.header, data.position, &[])
+                    }
+                }));
+
+                *offset += body + padding;
+                data.body -= body;
+                data.padding -= padding;
+
+                if data.body > 0 || data.padding > 0 {
+                    self.data = Some(data);
+                    return Ok(())
+                }
+
+                visitor.on_frame_end(&data.header);
+                self.data = None;
+            }
+
+            let start = *offset;
+            let header = match FrameHeader::parse(&self.buf[start..]) {
+                Ok(header) => header,
+                Err(_) => return Ok(())
+            };
+            let position = self.position + start;
+            try!(header.validate().and_then(|()| self.options.check_header(&header))
+                 .map_err(|error| parse_error(error, header, position, &[])));
+
+            if header.kind == Kind::Data {
+                let padded = header.flag.contains(Flag::padded());
+                let prefix = FRAME_HEADER_BYTES + padded as usize;
+                if self.buf.len() - start < prefix { return Ok(()) }
+
+                let padding = if padded { self.buf[start + FRAME_HEADER_BYTES] } else { 0 };
+                let length = header.length as usize;
+                let visit = visitor.on_header(&header);
+                if visit && padded && padding as usize >= length {
+                    return Err(parse_error(Error::TooMuchPadding(padding), header, position, &[]))
+                }
+
+                // A skipped frame's padding is passed over unchecked, as body.
+                let padding = if visit { padding as usize } else { 0 };
+                self.data = Some(DataState {
+                    header: header,
+                    position: position,
+                    body: length - padded as usize - padding,
+                    padding: padding,
+                    visit: visit
+                });
+                *offset += prefix;
+                continue
+            }
+
+            let end = start + FRAME_HEADER_BYTES + header.length as usize;
+            if self.buf.len() < end { return Ok(()) }
+
+            let payload = &self.buf[start + FRAME_HEADER_BYTES..end];
+            try!(visit_frame(header, payload, &self.options, visitor)
+                 .map_err(|error| parse_error(error, header, position, payload)));
+            *offset = end;
+        }
+    }
+}
+
+// Visit one complete frame, checking its payload only if it is visited.
+fn visit_frame<V: FrameVisitor>(header: FrameHeader, payload: &[u8], options: &ParserOptions,
+                                visitor: &mut V) -> Result<(), Error> {
+    if visitor.on_header(&header) {
+        try!(options.check_padding(&header, payload));
+        visit_payload(&header, try!(Payload::parse(header, payload)), visitor);
+    }
+
+    visitor.on_frame_end(&header);
+    Ok(())
+}
+
+// Dispatch the parts of a parsed payload. `Payload` only borrows the
+// buffer, and settings are decoded one at a time as they are visited.
+fn visit_payload<V: FrameVisitor>(header: &FrameHeader, payload: Payload, visitor: &mut V) {
+    let id = header.id;
+
+    match payload {
+        Payload::Data { data } => visitor.on_data_chunk(header, data),
+        Payload::Headers { priority, block } => {
+            if let Some(priority) = priority {
+                visitor.on_priority(id, priority);
+            }
+            visitor.on_header_block(header, block)
+        },
+        Payload::Priority(priority) => visitor.on_priority(id, priority),
+        Payload::Reset(error) => visitor.on_reset(id, error),
+        Payload::Settings(settings) => {
+            for setting in settings.iter() {
+                visitor.on_settings_entry(setting);
+            }
+        },
+        Payload::PushPromise { promised, block } => {
+            visitor.on_push_promise(id, promised);
+            visitor.on_header_block(header, block)
+        },
+        Payload::Ping(data) => visitor.on_ping(data, header.flag.contains(Flag::ack())),
+        Payload::GoAway { last, error, data } => visitor.on_goaway(last, error, data),
+        Payload::WindowUpdate(increment) => visitor.on_window_update(id, increment),
+        Payload::Continuation(block) => visitor.on_header_block(header, block),
+        Payload::Unregistered { data, .. } => visitor.on_unregistered(header, data)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use {Frame, FrameHeader, Kind, Setting, SettingIdentifier, Settings, Payload, Flag,
+         StreamIdentifier, SizeIncrement, Error};
+    use ParserOptions;
+    use super::{visit, FrameVisitor, EventParser};
+
+    #[derive(Default)]
+    struct Counter {
+        frames: usize,
+        data: usize,
+        settings: Vec<Setting>,
+        updates: u32
+    }
+
+    impl FrameVisitor for Counter {
+        fn on_header(&mut self, header: &FrameHeader) -> bool {
+            header.kind != Kind::Ping
+        }
+
+        fn on_data_chunk(&mut self, _: &FrameHeader, chunk: &[u8]) {
+            self.data += chunk.len();
+        }
+
+        fn on_settings_entry(&mut self, setting: Setting) {
+            self.settings.push(setting);
+        }
+
+        fn on_window_update(&mut self, _: StreamIdentifier, increment: SizeIncrement) {
+            self.updates += increment.0;
+        }
+
+        fn on_frame_end(&mut self, _: &FrameHeader) {
+            self.frames += 1;
+        }
+    }
+
+    fn encode(frames: &[Frame]) -> Vec<u8> {
+        frames.iter().flat_map(Vec::from).collect()
+    }
+
+    #[test]
+    fn test_visit() {
+        let settings = [Setting::new(SettingIdentifier::EnablePush, 0),
+                        Setting::new(SettingIdentifier::MaxFrameSize, 20000)];
+        let frame = |kind, id, payload: Payload<'static>| Frame {
+            header: FrameHeader {
+                length: payload.encoded_len() as u32,
+                kind: kind,
+                flag: Flag::empty(),
+                id: StreamIdentifier(id)
+            },
+            payload: payload
+        };
+        let mut buf = encode(&[
+            Frame {
+                header: FrameHeader {
+                    length: 12,
+                    kind: Kind::Settings,
+                    flag: Flag::empty(),
+                    id: StreamIdentifier(0)
+                },
+                payload: Payload::Settings(Settings::new(&settings))
+            },
+            frame(Kind::Data, 1, Payload::Data { data: b"hello" }),
+            frame(Kind::WindowUpdate, 0, Payload::WindowUpdate(SizeIncrement(100))),
+            frame(Kind::Data, 1, Payload::Data { data: b"world" })
+        ]);
+        let whole = buf.len();
+        buf.extend_from_slice(&[0, 0, 8, 6]);
+
+        let mut counter = Counter::default();
+        assert_eq!(visit(&buf, &mut counter), Ok(whole));
+        assert_eq!(counter.frames, 4);
+        assert_eq!(counter.data, 10);
+        assert_eq!(counter.settings, settings);
+        assert_eq!(counter.updates, 100);
+    }
+
+    #[test]
+    fn test_visit_skips_payloads() {
+        let mut bad_ping = Vec::from(Frame::ping([0; 8]));
+        bad_ping[2] = 7;
+        bad_ping.pop();
+
+        let mut counter = Counter::default();
+        assert_eq!(visit(&bad_ping, &mut counter), Ok(16));
+        assert_eq!(counter.frames, 1);
+
+        let mut bad_update = Vec::from(Frame::ping([0; 8]));
+        bad_update[3] = 0x8;
+        let error = visit(&bad_update, &mut counter).unwrap_err();
+        assert_eq!(error.error, Error::InvalidPayloadLength);
+        assert_eq!(counter.frames, 1);
+    }
+
+    #[derive(Default)]
+    struct Chunks(Vec<Vec<u8>>, usize);
+
+    impl FrameVisitor for Chunks {
+        fn on_data_chunk(&mut self, _: &FrameHeader, chunk: &[u8]) {
+            self.0.push(chunk.to_vec());
+        }
+
+        fn on_frame_end(&mut self, _: &FrameHeader) {
+            self.1 += 1;
+        }
+    }
+
+    #[test]
+    fn test_event_parser_streams_data() {
+        // A padded DATA frame: pad length 2, "hello world", then padding.
+        let mut buf = vec![0, 0, 14, 0, 0x8, 0, 0, 0, 1, 2];
+        buf.extend_from_slice(b"hello world\0\0");
+        buf.extend(Vec::from(Frame::ping([7; 8])));
+
+        let mut parser = EventParser::new();
+        let mut chunks = Chunks::default();
+        parser.feed(&buf[..9], &mut chunks).unwrap();
+        assert_eq!(chunks.0.len(), 0);
+        assert_eq!(parser.buffered(), 9);
+
+        parser.feed(&buf[9..15], &mut chunks).unwrap();
+        assert_eq!(chunks.0, [b"hello".to_vec()]);
+        assert_eq!(parser.buffered(), 0);
+        assert!(!parser.is_idle());
+
+        parser.feed(&buf[15..22], &mut chunks).unwrap();
+        parser.feed(&buf[22..30], &mut chunks).unwrap();
+        assert_eq!(chunks.0, [b"hello".to_vec(), b" world".to_vec()]);
+        assert_eq!(chunks.1, 1);
+        assert_eq!(parser.buffered(), 7);
+
+        parser.feed(&buf[30..], &mut chunks).unwrap();
+        assert_eq!(chunks.1, 2);
+        assert!(parser.is_idle());
+
+        let mut bad = vec![0, 0, 3, 0, 0x8, 0, 0, 0, 1, 3];
+        let error = parser.feed(&bad, &mut chunks).unwrap_err();
+        assert_eq!(error.error, Error::TooMuchPadding(3));
+        assert_eq!((error.frame_offset, error.offset), (buf.len(), buf.len() + 9));
+
+        bad[2] = 0;
+        let error = EventParser::new().feed(&bad, &mut chunks).unwrap_err();
+        assert_eq!(error.error, Error::PayloadLengthTooShort);
+
+        // Never buffer the payload of a frame that cannot be valid.
+        let huge_ping = [0xff, 0xff, 0xff, 0x6, 0, 0, 0, 0, 0];
+        let error = EventParser::new().feed(&huge_ping, &mut chunks).unwrap_err();
+        assert_eq!(error.error, Error::InvalidPayloadLength);
+
+        let mut options = ParserOptions::new();
+        options.set_max_frame_size(1 << 14);
+        options.set_strict_padding(true);
+        let mut parser = EventParser::with_options(options);
+        let huge_data = [0x1, 0x0, 0x0, 0x0, 0, 0, 0, 0, 1];
+        let error = parser.feed(&huge_data, &mut chunks).unwrap_err();
+        assert_eq!(error.error, Error::FrameTooLarge(1 << 16));
+
+        let mut parser = EventParser::with_options(options);
+        let mut dirty = buf[..23].to_vec();
+        dirty[22] = 1;
+        parser.feed(&dirty[..22], &mut chunks).unwrap();
+        let error = parser.feed(&dirty[22..], &mut chunks).unwrap_err();
+        assert_eq!((error.error, error.frame_offset, error.offset),
+                   (Error::NonZeroPadding, 0, 22));
+
+        // Skipped payloads are not checked.
+        let mut skipped = vec![0, 0, 3, 1, 0xc, 0, 0, 0, 1, 1, 0x82, 1];
+        skipped.extend_from_slice(&dirty);
+        skipped.extend_from_slice(&[0, 0, 2, 0, 0x8, 0, 0, 0, 1, 5, 0]);
+        let mut skip = Skip(0);
+        EventParser::with_options(options).feed(&skipped, &mut skip).unwrap();
+        assert_eq!(skip.0, 3);
+        assert!(EventParser::with_options(options).feed(&skipped, &mut chunks).is_err());
+    }
+
+    struct Skip(usize);
+
+    impl FrameVisitor for Skip {
+        fn on_header(&mut self, _: &FrameHeader) -> bool { false }
+
+        fn on_frame_end(&mut self, _: &FrameHeader) {
+            self.0 += 1;
+        }
+    }
+}
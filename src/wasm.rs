@@ -0,0 +1,76 @@
+//! `wasm-bindgen` bindings for tools which decode frames in the browser.
+//!
+//! Errors are thrown as JavaScript strings.
+
+use wasm_bindgen;
+use wasm_bindgen::prelude::*;
+
+use {Frames, PREFACE};
+use script;
+
+/// Describe the frames in hex-encoded bytes, one frame per line, in the
+/// format of `Frame`'s `Display`.
+///
+/// The input may start with the client connection preface, and may use
+/// whitespace and `:` between digits.
+#[wasm_bindgen(js_name = describeHex)]
+pub fn describe_hex(hex: &str) -> Result<String, JsValue> {
+    describe(hex).map_err(|error| JsValue::from_str(&error))
+}
+
+/// Encode the frames described by a `script` as lowercase hex.
+#[wasm_bindgen(js_name = encodeScript)]
+pub fn encode_script(text: &str) -> Result<String, JsValue> {
+    encode(text).map_err(|error| JsValue::from_str(&error))
+}
+
+fn describe(hex: &str) -> Result<String, String> {
+    let buf = try!(::hex::decode(hex).ok_or("invalid hex".to_string()));
+    let mut frames = if buf.starts_with(PREFACE) {
+        Frames::after_preface(&buf).unwrap()
+    } else {
+        Frames::new(&buf)
+    };
+
+    let mut lines = Vec::new();
+    for frame in frames.by_ref() {
+        lines.push(try!(frame.map_err(|error| error.to_string())).to_string());
+    }
+
+    if frames.offset() < buf.len() {
+        lines.push(format!("{} trailing bytes", buf.len() - frames.offset()));
+    }
+
+    Ok(lines.join("\n"))
+}
+
+fn encode(text: &str) -> Result<String, String> {
+    let frames = try!(script::parse_script(text)
+        .map_err(|(line, error)| format!("line {}: {:?}", line, error)));
+
+    Ok(frames.iter().map(|frame| frame.to_hex()).collect())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{describe, encode};
+
+    #[test]
+    fn test_describe() {
+        assert_eq!(describe("000000040100000000 00000806000000000001020304050607 08 00"),
+                   Ok("SETTINGS stream=0 len=0 ACK\n\
+                       PING stream=0 len=8 data=0102030405060708\n\
+                       1 trailing bytes".to_string()));
+        assert_eq!(describe("0"), Err("invalid hex".to_string()));
+        assert_eq!(describe("000002080000000003 0001"),
+                   Err("InvalidPayloadLength at offset 0 in WindowUpdate frame on stream 3"
+                           .to_string()));
+    }
+
+    #[test]
+    fn test_encode() {
+        assert_eq!(encode("SETTINGS ack\nPING hex=0102030405060708"),
+                   Ok("0000000401000000000000080600000000000102030405060708".to_string()));
+        assert!(encode("PANG").unwrap_err().starts_with("line 1: "));
+    }
+}
@@ -0,0 +1,259 @@
+use std::fmt;
+
+use {SizeIncrement, HttpError, consts};
+
+// Windows may never exceed 2^31 - 1.
+const MAX_WINDOW_SIZE: i32 = consts::MAX_WINDOW_SIZE as i32;
+
+const DEFAULT_WINDOW_SIZE: i32 = consts::DEFAULT_INITIAL_WINDOW_SIZE as i32;
+
+/// A flow-control window.
+///
+/// Windows are signed, since reducing SETTINGS_INITIAL_WINDOW_SIZE can
+/// leave a stream owing more than it has. Any change that would take a
+/// window above 2^31 - 1, or consume more than it allows, is a
+/// `HttpError::FlowControlError`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct WindowSize(i32);
+
+impl WindowSize {
+    /// A window of `size` bytes, as from SETTINGS_INITIAL_WINDOW_SIZE.
+    pub fn new(size: u32) -> Result<WindowSize, HttpError> {
+        if size > MAX_WINDOW_SIZE as u32 {
+            Err(HttpError::FlowControlError)
+        } else {
+            Ok(WindowSize(size as i32))
+        }
+    }
+
+    /// The size of the window, which is negative if it is overdrawn.
+    pub fn size(&self) -> i32 {
+        self.0
+    }
+
+    /// How many bytes may be sent, which is 0 if the window is overdrawn.
+    pub fn available(&self) -> u32 {
+        if self.0 < 0 { 0 } else { self.0 as u32 }
+    }
+
+    /// Grow the window by a WINDOW_UPDATE increment, ignoring its
+    /// reserved bit.
+    pub fn checked_add(&self, increment: SizeIncrement) -> Result<WindowSize, HttpError> {
+        self.offset((increment.0 & consts::MAX_STREAM_ID) as i64)
+    }
+
+    /// Grow the window by a WINDOW_UPDATE increment, stopping at 2^31 - 1.
+    pub fn saturating_add(&self, increment: SizeIncrement) -> WindowSize {
+        self.checked_add(increment).unwrap_or(WindowSize(MAX_WINDOW_SIZE))
+    }
+
+    /// Shrink the window by `len` bytes of flow-controlled data, which
+    /// must fit in the available window.
+    pub fn checked_sub(&self, len: u32) -> Result<WindowSize, HttpError> {
+        if len > self.available() {
+            Err(HttpError::FlowControlError)
+        } else {
+            Ok(WindowSize(self.0 - len as i32))
+        }
+    }
+
+    /// Apply a change of SETTINGS_INITIAL_WINDOW_SIZE from `old` to `new`,
+    /// which may leave the window negative.
+    pub fn adjust(&self, old: u32, new: u32) -> Result<WindowSize, HttpError> {
+        self.offset(new as i64 - old as i64)
+    }
+
+    fn offset(&self, delta: i64) -> Result<WindowSize, HttpError> {
+        let size = self.0 as i64 + delta;
+
+        if size > MAX_WINDOW_SIZE as i64 || size < -(MAX_WINDOW_SIZE as i64) {
+            Err(HttpError::FlowControlError)
+        } else {
+            Ok(WindowSize(size as i32))
+        }
+    }
+}
+
+impl Default for WindowSize {
+    fn default() -> WindowSize {
+        WindowSize(DEFAULT_WINDOW_SIZE)
+    }
+}
+
+/// When a `RecvWindow` sends WINDOW_UPDATE for the data released so far.
+pub enum WindowUpdateStrategy {
+    /// Once at least this many bytes have been released.
+    Threshold(u32),
+
+    /// Once at least half of the target window has been released, as most
+    /// implementations do.
+    HalfWindow,
+
+    /// When the closure, given the bytes released and the target window
+    /// size, returns true.
+    Custom(Box<dyn Fn(u32, u32) -> bool + Send + Sync>)
+}
+
+impl WindowUpdateStrategy {
+    pub fn custom<F>(decide: F) -> WindowUpdateStrategy
+    where F: Fn(u32, u32) -> bool + Send + Sync + 'static {
+        WindowUpdateStrategy::Custom(Box::new(decide))
+    }
+
+    /// Whether to send WINDOW_UPDATE with `released` bytes pending, for a
+    /// window of `target` bytes.
+    pub fn should_update(&self, released: u32, target: u32) -> bool {
+        match *self {
+            WindowUpdateStrategy::Threshold(threshold) => released >= threshold,
+            WindowUpdateStrategy::HalfWindow => released >= target / 2,
+            WindowUpdateStrategy::Custom(ref decide) => decide(released, target)
+        }
+    }
+}
+
+impl Default for WindowUpdateStrategy {
+    fn default() -> WindowUpdateStrategy {
+        WindowUpdateStrategy::HalfWindow
+    }
+}
+
+impl fmt::Debug for WindowUpdateStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            WindowUpdateStrategy::Threshold(threshold) =>
+                f.debug_tuple("Threshold").field(&threshold).finish(),
+            WindowUpdateStrategy::HalfWindow => f.write_str("HalfWindow"),
+            WindowUpdateStrategy::Custom(_) => f.write_str("Custom(..)")
+        }
+    }
+}
+
+/// The receiving side of a flow-control window, for a stream or the
+/// connection, deciding when to replenish it with WINDOW_UPDATE.
+///
+/// DATA frames are charged against the window as they arrive, with their
+/// whole payload length including padding. Once the application releases
+/// the bytes it has consumed, the strategy decides when they are returned
+/// to the peer, so WINDOW_UPDATE is not sent for every small read.
+#[derive(Debug)]
+pub struct RecvWindow {
+    window: WindowSize,
+    target: u32,
+    released: u32,
+    strategy: WindowUpdateStrategy
+}
+
+impl RecvWindow {
+    /// A window kept at `target` bytes, which is the size it starts with.
+    pub fn new(target: u32, strategy: WindowUpdateStrategy) -> Result<RecvWindow, HttpError> {
+        Ok(RecvWindow {
+            window: try!(WindowSize::new(target)),
+            target: target,
+            released: 0,
+            strategy: strategy
+        })
+    }
+
+    /// Charge a DATA frame's payload length against the window.
+    pub fn recv(&mut self, len: u32) -> Result<(), HttpError> {
+        self.window = try!(self.window.checked_sub(len));
+        Ok(())
+    }
+
+    /// Release `len` bytes consumed by the application, returning the
+    /// increment of the WINDOW_UPDATE to send, if the strategy says it is
+    /// time to send one.
+    pub fn release(&mut self, len: u32) -> Option<SizeIncrement> {
+        self.released = self.released.saturating_add(len);
+        if self.released == 0 || !self.strategy.should_update(self.released, self.target) {
+            return None
+        }
+
+        let increment = SizeIncrement(self.released);
+        self.window = self.window.saturating_add(increment);
+        self.released = 0;
+        Some(increment)
+    }
+
+    /// Change the size the window is kept at, as when changing our
+    /// SETTINGS_INITIAL_WINDOW_SIZE.
+    pub fn set_target(&mut self, target: u32) -> Result<(), HttpError> {
+        self.window = try!(self.window.adjust(self.target, target));
+        self.target = target;
+        Ok(())
+    }
+
+    /// The current window, which is what the peer may still send.
+    pub fn window(&self) -> WindowSize {
+        self.window
+    }
+
+    /// The bytes released but not yet returned with WINDOW_UPDATE.
+    pub fn released(&self) -> u32 {
+        self.released
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use {SizeIncrement, HttpError};
+    use super::{WindowSize, WindowUpdateStrategy, RecvWindow};
+
+    #[test]
+    fn test_window_limits() {
+        let window = WindowSize::default();
+        assert_eq!(window.size(), 65535);
+
+        assert_eq!(WindowSize::new(1 << 31), Err(HttpError::FlowControlError));
+
+        let full = WindowSize::new((1 << 31) - 1).unwrap();
+        assert_eq!(full.checked_add(SizeIncrement(1)), Err(HttpError::FlowControlError));
+        assert_eq!(full.saturating_add(SizeIncrement(1)), full);
+        assert_eq!(window.checked_add(SizeIncrement(1)).unwrap().size(), 65536);
+        assert_eq!(window.checked_add(SizeIncrement((1 << 31) | 1)).unwrap().size(), 65536);
+    }
+
+    #[test]
+    fn test_window_consume() {
+        let window = WindowSize::new(10).unwrap();
+
+        assert_eq!(window.checked_sub(10).unwrap().size(), 0);
+        assert_eq!(window.checked_sub(11), Err(HttpError::FlowControlError));
+    }
+
+    #[test]
+    fn test_window_goes_negative() {
+        let window = WindowSize::default().checked_sub(60000).unwrap();
+        let reduced = window.adjust(65535, 0).unwrap();
+
+        assert_eq!(reduced.size(), -60000);
+        assert_eq!(reduced.available(), 0);
+        assert_eq!(reduced.checked_sub(1), Err(HttpError::FlowControlError));
+        assert_eq!(reduced.checked_add(SizeIncrement(60010)).unwrap().available(), 10);
+        assert_eq!(WindowSize::new((1 << 31) - 1).unwrap().adjust(0, 1),
+                   Err(HttpError::FlowControlError));
+    }
+
+    #[test]
+    fn test_recv_window_replenishes() {
+        let mut window = RecvWindow::new(100, WindowUpdateStrategy::HalfWindow).unwrap();
+        window.recv(60).unwrap();
+        assert_eq!(window.recv(41), Err(HttpError::FlowControlError));
+
+        assert_eq!(window.release(30), None);
+        assert_eq!(window.release(20), Some(SizeIncrement(50)));
+        assert_eq!((window.window().size(), window.released()), (90, 0));
+
+        let mut window = RecvWindow::new(100, WindowUpdateStrategy::Threshold(10)).unwrap();
+        window.recv(100).unwrap();
+        assert_eq!(window.release(9), None);
+        assert_eq!(window.release(1), Some(SizeIncrement(10)));
+
+        let strategy = WindowUpdateStrategy::custom(|released, _| released % 2 == 1);
+        assert_eq!(format!("{:?}", strategy), "Custom(..)");
+        let mut window = RecvWindow::new(100, strategy).unwrap();
+        assert_eq!(window.release(2), None);
+        assert_eq!(window.release(1), Some(SizeIncrement(3)));
+        assert_eq!(window.release(0), None);
+    }
+}